@@ -0,0 +1,139 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Layout of the EVM transactions carried by engine transactions.
+//!
+//! The sidecar submits an EVM transaction as the arguments of an entry function: the engine
+//! address of the sender followed by the raw EIP-2718 transaction. These functions are the
+//! only place that layout is encoded or decoded.
+
+use crate::error::{Result, SidecarError};
+use alloy_primitives::{hex, Bytes};
+use aptos_api_types::EntryFunctionPayload;
+use move_core_types::account_address::AccountAddress;
+
+/// Encodes the BCS arguments of the entry function that executes the raw EVM transaction
+/// `evm_tx` on behalf of `sender`.
+pub fn encode_evm_tx_into_entry_function(sender: AccountAddress, evm_tx: &[u8]) -> Vec<Vec<u8>> {
+    vec![
+        bcs::to_bytes(&sender).expect("Failed to serialize sender"),
+        bcs::to_bytes(evm_tx).expect("Failed to serialize EVM transaction"),
+    ]
+}
+
+/// Decodes the sender and raw EVM transaction from the arguments of an entry function
+/// payload, as rendered by the engine API.
+///
+/// Fails unless the arguments match the layout above: exactly two arguments, the second a
+/// `0x`-prefixed byte vector. Entry functions that merely take an address first, such as
+/// coin transfers whose amount is rendered in decimal, are therefore not mistaken for EVM
+/// transactions.
+pub fn decode_evm_tx_from_entry_function(
+    payload: &EntryFunctionPayload,
+) -> Result<(AccountAddress, Bytes)> {
+    if payload.arguments.len() != 2 {
+        return Err(SidecarError::Conversion(format!(
+            "expected 2 entry function arguments, found {}",
+            payload.arguments.len()
+        )));
+    }
+    let sender = payload
+        .arguments
+        .first()
+        .and_then(|argument| argument.as_str())
+        .and_then(|argument| AccountAddress::from_hex_literal(argument).ok())
+        .ok_or_else(|| SidecarError::Conversion("missing sender argument".into()))?;
+    let evm_tx = payload
+        .arguments
+        .get(1)
+        .and_then(|argument| argument.as_str())
+        .and_then(|argument| argument.strip_prefix("0x"))
+        .and_then(|argument| hex::decode(argument).ok())
+        .ok_or_else(|| SidecarError::Conversion("missing EVM transaction argument".into()))?;
+
+    Ok((sender, evm_tx.into()))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{decode_evm_tx_from_entry_function, encode_evm_tx_into_entry_function};
+    use crate::error::SidecarError;
+    use alloy_primitives::hex;
+    use aptos_api_types::EntryFunctionPayload;
+    use move_core_types::account_address::AccountAddress;
+
+    /// Renders BCS entry function arguments the way the engine API does.
+    fn rendered(arguments: &[Vec<u8>]) -> EntryFunctionPayload {
+        let sender: AccountAddress = bcs::from_bytes(&arguments[0]).unwrap();
+        let evm_tx: Vec<u8> = bcs::from_bytes(&arguments[1]).unwrap();
+
+        serde_json::from_value(serde_json::json!({
+            "function": "0x100::evm::transact",
+            "type_arguments": [],
+            "arguments": [sender.to_hex_literal(), format!("0x{}", hex::encode(evm_tx))],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn round_trips_sender_and_evm_transaction() {
+        let sender = AccountAddress::from_hex_literal("0xa11ce").unwrap();
+        let evm_tx = vec![0x02, 0xf8, 0x6b, 0x04];
+
+        let arguments = encode_evm_tx_into_entry_function(sender, &evm_tx);
+        let (decoded_sender, decoded_tx) =
+            decode_evm_tx_from_entry_function(&rendered(&arguments)).unwrap();
+
+        assert_eq!(decoded_sender, sender);
+        assert_eq!(decoded_tx.as_ref(), evm_tx.as_slice());
+    }
+
+    #[test]
+    fn round_trips_empty_evm_transaction() {
+        let arguments = encode_evm_tx_into_entry_function(AccountAddress::ONE, &[]);
+        let (_, decoded_tx) = decode_evm_tx_from_entry_function(&rendered(&arguments)).unwrap();
+
+        assert!(decoded_tx.is_empty());
+    }
+
+    #[test]
+    fn rejects_payload_without_evm_transaction() {
+        let payload = serde_json::from_value(serde_json::json!({
+            "function": "0x1::aptos_account::transfer",
+            "type_arguments": [],
+            "arguments": ["0x1"],
+        }))
+        .unwrap();
+
+        let err = decode_evm_tx_from_entry_function(&payload).unwrap_err();
+        assert!(matches!(err, SidecarError::Conversion(_)));
+    }
+
+    #[test]
+    fn rejects_transfer_with_decimal_amount() {
+        // The amount would decode as hex if it were not required to be a byte vector.
+        let payload = serde_json::from_value(serde_json::json!({
+            "function": "0x1::aptos_account::transfer",
+            "type_arguments": [],
+            "arguments": ["0x1", "1000"],
+        }))
+        .unwrap();
+
+        let err = decode_evm_tx_from_entry_function(&payload).unwrap_err();
+        assert!(matches!(err, SidecarError::Conversion(_)));
+    }
+}
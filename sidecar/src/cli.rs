@@ -15,9 +15,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{config::Config, engine::EngineClient, server::Server};
+use crate::{
+    config::Config,
+    engine::{
+        accounts::AccountCache, detect_native_decimals, health::UpstreamHealth,
+        sync_lag::SyncLagMonitor, tracker::HeadTracker, verify_chain_id, EngineClient,
+    },
+    rpc::{block_cache::BlockCache, eth::to_aptos_address, revert::AbortReasons},
+    server::{subscription::SubscriptionManager, RpcOptions, Server},
+};
 use clap::{command, Parser};
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::Arc};
 
 #[derive(Parser, Debug)]
 #[command(version, about)]
@@ -28,17 +36,135 @@ pub struct Cli {
 }
 
 impl Cli {
-    pub async fn run(self) {
+    pub async fn run(self) -> anyhow::Result<()> {
         let config = Config::load_from_path(self.config);
+        let read_only = config.read_only();
+        let caches = config.caches();
+        caches.validate()?;
 
         let engine_config = config.engine.unwrap_or_default();
+        engine_config.validate()?;
+        let evm_chain_id = engine_config.evm_chain_id()?;
         let basic_config = engine_config.basic();
+        let server_config = config.server.unwrap_or_default();
+        server_config.validate()?;
+        let native_decimals = basic_config.native_decimals();
+        let entry_func = basic_config.entry_func();
+        let log_event = basic_config.log_event();
+        let create_event = basic_config.create_event();
+        let evm_gas_per_unit = basic_config.evm_gas_per_unit();
+        let code_func = basic_config.code_func();
+        let storage_func = basic_config.storage_func();
 
-        let adapter = engine_config.adapter().build_adapter(basic_config);
-        let client = EngineClient::new(adapter);
+        let adapter_config = engine_config.adapter();
+        let adapter = adapter_config.build_adapter(basic_config)?;
+        let mut client = EngineClient::new(adapter).with_deadline(server_config.request_timeout());
+        if let Some(submit_timeout) = adapter_config.submit_timeout() {
+            anyhow::ensure!(
+                submit_timeout <= server_config.request_timeout(),
+                "submit_timeout_secs {} exceeds server.request_timeout_seconds {}, which \
+                 bounds every request",
+                submit_timeout.as_secs(),
+                server_config.request_timeout().as_secs()
+            );
+            client = client.with_submit_deadline(submit_timeout);
+        }
+        if let Some(simulate_timeout) = adapter_config.simulate_timeout() {
+            client = client.with_simulate_deadline(simulate_timeout);
+        }
 
-        let server = Server::new(config.server.unwrap_or_default());
+        if let Some(chain_id) = adapter_config.chain_id() {
+            verify_chain_id(&client, chain_id).await?;
+        }
 
-        server.start(client).await;
+        let native_decimals = if engine_config.auto_detect_decimals() {
+            detect_native_decimals(&client, native_decimals).await
+        } else {
+            native_decimals
+        };
+
+        let tracker_config = engine_config.tracker();
+        let head_poll_interval = tracker_config.head_poll_interval();
+        let tracker = HeadTracker::new(client.clone(), tracker_config.head_fallback_timeout());
+        let tracker = if head_poll_interval.is_zero() {
+            tracing::info!("Head tracker disabled, fetching ledger info on every request");
+            Arc::new(tracker.with_on_demand_fetch())
+        } else {
+            let tracker = Arc::new(tracker);
+            tracker.spawn(head_poll_interval);
+            tracker
+        };
+
+        let accounts = Arc::new(AccountCache::new(
+            tracker_config
+                .warm_accounts()
+                .iter()
+                .map(to_aptos_address)
+                .collect(),
+        ));
+        if !tracker_config.warm_accounts().is_empty() {
+            if let Err(e) = accounts.refresh(&client).await {
+                tracing::warn!("Failed to warm cached accounts: {}", e);
+            }
+            accounts.spawn(client.clone(), caches.accounts_ttl());
+        }
+
+        let health = Arc::new(UpstreamHealth::new());
+        health.spawn(client.clone(), tracker_config.health_probe_interval());
+
+        let options = RpcOptions {
+            native_decimals,
+            assets: engine_config.assets(),
+            genesis: engine_config.genesis.clone(),
+            entry_func,
+            log_event,
+            create_event,
+            max_raw_tx_bytes: server_config.max_raw_tx_bytes(),
+            max_calldata_bytes: server_config.max_calldata_bytes(),
+            min_gas_price_wei: server_config.min_gas_price_wei(),
+            gas_price_ttl: caches.gas_price_ttl(),
+            blob_base_fee_wei: server_config.blob_base_fee_wei(),
+            default_base_fee_wei: server_config.default_base_fee_wei(),
+            default_priority_fee_wei: server_config.default_priority_fee_wei(),
+            block_gas_limit: server_config.block_gas_limit(),
+            evm_gas_per_unit,
+            evm_chain_id,
+            max_block_range: server_config.max_block_range(),
+            clamp_block_range: server_config.clamp_block_range(),
+            block_fetch_failure: server_config.block_fetch_failure(),
+            reject_pending_calls: server_config.reject_pending_calls(),
+            proof_unavailable: server_config.proof_unavailable(),
+            proof_unavailable_hint: server_config.proof_unavailable_hint(),
+            blocks: Arc::new(BlockCache::new(caches.blocks())),
+            sync_lag_threshold: tracker_config.sync_lag_threshold_versions(),
+            abort_reasons: AbortReasons::new(engine_config.abort_reasons()),
+            balance: engine_config.balance(),
+            code_func,
+            storage_func,
+            accounts,
+            subscriptions: SubscriptionManager::from_config(&server_config),
+            max_block_wait: server_config.max_block_wait(),
+            upstream_health: Some(health.clone()),
+        };
+        let mut server = Server::new(server_config)
+            .with_read_only(read_only)
+            .with_max_head_staleness(tracker_config.max_head_staleness())
+            .with_index_capacities(caches.index_capacities())
+            .with_upstream_health(health.clone());
+        // Fetching on demand always serves the upstream head, so there is no lag to monitor.
+        if !tracker.is_on_demand() {
+            let sync_lag = Arc::new(SyncLagMonitor::new(
+                tracker_config.sync_lag_alert_blocks(),
+                tracker_config.sync_lag_alert_period(),
+            ));
+            sync_lag.spawn(
+                tracker.clone(),
+                health,
+                tracker_config.health_probe_interval(),
+            );
+            server = server.with_sync_lag(sync_lag);
+        }
+
+        server.start(client, tracker, options).await
     }
 }
@@ -0,0 +1,104 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::engine::indexes::IndexCapacities;
+use anyhow::{ensure, Result};
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Sizes of the in-memory caches and indexes, for tuning memory usage in one place.
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct CachesConfig {
+    /// Transactions tracked as pending across all senders.
+    pub pending: Option<usize>,
+    /// Logs blooms of blocks kept for skipping blocks in log queries.
+    pub blooms: Option<usize>,
+    /// Block hashes kept for resolving blocks by hash.
+    pub block_hashes: Option<usize>,
+    /// EVM transaction hashes kept for looking transactions up by hash.
+    pub transaction_hashes: Option<usize>,
+    /// Converted blocks kept in memory, or 0 to convert every block on request.
+    pub blocks: Option<usize>,
+    /// How long a cached account is served before it is fetched again.
+    pub accounts_ttl_ms: Option<u64>,
+    /// How long the gas prices estimated by the engine are reused, or 0 to fetch them on
+    /// every request.
+    pub gas_price_ttl_ms: Option<u64>,
+}
+
+impl CachesConfig {
+    pub fn index_capacities(&self) -> IndexCapacities {
+        let defaults = IndexCapacities::default();
+
+        IndexCapacities {
+            pending: self.pending.unwrap_or(defaults.pending),
+            blooms: self.blooms.unwrap_or(defaults.blooms),
+            block_hashes: self.block_hashes.unwrap_or(defaults.block_hashes),
+            transaction_hashes: self
+                .transaction_hashes
+                .unwrap_or(defaults.transaction_hashes),
+        }
+    }
+
+    pub fn blocks(&self) -> usize {
+        self.blocks.unwrap_or(256)
+    }
+
+    pub fn accounts_ttl(&self) -> Duration {
+        Duration::from_millis(self.accounts_ttl_ms.unwrap_or(5000))
+    }
+
+    pub fn gas_price_ttl(&self) -> Duration {
+        Duration::from_millis(self.gas_price_ttl_ms.unwrap_or(1000))
+    }
+
+    /// Fails if a setting would leave an index unable to hold any entry, or the cached
+    /// accounts refreshed continuously.
+    pub fn validate(&self) -> Result<()> {
+        let capacities = self.index_capacities();
+        for (name, capacity) in [
+            ("pending", capacities.pending),
+            ("blooms", capacities.blooms),
+            ("block_hashes", capacities.block_hashes),
+            ("transaction_hashes", capacities.transaction_hashes),
+        ] {
+            ensure!(capacity > 0, "caches.{} must be at least 1", name);
+        }
+        ensure!(
+            !self.accounts_ttl().is_zero(),
+            "caches.accounts_ttl_ms must be at least 1"
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::CachesConfig;
+
+    #[test]
+    fn rejects_empty_index_capacity() {
+        let caches: CachesConfig = toml::from_str("blooms = 0").unwrap();
+
+        assert_eq!(
+            caches.validate().unwrap_err().to_string(),
+            "caches.blooms must be at least 1"
+        );
+        assert!(CachesConfig::default().validate().is_ok());
+    }
+}
@@ -15,16 +15,28 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::engine::adapter::{
-    local::LocalEngineAdapter, remote::RemoteEngineAdapter, EngineAdapter,
+use crate::{
+    engine::adapter::{local::LocalEngineAdapter, remote::RemoteEngineAdapter, EngineAdapter},
+    rpc::convert::DEFAULT_EVM_GAS_PER_UNIT,
 };
+use alloy_primitives::Bytes;
+use aptos_global_constants::{GAS_UNIT_PRICE, MAX_GAS_AMOUNT};
 use aptos_types::chain_id::NamedChain;
+use move_core_types::{language_storage::TypeTag, parser::parse_type_tag};
 use serde::Deserialize;
+use std::{fmt, time::Duration};
 
 #[derive(Debug, Clone, Deserialize, Default)]
 pub struct EngineConfig {
     pub basic: Option<EngineBasicConfig>,
     pub adapter: Option<AdapterConfig>,
+    pub tracker: Option<TrackerConfig>,
+    pub auto_detect_decimals: Option<bool>,
+    pub assets: Option<Vec<String>>,
+    pub genesis: Option<GenesisConfig>,
+    pub balance: Option<BalanceConfig>,
+    pub abort_reasons: Option<Vec<AbortReasonConfig>>,
+    pub evm_chain_id: Option<String>,
 }
 
 impl EngineConfig {
@@ -35,6 +47,78 @@ impl EngineConfig {
     pub fn adapter(&self) -> AdapterConfig {
         self.adapter.clone().unwrap_or_default()
     }
+
+    pub fn tracker(&self) -> TrackerConfig {
+        self.tracker.clone().unwrap_or_default()
+    }
+
+    pub fn auto_detect_decimals(&self) -> bool {
+        self.auto_detect_decimals.unwrap_or(false)
+    }
+
+    pub fn balance(&self) -> BalanceConfig {
+        self.balance.clone().unwrap_or_default()
+    }
+
+    /// Asset types whose balances can be queried through `sidecar_getAssetBalance`.
+    pub fn assets(&self) -> Vec<String> {
+        self.assets.clone().unwrap_or_default()
+    }
+
+    pub fn abort_reasons(&self) -> &[AbortReasonConfig] {
+        self.abort_reasons.as_deref().unwrap_or_default()
+    }
+
+    /// Chain id served to EVM clients in place of the engine chain id, which is a `u8` and
+    /// cannot represent most EVM chain ids. Engine transactions are still built for the
+    /// engine chain id.
+    pub fn evm_chain_id(&self) -> anyhow::Result<Option<u64>> {
+        self.evm_chain_id.as_deref().map(parse_chain_id).transpose()
+    }
+
+    /// Fails if a setting that is only read once the server runs is malformed.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Some(genesis) = &self.genesis {
+            genesis.extra_data()?;
+        }
+        self.basic().entry_type_args()?;
+        if let AdapterConfig::Remote(remote) = self.adapter() {
+            remote.validate()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a chain id given as a decimal or `0x`-prefixed hexadecimal string.
+pub fn parse_chain_id(chain_id: &str) -> anyhow::Result<u64> {
+    let parsed = match chain_id.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => chain_id.parse(),
+    };
+
+    parsed.map_err(|e| anyhow::anyhow!("Invalid EVM chain id {}: {}", chain_id, e))
+}
+
+/// Revert reason reported when `module` aborts with `code`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AbortReasonConfig {
+    /// Module that aborts, as printed in the VM status, e.g. `0x100::evm`.
+    pub module: String,
+    pub code: u64,
+    pub reason: String,
+}
+
+/// Where the native EVM balance of an account is read from.
+#[derive(Debug, Clone, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceConfig {
+    /// The coin store of the native coin type.
+    #[default]
+    CoinStore,
+    /// A view function that takes the mapped account address and returns its balance, for
+    /// engines that keep the native EVM balance in a contract.
+    ViewFunction(String),
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -42,6 +126,14 @@ pub struct EngineBasicConfig {
     pub coin_type: Option<String>,
     pub auth_func: Option<String>,
     pub entry_func: Option<String>,
+    pub entry_type_args: Option<Vec<String>>,
+    pub call_func: Option<String>,
+    pub code_func: Option<String>,
+    pub storage_func: Option<String>,
+    pub log_event: Option<String>,
+    pub create_event: Option<String>,
+    pub native_decimals: Option<u8>,
+    pub evm_gas_per_unit: Option<u64>,
 }
 
 impl EngineBasicConfig {
@@ -58,10 +150,66 @@ impl EngineBasicConfig {
     }
 
     pub fn entry_func(&self) -> String {
-        self.auth_func
+        self.entry_func
             .clone()
             .unwrap_or_else(|| "0x100::evm::transact".into())
     }
+
+    /// Type arguments of the entry function, for EVM modules generic over the coin type.
+    pub fn entry_type_args(&self) -> anyhow::Result<Vec<TypeTag>> {
+        self.entry_type_args
+            .iter()
+            .flatten()
+            .map(|ty| {
+                parse_type_tag(ty).map_err(|e| {
+                    anyhow::anyhow!("Invalid entry function type argument {}: {}", ty, e)
+                })
+            })
+            .collect()
+    }
+
+    /// View function that simulates an EVM message call.
+    pub fn call_func(&self) -> String {
+        self.call_func
+            .clone()
+            .unwrap_or_else(|| "0x100::evm::call".into())
+    }
+
+    /// View function that takes the mapped account address and returns the EVM code of the
+    /// account as hex. Without one, accounts are taken to hold no code.
+    pub fn code_func(&self) -> Option<String> {
+        self.code_func.clone()
+    }
+
+    /// View function that takes the mapped account address and a storage slot as hex and
+    /// returns the word stored at the slot as hex. Without one, accounts are taken to hold
+    /// empty storage.
+    pub fn storage_func(&self) -> Option<String> {
+        self.storage_func.clone()
+    }
+
+    /// Type of the engine event that records an EVM log.
+    pub fn log_event(&self) -> String {
+        self.log_event
+            .clone()
+            .unwrap_or_else(|| "0x100::evm::Log".into())
+    }
+
+    /// Type of the engine event that records the address of a created contract.
+    pub fn create_event(&self) -> String {
+        self.create_event
+            .clone()
+            .unwrap_or_else(|| "0x100::evm::ContractCreated".into())
+    }
+
+    pub fn native_decimals(&self) -> u8 {
+        self.native_decimals.unwrap_or(8)
+    }
+
+    /// EVM gas reported for every unit of gas the engine charges.
+    pub fn evm_gas_per_unit(&self) -> u64 {
+        self.evm_gas_per_unit.unwrap_or(DEFAULT_EVM_GAS_PER_UNIT)
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -77,42 +225,465 @@ impl Default for AdapterConfig {
 }
 
 impl AdapterConfig {
-    pub fn build_adapter(&self, config: EngineBasicConfig) -> Box<dyn EngineAdapter + Send + Sync> {
+    /// Builds the configured adapter, failing if its settings are malformed.
+    pub fn build_adapter(
+        &self,
+        config: EngineBasicConfig,
+    ) -> anyhow::Result<Box<dyn EngineAdapter + Send + Sync>> {
         let coin_type = config.coin_type();
         let auth_func = config.auth_func();
         let entry_func = config.entry_func();
+        let entry_type_args = config.entry_type_args()?;
+        let call_func = config.call_func();
 
-        match self {
+        Ok(match self {
             AdapterConfig::Remote(remote) => Box::new(RemoteEngineAdapter::new(
                 coin_type,
-                auth_func,
-                entry_func,
+                &auth_func,
+                &entry_func,
+                entry_type_args,
+                call_func,
                 remote.clone(),
-            )),
+            )?),
             AdapterConfig::Local => Box::new(LocalEngineAdapter::new(coin_type)),
+        })
+    }
+
+    /// Returns the chain id engine transactions are built for, if the adapter uses one.
+    pub fn chain_id(&self) -> Option<u8> {
+        match self {
+            AdapterConfig::Remote(remote) => Some(remote.chain_id()),
+            AdapterConfig::Local => None,
+        }
+    }
+
+    /// Returns the deadline of transaction submissions, if the adapter sets one.
+    pub fn submit_timeout(&self) -> Option<Duration> {
+        match self {
+            AdapterConfig::Remote(remote) => remote.submit_timeout(),
+            AdapterConfig::Local => None,
+        }
+    }
+
+    /// Returns the deadline of call simulations, if the adapter sets one.
+    pub fn simulate_timeout(&self) -> Option<Duration> {
+        match self {
+            AdapterConfig::Remote(remote) => remote.simulate_timeout(),
+            AdapterConfig::Local => None,
         }
     }
 }
 
+/// Default largest `max_gas_amount` of a sponsored transaction, see
+/// [`RemoteEngineConfig::max_sponsored_gas_amount`].
+pub const DEFAULT_MAX_SPONSORED_GAS_AMOUNT: u64 = 200_000;
+
 #[derive(Debug, Clone, Deserialize, Default)]
 pub struct RemoteEngineConfig {
     pub endpoint: Option<String>,
+    pub read_endpoint: Option<String>,
+    pub write_endpoint: Option<String>,
+    pub version_path_base: Option<String>,
     pub timeout: Option<u64>,
+    pub max_timeout: Option<u64>,
+    pub submit_timeout_secs: Option<u64>,
+    pub simulate_timeout_secs: Option<u64>,
     pub chain_id: Option<u8>,
+    pub min_gas_unit_price: Option<u64>,
+    pub max_gas_unit_price: Option<u64>,
+    pub max_gas_amount: Option<u64>,
+    pub max_concurrent_requests: Option<usize>,
+    pub max_upstream_requests: Option<usize>,
+    pub sponsor_gas: Option<bool>,
+    pub sponsor_account_creation: Option<bool>,
+    pub max_sponsored_gas_unit_price: Option<u64>,
+    pub max_sponsored_gas_amount: Option<u64>,
+    pub sponsor_private_key: Option<Secret>,
+    pub api_key: Option<Secret>,
+    pub auth_header: Option<String>,
+    pub allowed_functions: Option<Vec<String>>,
 }
 
 impl RemoteEngineConfig {
+    /// Fails if the gas bounds cannot be satisfied by any transaction.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.min_gas_unit_price() <= self.max_gas_unit_price(),
+            "min_gas_unit_price {} exceeds max_gas_unit_price {}",
+            self.min_gas_unit_price(),
+            self.max_gas_unit_price()
+        );
+        anyhow::ensure!(self.max_gas_amount() > 0, "max_gas_amount must not be zero");
+        anyhow::ensure!(
+            self.min_gas_unit_price() <= self.max_sponsored_gas_unit_price(),
+            "min_gas_unit_price {} exceeds max_sponsored_gas_unit_price {}",
+            self.min_gas_unit_price(),
+            self.max_sponsored_gas_unit_price()
+        );
+        anyhow::ensure!(
+            self.max_sponsored_gas_amount() > 0,
+            "max_sponsored_gas_amount must not be zero"
+        );
+
+        Ok(())
+    }
+
     pub fn endpoint(&self) -> &str {
         self.endpoint
             .as_deref()
             .unwrap_or("http://127.0.0.1:8080/v1")
     }
 
+    /// Endpoint serving reads and simulations, such as a fullnode replica, if not `endpoint`.
+    pub fn read_endpoint(&self) -> &str {
+        self.read_endpoint
+            .as_deref()
+            .unwrap_or_else(|| self.endpoint())
+    }
+
+    /// Endpoint accepting transactions, such as a validator or relayer, if not `endpoint`.
+    pub fn write_endpoint(&self) -> &str {
+        self.write_endpoint
+            .as_deref()
+            .unwrap_or_else(|| self.endpoint())
+    }
+
+    /// Path under `endpoint` where the REST API is mounted, if not the default `v1/`.
+    pub fn version_path_base(&self) -> Option<&str> {
+        self.version_path_base.as_deref()
+    }
+
     pub fn timeout(&self) -> u64 {
         self.timeout.unwrap_or(10)
     }
 
+    /// Latest a client may have a submitted transaction expire, in seconds from now.
+    pub fn max_timeout(&self) -> u64 {
+        self.max_timeout.unwrap_or(600)
+    }
+
+    /// How long a transaction submission may take, if it differs from the deadline of
+    /// other engine calls. The whole HTTP request is still bounded by the server request
+    /// timeout, so a longer submit timeout is rejected at startup.
+    pub fn submit_timeout(&self) -> Option<Duration> {
+        self.submit_timeout_secs.map(Duration::from_secs)
+    }
+
+    /// How long simulating a call may take, if it differs from the deadline of other engine
+    /// calls, so expensive simulations can be cut short before reads are.
+    pub fn simulate_timeout(&self) -> Option<Duration> {
+        self.simulate_timeout_secs.map(Duration::from_secs)
+    }
+
     pub fn chain_id(&self) -> u8 {
         self.chain_id.unwrap_or(NamedChain::TESTING.id())
     }
+
+    pub fn min_gas_unit_price(&self) -> u64 {
+        self.min_gas_unit_price.unwrap_or(GAS_UNIT_PRICE)
+    }
+
+    pub fn max_gas_unit_price(&self) -> u64 {
+        self.max_gas_unit_price.unwrap_or(10_000_000_000)
+    }
+
+    /// Largest `max_gas_amount` of a submitted transaction; the gas limit of the EVM
+    /// transaction is capped at it.
+    pub fn max_gas_amount(&self) -> u64 {
+        self.max_gas_amount.unwrap_or(MAX_GAS_AMOUNT)
+    }
+
+    /// Maximum number of upstream requests a range fetch keeps in flight.
+    pub fn max_concurrent_requests(&self) -> usize {
+        self.max_concurrent_requests.unwrap_or(8)
+    }
+
+    /// Maximum number of requests issued to the engine at the same time, across every RPC
+    /// method and range fetch.
+    pub fn max_upstream_requests(&self) -> usize {
+        self.max_upstream_requests.unwrap_or(32).max(1)
+    }
+
+    /// Whether transactions are submitted with their gas paid by the sponsor account of
+    /// `sponsor_private_key`.
+    pub fn sponsor_gas(&self) -> bool {
+        self.sponsor_gas.unwrap_or(false)
+    }
+
+    /// Whether the first transaction of every sender is sponsored by the account of
+    /// `sponsor_private_key`, which makes the engine create the sender account. Implied by
+    /// `sponsor_gas`.
+    ///
+    /// Anyone can generate fresh keys, so every new key can spend the sponsor funds once.
+    /// Each such transaction is capped by `max_sponsored_gas_unit_price` and
+    /// `max_sponsored_gas_amount`, which bound what a single key can spend, but not how many
+    /// keys are used; keep the sponsor account funded only as far as that exposure allows.
+    pub fn sponsor_account_creation(&self) -> bool {
+        self.sponsor_account_creation.unwrap_or(false)
+    }
+
+    /// Highest gas unit price a sponsor pays, whatever price the sender signed for, so that
+    /// senders cannot spend the sponsor funds faster by bidding up. Defaults to
+    /// `min_gas_unit_price`, which pins sponsored transactions to it.
+    pub fn max_sponsored_gas_unit_price(&self) -> u64 {
+        self.max_sponsored_gas_unit_price
+            .unwrap_or_else(|| self.min_gas_unit_price())
+    }
+
+    /// Largest `max_gas_amount` of a sponsored transaction.
+    pub fn max_sponsored_gas_amount(&self) -> u64 {
+        self.max_sponsored_gas_amount
+            .unwrap_or(DEFAULT_MAX_SPONSORED_GAS_AMOUNT)
+    }
+
+    pub fn sponsor_private_key(&self) -> Option<&str> {
+        self.sponsor_private_key.as_ref().map(Secret::expose)
+    }
+
+    /// API key sent with every request to `endpoint`, for hosted endpoints that require one.
+    pub fn api_key(&self) -> Option<&str> {
+        self.api_key.as_ref().map(Secret::expose)
+    }
+
+    /// Functions, as `<address>::<module>::<function>`, the sidecar may submit transactions
+    /// through or authenticate them with. If set, the adapter refuses to start unless the
+    /// configured entry and authentication functions are among them.
+    pub fn allowed_functions(&self) -> Option<&[String]> {
+        self.allowed_functions.as_deref()
+    }
+
+    /// Header carrying `api_key`. Without it the key is sent as a bearer token in the
+    /// `Authorization` header.
+    pub fn auth_header(&self) -> Option<&str> {
+        self.auth_header.as_deref()
+    }
+}
+
+/// A configuration value, such as a credential, that is redacted from `Debug` output so it
+/// never shows up in logs.
+#[derive(Clone, Deserialize, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+/// Parameters of the EVM genesis block synthesized in place of engine block 0.
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct GenesisConfig {
+    pub timestamp: Option<u64>,
+    pub extra_data: Option<String>,
+}
+
+impl GenesisConfig {
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp.unwrap_or(0)
+    }
+
+    pub fn extra_data(&self) -> anyhow::Result<Bytes> {
+        self.extra_data
+            .as_deref()
+            .map(|data| {
+                data.parse()
+                    .map_err(|e| anyhow::anyhow!("Invalid genesis extra data {}: {}", data, e))
+            })
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct TrackerConfig {
+    pub head_poll_interval_ms: Option<u64>,
+    pub head_fallback_timeout_ms: Option<u64>,
+    pub max_head_staleness_secs: Option<u64>,
+    pub warm_accounts: Option<Vec<alloy_primitives::Address>>,
+    /// Superseded by `caches.accounts_ttl_ms`.
+    pub account_refresh_interval_ms: Option<u64>,
+    pub health_probe_interval_ms: Option<u64>,
+    pub sync_lag_threshold_versions: Option<u64>,
+    pub sync_lag_alert_blocks: Option<u64>,
+    pub sync_lag_alert_period_secs: Option<u64>,
+}
+
+impl TrackerConfig {
+    /// How often the head is polled, or 0 to fetch it on every request that reads it, at
+    /// the cost of an engine round trip per request, which also disables the sync lag
+    /// monitor.
+    pub fn head_poll_interval(&self) -> Duration {
+        Duration::from_millis(self.head_poll_interval_ms.unwrap_or(1000))
+    }
+
+    pub fn head_fallback_timeout(&self) -> Duration {
+        Duration::from_millis(self.head_fallback_timeout_ms.unwrap_or(2000))
+    }
+
+    /// How far the engine ledger may lag the wall clock before the sidecar reports itself
+    /// not ready.
+    pub fn max_head_staleness(&self) -> Duration {
+        Duration::from_secs(self.max_head_staleness_secs.unwrap_or(60))
+    }
+
+    /// Senders, such as relayers, whose accounts are fetched on startup and kept cached.
+    pub fn warm_accounts(&self) -> &[alloy_primitives::Address] {
+        self.warm_accounts.as_deref().unwrap_or_default()
+    }
+
+    /// How often the engine is probed to detect whether it is healthy.
+    pub fn health_probe_interval(&self) -> Duration {
+        Duration::from_millis(self.health_probe_interval_ms.unwrap_or(5000))
+    }
+
+    /// How many versions the served ledger may lag the highest ledger ever observed before
+    /// `eth_syncing` reports the sidecar as syncing.
+    pub fn sync_lag_threshold_versions(&self) -> u64 {
+        self.sync_lag_threshold_versions.unwrap_or(1000)
+    }
+
+    /// How many blocks the served head may lag the probed engine head before a warning is
+    /// logged.
+    pub fn sync_lag_alert_blocks(&self) -> u64 {
+        self.sync_lag_alert_blocks.unwrap_or(100)
+    }
+
+    /// How long the lag must stay above [`Self::sync_lag_alert_blocks`] before it is logged.
+    pub fn sync_lag_alert_period(&self) -> Duration {
+        Duration::from_secs(self.sync_lag_alert_period_secs.unwrap_or(60))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{parse_chain_id, EngineConfig, RemoteEngineConfig};
+
+    #[test]
+    fn rejects_unsatisfiable_gas_bounds() {
+        let config: EngineConfig = toml::from_str(
+            r#"
+            [adapter.Remote]
+            min_gas_unit_price = 200
+            max_gas_unit_price = 50
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "min_gas_unit_price 200 exceeds max_gas_unit_price 50"
+        );
+
+        let config: EngineConfig = toml::from_str(
+            r#"
+            [adapter.Remote]
+            max_gas_amount = 0
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "max_gas_amount must not be zero"
+        );
+
+        let config: EngineConfig = toml::from_str(
+            r#"
+            [adapter.Remote]
+            min_gas_unit_price = 50
+            max_gas_unit_price = 50
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn redacts_sponsor_key_from_debug_output() {
+        let config: RemoteEngineConfig =
+            toml::from_str(r#"sponsor_private_key = "0x0707""#).unwrap();
+
+        assert_eq!(config.sponsor_private_key(), Some("0x0707"));
+        assert!(!format!("{:?}", config).contains("0x0707"));
+    }
+
+    #[test]
+    fn rejects_malformed_genesis_extra_data() {
+        let config: EngineConfig = toml::from_str(
+            r#"
+            [genesis]
+            extra_data = "0xzz"
+            "#,
+        )
+        .unwrap();
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .starts_with("Invalid genesis extra data 0xzz"));
+
+        let config: EngineConfig = toml::from_str(
+            r#"
+            [genesis]
+            extra_data = "0x01"
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert!(EngineConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_entry_type_args() {
+        let config: EngineConfig = toml::from_str(
+            r#"
+            [basic]
+            entry_type_args = ["0x1::aptos_coin::AptosCoin", "not a type"]
+            "#,
+        )
+        .unwrap();
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .starts_with("Invalid entry function type argument not a type"));
+
+        let config: EngineConfig = toml::from_str(
+            r#"
+            [basic]
+            entry_type_args = ["0x1::aptos_coin::AptosCoin"]
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.basic().entry_type_args().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn parses_decimal_and_hex_chain_ids() {
+        assert_eq!(parse_chain_id("42161").unwrap(), 42161);
+        assert_eq!(parse_chain_id("0xa4b1").unwrap(), 42161);
+        assert_eq!(parse_chain_id("0x4").unwrap(), 4);
+        assert!(parse_chain_id("0x").is_err());
+        assert!(parse_chain_id("arbitrum").is_err());
+    }
+
+    #[test]
+    fn reads_evm_chain_id_from_config() {
+        let config: EngineConfig = toml::from_str(r#"evm_chain_id = "0xa4b1""#).unwrap();
+
+        assert_eq!(config.evm_chain_id().unwrap(), Some(42161));
+        assert_eq!(EngineConfig::default().evm_chain_id().unwrap(), None);
+    }
 }
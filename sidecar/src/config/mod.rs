@@ -15,9 +15,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod caches;
 pub mod engine;
 pub mod server;
 
+use caches::CachesConfig;
 use engine::EngineConfig;
 use serde::Deserialize;
 use server::ServerConfig;
@@ -27,6 +29,8 @@ use std::{fs, path::Path};
 pub struct Config {
     pub server: Option<ServerConfig>,
     pub engine: Option<EngineConfig>,
+    pub caches: Option<CachesConfig>,
+    pub read_only: Option<bool>,
 }
 
 impl Config {
@@ -38,4 +42,24 @@ impl Config {
             Config::default()
         }
     }
+
+    /// Whether transaction submission and signing are disabled, regardless of other settings.
+    pub fn read_only(&self) -> bool {
+        self.read_only.unwrap_or(false)
+    }
+
+    /// The `caches` section, falling back to `server.block_cache_size` and
+    /// `engine.tracker.account_refresh_interval_ms`, where these were configured before.
+    pub fn caches(&self) -> CachesConfig {
+        let mut caches = self.caches.clone().unwrap_or_default();
+        if let Some(server) = &self.server {
+            caches.blocks = caches.blocks.or(server.block_cache_size);
+        }
+        if let Some(engine) = &self.engine {
+            caches.accounts_ttl_ms = caches
+                .accounts_ttl_ms
+                .or(engine.tracker().account_refresh_interval_ms);
+        }
+        caches
+    }
 }
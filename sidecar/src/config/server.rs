@@ -16,7 +16,12 @@
 // limitations under the License.
 
 use serde::Deserialize;
-use std::{net::SocketAddr, time::Duration};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 #[derive(Deserialize, Debug, Clone)]
 #[serde(untagged)]
@@ -34,12 +39,66 @@ impl<T> ItemOrList<T> {
     }
 }
 
+/// What a query over a range of blocks does when fetching one of the blocks fails.
+#[derive(Debug, Clone, Copy, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockFetchFailure {
+    /// The whole query fails.
+    #[default]
+    FailFast,
+    /// The block is left out of the results and its height logged in a warning.
+    SkipAndWarn,
+}
+
+/// Error `eth_getProof` returns while the engine serves no state proofs.
+#[derive(Debug, Clone, Copy, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofUnavailable {
+    /// Method not found (-32601), as if the method were not served at all.
+    #[default]
+    MethodNotFound,
+    /// Method not supported (-32004), see EIP-1474.
+    MethodNotSupported,
+}
+
 #[derive(Debug, Clone, Deserialize, Default)]
 pub struct ServerConfig {
     pub host: Option<String>,
     pub port: Option<u16>,
     pub request_timeout_seconds: Option<u64>,
-    pub cors: Option<ItemOrList<String>>,
+    pub cors: Option<CorsSetting>,
+    pub trusted_proxies: Option<Vec<String>>,
+    pub rate_limit_per_second: Option<f64>,
+    pub rate_limit_burst: Option<u32>,
+    pub max_request_bytes: Option<usize>,
+    pub max_response_bytes: Option<usize>,
+    pub stream_logs: Option<bool>,
+    pub max_raw_tx_bytes: Option<usize>,
+    pub max_calldata_bytes: Option<usize>,
+    pub min_gas_price_wei: Option<u64>,
+    pub blob_base_fee_wei: Option<u64>,
+    pub default_base_fee_wei: Option<u64>,
+    pub default_priority_fee_wei: Option<u64>,
+    pub block_gas_limit: Option<u64>,
+    pub max_block_range: Option<u64>,
+    pub clamp_block_range: Option<bool>,
+    pub block_fetch_failure: Option<BlockFetchFailure>,
+    pub reject_pending_calls: Option<bool>,
+    pub proof_unavailable: Option<ProofUnavailable>,
+    pub proof_unavailable_hint: Option<String>,
+    /// Superseded by `caches.blocks`.
+    pub block_cache_size: Option<usize>,
+    pub method_aliases: Option<HashMap<String, String>>,
+    pub log_sample_rate: Option<f64>,
+    pub strict_params: Option<bool>,
+    pub max_subscriptions_per_connection: Option<usize>,
+    pub max_total_subscriptions: Option<usize>,
+    pub subscription_queue_size: Option<usize>,
+    pub subscription_send_timeout_seconds: Option<u64>,
+    pub ws_ping_interval_seconds: Option<u64>,
+    pub ws_pong_timeout_seconds: Option<u64>,
+    pub max_block_wait_seconds: Option<u64>,
+    pub persist_state_path: Option<PathBuf>,
 }
 
 impl ServerConfig {
@@ -55,4 +114,280 @@ impl ServerConfig {
     pub fn request_timeout(&self) -> Duration {
         Duration::from_secs(self.request_timeout_seconds.unwrap_or(30))
     }
+
+    /// Networks of the reverse proxies whose `X-Forwarded-For` header is believed when
+    /// resolving client addresses, in CIDR notation.
+    pub fn trusted_proxies(&self) -> &[String] {
+        self.trusted_proxies.as_deref().unwrap_or_default()
+    }
+
+    /// Requests per second and burst size allowed to every client IP, or `None` if clients
+    /// are not rate limited. The burst defaults to one second worth of requests.
+    pub fn rate_limit(&self) -> Option<(f64, u32)> {
+        let rate = self.rate_limit_per_second?;
+        let burst = self.rate_limit_burst.unwrap_or(rate.ceil() as u32);
+
+        Some((rate, burst))
+    }
+
+    /// Largest request body read, rejected before it is parsed.
+    pub fn max_request_bytes(&self) -> usize {
+        self.max_request_bytes.unwrap_or(2 * 1024 * 1024)
+    }
+
+    /// Largest response served, for a single call or summed over the calls of a batch.
+    pub fn max_response_bytes(&self) -> usize {
+        self.max_response_bytes.unwrap_or(10 * 1024 * 1024)
+    }
+
+    /// Whether `GET /logs/stream` streams the logs of `eth_getLogs` filters as
+    /// newline-delimited JSON.
+    pub fn stream_logs(&self) -> bool {
+        self.stream_logs.unwrap_or(false)
+    }
+
+    /// Method names, such as deprecated ones, that are served by the method they map to.
+    pub fn method_aliases(&self) -> HashMap<String, String> {
+        self.method_aliases.clone().unwrap_or_default()
+    }
+
+    /// Fraction of successful RPC calls that are traced, between 0.0 and 1.0. Failed calls
+    /// are always traced.
+    pub fn log_sample_rate(&self) -> f64 {
+        self.log_sample_rate.unwrap_or(1.0)
+    }
+
+    /// Whether calls passing more params than their method takes are rejected, as the
+    /// JSON-RPC spec expects, instead of having the extra params dropped.
+    pub fn strict_params(&self) -> bool {
+        self.strict_params.unwrap_or(true)
+    }
+
+    pub fn max_raw_tx_bytes(&self) -> usize {
+        self.max_raw_tx_bytes.unwrap_or(128 * 1024)
+    }
+
+    /// Largest calldata of a call simulated by `eth_call` and similar methods.
+    pub fn max_calldata_bytes(&self) -> usize {
+        self.max_calldata_bytes.unwrap_or(128 * 1024)
+    }
+
+    /// Lowest gas price reported to wallets, so they do not build zero-fee transactions
+    /// while the engine estimates a zero gas price.
+    pub fn min_gas_price_wei(&self) -> u64 {
+        self.min_gas_price_wei.unwrap_or(1_000_000_000)
+    }
+
+    /// Blob base fee reported by `eth_blobBaseFee`. The engine has no blob market, so it is a
+    /// nominal constant.
+    pub fn blob_base_fee_wei(&self) -> u64 {
+        self.blob_base_fee_wei.unwrap_or(1)
+    }
+
+    /// Base fee of every block header, also reported by `eth_feeHistory`. The engine has no
+    /// dynamic fee market, so there is no base fee to burn by default.
+    pub fn default_base_fee_wei(&self) -> u64 {
+        self.default_base_fee_wei.unwrap_or(0)
+    }
+
+    /// Priority fee reported for every percentile of every block by `eth_feeHistory`.
+    pub fn default_priority_fee_wei(&self) -> u64 {
+        self.default_priority_fee_wei.unwrap_or(1_000_000_000)
+    }
+
+    /// Gas limit reported in block headers, in EVM gas units.
+    pub fn block_gas_limit(&self) -> u64 {
+        self.block_gas_limit.unwrap_or(30_000_000)
+    }
+
+    /// Largest number of blocks an `eth_getLogs` query may span.
+    pub fn max_block_range(&self) -> u64 {
+        self.max_block_range.unwrap_or(10_000)
+    }
+
+    /// Whether `eth_getLogs` queries spanning more than `max_block_range` blocks are cut down
+    /// to their first `max_block_range` blocks instead of rejected.
+    pub fn clamp_block_range(&self) -> bool {
+        self.clamp_block_range.unwrap_or(false)
+    }
+
+    /// What `eth_getLogs` does when one of the blocks in its range cannot be fetched.
+    pub fn block_fetch_failure(&self) -> BlockFetchFailure {
+        self.block_fetch_failure.unwrap_or_default()
+    }
+
+    /// Whether `eth_call` at the `pending` block is rejected instead of answered from the
+    /// latest ledger.
+    pub fn reject_pending_calls(&self) -> bool {
+        self.reject_pending_calls.unwrap_or(false)
+    }
+
+    /// Error code of `eth_getProof`, which is not served until the engine exposes state
+    /// proofs.
+    pub fn proof_unavailable(&self) -> ProofUnavailable {
+        self.proof_unavailable.unwrap_or_default()
+    }
+
+    /// Error data of `eth_getProof` telling clients where proofs can be obtained instead.
+    pub fn proof_unavailable_hint(&self) -> String {
+        self.proof_unavailable_hint.clone().unwrap_or_else(|| {
+            "state proofs are not served by this node, query a node with proof support".into()
+        })
+    }
+
+    pub fn max_subscriptions_per_connection(&self) -> usize {
+        self.max_subscriptions_per_connection.unwrap_or(1024)
+    }
+
+    pub fn max_total_subscriptions(&self) -> usize {
+        self.max_total_subscriptions.unwrap_or(10_000)
+    }
+
+    /// Number of messages queued for a subscriber connection before sends start waiting.
+    pub fn subscription_queue_size(&self) -> usize {
+        self.subscription_queue_size.unwrap_or(1024)
+    }
+
+    /// How long a send waits on a full subscriber queue before the connection is dropped.
+    pub fn subscription_send_timeout(&self) -> Duration {
+        Duration::from_secs(self.subscription_send_timeout_seconds.unwrap_or(10))
+    }
+
+    /// How often idle WebSocket connections are pinged so proxies keep them open, or `None`
+    /// if the interval is 0 and connections are never pinged.
+    pub fn ws_ping_interval(&self) -> Option<Duration> {
+        Some(Duration::from_secs(
+            self.ws_ping_interval_seconds.unwrap_or(30),
+        ))
+        .filter(|interval| !interval.is_zero())
+    }
+
+    /// How long a pinged WebSocket connection has to answer before it is closed.
+    pub fn ws_pong_timeout(&self) -> Duration {
+        Duration::from_secs(self.ws_pong_timeout_seconds.unwrap_or(10))
+    }
+
+    /// Longest time `sidecar_waitForBlock` holds a request, kept below the request timeout.
+    pub fn max_block_wait(&self) -> Duration {
+        Duration::from_secs(self.max_block_wait_seconds.unwrap_or(20))
+    }
+
+    /// Returns the CORS settings, with allowed origins given alone allowing any method and
+    /// header.
+    pub fn cors(&self) -> Option<CorsConfig> {
+        match self.cors.clone()? {
+            CorsSetting::Origins(origins) => Some(CorsConfig {
+                allowed_origins: Some(origins),
+                ..Default::default()
+            }),
+            CorsSetting::Config(config) => Some(config),
+        }
+    }
+
+    /// File the in-memory indexes are saved to on shutdown and loaded from on startup.
+    pub fn persist_state_path(&self) -> Option<&Path> {
+        self.persist_state_path.as_deref()
+    }
+
+    /// Fails if a setting would leave every client rate limited forever.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Some(rate) = self.rate_limit_per_second {
+            anyhow::ensure!(
+                rate.is_finite() && rate > 0.0,
+                "server.rate_limit_per_second must be a positive number, got {}",
+                rate
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// CORS settings as written in the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum CorsSetting {
+    /// The allowed origins alone, as in `cors = "all"`.
+    Origins(ItemOrList<String>),
+    Config(CorsConfig),
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct CorsConfig {
+    pub allowed_origins: Option<ItemOrList<String>>,
+    pub allowed_methods: Option<ItemOrList<String>>,
+    pub allowed_headers: Option<ItemOrList<String>>,
+    pub allow_credentials: Option<bool>,
+    pub max_age_seconds: Option<u64>,
+}
+
+impl CorsConfig {
+    pub fn allowed_origins(&self) -> Vec<String> {
+        self.allowed_origins
+            .clone()
+            .map(ItemOrList::into_list)
+            .unwrap_or_default()
+    }
+
+    /// Returns the allowed methods, or `None` to allow the methods a preflight asks for.
+    pub fn allowed_methods(&self) -> Option<Vec<String>> {
+        self.allowed_methods.clone().map(ItemOrList::into_list)
+    }
+
+    /// Returns the allowed headers, or `None` to allow the headers a preflight asks for.
+    pub fn allowed_headers(&self) -> Option<Vec<String>> {
+        self.allowed_headers.clone().map(ItemOrList::into_list)
+    }
+
+    pub fn allow_credentials(&self) -> bool {
+        self.allow_credentials.unwrap_or(false)
+    }
+
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age_seconds.map(Duration::from_secs)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::ServerConfig;
+
+    #[test]
+    fn reads_allowed_origins_alone_or_cors_table() {
+        let config: ServerConfig = toml::from_str(r#"cors = "all""#).unwrap();
+        assert_eq!(config.cors().unwrap().allowed_origins(), vec!["all"]);
+
+        let config: ServerConfig =
+            toml::from_str(r#"cors = ["https://a.example", "https://b.example"]"#).unwrap();
+        assert_eq!(
+            config.cors().unwrap().allowed_origins(),
+            vec!["https://a.example", "https://b.example"]
+        );
+
+        let config: ServerConfig = toml::from_str(
+            r#"
+            [cors]
+            allowed_origins = "https://a.example"
+            allow_credentials = true
+            "#,
+        )
+        .unwrap();
+        let cors = config.cors().unwrap();
+        assert_eq!(cors.allowed_origins(), vec!["https://a.example"]);
+        assert!(cors.allow_credentials());
+
+        assert!(ServerConfig::default().cors().is_none());
+    }
+
+    #[test]
+    fn rejects_rate_limit_that_never_refills() {
+        for rate in ["0.0", "-1.0", "nan", "inf"] {
+            let config: ServerConfig =
+                toml::from_str(&format!("rate_limit_per_second = {}", rate)).unwrap();
+            assert!(config.validate().is_err(), "accepted {}", rate);
+        }
+
+        let config: ServerConfig = toml::from_str("rate_limit_per_second = 0.5").unwrap();
+        assert!(config.validate().is_ok());
+    }
 }
@@ -0,0 +1,168 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::adapter::EngineAdapter;
+use anyhow::Result;
+use aptos_rest_client::types::Account;
+use move_core_types::account_address::AccountAddress;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+
+/// Keeps the engine accounts of a few known senders, such as relayers, so that their
+/// lookups do not wait on the engine.
+///
+/// The accounts are fetched on startup and refreshed by a background poll; other accounts
+/// are not cached.
+#[derive(Debug, Default)]
+pub struct AccountCache {
+    addresses: Vec<AccountAddress>,
+    accounts: RwLock<HashMap<AccountAddress, Option<Account>>>,
+}
+
+impl AccountCache {
+    pub fn new(addresses: Vec<AccountAddress>) -> Self {
+        Self {
+            addresses,
+            accounts: Default::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the cached account at `address`, which is `Some(None)` if the engine has no
+    /// such account, or `None` if the address is not cached.
+    pub fn get(&self, address: &AccountAddress) -> Option<Option<Account>> {
+        self.accounts.read().unwrap().get(address).cloned()
+    }
+
+    /// Fetches every known account from the engine.
+    pub async fn refresh<Adapter>(&self, adapter: &Adapter) -> Result<()>
+    where
+        Adapter: EngineAdapter + Sync,
+    {
+        for address in &self.addresses {
+            let account = adapter.get_account(*address).await?;
+            self.accounts.write().unwrap().insert(*address, account);
+        }
+
+        Ok(())
+    }
+
+    /// Evicts the cached account at `address`, so lookups fetch it from the engine until the
+    /// next refresh.
+    pub fn invalidate(&self, address: &AccountAddress) {
+        self.accounts.write().unwrap().remove(address);
+    }
+
+    /// Evicts the cached account at `address` and fetches it again, keeping it cached if it
+    /// is one of the known accounts.
+    ///
+    /// The entry stays evicted if the fetch fails, so lookups fall back to the engine.
+    pub async fn refresh_account<Adapter>(
+        &self,
+        adapter: &Adapter,
+        address: AccountAddress,
+    ) -> Result<Option<Account>>
+    where
+        Adapter: EngineAdapter + Sync,
+    {
+        self.invalidate(&address);
+        let account = adapter.get_account(address).await?;
+        if self.addresses.contains(&address) {
+            self.accounts
+                .write()
+                .unwrap()
+                .insert(address, account.clone());
+        }
+
+        Ok(account)
+    }
+
+    pub fn spawn<Adapter>(self: &Arc<Self>, adapter: Adapter, interval: Duration) -> JoinHandle<()>
+    where
+        Adapter: EngineAdapter + Send + Sync + 'static,
+    {
+        let cache = self.clone();
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = cache.refresh(&adapter).await {
+                    tracing::warn!("Failed to refresh cached accounts: {}", e);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::AccountCache;
+    use crate::engine::adapter::mock::MockEngineAdapter;
+    use move_core_types::account_address::AccountAddress;
+
+    #[tokio::test]
+    async fn populates_known_accounts_on_refresh() {
+        let adapter = MockEngineAdapter::new().with_sequence_number(3);
+        let relayer = AccountAddress::from_hex_literal("0x44").unwrap();
+        let cache = AccountCache::new(vec![relayer]);
+        assert!(cache.get(&relayer).is_none());
+
+        cache.refresh(&adapter).await.unwrap();
+
+        let account = cache.get(&relayer).unwrap().unwrap();
+        assert_eq!(account.sequence_number, 3);
+        assert!(cache.get(&AccountAddress::ONE).is_none());
+        assert_eq!(adapter.calls("get_account"), 1);
+    }
+
+    #[tokio::test]
+    async fn replaces_cached_account_on_refresh() {
+        let relayer = AccountAddress::from_hex_literal("0x44").unwrap();
+        let cache = AccountCache::new(vec![relayer]);
+        cache
+            .refresh(&MockEngineAdapter::new().with_sequence_number(3))
+            .await
+            .unwrap();
+
+        let account = cache
+            .refresh_account(&MockEngineAdapter::new().with_sequence_number(7), relayer)
+            .await
+            .unwrap();
+
+        assert_eq!(account.unwrap().sequence_number, 7);
+        assert_eq!(cache.get(&relayer).unwrap().unwrap().sequence_number, 7);
+
+        // Other accounts are fetched but not cached.
+        cache
+            .refresh_account(&MockEngineAdapter::new(), AccountAddress::ONE)
+            .await
+            .unwrap();
+        assert!(cache.get(&AccountAddress::ONE).is_none());
+    }
+}
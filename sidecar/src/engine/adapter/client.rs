@@ -15,49 +15,156 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::aa::encode_evm_tx_into_entry_function;
 use anyhow::{Context, Result};
 use aptos_api_types::PendingTransaction;
-use aptos_rest_client::Client as ApiClient;
-use aptos_sdk::transaction_builder::TransactionBuilder;
+use aptos_rest_client::{error::RestError, Client as ApiClient};
+use aptos_sdk::{
+    crypto::{ed25519::Ed25519PrivateKey, PrivateKey, SigningKey, ValidCryptoMaterialStringExt},
+    transaction_builder::TransactionBuilder,
+};
 use aptos_types::{
     chain_id::ChainId,
     function_info::FunctionInfo,
     move_utils::MemberId,
     transaction::{
-        authenticator::AccountAuthenticator, EntryFunction, SignedTransaction, TransactionPayload,
+        authenticator::{AccountAuthenticator, AuthenticationKey},
+        EntryFunction, RawTransactionWithData, SignedTransaction, TransactionPayload,
     },
 };
-use move_core_types::account_address::AccountAddress;
+use move_core_types::{account_address::AccountAddress, language_storage::TypeTag};
 use std::{
-    borrow::Cow,
     str::FromStr,
+    sync::Arc,
     time::{SystemTime, UNIX_EPOCH},
 };
 
+/// An account that pays the gas of submitted transactions in place of their senders.
+///
+/// The gas of a sponsored transaction is capped by the sponsor limits, see
+/// [`Sponsor::with_limits`], rather than left to the sender.
+#[derive(Clone, Debug)]
+pub struct Sponsor {
+    address: AccountAddress,
+    key: Arc<Ed25519PrivateKey>,
+    max_gas_unit_price: u64,
+    max_gas_amount: u64,
+}
+
+impl Sponsor {
+    /// Creates a sponsor for the account of the Ed25519 private `key`.
+    pub fn new(key: Ed25519PrivateKey) -> Self {
+        Self {
+            address: AuthenticationKey::ed25519(&key.public_key()).account_address(),
+            key: Arc::new(key),
+            max_gas_unit_price: u64::MAX,
+            max_gas_amount: u64::MAX,
+        }
+    }
+
+    /// Pays at most `max_gas_unit_price` per gas unit and `max_gas_amount` gas units for a
+    /// transaction, whatever its sender asked for.
+    pub fn with_limits(mut self, max_gas_unit_price: u64, max_gas_amount: u64) -> Self {
+        self.max_gas_unit_price = max_gas_unit_price;
+        self.max_gas_amount = max_gas_amount;
+        self
+    }
+
+    /// Creates a sponsor from a hex-encoded Ed25519 private key.
+    pub fn from_encoded_key(key: &str) -> Result<Self> {
+        let key =
+            Ed25519PrivateKey::from_encoded_string(key).context("Failed to parse sponsor key")?;
+
+        Ok(Self::new(key))
+    }
+
+    pub fn address(&self) -> AccountAddress {
+        self.address
+    }
+
+    /// Signs `raw_transaction` as its fee payer.
+    fn authenticate(&self, raw_transaction: &RawTransactionWithData) -> AccountAuthenticator {
+        let signature = self
+            .key
+            .sign(raw_transaction)
+            .expect("Failed to sign as fee payer");
+
+        AccountAuthenticator::ed25519(self.key.public_key(), signature)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct AAClient {
     pub api_client: ApiClient,
-    auth_func: Cow<'static, str>,
-    entry_func: Cow<'static, str>,
+    auth_func: FunctionInfo,
+    entry_func: MemberId,
+    entry_type_args: Vec<TypeTag>,
     chain_id: u8,
     timeout: u64,
+    max_timeout: u64,
+    sponsor: Option<Sponsor>,
+    sponsor_first_only: bool,
 }
 
 impl AAClient {
+    /// Creates a client that authenticates transactions with `auth_func` and submits them
+    /// through `entry_func`, both given as `<address>::<module>::<function>`.
     pub fn new(
         api_client: ApiClient,
-        auth_func: String,
-        entry_func: String,
+        auth_func: &str,
+        entry_func: &str,
         chain_id: u8,
         timeout: u64,
-    ) -> Self {
-        Self {
+    ) -> Result<Self> {
+        let auth_func = FunctionInfo::from_str(auth_func)
+            .with_context(|| format!("Invalid authentication function {:?}", auth_func))?;
+        let entry_func = MemberId::from_str(entry_func)
+            .with_context(|| format!("Invalid entry function {:?}", entry_func))?;
+
+        Ok(Self {
             api_client,
-            auth_func: Cow::from(auth_func),
-            entry_func: Cow::from(entry_func),
+            auth_func,
+            entry_func,
+            entry_type_args: vec![],
             chain_id,
             timeout,
-        }
+            max_timeout: timeout,
+            sponsor: None,
+            sponsor_first_only: false,
+        })
+    }
+
+    /// Passes `entry_type_args` as the type arguments of the entry function.
+    pub fn with_entry_type_args(mut self, entry_type_args: Vec<TypeTag>) -> Self {
+        self.entry_type_args = entry_type_args;
+        self
+    }
+
+    /// Lets transactions submitted with an expiration expire up to `max_timeout` seconds from
+    /// now, instead of after the default timeout.
+    pub fn with_max_timeout(mut self, max_timeout: u64) -> Self {
+        self.max_timeout = max_timeout;
+        self
+    }
+
+    /// Submits every transaction as a fee-payer transaction whose gas is paid by `sponsor`.
+    pub fn with_sponsor(mut self, sponsor: Sponsor) -> Self {
+        self.sponsor = Some(sponsor);
+        self.sponsor_first_only = false;
+        self
+    }
+
+    /// Submits only the first transaction of every sender, the one with sequence number 0, as
+    /// a fee-payer transaction whose gas is paid by `sponsor`.
+    ///
+    /// The engine creates the account of the sender of a sponsored first transaction, so EVM
+    /// senders whose account does not exist yet can transact without being funded first.
+    /// Any fresh key gets one such transaction, whose gas is capped by the limits of
+    /// `sponsor`, see [`Sponsor::with_limits`].
+    pub fn with_account_creation_sponsor(mut self, sponsor: Sponsor) -> Self {
+        self.sponsor = Some(sponsor);
+        self.sponsor_first_only = true;
+        self
     }
 
     pub async fn submit_transaction(
@@ -67,23 +174,56 @@ impl AAClient {
         sequence_number: u64,
         max_gas_amount: u64,
         gas_unit_price: u64,
+        expiration: Option<u64>,
     ) -> Result<PendingTransaction> {
-        let transaction = self.get_aa_transaction(
+        let transaction = self.build_transaction(
             tx,
             sender,
             sequence_number,
             max_gas_amount,
             gas_unit_price,
-            self.chain_id,
-            self.timeout,
+            expiration,
         );
 
-        Ok(self
-            .api_client
-            .submit(&transaction)
+        self.submit(&transaction)
             .await
-            .context("Failed to submit transaction")?
-            .into_inner())
+            .context("Failed to submit transaction")
+    }
+
+    /// Builds the engine transaction of an EVM transaction.
+    ///
+    /// It expires after the configured timeout, or at `expiration`, in seconds since the Unix
+    /// epoch, if given, but never later than the maximum timeout from now.
+    pub fn build_transaction(
+        &self,
+        tx: Vec<u8>,
+        sender: AccountAddress,
+        sequence_number: u64,
+        max_gas_amount: u64,
+        gas_unit_price: u64,
+        expiration: Option<u64>,
+    ) -> SignedTransaction {
+        let timeout = match expiration {
+            Some(expiration) => expiration.saturating_sub(now()).min(self.max_timeout),
+            None => self.timeout,
+        };
+
+        self.get_aa_transaction(
+            tx,
+            sender,
+            sequence_number,
+            max_gas_amount,
+            gas_unit_price,
+            self.chain_id,
+            timeout,
+        )
+    }
+
+    pub async fn submit(
+        &self,
+        transaction: &SignedTransaction,
+    ) -> Result<PendingTransaction, RestError> {
+        Ok(self.api_client.submit(transaction).await?.into_inner())
     }
 
     pub fn get_aa_transaction(
@@ -96,19 +236,26 @@ impl AAClient {
         chain_id: u8,
         timeout: u64,
     ) -> SignedTransaction {
-        let entry_func = MemberId::from_str(&self.entry_func).unwrap();
+        let sponsor = self
+            .sponsor
+            .as_ref()
+            .filter(|_| !self.sponsor_first_only || sequence_number == 0);
+        let (max_gas_amount, gas_unit_price) = match sponsor {
+            Some(sponsor) => (
+                max_gas_amount.min(sponsor.max_gas_amount),
+                gas_unit_price.min(sponsor.max_gas_unit_price),
+            ),
+            None => (max_gas_amount, gas_unit_price),
+        };
+
         let raw_transaction = TransactionBuilder::new(
             TransactionPayload::EntryFunction(EntryFunction::new(
-                entry_func.module_id,
-                entry_func.member_id,
-                vec![],
-                vec![bcs::to_bytes(&sender).unwrap(), bcs::to_bytes(&tx).unwrap()],
+                self.entry_func.module_id.clone(),
+                self.entry_func.member_id.clone(),
+                self.entry_type_args.clone(),
+                encode_evm_tx_into_entry_function(sender, &tx),
             )),
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs()
-                + timeout,
+            now() + timeout,
             ChainId::new(chain_id),
         )
         .sender(sender)
@@ -117,12 +264,33 @@ impl AAClient {
         .gas_unit_price(gas_unit_price)
         .build();
 
-        let authenticator = AccountAuthenticator::abstraction(
-            FunctionInfo::from_str(&self.auth_func).unwrap(),
+        let authenticator =
+            AccountAuthenticator::abstraction(self.auth_func.clone(), vec![], vec![]);
+
+        let Some(sponsor) = sponsor else {
+            return SignedTransaction::new_single_sender(raw_transaction, authenticator);
+        };
+
+        let fee_payer_authenticator = sponsor.authenticate(&RawTransactionWithData::new_fee_payer(
+            raw_transaction.clone(),
             vec![],
+            sponsor.address(),
+        ));
+        SignedTransaction::new_fee_payer(
+            raw_transaction,
+            authenticator,
             vec![],
-        );
-
-        SignedTransaction::new_single_sender(raw_transaction, authenticator)
+            vec![],
+            sponsor.address(),
+            fee_payer_authenticator,
+        )
     }
 }
+
+/// Returns the current time in seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
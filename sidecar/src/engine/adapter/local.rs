@@ -15,7 +15,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::EngineAdapter;
+use super::{EngineAdapter, EngineGas, EvmCall, GasPriceEstimate};
 use anyhow::Result;
 use std::borrow::Cow;
 
@@ -46,6 +46,9 @@ impl EngineAdapter for LocalEngineAdapter {
         &self,
         _sender: move_core_types::account_address::AccountAddress,
         _transaction: Vec<u8>,
+        _sequence_number: u64,
+        _gas: EngineGas,
+        _expiration: Option<u64>,
     ) -> Result<aptos_api_types::PendingTransaction> {
         unimplemented!();
     }
@@ -58,17 +61,62 @@ impl EngineAdapter for LocalEngineAdapter {
         unimplemented!();
     }
 
+    async fn get_block_by_version(
+        &self,
+        _version: u64,
+        _with_transactions: bool,
+    ) -> Result<aptos_api_types::Block> {
+        unimplemented!();
+    }
+
     async fn get_account(
         &self,
         _address: move_core_types::account_address::AccountAddress,
-    ) -> Result<aptos_rest_client::types::Account> {
+    ) -> Result<Option<aptos_rest_client::types::Account>> {
         unimplemented!();
     }
 
     async fn get_account_balance(
         &self,
         _address: move_core_types::account_address::AccountAddress,
+        _asset: &str,
     ) -> Result<u64> {
         unimplemented!();
     }
+
+    async fn get_transaction_by_hash(
+        &self,
+        _hash: aptos_api_types::HashValue,
+    ) -> Result<Option<aptos_api_types::Transaction>> {
+        unimplemented!();
+    }
+
+    async fn get_account_transactions(
+        &self,
+        _address: move_core_types::account_address::AccountAddress,
+        _start: Option<u64>,
+        _limit: u64,
+    ) -> Result<Vec<aptos_api_types::Transaction>> {
+        unimplemented!();
+    }
+
+    async fn get_coin_decimals(&self) -> Result<u8> {
+        unimplemented!();
+    }
+
+    async fn estimate_gas_price(&self) -> Result<GasPriceEstimate> {
+        unimplemented!();
+    }
+
+    async fn call(&self, _call: EvmCall, _version: Option<u64>) -> Result<Vec<u8>> {
+        unimplemented!();
+    }
+
+    async fn view(
+        &self,
+        _function: &str,
+        _arguments: Vec<serde_json::Value>,
+    ) -> Result<Vec<serde_json::Value>> {
+        unimplemented!();
+    }
 }
@@ -0,0 +1,525 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Endpoint, EngineAdapter, EngineGas, EvmCall, GasPriceEstimate};
+use crate::error::SidecarError;
+use anyhow::{Context, Result};
+use move_core_types::account_address::AccountAddress;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// An [`EngineAdapter`] with canned responses for unit testing RPC handlers.
+///
+/// Every call is recorded, so tests can assert which adapter methods a handler used and
+/// with which arguments. Clones share the recorded calls and the transaction returned by hash.
+#[derive(Clone)]
+pub struct MockEngineAdapter {
+    ledger_info: Option<aptos_api_types::IndexResponse>,
+    /// Ledger info of the separate write endpoint, if there is one.
+    write_endpoint: Option<Option<aptos_api_types::IndexResponse>>,
+    sequence_number: Option<u64>,
+    balance: u64,
+    view_results: HashMap<String, Vec<serde_json::Value>>,
+    decimals: Option<u8>,
+    gas_price: GasPriceEstimate,
+    submit: Option<aptos_api_types::PendingTransaction>,
+    block_transactions: Vec<aptos_api_types::UserTransaction>,
+    failing_blocks: Vec<u64>,
+    call_errors: HashMap<Vec<u8>, String>,
+    transaction: Arc<Mutex<Option<aptos_api_types::Transaction>>>,
+    account_transactions: Vec<aptos_api_types::UserTransaction>,
+    delays: HashMap<&'static str, Duration>,
+    calls: Arc<Mutex<HashMap<&'static str, usize>>>,
+    completed: Arc<Mutex<HashMap<&'static str, usize>>>,
+    submitted: Arc<Mutex<Vec<(AccountAddress, Vec<u8>, u64, EngineGas)>>>,
+    balance_queries: Arc<Mutex<Vec<(AccountAddress, String)>>>,
+    views: Arc<Mutex<Vec<(String, Vec<serde_json::Value>)>>>,
+    simulated: Arc<Mutex<Vec<(EvmCall, Option<u64>)>>>,
+}
+
+impl Default for MockEngineAdapter {
+    fn default() -> Self {
+        Self {
+            ledger_info: Some(ledger_info(4, 42)),
+            write_endpoint: None,
+            sequence_number: None,
+            balance: 0,
+            view_results: HashMap::new(),
+            decimals: None,
+            gas_price: GasPriceEstimate::default(),
+            submit: None,
+            block_transactions: vec![],
+            failing_blocks: vec![],
+            call_errors: HashMap::new(),
+            transaction: Default::default(),
+            account_transactions: vec![],
+            delays: HashMap::new(),
+            calls: Default::default(),
+            completed: Default::default(),
+            submitted: Default::default(),
+            balance_queries: Default::default(),
+            views: Default::default(),
+            simulated: Default::default(),
+        }
+    }
+}
+
+impl MockEngineAdapter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the ledger info, or makes the ledger info unavailable with `None`.
+    pub fn with_ledger_info(mut self, ledger_info: Option<aptos_api_types::IndexResponse>) -> Self {
+        self.ledger_info = ledger_info;
+        self
+    }
+
+    /// Adds a separate write endpoint serving `ledger_info`, or unavailable with `None`.
+    pub fn with_write_endpoint(
+        mut self,
+        ledger_info: Option<aptos_api_types::IndexResponse>,
+    ) -> Self {
+        self.write_endpoint = Some(ledger_info);
+        self
+    }
+
+    /// Makes every account exist with the given sequence number.
+    pub fn with_sequence_number(mut self, sequence_number: u64) -> Self {
+        self.sequence_number = Some(sequence_number);
+        self
+    }
+
+    pub fn with_balance(mut self, balance: u64) -> Self {
+        self.balance = balance;
+        self
+    }
+
+    /// Answers view calls of `function` with `values` instead of the balance.
+    pub fn with_view_result(mut self, function: &str, values: Vec<serde_json::Value>) -> Self {
+        self.view_results.insert(function.to_string(), values);
+        self
+    }
+
+    pub fn with_decimals(mut self, decimals: u8) -> Self {
+        self.decimals = Some(decimals);
+        self
+    }
+
+    /// Sets the gas unit price estimated by the engine for every priority.
+    pub fn with_gas_price(mut self, gas_price: u64) -> Self {
+        self.gas_price = GasPriceEstimate {
+            deprioritized: gas_price,
+            normal: gas_price,
+            prioritized: gas_price,
+        };
+        self
+    }
+
+    /// Sets the gas prices estimated by the engine for each priority.
+    pub fn with_gas_price_estimate(mut self, estimate: GasPriceEstimate) -> Self {
+        self.gas_price = estimate;
+        self
+    }
+
+    /// Accepts submitted transactions with the given response.
+    pub fn expect_submit(mut self, pending: aptos_api_types::PendingTransaction) -> Self {
+        self.submit = Some(pending);
+        self
+    }
+
+    /// Makes every block fetched with its transactions contain `transactions`.
+    pub fn with_block_transactions(
+        mut self,
+        transactions: Vec<aptos_api_types::UserTransaction>,
+    ) -> Self {
+        self.block_transactions = transactions;
+        self
+    }
+
+    /// Makes fetching the block at `height` fail.
+    pub fn with_failing_block(mut self, height: u64) -> Self {
+        self.failing_blocks.push(height);
+        self
+    }
+
+    /// Makes calls with `data` fail with the engine error `message`.
+    pub fn with_call_error(mut self, data: &[u8], message: &str) -> Self {
+        self.call_errors.insert(data.to_vec(), message.into());
+        self
+    }
+
+    /// Makes every account have sent `transactions`, which must be in sequence number order.
+    pub fn with_account_transactions(
+        mut self,
+        transactions: Vec<aptos_api_types::UserTransaction>,
+    ) -> Self {
+        self.account_transactions = transactions;
+        self
+    }
+
+    /// Makes every call of `method` take `delay` before it is answered, like a slow
+    /// upstream node.
+    pub fn with_delay(mut self, method: &'static str, delay: Duration) -> Self {
+        self.delays.insert(method, delay);
+        self
+    }
+
+    /// Makes every transaction lookup by hash find `transaction`.
+    pub fn with_transaction(self, transaction: aptos_api_types::Transaction) -> Self {
+        self.set_transaction(transaction);
+        self
+    }
+
+    /// Replaces the transaction returned by hash, for every clone, such as when a pending
+    /// transaction commits.
+    pub fn set_transaction(&self, transaction: aptos_api_types::Transaction) {
+        *self.transaction.lock().unwrap() = Some(transaction);
+    }
+
+    /// Returns how many times `method` was called.
+    pub fn calls(&self, method: &str) -> usize {
+        self.calls.lock().unwrap().get(method).copied().unwrap_or(0)
+    }
+
+    /// Returns how many calls of `method` ran to completion instead of being abandoned
+    /// during their delay.
+    pub fn completed(&self, method: &str) -> usize {
+        self.completed
+            .lock()
+            .unwrap()
+            .get(method)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Returns the sender, transaction bytes, sequence number and gas of every submitted
+    /// transaction.
+    pub fn submitted(&self) -> Vec<(AccountAddress, Vec<u8>, u64, EngineGas)> {
+        self.submitted.lock().unwrap().clone()
+    }
+
+    /// Returns the account and asset of every balance query.
+    pub fn balance_queries(&self) -> Vec<(AccountAddress, String)> {
+        self.balance_queries.lock().unwrap().clone()
+    }
+
+    /// Returns the function and arguments of every view call.
+    pub fn views(&self) -> Vec<(String, Vec<serde_json::Value>)> {
+        self.views.lock().unwrap().clone()
+    }
+
+    /// Returns every simulated call with the ledger version it was simulated against.
+    pub fn simulated(&self) -> Vec<(EvmCall, Option<u64>)> {
+        self.simulated.lock().unwrap().clone()
+    }
+
+    fn block(&self, height: u64, with_transactions: bool) -> aptos_api_types::Block {
+        let mut block = block(height);
+        if with_transactions {
+            block.transactions = Some(
+                self.block_transactions
+                    .iter()
+                    .cloned()
+                    .map(aptos_api_types::Transaction::UserTransaction)
+                    .collect(),
+            );
+        }
+        block
+    }
+
+    /// Records a call of `method`, then answers it after the delay set for the method.
+    async fn record(&self, method: &'static str) {
+        *self.calls.lock().unwrap().entry(method).or_default() += 1;
+        if let Some(delay) = self.delays.get(method) {
+            tokio::time::sleep(*delay).await;
+        }
+        *self.completed.lock().unwrap().entry(method).or_default() += 1;
+    }
+}
+
+/// Returns ledger info for the given chain id and block height.
+pub fn ledger_info(chain_id: u8, block_height: u64) -> aptos_api_types::IndexResponse {
+    serde_json::from_value(serde_json::json!({
+        "chain_id": chain_id,
+        "epoch": "1",
+        "ledger_version": (block_height * 10).to_string(),
+        "oldest_ledger_version": "0",
+        "ledger_timestamp": "1700000000000000",
+        "node_role": "full_node",
+        "oldest_block_height": "0",
+        "block_height": block_height.to_string(),
+    }))
+    .unwrap()
+}
+
+/// Returns a pending transaction response.
+pub fn pending_transaction() -> aptos_api_types::PendingTransaction {
+    pending_user_transaction(&[])
+}
+
+/// Returns a pending transaction carrying the raw EVM transaction `raw`.
+pub fn pending_user_transaction(raw: &[u8]) -> aptos_api_types::PendingTransaction {
+    serde_json::from_value(serde_json::json!({
+        "hash": format!("0x{}", "ab".repeat(32)),
+        "sender": format!("0x{}", "11".repeat(32)),
+        "sequence_number": "0",
+        "max_gas_amount": "2000000",
+        "gas_unit_price": "100",
+        "expiration_timestamp_secs": "1700000000",
+        "payload": {
+            "type": "entry_function_payload",
+            "function": "0x100::evm::transact",
+            "type_arguments": [],
+            "arguments": [
+                format!("0x{}", "11".repeat(32)),
+                format!("0x{}", alloy_primitives::hex::encode(raw)),
+            ],
+        },
+    }))
+    .unwrap()
+}
+
+/// Signs the EVM transaction `tx` with a fixed test key, returning it along with its signer.
+#[cfg(any(test, feature = "test-utils"))]
+pub fn sign_evm_transaction<T>(tx: T) -> (alloy_consensus::TxEnvelope, alloy_primitives::Address)
+where
+    T: alloy_consensus::SignableTransaction<alloy_primitives::PrimitiveSignature>,
+    alloy_consensus::TxEnvelope: From<alloy_consensus::Signed<T>>,
+{
+    use reth_primitives_traits::crypto::secp256k1::{recover_signer, sign_message};
+
+    let key = alloy_primitives::B256::repeat_byte(0x01);
+    let signature = sign_message(key, tx.signature_hash()).unwrap();
+    let signer = recover_signer(&signature, tx.signature_hash()).unwrap();
+
+    (tx.into_signed(signature).into(), signer)
+}
+
+/// Returns a block at the given height whose hash is derived from the height.
+pub fn block(height: u64) -> aptos_api_types::Block {
+    serde_json::from_value(serde_json::json!({
+        "block_height": height.to_string(),
+        "block_hash": format!("0x{:064x}", height + 1),
+        "block_timestamp": (1_700_000_000_000_000 + height * 1_000_000).to_string(),
+        "first_version": (height * 10).to_string(),
+        "last_version": (height * 10 + 9).to_string(),
+    }))
+    .unwrap()
+}
+
+/// Returns a committed user transaction carrying the raw EVM transaction `raw`.
+pub fn user_transaction(raw: &[u8]) -> aptos_api_types::UserTransaction {
+    serde_json::from_value(serde_json::json!({
+        "version": "100",
+        "hash": format!("0x{}", "cd".repeat(32)),
+        "state_change_hash": format!("0x{}", "00".repeat(32)),
+        "event_root_hash": format!("0x{}", "00".repeat(32)),
+        "gas_used": "21",
+        "success": true,
+        "vm_status": "Executed successfully",
+        "accumulator_root_hash": format!("0x{}", "00".repeat(32)),
+        "changes": [],
+        "sender": format!("0x{}", "11".repeat(32)),
+        "sequence_number": "0",
+        "max_gas_amount": "2000000",
+        "gas_unit_price": "100",
+        "expiration_timestamp_secs": "1700000000",
+        "payload": {
+            "type": "entry_function_payload",
+            "function": "0x100::evm::transact",
+            "type_arguments": [],
+            "arguments": [
+                format!("0x{}", "11".repeat(32)),
+                format!("0x{}", alloy_primitives::hex::encode(raw)),
+            ],
+        },
+        "events": [],
+        "timestamp": "1700000000000000",
+    }))
+    .unwrap()
+}
+
+#[async_trait::async_trait]
+impl EngineAdapter for MockEngineAdapter {
+    fn coin_type(&self) -> &str {
+        "0x1::aptos_coin::AptosCoin"
+    }
+
+    async fn get_ledger_info(&self) -> Result<aptos_api_types::IndexResponse> {
+        self.record("get_ledger_info").await;
+        self.ledger_info.clone().context("ledger info unavailable")
+    }
+
+    fn endpoints(&self) -> Vec<Endpoint> {
+        match self.write_endpoint {
+            Some(_) => vec![Endpoint::Read, Endpoint::Write],
+            None => vec![Endpoint::Read],
+        }
+    }
+
+    async fn get_endpoint_ledger_info(
+        &self,
+        endpoint: Endpoint,
+    ) -> Result<aptos_api_types::IndexResponse> {
+        self.record("get_endpoint_ledger_info").await;
+        let ledger_info = match (endpoint, &self.write_endpoint) {
+            (Endpoint::Write, Some(ledger_info)) => ledger_info,
+            _ => &self.ledger_info,
+        };
+        ledger_info.clone().context("ledger info unavailable")
+    }
+
+    async fn submit_transaction(
+        &self,
+        sender: AccountAddress,
+        transaction: Vec<u8>,
+        sequence_number: u64,
+        gas: EngineGas,
+        _expiration: Option<u64>,
+    ) -> Result<aptos_api_types::PendingTransaction> {
+        self.record("submit_transaction").await;
+        let pending = self.submit.clone().context("unexpected submission")?;
+        self.submitted
+            .lock()
+            .unwrap()
+            .push((sender, transaction, sequence_number, gas));
+
+        Ok(pending)
+    }
+
+    async fn get_block_by_height(
+        &self,
+        height: u64,
+        with_transactions: bool,
+    ) -> Result<aptos_api_types::Block> {
+        self.record("get_block_by_height").await;
+        if self.failing_blocks.contains(&height) {
+            let err = SidecarError::Transport(format!("block {} unavailable", height));
+            return Err(err.into());
+        }
+        Ok(self.block(height, with_transactions))
+    }
+
+    /// Finds the version in the block at `version / 10`, like the [`block`] fixture.
+    async fn get_block_by_version(
+        &self,
+        version: u64,
+        with_transactions: bool,
+    ) -> Result<aptos_api_types::Block> {
+        self.record("get_block_by_version").await;
+        Ok(self.block(version / 10, with_transactions))
+    }
+
+    async fn get_account(
+        &self,
+        _address: AccountAddress,
+    ) -> Result<Option<aptos_rest_client::types::Account>> {
+        self.record("get_account").await;
+        self.sequence_number
+            .map(|sequence_number| {
+                Ok(serde_json::from_value(serde_json::json!({
+                    "authentication_key": format!("0x{}", "00".repeat(32)),
+                    "sequence_number": sequence_number.to_string(),
+                }))?)
+            })
+            .transpose()
+    }
+
+    async fn get_account_balance(&self, address: AccountAddress, asset: &str) -> Result<u64> {
+        self.record("get_account_balance").await;
+        self.balance_queries
+            .lock()
+            .unwrap()
+            .push((address, asset.to_string()));
+
+        Ok(self.balance)
+    }
+
+    async fn get_transaction_by_hash(
+        &self,
+        _hash: aptos_api_types::HashValue,
+    ) -> Result<Option<aptos_api_types::Transaction>> {
+        self.record("get_transaction_by_hash").await;
+        Ok(self.transaction.lock().unwrap().clone())
+    }
+
+    async fn get_account_transactions(
+        &self,
+        _address: AccountAddress,
+        start: Option<u64>,
+        limit: u64,
+    ) -> Result<Vec<aptos_api_types::Transaction>> {
+        self.record("get_account_transactions").await;
+        Ok(self
+            .account_transactions
+            .iter()
+            .filter(|tx| tx.request.sequence_number.0 >= start.unwrap_or(0))
+            .take(limit as usize)
+            .cloned()
+            .map(aptos_api_types::Transaction::UserTransaction)
+            .collect())
+    }
+
+    async fn get_coin_decimals(&self) -> Result<u8> {
+        self.record("get_coin_decimals").await;
+        self.decimals.context("decimals view failed")
+    }
+
+    async fn estimate_gas_price(&self) -> Result<GasPriceEstimate> {
+        self.record("estimate_gas_price").await;
+        Ok(self.gas_price)
+    }
+
+    /// Echoes the call data as the output, reverts calls without data and fails calls with
+    /// the error set for their data.
+    async fn call(&self, call: EvmCall, version: Option<u64>) -> Result<Vec<u8>> {
+        self.record("call").await;
+        self.simulated.lock().unwrap().push((call.clone(), version));
+        if call.data.is_empty() {
+            return Err(SidecarError::Engine("execution reverted".into()).into());
+        }
+        if let Some(message) = self.call_errors.get(&call.data) {
+            return Err(SidecarError::Engine(message.clone()).into());
+        }
+
+        Ok(call.data)
+    }
+
+    /// Answers view calls with the result set for the function, or with the balance,
+    /// encoded as a Move `u64`.
+    async fn view(
+        &self,
+        function: &str,
+        arguments: Vec<serde_json::Value>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.record("view").await;
+        self.views
+            .lock()
+            .unwrap()
+            .push((function.to_string(), arguments));
+
+        Ok(self
+            .view_results
+            .get(function)
+            .cloned()
+            .unwrap_or_else(|| vec![serde_json::Value::String(self.balance.to_string())]))
+    }
+}
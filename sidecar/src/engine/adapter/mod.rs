@@ -17,20 +17,113 @@
 
 pub mod client;
 pub mod local;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod mock;
 pub mod remote;
 
+use alloy_primitives::{Address, U256};
 use anyhow::Result;
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::Serialize;
+use std::{fmt, future::Future, ops::Range};
+
+/// An upstream endpoint of the engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Endpoint {
+    /// Endpoint serving reads and simulations.
+    Read,
+    /// Endpoint accepting transactions.
+    Write,
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::Read => f.write_str("read"),
+            Endpoint::Write => f.write_str("write"),
+        }
+    }
+}
+
+/// Gas settings of a submitted EVM transaction, in engine units.
+///
+/// The EVM gas settings of the transaction are converted by the RPC layer, see
+/// [`crate::rpc::convert::GasConversion`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EngineGas {
+    /// Engine gas units the transaction may use.
+    pub max_gas_amount: u64,
+    /// Price per engine gas unit, in the smallest unit of the native coin.
+    pub gas_unit_price: u64,
+}
+
+/// Gas unit prices the engine estimates for transactions of different priorities, in the
+/// smallest unit of the native coin per engine gas unit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GasPriceEstimate {
+    /// Price of transactions that can wait for a quiet period.
+    pub deprioritized: u64,
+    /// Price of transactions to be included in a timely manner.
+    pub normal: u64,
+    /// Price of transactions to be included ahead of others.
+    pub prioritized: u64,
+}
+
+/// An EVM message call to simulate, in EVM units.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EvmCall {
+    pub from: Address,
+    /// Callee, or `None` to simulate a contract creation.
+    pub to: Option<Address>,
+    pub data: Vec<u8>,
+    /// Value in wei.
+    pub value: U256,
+}
 
 #[async_trait::async_trait]
 pub trait EngineAdapter {
     fn coin_type(&self) -> &str;
 
+    /// Returns how many requests to the upstream node are in flight, for adapters that cap
+    /// them.
+    fn upstream_requests_in_use(&self) -> usize {
+        0
+    }
+
     async fn get_ledger_info(&self) -> Result<aptos_api_types::IndexResponse>;
 
+    /// Returns the distinct endpoints the adapter talks to. The read endpoint is listed
+    /// alone when it also accepts transactions.
+    fn endpoints(&self) -> Vec<Endpoint> {
+        vec![Endpoint::Read]
+    }
+
+    /// Returns the ledger info as served by `endpoint`, for checking each endpoint on its
+    /// own.
+    ///
+    /// The default implementation serves every endpoint from [`Self::get_ledger_info`], for
+    /// adapters with a single endpoint.
+    async fn get_endpoint_ledger_info(
+        &self,
+        endpoint: Endpoint,
+    ) -> Result<aptos_api_types::IndexResponse> {
+        let _ = endpoint;
+        self.get_ledger_info().await
+    }
+
+    /// Submits an EVM transaction from `sender` as the engine transaction with
+    /// `sequence_number`, which is the EVM transaction nonce.
+    ///
+    /// The engine transaction expires at `expiration`, in seconds since the Unix epoch, or
+    /// after the configured timeout if `None`.
     async fn submit_transaction(
         &self,
         sender: move_core_types::account_address::AccountAddress,
         transaction: Vec<u8>,
+        sequence_number: u64,
+        gas: EngineGas,
+        expiration: Option<u64>,
     ) -> Result<aptos_api_types::PendingTransaction>;
 
     async fn get_block_by_height(
@@ -39,13 +132,119 @@ pub trait EngineAdapter {
         with_transactions: bool,
     ) -> Result<aptos_api_types::Block, anyhow::Error>;
 
+    /// Returns the blocks in `range` in ascending order.
+    ///
+    /// The default implementation fetches the blocks one at a time.
+    async fn get_blocks(
+        &self,
+        range: Range<u64>,
+        with_transactions: bool,
+    ) -> Result<Vec<aptos_api_types::Block>> {
+        fetch_ordered(range, 1, |height| {
+            self.get_block_by_height(height, with_transactions)
+        })
+        .await
+    }
+
+    /// Returns the account, or `None` if it does not exist on chain.
     async fn get_account(
         &self,
         address: move_core_types::account_address::AccountAddress,
-    ) -> Result<aptos_rest_client::types::Account>;
+    ) -> Result<Option<aptos_rest_client::types::Account>>;
 
+    /// Returns the balance of `asset`, a coin or fungible asset type, held by the account.
     async fn get_account_balance(
         &self,
         address: move_core_types::account_address::AccountAddress,
+        asset: &str,
     ) -> Result<u64, anyhow::Error>;
+
+    /// Returns the block containing the transaction at ledger `version`.
+    async fn get_block_by_version(
+        &self,
+        version: u64,
+        with_transactions: bool,
+    ) -> Result<aptos_api_types::Block>;
+
+    /// Returns the transaction with the given Aptos hash, or `None` if the engine does not
+    /// know it.
+    async fn get_transaction_by_hash(
+        &self,
+        hash: aptos_api_types::HashValue,
+    ) -> Result<Option<aptos_api_types::Transaction>>;
+
+    /// Returns up to `limit` committed transactions sent by the account, in sequence number
+    /// order, starting at sequence number `start`, or at the first one if `None`.
+    ///
+    /// An account that does not exist on chain has no transactions.
+    async fn get_account_transactions(
+        &self,
+        address: move_core_types::account_address::AccountAddress,
+        start: Option<u64>,
+        limit: u64,
+    ) -> Result<Vec<aptos_api_types::Transaction>>;
+
+    /// Returns the decimals of the native coin from its on-chain metadata.
+    async fn get_coin_decimals(&self) -> Result<u8>;
+
+    /// Returns the gas prices the engine currently estimates for each priority.
+    async fn estimate_gas_price(&self) -> Result<GasPriceEstimate>;
+
+    /// Simulates `call` against the ledger at `version`, or the latest ledger, and returns
+    /// its output. State changes of the call are discarded.
+    async fn call(&self, call: EvmCall, version: Option<u64>) -> Result<Vec<u8>>;
+
+    /// Calls the view `function` with JSON-encoded `arguments` and returns its return values.
+    async fn view(
+        &self,
+        function: &str,
+        arguments: Vec<serde_json::Value>,
+    ) -> Result<Vec<serde_json::Value>>;
+}
+
+/// Runs `fetch` for every height in `range` with at most `concurrency` calls in flight,
+/// returning the results in the order of `range`.
+pub async fn fetch_ordered<T, F, Fut>(
+    range: Range<u64>,
+    concurrency: usize,
+    fetch: F,
+) -> Result<Vec<T>>
+where
+    F: Fn(u64) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    stream::iter(range)
+        .map(fetch)
+        .buffered(concurrency.max(1))
+        .try_collect()
+        .await
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::fetch_ordered;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    #[tokio::test]
+    async fn fetches_in_order_with_bounded_concurrency() {
+        let in_flight = &AtomicUsize::new(0);
+        let peak = &AtomicUsize::new(0);
+
+        let heights = fetch_ordered(0..10, 3, move |height| async move {
+            let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(current, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(10 * (10 - height))).await;
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            Ok(height)
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(heights, (0..10).collect::<Vec<_>>());
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+    }
 }
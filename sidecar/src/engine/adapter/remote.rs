@@ -15,41 +15,140 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::{client::AAClient, EngineAdapter};
-use crate::config::engine::RemoteEngineConfig;
-use anyhow::{Ok, Result};
-use aptos_global_constants::{GAS_UNIT_PRICE, MAX_GAS_AMOUNT};
-use aptos_rest_client::{types::Account, Client};
+use super::{
+    client::{AAClient, Sponsor},
+    fetch_ordered, Endpoint, EngineAdapter, EngineGas, EvmCall, GasPriceEstimate,
+};
+use crate::{config::engine::RemoteEngineConfig, error::SidecarError};
+use anyhow::{bail, Context, Ok, Result};
+use aptos_api_types::{
+    AptosErrorCode, EntryFunctionId, GasEstimation, MoveType, PendingTransaction, Transaction,
+    ViewRequest,
+};
+use aptos_rest_client::{error::RestError, types::Account, AptosBaseUrl, Client};
+use aptos_types::move_utils::MemberId;
+use move_core_types::language_storage::TypeTag;
 use reqwest::Url;
-use std::borrow::Cow;
+use std::{borrow::Cow, future::Future, ops::Range, str::FromStr, sync::Arc};
+use tokio::sync::Semaphore;
 
 #[derive(Debug, Clone)]
 pub struct RemoteEngineAdapter {
     coin_type: Cow<'static, str>,
-    client: AAClient,
+    call_func: String,
+    /// Client of the endpoint serving reads.
+    reader: AAClient,
+    /// Client of the endpoint accepting transactions, which also builds and sponsors them.
+    writer: AAClient,
+    /// Whether `writer` talks to another endpoint than `reader`.
+    separate_writer: bool,
+    min_gas_unit_price: u64,
+    max_gas_unit_price: u64,
+    max_gas_amount: u64,
+    max_concurrent_requests: usize,
+    /// Permits of the requests issued to the engine at the same time, shared by clones.
+    upstream: Arc<Semaphore>,
+    max_upstream_requests: usize,
 }
 
 impl RemoteEngineAdapter {
     pub fn new(
         coin_type: String,
-        auth_func: String,
-        entry_func: String,
+        auth_func: &str,
+        entry_func: &str,
+        entry_type_args: Vec<TypeTag>,
+        call_func: String,
         config: RemoteEngineConfig,
-    ) -> Self {
-        let node_url = Url::parse(config.endpoint()).expect("Failed parse adapter url");
-        let client = AAClient::new(
-            Client::new(node_url),
-            auth_func,
-            entry_func,
-            config.chain_id(),
-            config.timeout(),
-        );
+    ) -> Result<Self> {
+        if let Some(allowed) = config.allowed_functions() {
+            ensure_allowed(&[auth_func, entry_func], allowed)?;
+        }
+        let aa_client = |endpoint: &str| -> Result<AAClient> {
+            Ok(AAClient::new(
+                api_client(&config, endpoint).context("Failed to build engine client")?,
+                auth_func,
+                entry_func,
+                config.chain_id(),
+                config.timeout(),
+            )?
+            .with_entry_type_args(entry_type_args.clone())
+            .with_max_timeout(config.max_timeout()))
+        };
+        let reader = aa_client(config.read_endpoint())?;
+        let mut writer = aa_client(config.write_endpoint())?;
+        let separate_writer = config.read_endpoint() != config.write_endpoint();
+        if separate_writer {
+            tracing::info!("Submitting transactions to a separate write endpoint");
+        }
+        if config.sponsor_gas() || config.sponsor_account_creation() {
+            let key = config
+                .sponsor_private_key()
+                .context("Sponsoring requires sponsor_private_key")?;
+            let sponsor = Sponsor::from_encoded_key(key)?.with_limits(
+                config.max_sponsored_gas_unit_price(),
+                config.max_sponsored_gas_amount(),
+            );
+            writer = if config.sponsor_gas() {
+                tracing::info!("Sponsoring gas from {}", sponsor.address());
+                writer.with_sponsor(sponsor)
+            } else {
+                tracing::info!("Sponsoring account creation from {}", sponsor.address());
+                writer.with_account_creation_sponsor(sponsor)
+            };
+        }
 
-        Self {
+        Ok(Self {
             coin_type: Cow::Owned(coin_type),
-            client,
+            call_func,
+            reader,
+            writer,
+            separate_writer,
+            min_gas_unit_price: config.min_gas_unit_price(),
+            max_gas_unit_price: config.max_gas_unit_price(),
+            max_gas_amount: config.max_gas_amount(),
+            max_concurrent_requests: config.max_concurrent_requests(),
+            upstream: Arc::new(Semaphore::new(config.max_upstream_requests())),
+            max_upstream_requests: config.max_upstream_requests(),
+        })
+    }
+
+    /// Returns the `(max_gas_amount, gas_unit_price)` pair of the Aptos transaction, clamped
+    /// to the configured bounds.
+    pub fn gas_params(&self, gas: EngineGas) -> (u64, u64) {
+        let max_gas_amount = gas.max_gas_amount.min(self.max_gas_amount);
+        let gas_unit_price = gas
+            .gas_unit_price
+            .clamp(self.min_gas_unit_price, self.max_gas_unit_price);
+
+        (max_gas_amount, gas_unit_price)
+    }
+
+    /// Looks the transaction of `hash` up through `client`, returning `None` if the engine
+    /// does not know it.
+    async fn transaction_by_hash(
+        &self,
+        client: &AAClient,
+        hash: aptos_api_types::HashValue,
+    ) -> Result<Option<aptos_api_types::Transaction>> {
+        match self
+            .limited(client.api_client.get_transaction_by_hash(hash.into()))
+            .await
+        {
+            std::result::Result::Ok(response) => Ok(Some(response.into_inner())),
+            Err(e) if is_not_found(&e) => Ok(None),
+            Err(e) => Err(e.into()),
         }
     }
+
+    /// Issues `request` to the engine once fewer than `max_upstream_requests` are in flight.
+    async fn limited<T>(&self, request: impl Future<Output = T>) -> T {
+        let _permit = self
+            .upstream
+            .acquire()
+            .await
+            .expect("Upstream semaphore is never closed");
+        request.await
+    }
 }
 
 #[async_trait::async_trait]
@@ -58,26 +157,68 @@ impl EngineAdapter for RemoteEngineAdapter {
         &self.coin_type
     }
 
+    fn upstream_requests_in_use(&self) -> usize {
+        self.max_upstream_requests - self.upstream.available_permits()
+    }
+
     async fn get_ledger_info(&self) -> Result<aptos_api_types::IndexResponse> {
-        Ok(self.client.api_client.get_index().await?.into_inner())
+        Ok(self
+            .limited(self.reader.api_client.get_index())
+            .await?
+            .into_inner())
+    }
+
+    fn endpoints(&self) -> Vec<Endpoint> {
+        if self.separate_writer {
+            vec![Endpoint::Read, Endpoint::Write]
+        } else {
+            vec![Endpoint::Read]
+        }
+    }
+
+    async fn get_endpoint_ledger_info(
+        &self,
+        endpoint: Endpoint,
+    ) -> Result<aptos_api_types::IndexResponse> {
+        let client = match endpoint {
+            Endpoint::Read => &self.reader,
+            Endpoint::Write => &self.writer,
+        };
+
+        Ok(self
+            .limited(client.api_client.get_index())
+            .await?
+            .into_inner())
     }
 
     async fn submit_transaction(
         &self,
         sender: move_core_types::account_address::AccountAddress,
         tx: Vec<u8>,
+        sequence_number: u64,
+        gas: EngineGas,
+        expiration: Option<u64>,
     ) -> Result<aptos_api_types::PendingTransaction> {
-        let account = self.get_account(sender).await?;
-
-        self.client
-            .submit_transaction(
-                sender,
-                tx,
-                account.sequence_number,
-                MAX_GAS_AMOUNT,
-                GAS_UNIT_PRICE,
-            )
-            .await
+        let (max_gas_amount, gas_unit_price) = self.gas_params(gas);
+
+        let transaction = self.writer.build_transaction(
+            tx,
+            sender,
+            sequence_number,
+            max_gas_amount,
+            gas_unit_price,
+            expiration,
+        );
+        let hash: aptos_api_types::HashValue = transaction.committed_hash().into();
+
+        // The outcome is checked on the write endpoint, which a read replica may lag.
+        let writer = &self.writer;
+        let transaction = &transaction;
+        submit_checked(
+            move || self.limited(writer.submit(transaction)),
+            move || self.transaction_by_hash(writer, hash),
+        )
+        .await
     }
 
     async fn get_block_by_height(
@@ -86,34 +227,933 @@ impl EngineAdapter for RemoteEngineAdapter {
         with_transactions: bool,
     ) -> Result<aptos_api_types::Block> {
         Ok(self
-            .client
-            .api_client
-            .get_block_by_height(height, with_transactions)
+            .limited(
+                self.reader
+                    .api_client
+                    .get_block_by_height(height, with_transactions),
+            )
             .await?
             .into_inner())
     }
 
-    async fn get_account(
+    async fn get_block_by_version(
         &self,
-        address: move_core_types::account_address::AccountAddress,
-    ) -> Result<Account> {
+        version: u64,
+        with_transactions: bool,
+    ) -> Result<aptos_api_types::Block> {
         Ok(self
-            .client
-            .api_client
-            .get_account(address)
+            .limited(
+                self.reader
+                    .api_client
+                    .get_block_by_version(version, with_transactions),
+            )
             .await?
             .into_inner())
     }
 
+    async fn get_blocks(
+        &self,
+        range: Range<u64>,
+        with_transactions: bool,
+    ) -> Result<Vec<aptos_api_types::Block>> {
+        fetch_ordered(range, self.max_concurrent_requests, |height| {
+            self.get_block_by_height(height, with_transactions)
+        })
+        .await
+    }
+
+    async fn get_account(
+        &self,
+        address: move_core_types::account_address::AccountAddress,
+    ) -> Result<Option<Account>> {
+        match self
+            .limited(self.reader.api_client.get_account(address))
+            .await
+        {
+            std::result::Result::Ok(response) => Ok(Some(response.into_inner())),
+            Err(e) if is_not_found(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     async fn get_account_balance(
         &self,
         address: move_core_types::account_address::AccountAddress,
+        asset: &str,
     ) -> Result<u64> {
+        match self
+            .limited(self.reader.api_client.get_account_balance(address, asset))
+            .await
+        {
+            std::result::Result::Ok(response) => Ok(response.into_inner()),
+            Err(e) if is_not_found(&e) => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn get_transaction_by_hash(
+        &self,
+        hash: aptos_api_types::HashValue,
+    ) -> Result<Option<aptos_api_types::Transaction>> {
+        self.transaction_by_hash(&self.reader, hash).await
+    }
+
+    async fn get_account_transactions(
+        &self,
+        address: move_core_types::account_address::AccountAddress,
+        start: Option<u64>,
+        limit: u64,
+    ) -> Result<Vec<aptos_api_types::Transaction>> {
+        match self
+            .limited(
+                self.reader
+                    .api_client
+                    .get_account_transactions(address, start, Some(limit)),
+            )
+            .await
+        {
+            std::result::Result::Ok(response) => Ok(response.into_inner()),
+            Err(e) if is_not_found(&e) => Ok(vec![]),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn get_coin_decimals(&self) -> Result<u8> {
+        let request = ViewRequest {
+            function: EntryFunctionId::from_str("0x1::coin::decimals")?,
+            type_arguments: vec![MoveType::from_str(&self.coin_type)?],
+            arguments: vec![],
+        };
+        let values = self
+            .limited(self.reader.api_client.view(&request, None))
+            .await?
+            .into_inner();
+        let decimals = values
+            .first()
+            .and_then(|value| value.as_u64())
+            .ok_or_else(|| unexpected_response("decimals"))?;
+
+        Ok(u8::try_from(decimals)?)
+    }
+
+    async fn estimate_gas_price(&self) -> Result<GasPriceEstimate> {
+        let estimation = self
+            .limited(self.reader.api_client.estimate_gas_price())
+            .await?
+            .into_inner();
+
+        Ok(to_gas_price_estimate(&estimation))
+    }
+
+    async fn call(&self, call: EvmCall, version: Option<u64>) -> Result<Vec<u8>> {
+        let request = ViewRequest {
+            function: EntryFunctionId::from_str(&self.call_func)?,
+            type_arguments: vec![],
+            arguments: vec![
+                serde_json::Value::String(call.from.to_string()),
+                serde_json::Value::String(call.to.map(|to| to.to_string()).unwrap_or_default()),
+                serde_json::Value::String(alloy_primitives::hex::encode_prefixed(&call.data)),
+                serde_json::Value::String(call.value.to_string()),
+            ],
+        };
+        let values = self
+            .limited(self.reader.api_client.view(&request, version))
+            .await?
+            .into_inner();
+        let output = values
+            .first()
+            .and_then(|value| value.as_str())
+            .ok_or_else(|| unexpected_response("call"))?;
+
+        Ok(alloy_primitives::hex::decode(output).map_err(|_| unexpected_response("call"))?)
+    }
+
+    async fn view(
+        &self,
+        function: &str,
+        arguments: Vec<serde_json::Value>,
+    ) -> Result<Vec<serde_json::Value>> {
+        let request = ViewRequest {
+            function: EntryFunctionId::from_str(function)?,
+            type_arguments: vec![],
+            arguments,
+        };
+
         Ok(self
-            .client
-            .api_client
-            .get_account_balance(address, &self.coin_type)
+            .limited(self.reader.api_client.view(&request, None))
             .await?
             .into_inner())
     }
 }
+
+/// Builds the REST client for the configured endpoint, API path and authentication header.
+fn api_client(config: &RemoteEngineConfig, endpoint: &str) -> Result<Client> {
+    let node_url = Url::parse(endpoint).context("Failed to parse adapter url")?;
+    let mut builder = Client::builder(AptosBaseUrl::Custom(node_url));
+    if let Some(version_path_base) = config.version_path_base() {
+        if !version_path_base.ends_with('/') {
+            bail!("version_path_base must end with '/': {}", version_path_base);
+        }
+        builder = builder.version_path_base(version_path_base.to_string())?;
+    }
+    if let Some(api_key) = config.api_key() {
+        // Only the header name is logged; the key itself is never.
+        builder = match config.auth_header() {
+            Some(header) => {
+                tracing::info!("Authenticating engine requests with the {} header", header);
+                builder
+                    .header(header, api_key)
+                    .context("Invalid engine authentication header")?
+            }
+            None => {
+                tracing::info!("Authenticating engine requests with a bearer token");
+                builder.api_key(api_key).context("Invalid engine API key")?
+            }
+        };
+    }
+
+    Ok(builder.build())
+}
+
+/// Fails unless each of `functions` is one of `allowed`, so a misconfigured entry or
+/// authentication function never reaches the engine.
+///
+/// Functions are compared by their parsed form, so addresses match regardless of how they
+/// are written. An allowed function that does not parse denies nothing and allows nothing.
+fn ensure_allowed(functions: &[&str], allowed: &[String]) -> Result<()> {
+    let allowed = allowed
+        .iter()
+        .filter_map(|function| MemberId::from_str(function).ok())
+        .collect::<Vec<_>>();
+    for function in functions {
+        let member = MemberId::from_str(function)
+            .with_context(|| format!("Invalid function {:?}", function))?;
+        if !allowed.contains(&member) {
+            bail!("Function {} is not in allowed_functions", function);
+        }
+    }
+
+    Ok(())
+}
+
+/// Reports a view function response the adapter cannot read, which the engine is to blame
+/// for.
+fn unexpected_response(function: &str) -> SidecarError {
+    SidecarError::Transport(format!(
+        "Unexpected response from the {} view function",
+        function
+    ))
+}
+
+/// Submits a transaction, and on an ambiguous failure checks whether the transaction reached
+/// the engine before submitting it again, so it is never sent twice.
+async fn submit_checked<Submit, SubmitFut, Lookup, LookupFut>(
+    submit: Submit,
+    lookup: Lookup,
+) -> Result<PendingTransaction>
+where
+    Submit: Fn() -> SubmitFut,
+    SubmitFut: Future<Output = Result<PendingTransaction, RestError>>,
+    Lookup: FnOnce() -> LookupFut,
+    LookupFut: Future<Output = Result<Option<Transaction>>>,
+{
+    match submit().await {
+        std::result::Result::Ok(pending) => Ok(pending),
+        Err(e) if is_ambiguous(&e) => {
+            tracing::warn!("Submission outcome unknown, checking the engine: {}", e);
+
+            match lookup().await? {
+                Some(transaction) => into_pending(transaction),
+                None => Ok(submit().await?),
+            }
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn into_pending(transaction: Transaction) -> Result<PendingTransaction> {
+    match transaction {
+        Transaction::PendingTransaction(pending) => Ok(pending),
+        Transaction::UserTransaction(user) => Ok(PendingTransaction {
+            hash: user.info.hash,
+            request: user.request,
+        }),
+        _ => bail!("Unexpected transaction type for a submitted transaction"),
+    }
+}
+
+/// Returns whether the request may have reached the engine despite the error.
+fn is_ambiguous(err: &RestError) -> bool {
+    !matches!(
+        err,
+        RestError::Api(_) | RestError::Bcs(_) | RestError::UrlParse(_)
+    )
+}
+
+/// Returns whether the engine rejected the request because the requested account, resource
+/// or transaction does not exist.
+fn is_not_found(err: &RestError) -> bool {
+    matches!(
+        err,
+        RestError::Api(response) if matches!(
+            response.error.error_code,
+            AptosErrorCode::AccountNotFound
+                | AptosErrorCode::ResourceNotFound
+                | AptosErrorCode::TransactionNotFound
+        )
+    )
+}
+
+/// Reads the gas unit prices estimated by the engine per priority.
+///
+/// The engine omits the deprioritized and prioritized prices when they equal the normal
+/// one.
+fn to_gas_price_estimate(estimation: &GasEstimation) -> GasPriceEstimate {
+    let normal = estimation.gas_estimate;
+
+    GasPriceEstimate {
+        deprioritized: estimation.deprioritized_gas_estimate.unwrap_or(normal),
+        normal,
+        prioritized: estimation.prioritized_gas_estimate.unwrap_or(normal),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{
+        api_client, ensure_allowed, submit_checked, to_gas_price_estimate, RemoteEngineAdapter,
+    };
+    use crate::{
+        config::engine::RemoteEngineConfig,
+        engine::adapter::{client::Sponsor, EngineAdapter, EngineGas, GasPriceEstimate},
+    };
+    use aptos_api_types::{PendingTransaction, Transaction};
+    use aptos_global_constants::MAX_GAS_AMOUNT;
+    use aptos_rest_client::error::RestError;
+    use aptos_types::transaction::{
+        authenticator::TransactionAuthenticator, RawTransactionWithData, SignedTransaction,
+        TransactionPayload,
+    };
+    use move_core_types::{account_address::AccountAddress, parser::parse_type_tag};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+
+    fn adapter(config: RemoteEngineConfig) -> RemoteEngineAdapter {
+        RemoteEngineAdapter::new(
+            "0x1::aptos_coin::AptosCoin".into(),
+            "0x100::evm::authenticate",
+            "0x100::evm::transact",
+            vec![],
+            "0x100::evm::call".into(),
+            config,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn gas_settings_flow_into_aa_transaction() {
+        let adapter = adapter(RemoteEngineConfig {
+            min_gas_unit_price: Some(100),
+            max_gas_unit_price: Some(1_000),
+            ..Default::default()
+        });
+        let gas = EngineGas {
+            max_gas_amount: 50_000,
+            gas_unit_price: 150,
+        };
+
+        let (max_gas_amount, gas_unit_price) = adapter.gas_params(gas);
+        let transaction = adapter.writer.get_aa_transaction(
+            vec![],
+            AccountAddress::ONE,
+            0,
+            max_gas_amount,
+            gas_unit_price,
+            4,
+            10,
+        );
+
+        assert_eq!(transaction.max_gas_amount(), 50_000);
+        assert_eq!(transaction.gas_unit_price(), 150);
+    }
+
+    #[test]
+    fn applies_and_clamps_requested_expiration() {
+        let adapter = adapter(RemoteEngineConfig {
+            timeout: Some(10),
+            max_timeout: Some(600),
+            ..Default::default()
+        });
+        let build = |expiration| {
+            adapter.writer.build_transaction(
+                vec![],
+                AccountAddress::ONE,
+                0,
+                50_000,
+                100,
+                expiration,
+            )
+        };
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let expires_in = |expiration| build(expiration).expiration_timestamp_secs() - now;
+        assert!((10..=11).contains(&expires_in(None)));
+        assert!((120..=121).contains(&expires_in(Some(now + 120))));
+        assert!((600..=601).contains(&expires_in(Some(now + 86_400))));
+    }
+
+    #[test]
+    fn passes_entry_type_args() {
+        let coin = parse_type_tag("0x1::aptos_coin::AptosCoin").unwrap();
+        let adapter = RemoteEngineAdapter::new(
+            "0x1::aptos_coin::AptosCoin".into(),
+            "0x100::evm::authenticate",
+            "0x100::evm::transact",
+            vec![coin.clone()],
+            "0x100::evm::call".into(),
+            RemoteEngineConfig::default(),
+        )
+        .unwrap();
+
+        let transaction =
+            adapter
+                .writer
+                .get_aa_transaction(vec![], AccountAddress::ONE, 0, 50_000, 100, 4, 10);
+        let transaction: SignedTransaction =
+            bcs::from_bytes(&bcs::to_bytes(&transaction).unwrap()).unwrap();
+
+        let TransactionPayload::EntryFunction(entry_function) = transaction.payload() else {
+            panic!("expected an entry function payload");
+        };
+        assert_eq!(entry_function.ty_args(), &[coin]);
+    }
+
+    #[test]
+    fn rejects_malformed_function_identifiers() {
+        for (auth_func, entry_func) in [
+            ("0x100::evm", "0x100::evm::transact"),
+            ("0x100::evm::authenticate", "evm::transact"),
+            ("0x100::evm::authenticate", "0xzz::evm::transact"),
+        ] {
+            let err = RemoteEngineAdapter::new(
+                "0x1::aptos_coin::AptosCoin".into(),
+                auth_func,
+                entry_func,
+                vec![],
+                "0x100::evm::call".into(),
+                RemoteEngineConfig::default(),
+            )
+            .unwrap_err();
+            assert!(err.to_string().starts_with("Invalid"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn uses_configured_entry_function() {
+        let adapter = RemoteEngineAdapter::new(
+            "0x1::aptos_coin::AptosCoin".into(),
+            "0x200::evm::authenticate",
+            "0x200::evm::transact",
+            vec![],
+            "0x100::evm::call".into(),
+            RemoteEngineConfig::default(),
+        )
+        .unwrap();
+
+        let transaction =
+            adapter
+                .writer
+                .get_aa_transaction(vec![], AccountAddress::ONE, 0, 50_000, 100, 4, 10);
+
+        let TransactionPayload::EntryFunction(entry_function) = transaction.payload() else {
+            panic!("expected an entry function payload");
+        };
+        assert_eq!(
+            entry_function.module().address(),
+            &AccountAddress::from_hex_literal("0x200").unwrap()
+        );
+        assert_eq!(entry_function.module().name().as_str(), "evm");
+        assert_eq!(entry_function.function().as_str(), "transact");
+    }
+
+    #[test]
+    fn sponsors_gas_as_fee_payer() {
+        let key = format!("0x{}", "07".repeat(32));
+        let sponsor = Sponsor::from_encoded_key(&key).unwrap();
+        let adapter = adapter(RemoteEngineConfig {
+            sponsor_gas: Some(true),
+            sponsor_private_key: Some(key.into()),
+            ..Default::default()
+        });
+
+        let transaction =
+            adapter
+                .writer
+                .get_aa_transaction(vec![], AccountAddress::ONE, 0, 50_000, 100, 4, 10);
+
+        let TransactionAuthenticator::FeePayer {
+            fee_payer_address,
+            fee_payer_signer,
+            ..
+        } = transaction.authenticator()
+        else {
+            panic!("expected a fee payer transaction");
+        };
+        assert_eq!(fee_payer_address, sponsor.address());
+        assert_eq!(transaction.sender(), AccountAddress::ONE);
+        fee_payer_signer
+            .verify(&RawTransactionWithData::new_fee_payer(
+                transaction.into_raw_transaction(),
+                vec![],
+                fee_payer_address,
+            ))
+            .unwrap();
+    }
+
+    #[test]
+    fn caps_gas_paid_by_sponsor() {
+        let adapter = adapter(RemoteEngineConfig {
+            sponsor_gas: Some(true),
+            sponsor_private_key: Some(format!("0x{}", "07".repeat(32)).into()),
+            min_gas_unit_price: Some(100),
+            max_sponsored_gas_amount: Some(50_000),
+            ..Default::default()
+        });
+
+        let transaction = adapter.writer.get_aa_transaction(
+            vec![],
+            AccountAddress::ONE,
+            0,
+            2_000_000,
+            1_000_000,
+            4,
+            10,
+        );
+
+        assert_eq!(transaction.gas_unit_price(), 100);
+        assert_eq!(transaction.max_gas_amount(), 50_000);
+    }
+
+    #[test]
+    fn sponsors_first_transaction_of_new_accounts() {
+        let key = format!("0x{}", "07".repeat(32));
+        let sponsor = Sponsor::from_encoded_key(&key).unwrap();
+        let adapter = adapter(RemoteEngineConfig {
+            sponsor_account_creation: Some(true),
+            sponsor_private_key: Some(key.into()),
+            ..Default::default()
+        });
+
+        let first =
+            adapter
+                .writer
+                .get_aa_transaction(vec![], AccountAddress::ONE, 0, 50_000, 100, 4, 10);
+        let TransactionAuthenticator::FeePayer {
+            fee_payer_address, ..
+        } = first.authenticator()
+        else {
+            panic!("expected a fee payer transaction");
+        };
+        assert_eq!(fee_payer_address, sponsor.address());
+
+        let next =
+            adapter
+                .writer
+                .get_aa_transaction(vec![], AccountAddress::ONE, 1, 50_000, 100, 4, 10);
+        assert!(!matches!(
+            next.authenticator(),
+            TransactionAuthenticator::FeePayer { .. }
+        ));
+    }
+
+    #[test]
+    fn caps_gas_of_sponsored_account_creation_only() {
+        let adapter = adapter(RemoteEngineConfig {
+            sponsor_account_creation: Some(true),
+            sponsor_private_key: Some(format!("0x{}", "07".repeat(32)).into()),
+            min_gas_unit_price: Some(100),
+            max_sponsored_gas_amount: Some(50_000),
+            ..Default::default()
+        });
+
+        let first = adapter.writer.get_aa_transaction(
+            vec![],
+            AccountAddress::ONE,
+            0,
+            2_000_000,
+            1_000_000,
+            4,
+            10,
+        );
+        assert_eq!(first.gas_unit_price(), 100);
+        assert_eq!(first.max_gas_amount(), 50_000);
+
+        // The sender pays for its later transactions at the price it signed for.
+        let next = adapter.writer.get_aa_transaction(
+            vec![],
+            AccountAddress::ONE,
+            1,
+            2_000_000,
+            1_000_000,
+            4,
+            10,
+        );
+        assert_eq!(next.gas_unit_price(), 1_000_000);
+        assert_eq!(next.max_gas_amount(), 2_000_000);
+    }
+
+    #[test]
+    fn clamps_gas_settings_to_bounds() {
+        let adapter = adapter(RemoteEngineConfig {
+            min_gas_unit_price: Some(100),
+            max_gas_unit_price: Some(1_000),
+            ..Default::default()
+        });
+
+        let low = EngineGas {
+            max_gas_amount: u64::MAX,
+            gas_unit_price: 0,
+        };
+        assert_eq!(adapter.gas_params(low), (MAX_GAS_AMOUNT, 100));
+
+        let high = EngineGas {
+            max_gas_amount: 21_000,
+            gas_unit_price: u64::MAX,
+        };
+        assert_eq!(adapter.gas_params(high), (21_000, 1_000));
+    }
+
+    #[test]
+    fn caps_gas_limit_at_configured_max_gas_amount() {
+        let adapter = adapter(RemoteEngineConfig {
+            max_gas_amount: Some(100_000),
+            ..Default::default()
+        });
+
+        let gas = EngineGas {
+            max_gas_amount: 500_000,
+            gas_unit_price: 0,
+        };
+        assert_eq!(adapter.gas_params(gas).0, 100_000);
+    }
+
+    #[test]
+    fn maps_estimated_gas_prices_per_priority() {
+        let estimation = serde_json::from_value(serde_json::json!({
+            "deprioritized_gas_estimate": 100,
+            "gas_estimate": 150,
+            "prioritized_gas_estimate": 300,
+        }))
+        .unwrap();
+        assert_eq!(
+            to_gas_price_estimate(&estimation),
+            GasPriceEstimate {
+                deprioritized: 100,
+                normal: 150,
+                prioritized: 300,
+            }
+        );
+
+        let estimation =
+            serde_json::from_value(serde_json::json!({ "gas_estimate": 150 })).unwrap();
+        assert_eq!(
+            to_gas_price_estimate(&estimation),
+            GasPriceEstimate {
+                deprioritized: 150,
+                normal: 150,
+                prioritized: 150,
+            }
+        );
+    }
+
+    fn pending() -> PendingTransaction {
+        serde_json::from_value(serde_json::json!({
+            "hash": format!("0x{}", "ab".repeat(32)),
+            "sender": "0x1",
+            "sequence_number": "0",
+            "max_gas_amount": "2000000",
+            "gas_unit_price": "100",
+            "expiration_timestamp_secs": "1700000000",
+            "payload": {
+                "type": "entry_function_payload",
+                "function": "0x100::evm::transact",
+                "type_arguments": [],
+                "arguments": [],
+            },
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn does_not_resubmit_accepted_transaction() {
+        let submits = &AtomicUsize::new(0);
+
+        let result = submit_checked(
+            move || async move {
+                submits.fetch_add(1, Ordering::SeqCst);
+                Err(RestError::Unknown(anyhow::anyhow!("connection reset")))
+            },
+            || async { Ok(Some(Transaction::PendingTransaction(pending()))) },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.hash, pending().hash);
+        assert_eq!(submits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn resubmits_missing_transaction() {
+        let submits = &AtomicUsize::new(0);
+
+        let result = submit_checked(
+            move || async move {
+                if submits.fetch_add(1, Ordering::SeqCst) == 0 {
+                    Err(RestError::Unknown(anyhow::anyhow!("connection reset")))
+                } else {
+                    Ok(pending())
+                }
+            },
+            || async { Ok(None) },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.hash, pending().hash);
+        assert_eq!(submits.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn mounts_api_under_custom_path() {
+        let client = api_client(
+            &RemoteEngineConfig {
+                version_path_base: Some("aptos/v1/".into()),
+                ..Default::default()
+            },
+            "http://127.0.0.1:8080/",
+        )
+        .unwrap();
+
+        assert_eq!(
+            client.build_path("transactions").unwrap().as_str(),
+            "http://127.0.0.1:8080/aptos/v1/transactions"
+        );
+    }
+
+    #[test]
+    fn rejects_path_without_trailing_slash() {
+        let result = api_client(
+            &RemoteEngineConfig {
+                version_path_base: Some("aptos/v1".into()),
+                ..Default::default()
+            },
+            "http://127.0.0.1:8080/v1",
+        );
+
+        assert!(result.is_err());
+    }
+
+    /// Serves an engine endpoint that fails every request, recording the value of `header`
+    /// on the last one.
+    async fn recording_endpoint(
+        header: &'static str,
+    ) -> (String, Arc<Mutex<Option<axum::http::HeaderValue>>>) {
+        let seen = Arc::new(Mutex::new(None));
+        let recorded = seen.clone();
+        let app = axum::Router::new().fallback(move |headers: axum::http::HeaderMap| {
+            *recorded.lock().unwrap() = headers.get(header).cloned();
+            async { axum::http::StatusCode::SERVICE_UNAVAILABLE }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/v1", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        (endpoint, seen)
+    }
+
+    #[tokio::test]
+    async fn sends_api_key_in_configured_header() {
+        let (endpoint, seen) = recording_endpoint("x-api-key").await;
+        let adapter = adapter(RemoteEngineConfig {
+            endpoint: Some(endpoint),
+            api_key: Some("secret-key".to_string().into()),
+            auth_header: Some("x-api-key".into()),
+            ..Default::default()
+        });
+
+        let _ = adapter.get_ledger_info().await;
+
+        assert_eq!(seen.lock().unwrap().as_ref().unwrap(), "secret-key");
+    }
+
+    #[tokio::test]
+    async fn sends_api_key_as_bearer_token_by_default() {
+        let (endpoint, seen) = recording_endpoint("authorization").await;
+        let adapter = adapter(RemoteEngineConfig {
+            endpoint: Some(endpoint),
+            api_key: Some("secret-key".to_string().into()),
+            ..Default::default()
+        });
+
+        let _ = adapter.get_ledger_info().await;
+
+        assert_eq!(seen.lock().unwrap().as_ref().unwrap(), "Bearer secret-key");
+    }
+
+    /// Serves an engine endpoint that fails every request, recording the path of each.
+    async fn path_recording_endpoint() -> (String, Arc<Mutex<Vec<String>>>) {
+        let paths = Arc::new(Mutex::new(vec![]));
+        let recorded = paths.clone();
+        let app = axum::Router::new().fallback(move |uri: axum::http::Uri| {
+            recorded.lock().unwrap().push(uri.path().to_string());
+            async { axum::http::StatusCode::SERVICE_UNAVAILABLE }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/v1", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        (endpoint, paths)
+    }
+
+    #[tokio::test]
+    async fn routes_submissions_to_write_endpoint() {
+        let (read_endpoint, reads) = path_recording_endpoint().await;
+        let (write_endpoint, writes) = path_recording_endpoint().await;
+        let adapter = adapter(RemoteEngineConfig {
+            read_endpoint: Some(read_endpoint),
+            write_endpoint: Some(write_endpoint),
+            ..Default::default()
+        });
+
+        let _ = adapter.get_ledger_info().await;
+        let _ = adapter
+            .submit_transaction(AccountAddress::ONE, vec![], 0, EngineGas::default(), None)
+            .await;
+
+        let reads = reads.lock().unwrap();
+        assert_eq!(reads.len(), 1);
+        assert!(!reads[0].starts_with("/v1/transactions"), "{:?}", reads);
+        let writes = writes.lock().unwrap();
+        assert!(!writes.is_empty());
+        assert!(
+            writes
+                .iter()
+                .all(|path| path.starts_with("/v1/transactions")),
+            "{:?}",
+            writes
+        );
+    }
+
+    #[test]
+    fn falls_back_to_single_endpoint() {
+        let config = RemoteEngineConfig {
+            endpoint: Some("http://engine:8080/v1".into()),
+            write_endpoint: Some("http://relayer:8080/v1".into()),
+            ..Default::default()
+        };
+
+        assert_eq!(config.read_endpoint(), "http://engine:8080/v1");
+        assert_eq!(config.write_endpoint(), "http://relayer:8080/v1");
+    }
+
+    #[tokio::test]
+    async fn caps_concurrent_upstream_requests() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        // Requests are held by the engine until released, so they are sampled while held.
+        let (arrived, mut arrivals) = tokio::sync::mpsc::unbounded_channel();
+        let (release, released) = tokio::sync::watch::channel(false);
+        let (current, highest) = (in_flight.clone(), peak.clone());
+        let app = axum::Router::new().fallback(move || {
+            let (current, highest) = (current.clone(), highest.clone());
+            let (arrived, mut released) = (arrived.clone(), released.clone());
+            async move {
+                highest.fetch_max(current.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                let _ = arrived.send(());
+                let _ = released.wait_for(|released| *released).await;
+                current.fetch_sub(1, Ordering::SeqCst);
+                axum::http::StatusCode::SERVICE_UNAVAILABLE
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/v1", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let adapter = adapter(RemoteEngineConfig {
+            endpoint: Some(endpoint),
+            max_upstream_requests: Some(2),
+            ..Default::default()
+        });
+
+        let requests = futures::future::join_all((0..8).map(|_| adapter.get_ledger_info()));
+        let sample = async {
+            arrivals.recv().await;
+            arrivals.recv().await;
+            let sampled = (
+                adapter.upstream_requests_in_use(),
+                in_flight.load(Ordering::SeqCst),
+            );
+            release.send(true).unwrap();
+            sampled
+        };
+        let (_, (in_use, held)) = futures::future::join(requests, sample).await;
+
+        assert_eq!(in_use, 2);
+        assert_eq!(held, 2);
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(adapter.upstream_requests_in_use(), 0);
+    }
+
+    #[test]
+    fn redacts_api_key_from_debug_output() {
+        let config = RemoteEngineConfig {
+            api_key: Some("secret-key".to_string().into()),
+            ..Default::default()
+        };
+
+        assert!(!format!("{:?}", config).contains("secret-key"));
+    }
+
+    #[test]
+    fn allows_configured_functions() {
+        let allowed = vec![
+            "0x100::evm::authenticate".to_string(),
+            "0x0000000000000000000000000000000000000000000000000000000000000100::evm::transact"
+                .to_string(),
+        ];
+
+        ensure_allowed(
+            &["0x100::evm::authenticate", "0x100::evm::transact"],
+            &allowed,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn refuses_to_start_with_function_outside_allowlist() {
+        let result = RemoteEngineAdapter::new(
+            "0x1::aptos_coin::AptosCoin".into(),
+            "0x100::evm::authenticate",
+            "0x200::evm::transact",
+            vec![],
+            "0x100::evm::call".into(),
+            RemoteEngineConfig {
+                allowed_functions: Some(vec![
+                    "0x100::evm::authenticate".into(),
+                    "0x100::evm::transact".into(),
+                ]),
+                ..Default::default()
+            },
+        );
+
+        let error = result.unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "Function 0x200::evm::transact is not in allowed_functions"
+        );
+    }
+}
@@ -0,0 +1,101 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy_primitives::B256;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::RwLock,
+};
+
+/// Default number of block hashes kept for resolving blocks by hash.
+pub const BLOCK_HASH_INDEX_SIZE: usize = 100_000;
+
+/// Heights of recently seen blocks, indexed by their EVM block hash.
+///
+/// The engine cannot look blocks up by hash, so blocks can only be resolved by hash once
+/// they have been served by height. Only the most recent heights are kept once the index
+/// is full.
+#[derive(Serialize, Deserialize)]
+pub struct BlockHashIndex {
+    inner: RwLock<Inner>,
+    #[serde(skip, default = "default_capacity")]
+    capacity: usize,
+}
+
+impl Default for BlockHashIndex {
+    fn default() -> Self {
+        Self {
+            inner: Default::default(),
+            capacity: BLOCK_HASH_INDEX_SIZE,
+        }
+    }
+}
+
+fn default_capacity() -> usize {
+    BLOCK_HASH_INDEX_SIZE
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Inner {
+    heights: HashMap<B256, u64>,
+    hashes: BTreeMap<u64, B256>,
+}
+
+impl Inner {
+    /// Drops the hashes of the lowest heights until at most `capacity` are left.
+    fn truncate(&mut self, capacity: usize) {
+        while self.hashes.len() > capacity {
+            if let Some((_, hash)) = self.hashes.pop_first() {
+                self.heights.remove(&hash);
+            }
+        }
+    }
+}
+
+impl BlockHashIndex {
+    /// Keeps up to `capacity` hashes, dropping those of the lowest heights beyond it.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.inner.get_mut().unwrap().truncate(capacity);
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap().hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn height(&self, hash: &B256) -> Option<u64> {
+        self.inner.read().unwrap().heights.get(hash).copied()
+    }
+
+    pub fn hash(&self, height: u64) -> Option<B256> {
+        self.inner.read().unwrap().hashes.get(&height).copied()
+    }
+
+    pub fn insert(&self, height: u64, hash: B256) {
+        let mut inner = self.inner.write().unwrap();
+        if let Some(previous) = inner.hashes.insert(height, hash) {
+            inner.heights.remove(&previous);
+        }
+        inner.heights.insert(hash, height);
+        inner.truncate(self.capacity);
+    }
+}
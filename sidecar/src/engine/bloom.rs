@@ -0,0 +1,77 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy_primitives::Bloom;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::RwLock};
+
+/// Default number of block blooms kept for skipping blocks in log queries.
+pub const BLOOM_INDEX_SIZE: usize = 100_000;
+
+/// Logs blooms of recently seen blocks, indexed by height.
+///
+/// Only the most recent heights are kept once the index is full.
+#[derive(Serialize, Deserialize)]
+pub struct BloomIndex {
+    blooms: RwLock<BTreeMap<u64, Bloom>>,
+    #[serde(skip, default = "default_capacity")]
+    capacity: usize,
+}
+
+impl Default for BloomIndex {
+    fn default() -> Self {
+        Self {
+            blooms: Default::default(),
+            capacity: BLOOM_INDEX_SIZE,
+        }
+    }
+}
+
+fn default_capacity() -> usize {
+    BLOOM_INDEX_SIZE
+}
+
+impl BloomIndex {
+    /// Keeps up to `capacity` blooms, dropping those of the lowest heights beyond it.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        let blooms = self.blooms.get_mut().unwrap();
+        while blooms.len() > capacity {
+            blooms.pop_first();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.blooms.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, height: u64) -> Option<Bloom> {
+        self.blooms.read().unwrap().get(&height).copied()
+    }
+
+    pub fn insert(&self, height: u64, bloom: Bloom) {
+        let mut blooms = self.blooms.write().unwrap();
+        blooms.insert(height, bloom);
+        if blooms.len() > self.capacity {
+            blooms.pop_first();
+        }
+    }
+}
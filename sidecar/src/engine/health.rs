@@ -0,0 +1,316 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::adapter::{Endpoint, EngineAdapter};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+
+/// Default number of probes in a row an endpoint may fail before it is reported down.
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+
+/// State of an upstream endpoint as reported to operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndpointState {
+    /// The endpoint answers, or failed fewer probes in a row than the threshold.
+    Up,
+    /// The endpoint failed the threshold of probes in a row. It is up again after the next
+    /// successful probe.
+    Down,
+}
+
+/// Health of an upstream endpoint as of its last probe.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointStatus {
+    pub endpoint: Endpoint,
+    pub state: EndpointState,
+    /// Why the last probe failed, if it did.
+    pub error: Option<String>,
+    /// Block height reported by the last successful probe.
+    pub block_height: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct EndpointHealth {
+    /// Outcome of the last probe, or `None` before the first one.
+    last_probe: Option<Result<(), String>>,
+    block_height: Option<u64>,
+    consecutive_failures: u32,
+}
+
+impl EndpointHealth {
+    fn state(&self, failure_threshold: u32) -> EndpointState {
+        if self.consecutive_failures >= failure_threshold {
+            EndpointState::Down
+        } else {
+            EndpointState::Up
+        }
+    }
+}
+
+/// Health of the upstream endpoints of the engine, as seen by a periodic probe of the ledger
+/// info each of them serves.
+///
+/// Failures are detected by the probe rather than by live requests, and only transitions
+/// between healthy and unhealthy are logged. An endpoint is reported down by
+/// `sidecar_status` once it fails [`DEFAULT_FAILURE_THRESHOLD`] probes in a row. The state
+/// is informational only: requests are neither failed fast nor sent to another endpoint
+/// while one is down, as each kind of request has a single endpoint to go to.
+#[derive(Debug)]
+pub struct UpstreamHealth {
+    failure_threshold: u32,
+    endpoints: RwLock<BTreeMap<Endpoint, EndpointHealth>>,
+}
+
+impl Default for UpstreamHealth {
+    fn default() -> Self {
+        Self {
+            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+            endpoints: Default::default(),
+        }
+    }
+}
+
+impl UpstreamHealth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reports an endpoint down once it fails `failure_threshold` probes in a row.
+    pub fn with_failure_threshold(mut self, failure_threshold: u32) -> Self {
+        self.failure_threshold = failure_threshold.max(1);
+        self
+    }
+
+    /// Returns whether the last probe of every endpoint succeeded. The engine is assumed
+    /// healthy until it is first probed.
+    pub fn is_healthy(&self) -> bool {
+        self.error().is_none()
+    }
+
+    /// Returns why the last probe of an endpoint failed, if one did.
+    pub fn error(&self) -> Option<String> {
+        self.endpoints
+            .read()
+            .unwrap()
+            .iter()
+            .find_map(|(endpoint, health)| match &health.last_probe {
+                Some(Err(e)) => Some(format!("{} endpoint: {}", endpoint, e)),
+                _ => None,
+            })
+    }
+
+    /// Returns the block height of the engine as of the last successful probe of the read
+    /// endpoint, which serves the head.
+    pub fn upstream_height(&self) -> Option<u64> {
+        self.endpoints
+            .read()
+            .unwrap()
+            .get(&Endpoint::Read)
+            .and_then(|health| health.block_height)
+    }
+
+    /// Returns the state of `endpoint`, which is up until it is probed.
+    pub fn state(&self, endpoint: Endpoint) -> EndpointState {
+        self.endpoints
+            .read()
+            .unwrap()
+            .get(&endpoint)
+            .map_or(EndpointState::Up, |health| {
+                health.state(self.failure_threshold)
+            })
+    }
+
+    /// Returns the health of every probed endpoint.
+    pub fn endpoints(&self) -> Vec<EndpointStatus> {
+        let endpoints = self.endpoints.read().unwrap();
+        endpoints
+            .iter()
+            .map(|(endpoint, health)| EndpointStatus {
+                endpoint: *endpoint,
+                state: health.state(self.failure_threshold),
+                error: health.last_probe.clone().and_then(Result::err),
+                block_height: health.block_height,
+            })
+            .collect()
+    }
+
+    /// Records the outcome of a probe of `endpoint`, logging when the endpoint becomes
+    /// healthy or unhealthy and when it is reported down or up again.
+    pub fn record(&self, endpoint: Endpoint, outcome: Result<u64, String>) {
+        let mut endpoints = self.endpoints.write().unwrap();
+        let health = endpoints.entry(endpoint).or_default();
+        let previous = health.last_probe.replace(outcome.clone().map(|_| ()));
+
+        match outcome {
+            Ok(block_height) => {
+                health.block_height = Some(block_height);
+                if health.consecutive_failures >= self.failure_threshold {
+                    tracing::info!("Engine {} endpoint is up again", endpoint);
+                }
+                health.consecutive_failures = 0;
+                match previous {
+                    None => tracing::info!("Engine {} endpoint is healthy", endpoint),
+                    Some(Err(_)) => tracing::info!("Engine {} endpoint recovered", endpoint),
+                    Some(Ok(())) => {}
+                }
+            }
+            Err(e) => {
+                health.consecutive_failures += 1;
+                if !matches!(previous, Some(Err(_))) {
+                    tracing::warn!("Engine {} endpoint became unhealthy: {}", endpoint, e);
+                }
+                if health.consecutive_failures == self.failure_threshold {
+                    tracing::warn!(
+                        "Engine {} endpoint is down after {} failed probes",
+                        endpoint,
+                        self.failure_threshold
+                    );
+                }
+            }
+        }
+    }
+
+    /// Fetches the ledger info from every endpoint of the engine and records whether it
+    /// succeeded.
+    pub async fn probe<Adapter>(&self, adapter: &Adapter)
+    where
+        Adapter: EngineAdapter + Sync,
+    {
+        for endpoint in adapter.endpoints() {
+            let outcome = adapter
+                .get_endpoint_ledger_info(endpoint)
+                .await
+                .map(|info| info.block_height.0)
+                .map_err(|e| format!("{:#}", e));
+            self.record(endpoint, outcome);
+        }
+    }
+
+    pub fn spawn<Adapter>(self: &Arc<Self>, adapter: Adapter, interval: Duration) -> JoinHandle<()>
+    where
+        Adapter: EngineAdapter + Send + Sync + 'static,
+    {
+        let health = self.clone();
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                health.probe(&adapter).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{EndpointState, UpstreamHealth};
+    use crate::engine::adapter::{mock::MockEngineAdapter, Endpoint};
+    use std::sync::{Arc, Mutex};
+    use tracing::{
+        field::{Field, Visit},
+        Event, Subscriber,
+    };
+    use tracing_subscriber::{layer::Context, prelude::*, Layer};
+
+    /// Collects the message of every event.
+    #[derive(Clone, Default)]
+    struct Messages(Arc<Mutex<Vec<String>>>);
+
+    impl Visit for Messages {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                self.0.lock().unwrap().push(format!("{:?}", value));
+            }
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for Messages {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            event.record(&mut self.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn logs_transitions_between_healthy_and_unhealthy() {
+        let messages = Messages::default();
+        let _guard = tracing_subscriber::registry()
+            .with(messages.clone())
+            .set_default();
+        let up = MockEngineAdapter::new();
+        let down = MockEngineAdapter::new().with_ledger_info(None);
+        let health = UpstreamHealth::new();
+        assert!(health.is_healthy());
+
+        health.probe(&up).await;
+        health.probe(&up).await;
+        assert!(health.is_healthy());
+
+        health.probe(&down).await;
+        health.probe(&down).await;
+        assert!(!health.is_healthy());
+        assert_eq!(
+            health.error().unwrap(),
+            "read endpoint: ledger info unavailable"
+        );
+
+        health.probe(&up).await;
+        assert!(health.is_healthy());
+
+        assert_eq!(
+            *messages.0.lock().unwrap(),
+            vec![
+                "Engine read endpoint is healthy",
+                "Engine read endpoint became unhealthy: ledger info unavailable",
+                "Engine read endpoint recovered",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_failing_endpoint_down() {
+        let adapter = MockEngineAdapter::new().with_write_endpoint(None);
+        let health = UpstreamHealth::new().with_failure_threshold(2);
+
+        health.probe(&adapter).await;
+        assert!(!health.is_healthy());
+        assert_eq!(health.state(Endpoint::Write), EndpointState::Up);
+
+        health.probe(&adapter).await;
+        assert_eq!(health.state(Endpoint::Read), EndpointState::Up);
+        assert_eq!(health.state(Endpoint::Write), EndpointState::Down);
+        assert_eq!(adapter.calls("get_endpoint_ledger_info"), 4);
+
+        let statuses = health.endpoints();
+        assert_eq!(statuses.len(), 2);
+        assert_eq!(statuses[0].endpoint, Endpoint::Read);
+        assert!(statuses[0].error.is_none());
+        assert_eq!(statuses[1].endpoint, Endpoint::Write);
+        assert_eq!(statuses[1].state, EndpointState::Down);
+        assert_eq!(
+            statuses[1].error.as_deref(),
+            Some("ledger info unavailable")
+        );
+    }
+}
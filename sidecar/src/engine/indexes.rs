@@ -0,0 +1,252 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    engine::{
+        block_hashes::{BlockHashIndex, BLOCK_HASH_INDEX_SIZE},
+        bloom::{BloomIndex, BLOOM_INDEX_SIZE},
+        pending::{PendingTransactions, PENDING_TRANSACTIONS_SIZE},
+        transaction_hashes::{TransactionHashIndex, TRANSACTION_HASH_INDEX_SIZE},
+    },
+    rpc::convert,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+/// In-memory indexes shared by the RPC handlers.
+///
+/// They are rebuilt from the engine as requests come in, but can be saved on shutdown and
+/// reloaded on startup to shorten the window where lookups miss after a restart.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Indexes {
+    pub pending: PendingTransactions,
+    pub blooms: BloomIndex,
+    pub block_hashes: BlockHashIndex,
+    pub transaction_hashes: TransactionHashIndex,
+}
+
+/// Number of entries each of the [`Indexes`] keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexCapacities {
+    pub pending: usize,
+    pub blooms: usize,
+    pub block_hashes: usize,
+    pub transaction_hashes: usize,
+}
+
+impl Default for IndexCapacities {
+    fn default() -> Self {
+        Self {
+            pending: PENDING_TRANSACTIONS_SIZE,
+            blooms: BLOOM_INDEX_SIZE,
+            block_hashes: BLOCK_HASH_INDEX_SIZE,
+            transaction_hashes: TRANSACTION_HASH_INDEX_SIZE,
+        }
+    }
+}
+
+impl Indexes {
+    /// Bounds the indexes by `capacities`, dropping entries of loaded indexes beyond them.
+    pub fn with_capacities(mut self, capacities: IndexCapacities) -> Self {
+        self.pending.set_capacity(capacities.pending);
+        self.blooms.set_capacity(capacities.blooms);
+        self.block_hashes.set_capacity(capacities.block_hashes);
+        self.transaction_hashes
+            .set_capacity(capacities.transaction_hashes);
+        self
+    }
+
+    /// Indexes the EVM hashes of the transactions of `block`, fetched with its transactions,
+    /// so that they can be looked up by hash even if they were submitted elsewhere.
+    pub fn insert_transactions(&self, block: &aptos_api_types::Block, entry_func: &str) {
+        for (hash, engine_hash) in convert::evm_transaction_hashes(block, entry_func) {
+            self.transaction_hashes.insert(hash, engine_hash);
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = fs::File::open(path)
+            .with_context(|| format!("Failed to open state file {}", path.display()))?;
+        serde_json::from_reader(std::io::BufReader::new(file))
+            .with_context(|| format!("Failed to parse state file {}", path.display()))
+    }
+
+    /// Loads the indexes saved at `path`, starting empty if there are none or they cannot
+    /// be read.
+    pub fn load_or_default(path: &Path) -> Self {
+        if !path.exists() {
+            return Self::default();
+        }
+
+        Self::load(path).unwrap_or_else(|e| {
+            tracing::warn!("Starting with empty indexes: {:#}", e);
+            Self::default()
+        })
+    }
+
+    /// Saves the indexes to `path`.
+    ///
+    /// They are written to a temporary file next to it, which then replaces `path`, so a
+    /// crash while saving leaves the previously saved indexes intact.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+
+        let file = fs::File::create(&temp_path)
+            .with_context(|| format!("Failed to create state file {}", temp_path.display()))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, self)
+            .with_context(|| format!("Failed to write state file {}", temp_path.display()))?;
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())
+            .and_then(|file| file.sync_all())
+            .with_context(|| format!("Failed to flush state file {}", temp_path.display()))?;
+
+        fs::rename(&temp_path, path)
+            .with_context(|| format!("Failed to replace state file {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{IndexCapacities, Indexes};
+    use crate::engine::pending::PendingTransaction;
+    use alloy_primitives::{Address, Bloom, B256};
+
+    #[test]
+    fn round_trips_through_state_file() {
+        let path = std::env::temp_dir().join(format!("sidecar-state-{}.json", std::process::id()));
+        let indexes = Indexes::default();
+        let sender = Address::repeat_byte(0x44);
+        let pending = PendingTransaction {
+            hash: B256::repeat_byte(0x01),
+            gas_price: 100,
+        };
+        indexes.pending.insert(sender, 3, pending);
+        indexes.blooms.insert(7, Bloom::repeat_byte(0x02));
+        indexes.block_hashes.insert(7, B256::repeat_byte(0x07));
+        let engine_hash = aptos_sdk::crypto::HashValue::sha3_256_of(b"submitted").into();
+        indexes
+            .transaction_hashes
+            .insert(B256::repeat_byte(0x03), engine_hash);
+
+        indexes.save(&path).unwrap();
+        let loaded = Indexes::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.pending.get(&sender, 3), Some(pending));
+        assert_eq!(loaded.blooms.get(7), Some(Bloom::repeat_byte(0x02)));
+        assert_eq!(loaded.blooms.get(8), None);
+        assert_eq!(
+            loaded.block_hashes.height(&B256::repeat_byte(0x07)),
+            Some(7)
+        );
+        assert_eq!(
+            loaded.transaction_hashes.get(&B256::repeat_byte(0x03)),
+            Some(engine_hash)
+        );
+    }
+
+    #[test]
+    fn replaces_state_file_when_saving() {
+        let path =
+            std::env::temp_dir().join(format!("sidecar-state-replace-{}.json", std::process::id()));
+        std::fs::write(&path, "stale").unwrap();
+        let indexes = Indexes::default();
+        indexes.blooms.insert(7, Bloom::repeat_byte(0x02));
+
+        indexes.save(&path).unwrap();
+        let loaded = Indexes::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.blooms.get(7), Some(Bloom::repeat_byte(0x02)));
+        assert!(!path.with_extension("json.tmp").exists());
+    }
+
+    #[test]
+    fn evicts_entries_beyond_configured_capacities() {
+        let indexes = Indexes::default().with_capacities(IndexCapacities {
+            pending: 2,
+            blooms: 2,
+            block_hashes: 2,
+            transaction_hashes: 2,
+        });
+        let sender = Address::repeat_byte(0x44);
+
+        for height in 0..3u8 {
+            indexes
+                .blooms
+                .insert(height.into(), Bloom::repeat_byte(height));
+            indexes
+                .block_hashes
+                .insert(height.into(), B256::repeat_byte(height));
+            indexes.transaction_hashes.insert(
+                B256::repeat_byte(height),
+                aptos_sdk::crypto::HashValue::zero().into(),
+            );
+            indexes.pending.insert(
+                sender,
+                height.into(),
+                PendingTransaction {
+                    hash: B256::repeat_byte(height),
+                    gas_price: 100,
+                },
+            );
+        }
+
+        assert_eq!(indexes.blooms.len(), 2);
+        assert_eq!(indexes.blooms.get(0), None);
+        assert_eq!(indexes.block_hashes.len(), 2);
+        assert_eq!(indexes.block_hashes.height(&B256::repeat_byte(0)), None);
+        assert_eq!(indexes.block_hashes.hash(2), Some(B256::repeat_byte(2)));
+        assert_eq!(indexes.transaction_hashes.len(), 2);
+        assert_eq!(indexes.transaction_hashes.get(&B256::repeat_byte(0)), None);
+        // Pending transactions are not evicted; the third one is left untracked.
+        assert_eq!(indexes.pending.len(), 2);
+        assert_eq!(indexes.pending.get(&sender, 2), None);
+    }
+
+    #[test]
+    fn trims_loaded_indexes_to_capacities() {
+        let indexes = Indexes::default();
+        for height in 0..4 {
+            indexes.blooms.insert(height, Bloom::ZERO);
+        }
+
+        let indexes = indexes.with_capacities(IndexCapacities {
+            blooms: 1,
+            ..Default::default()
+        });
+
+        assert_eq!(indexes.blooms.len(), 1);
+        assert!(indexes.blooms.get(3).is_some());
+    }
+
+    #[test]
+    fn starts_empty_without_state_file() {
+        let indexes = Indexes::load_or_default(std::path::Path::new("/nonexistent/state.json"));
+
+        assert_eq!(indexes.blooms.get(0), None);
+    }
+}
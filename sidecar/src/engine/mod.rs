@@ -15,19 +15,156 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod accounts;
 pub mod adapter;
+pub mod block_hashes;
+pub mod bloom;
+pub mod health;
+pub mod indexes;
+pub mod pending;
+pub mod sync_lag;
+pub mod tracker;
+pub mod transaction_hashes;
 
-use adapter::EngineAdapter;
-use anyhow::Result;
+use adapter::{Endpoint, EngineAdapter, EngineGas, EvmCall, GasPriceEstimate};
+use anyhow::{Context, Result};
+use std::{future::Future, ops::Range, sync::Arc, time::Duration};
+use tokio::time::Instant;
 
+#[derive(Clone)]
 pub struct EngineClient {
-    inner: Box<dyn EngineAdapter + Send + Sync>,
+    inner: Arc<dyn EngineAdapter + Send + Sync>,
+    deadline: Option<Duration>,
+    submit_deadline: Option<Duration>,
+    simulate_deadline: Option<Duration>,
 }
 
 impl EngineClient {
     pub fn new(adapter: Box<dyn EngineAdapter + Send + Sync>) -> Self {
-        EngineClient { inner: adapter }
+        EngineClient {
+            inner: Arc::from(adapter),
+            deadline: None,
+            submit_deadline: None,
+            simulate_deadline: None,
+        }
     }
+
+    /// Bounds every engine call by `deadline`.
+    ///
+    /// A call that exceeds the deadline is dropped, which also cancels the in-flight
+    /// upstream HTTP request and releases its connection.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Bounds transaction submissions by `deadline` in place of the deadline of other calls,
+    /// since a submission may wait for the engine mempool to accept the transaction.
+    pub fn with_submit_deadline(mut self, deadline: Duration) -> Self {
+        self.submit_deadline = Some(deadline);
+        self
+    }
+
+    /// Bounds call simulations by `deadline` in place of the deadline of other calls, so an
+    /// expensive simulation is abandoned early without shortening reads.
+    pub fn with_simulate_deadline(mut self, deadline: Duration) -> Self {
+        self.simulate_deadline = Some(deadline);
+        self
+    }
+
+    async fn within_deadline<T>(&self, call: impl Future<Output = Result<T>>) -> Result<T> {
+        within(self.deadline, call).await
+    }
+}
+
+tokio::task_local! {
+    /// When the request being served times out, see [`within_request_deadline`].
+    static REQUEST_DEADLINE: Instant;
+}
+
+/// Serves `request` with every engine call it makes abandoned once `timeout` has passed
+/// since the request started, on top of the deadline of the call itself.
+///
+/// The deadlines of the calls only bound each call on its own, so a request making several
+/// calls in a row could otherwise keep running and holding upstream connections well past
+/// its timeout.
+pub async fn within_request_deadline<F: Future>(timeout: Duration, request: F) -> F::Output {
+    REQUEST_DEADLINE
+        .scope(Instant::now() + timeout, request)
+        .await
+}
+
+async fn within<T>(deadline: Option<Duration>, call: impl Future<Output = Result<T>>) -> Result<T> {
+    bounded(deadline, "Engine request deadline exceeded", call).await
+}
+
+/// Runs `call` until `deadline` or the deadline of the request being served, whichever
+/// comes first, failing with `context` once it passes.
+async fn bounded<T>(
+    deadline: Option<Duration>,
+    context: &'static str,
+    call: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let deadline = deadline.map(|deadline| Instant::now() + deadline);
+    let deadline = match (deadline, REQUEST_DEADLINE.try_with(|request| *request).ok()) {
+        (Some(call), Some(request)) => Some(call.min(request)),
+        (call, request) => call.or(request),
+    };
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, call)
+            .await
+            .context(context)?,
+        None => call.await,
+    }
+}
+
+/// Queries the native coin decimals from the chain, falling back to `configured` when the
+/// query fails.
+pub async fn detect_native_decimals(client: &EngineClient, configured: u8) -> u8 {
+    match client.get_coin_decimals().await {
+        Ok(decimals) => {
+            tracing::info!("Detected native coin decimals: {}", decimals);
+            decimals
+        }
+        Err(e) => {
+            tracing::warn!(
+                "Failed to detect native coin decimals, using {}: {}",
+                configured,
+                e
+            );
+            configured
+        }
+    }
+}
+
+/// Checks that the chain id used to build engine transactions matches the chain every engine
+/// endpoint serves.
+///
+/// A mismatch would make the engine reject every submitted transaction, or serve state of
+/// another chain than the one transactions are submitted to, so it is reported as an error.
+/// The check of an endpoint is skipped with a warning when it cannot be reached.
+pub async fn verify_chain_id(client: &EngineClient, configured: u8) -> Result<()> {
+    for endpoint in client.endpoints() {
+        match client.get_endpoint_ledger_info(endpoint).await {
+            Ok(info) if info.chain_id != configured => anyhow::bail!(
+                "Configured chain id {} does not match chain id {} of the engine {} endpoint",
+                configured,
+                info.chain_id,
+                endpoint
+            ),
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to verify chain id {} of the engine {} endpoint: {}",
+                    configured,
+                    endpoint,
+                    e
+                );
+            }
+        }
+    }
+
+    Ok(())
 }
 
 #[async_trait::async_trait]
@@ -36,16 +173,40 @@ impl EngineAdapter for EngineClient {
         self.inner.coin_type()
     }
 
+    fn upstream_requests_in_use(&self) -> usize {
+        self.inner.upstream_requests_in_use()
+    }
+
     async fn get_ledger_info(&self) -> Result<aptos_api_types::IndexResponse> {
-        self.inner.get_ledger_info().await
+        self.within_deadline(self.inner.get_ledger_info()).await
+    }
+
+    fn endpoints(&self) -> Vec<Endpoint> {
+        self.inner.endpoints()
+    }
+
+    async fn get_endpoint_ledger_info(
+        &self,
+        endpoint: Endpoint,
+    ) -> Result<aptos_api_types::IndexResponse> {
+        self.within_deadline(self.inner.get_endpoint_ledger_info(endpoint))
+            .await
     }
 
     async fn submit_transaction(
         &self,
         sender: move_core_types::account_address::AccountAddress,
         transaction: Vec<u8>,
+        sequence_number: u64,
+        gas: EngineGas,
+        expiration: Option<u64>,
     ) -> Result<aptos_api_types::PendingTransaction> {
-        self.inner.submit_transaction(sender, transaction).await
+        within(
+            self.submit_deadline.or(self.deadline),
+            self.inner
+                .submit_transaction(sender, transaction, sequence_number, gas, expiration),
+        )
+        .await
     }
 
     async fn get_block_by_height(
@@ -53,22 +214,250 @@ impl EngineAdapter for EngineClient {
         block_height: u64,
         with_transactions: bool,
     ) -> Result<aptos_api_types::Block> {
-        self.inner
-            .get_block_by_height(block_height, with_transactions)
+        self.within_deadline(
+            self.inner
+                .get_block_by_height(block_height, with_transactions),
+        )
+        .await
+    }
+
+    async fn get_block_by_version(
+        &self,
+        version: u64,
+        with_transactions: bool,
+    ) -> Result<aptos_api_types::Block> {
+        self.within_deadline(self.inner.get_block_by_version(version, with_transactions))
+            .await
+    }
+
+    async fn get_blocks(
+        &self,
+        range: Range<u64>,
+        with_transactions: bool,
+    ) -> Result<Vec<aptos_api_types::Block>> {
+        self.within_deadline(self.inner.get_blocks(range, with_transactions))
             .await
     }
 
     async fn get_account(
         &self,
         address: move_core_types::account_address::AccountAddress,
-    ) -> Result<aptos_rest_client::types::Account> {
-        self.inner.get_account(address).await
+    ) -> Result<Option<aptos_rest_client::types::Account>> {
+        self.within_deadline(self.inner.get_account(address)).await
     }
 
     async fn get_account_balance(
         &self,
         address: move_core_types::account_address::AccountAddress,
+        asset: &str,
     ) -> Result<u64> {
-        self.inner.get_account_balance(address).await
+        self.within_deadline(self.inner.get_account_balance(address, asset))
+            .await
+    }
+
+    async fn get_transaction_by_hash(
+        &self,
+        hash: aptos_api_types::HashValue,
+    ) -> Result<Option<aptos_api_types::Transaction>> {
+        self.within_deadline(self.inner.get_transaction_by_hash(hash))
+            .await
+    }
+
+    async fn get_account_transactions(
+        &self,
+        address: move_core_types::account_address::AccountAddress,
+        start: Option<u64>,
+        limit: u64,
+    ) -> Result<Vec<aptos_api_types::Transaction>> {
+        self.within_deadline(self.inner.get_account_transactions(address, start, limit))
+            .await
+    }
+
+    async fn get_coin_decimals(&self) -> Result<u8> {
+        self.within_deadline(self.inner.get_coin_decimals()).await
+    }
+
+    async fn estimate_gas_price(&self) -> Result<GasPriceEstimate> {
+        self.within_deadline(self.inner.estimate_gas_price()).await
+    }
+
+    async fn call(&self, call: EvmCall, version: Option<u64>) -> Result<Vec<u8>> {
+        match self.simulate_deadline {
+            Some(deadline) => {
+                bounded(
+                    Some(deadline),
+                    "Engine simulation deadline exceeded",
+                    self.inner.call(call, version),
+                )
+                .await
+            }
+            None => self.within_deadline(self.inner.call(call, version)).await,
+        }
+    }
+
+    async fn view(
+        &self,
+        function: &str,
+        arguments: Vec<serde_json::Value>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.within_deadline(self.inner.view(function, arguments))
+            .await
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{
+        adapter::{
+            mock::{ledger_info, pending_transaction, MockEngineAdapter},
+            EngineAdapter, EngineGas, EvmCall,
+        },
+        detect_native_decimals, verify_chain_id, within_request_deadline, EngineClient,
+    };
+    use anyhow::Result;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn abandons_slow_upstream_call_at_deadline() {
+        let adapter =
+            MockEngineAdapter::new().with_delay("get_ledger_info", Duration::from_secs(10));
+        let client =
+            EngineClient::new(Box::new(adapter.clone())).with_deadline(Duration::from_millis(50));
+
+        let result = client.get_ledger_info().await;
+
+        assert!(result.is_err());
+        assert_eq!(adapter.calls("get_ledger_info"), 1);
+        assert_eq!(adapter.completed("get_ledger_info"), 0);
+    }
+
+    async fn submit(client: &EngineClient) -> Result<aptos_api_types::PendingTransaction> {
+        client
+            .submit_transaction(
+                move_core_types::account_address::AccountAddress::ONE,
+                vec![],
+                0,
+                EngineGas::default(),
+                None,
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn bounds_submissions_by_submit_deadline() {
+        let adapter = || {
+            MockEngineAdapter::new()
+                .expect_submit(pending_transaction())
+                .with_delay("submit_transaction", Duration::from_millis(100))
+                .with_delay("get_ledger_info", Duration::from_millis(100))
+        };
+
+        let client = EngineClient::new(Box::new(adapter()))
+            .with_deadline(Duration::from_millis(50))
+            .with_submit_deadline(Duration::from_secs(1));
+        assert!(submit(&client).await.is_ok());
+        assert!(client.get_ledger_info().await.is_err());
+
+        let client = EngineClient::new(Box::new(adapter()))
+            .with_deadline(Duration::from_secs(1))
+            .with_submit_deadline(Duration::from_millis(50));
+        assert!(submit(&client).await.is_err());
+
+        let client =
+            EngineClient::new(Box::new(adapter())).with_deadline(Duration::from_millis(50));
+        assert!(submit(&client).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn bounds_simulations_by_simulate_deadline() {
+        let adapter = || MockEngineAdapter::new().with_delay("call", Duration::from_millis(100));
+        let call = || EvmCall {
+            data: vec![1],
+            ..Default::default()
+        };
+
+        let client = EngineClient::new(Box::new(adapter()))
+            .with_deadline(Duration::from_secs(1))
+            .with_simulate_deadline(Duration::from_millis(50));
+        let err = client.call(call(), None).await.unwrap_err();
+        assert_eq!(err.to_string(), "Engine simulation deadline exceeded");
+
+        let client = EngineClient::new(Box::new(adapter()))
+            .with_deadline(Duration::from_millis(50))
+            .with_simulate_deadline(Duration::from_secs(1));
+        assert_eq!(client.call(call(), None).await.unwrap(), vec![1]);
+    }
+
+    #[tokio::test]
+    async fn bounds_engine_calls_by_request_deadline() {
+        let adapter = MockEngineAdapter::new().with_delay("call", Duration::from_millis(60));
+        let client = EngineClient::new(Box::new(adapter)).with_deadline(Duration::from_secs(1));
+        let call = || EvmCall {
+            data: vec![1],
+            ..Default::default()
+        };
+
+        // Each call fits within its own deadline, but the second one runs past the deadline
+        // of the request.
+        let (first, second) = within_request_deadline(Duration::from_millis(100), async {
+            let first = client.call(call(), None).await;
+            let second = client.call(call(), None).await;
+            (first, second)
+        })
+        .await;
+        assert_eq!(first.unwrap(), vec![1]);
+        assert_eq!(
+            second.unwrap_err().to_string(),
+            "Engine request deadline exceeded"
+        );
+
+        assert!(client.call(call(), None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn detects_native_decimals() {
+        let client = EngineClient::new(Box::new(MockEngineAdapter::new().with_decimals(6)));
+
+        assert_eq!(detect_native_decimals(&client, 8).await, 6);
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_configured_decimals() {
+        let client = EngineClient::new(Box::new(MockEngineAdapter::new()));
+
+        assert_eq!(detect_native_decimals(&client, 8).await, 8);
+    }
+
+    #[tokio::test]
+    async fn rejects_mismatched_chain_id() {
+        let adapter = MockEngineAdapter::new().with_ledger_info(Some(ledger_info(2, 42)));
+        let client = EngineClient::new(Box::new(adapter));
+
+        let err = verify_chain_id(&client, 4).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Configured chain id 4 does not match chain id 2 of the engine read endpoint"
+        );
+        assert!(verify_chain_id(&client, 2).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn rejects_mismatched_chain_id_of_write_endpoint() {
+        let adapter = MockEngineAdapter::new()
+            .with_ledger_info(Some(ledger_info(4, 42)))
+            .with_write_endpoint(Some(ledger_info(2, 42)));
+        let client = EngineClient::new(Box::new(adapter));
+
+        let err = verify_chain_id(&client, 4).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Configured chain id 4 does not match chain id 2 of the engine write endpoint"
+        );
+
+        let adapter = MockEngineAdapter::new()
+            .with_ledger_info(Some(ledger_info(4, 42)))
+            .with_write_endpoint(None);
+        let client = EngineClient::new(Box::new(adapter));
+        assert!(verify_chain_id(&client, 4).await.is_ok());
     }
 }
@@ -0,0 +1,240 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy_primitives::{Address, B256};
+use serde::{Deserialize, Serialize, Serializer};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::RwLock,
+};
+
+/// A transaction submitted through the sidecar that may not be committed yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingTransaction {
+    pub hash: B256,
+    pub gas_price: u128,
+}
+
+/// Default number of pending transactions tracked across all senders.
+pub const PENDING_TRANSACTIONS_SIZE: usize = 100_000;
+
+type SenderTransactions = HashMap<Address, BTreeMap<u64, PendingTransaction>>;
+
+/// Pending transactions of every sender along with their total count, so that the count
+/// is checked and updated under the same lock as the transactions.
+#[derive(Default, Deserialize)]
+#[serde(from = "SenderTransactions")]
+struct Senders {
+    transactions: SenderTransactions,
+    len: usize,
+}
+
+impl From<SenderTransactions> for Senders {
+    fn from(transactions: SenderTransactions) -> Self {
+        let len = transactions.values().map(BTreeMap::len).sum();
+        Self { transactions, len }
+    }
+}
+
+impl Serialize for Senders {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.transactions.serialize(serializer)
+    }
+}
+
+/// Transactions submitted through the sidecar, indexed by sender and nonce.
+///
+/// Entries are dropped once the sender's on-chain sequence number moves past their nonce.
+#[derive(Serialize, Deserialize)]
+pub struct PendingTransactions {
+    senders: RwLock<Senders>,
+    #[serde(skip, default = "default_capacity")]
+    capacity: usize,
+}
+
+impl Default for PendingTransactions {
+    fn default() -> Self {
+        Self {
+            senders: Default::default(),
+            capacity: PENDING_TRANSACTIONS_SIZE,
+        }
+    }
+}
+
+fn default_capacity() -> usize {
+    PENDING_TRANSACTIONS_SIZE
+}
+
+impl PendingTransactions {
+    /// Tracks up to `capacity` transactions.
+    ///
+    /// Once full, new transactions are rejected rather than evicting tracked ones: they are
+    /// left untracked, as evicting would lower the pending nonce of a sender that is still
+    /// sending. Replacing a tracked transaction is always allowed.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+    }
+
+    /// Adds a transaction, replacing any pending transaction of `sender` with the same nonce.
+    pub fn insert(&self, sender: Address, nonce: u64, transaction: PendingTransaction) {
+        let mut senders = self.senders.write().unwrap();
+        let replaces = senders
+            .transactions
+            .get(&sender)
+            .is_some_and(|transactions| transactions.contains_key(&nonce));
+        if !replaces && senders.len >= self.capacity {
+            tracing::warn!(
+                "Not tracking pending transaction {} of {}: {} transactions are tracked",
+                transaction.hash,
+                sender,
+                self.capacity
+            );
+            return;
+        }
+
+        senders
+            .transactions
+            .entry(sender)
+            .or_default()
+            .insert(nonce, transaction);
+        if !replaces {
+            senders.len += 1;
+        }
+    }
+
+    /// Returns the number of tracked transactions across all senders.
+    pub fn len(&self) -> usize {
+        self.senders.read().unwrap().len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, sender: &Address, nonce: u64) -> Option<PendingTransaction> {
+        self.senders
+            .read()
+            .unwrap()
+            .transactions
+            .get(sender)
+            .and_then(|transactions| transactions.get(&nonce))
+            .copied()
+    }
+
+    /// Drops every pending transaction of `sender`, for when the engine reports that its
+    /// view of the sender's sequence number is stale.
+    pub fn clear(&self, sender: &Address) {
+        let mut senders = self.senders.write().unwrap();
+        if let Some(transactions) = senders.transactions.remove(sender) {
+            senders.len -= transactions.len();
+        }
+    }
+
+    /// Returns the number of transactions from `sender` that are still pending given its
+    /// on-chain `sequence_number`.
+    pub fn count(&self, sender: &Address, sequence_number: u64) -> u64 {
+        let mut senders = self.senders.write().unwrap();
+        let Some(transactions) = senders.transactions.get_mut(sender) else {
+            return 0;
+        };
+
+        let dropped = transactions.len();
+        *transactions = transactions.split_off(&sequence_number);
+        let count = transactions.len();
+        if count == 0 {
+            senders.transactions.remove(sender);
+        }
+        senders.len -= dropped - count;
+
+        count as u64
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{PendingTransaction, PendingTransactions};
+    use alloy_primitives::{Address, B256};
+
+    #[test]
+    fn drops_transactions_below_sequence_number() {
+        let pending = PendingTransactions::default();
+        let sender = Address::repeat_byte(0x44);
+        for nonce in 0..3 {
+            pending.insert(
+                sender,
+                nonce,
+                PendingTransaction {
+                    hash: B256::with_last_byte(nonce as u8),
+                    gas_price: 100,
+                },
+            );
+        }
+
+        assert_eq!(pending.count(&sender, 0), 3);
+        assert_eq!(pending.count(&sender, 2), 1);
+        assert_eq!(pending.count(&sender, 0), 1);
+        assert_eq!(pending.count(&Address::repeat_byte(0x55), 0), 0);
+        assert_eq!(pending.len(), 1);
+    }
+
+    #[test]
+    fn rejects_new_transactions_once_full() {
+        let mut pending = PendingTransactions::default();
+        pending.set_capacity(2);
+        let sender = Address::repeat_byte(0x44);
+        let transaction = |byte| PendingTransaction {
+            hash: B256::with_last_byte(byte),
+            gas_price: 100,
+        };
+
+        pending.insert(sender, 0, transaction(0));
+        pending.insert(sender, 1, transaction(1));
+        pending.insert(sender, 2, transaction(2));
+        assert_eq!(pending.len(), 2);
+        assert!(pending.get(&sender, 2).is_none());
+
+        pending.insert(sender, 1, transaction(3));
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending.get(&sender, 1), Some(transaction(3)));
+
+        pending.clear(&sender);
+        assert!(pending.is_empty());
+        pending.insert(sender, 2, transaction(2));
+        assert_eq!(pending.len(), 1);
+    }
+
+    #[test]
+    fn counts_loaded_transactions() {
+        let pending = PendingTransactions::default();
+        let sender = Address::repeat_byte(0x44);
+        for nonce in 0..3 {
+            pending.insert(
+                sender,
+                nonce,
+                PendingTransaction {
+                    hash: B256::with_last_byte(nonce as u8),
+                    gas_price: 100,
+                },
+            );
+        }
+
+        let json = serde_json::to_string(&pending).unwrap();
+        let loaded: PendingTransactions = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded.get(&sender, 1), pending.get(&sender, 1));
+    }
+}
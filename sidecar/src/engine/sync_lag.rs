@@ -0,0 +1,172 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{health::UpstreamHealth, tracker::HeadTracker};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+
+/// Watches how many blocks the served head lags the head reported by the upstream health
+/// probe.
+///
+/// The lag is published as the `sync_lag_blocks` gauge on every check. A warning is logged
+/// once the lag has stayed above the threshold for the sustained period, and again when it
+/// falls back below it.
+#[derive(Debug)]
+pub struct SyncLagMonitor {
+    threshold: u64,
+    period: Duration,
+    sync_lag_blocks: AtomicU64,
+    /// When the lag first exceeded the threshold, and whether that was alerted.
+    behind_since: Mutex<Option<(Instant, bool)>>,
+}
+
+impl SyncLagMonitor {
+    pub fn new(threshold: u64, period: Duration) -> Self {
+        Self {
+            threshold,
+            period,
+            sync_lag_blocks: AtomicU64::new(0),
+            behind_since: Mutex::new(None),
+        }
+    }
+
+    /// Blocks the served head lagged the upstream head as of the last check.
+    pub fn sync_lag_blocks(&self) -> u64 {
+        self.sync_lag_blocks.load(Ordering::Relaxed)
+    }
+
+    /// Renders the gauge in the Prometheus text format.
+    pub fn render(&self) -> String {
+        format!(
+            "# HELP sync_lag_blocks Blocks the served head lags the upstream head.\n\
+             # TYPE sync_lag_blocks gauge\n\
+             sync_lag_blocks {}\n",
+            self.sync_lag_blocks()
+        )
+    }
+
+    /// Updates the gauge from the served and upstream heights observed at `now`.
+    pub fn check(&self, served: u64, upstream: u64, now: Instant) {
+        let lag = upstream.saturating_sub(served);
+        self.sync_lag_blocks.store(lag, Ordering::Relaxed);
+
+        let mut behind_since = self.behind_since.lock().unwrap();
+        if lag <= self.threshold {
+            if let Some((_, true)) = behind_since.take() {
+                tracing::info!("Served head caught up with the engine");
+            }
+            return;
+        }
+
+        let (since, alerted) = behind_since.get_or_insert((now, false));
+        if !*alerted && now.duration_since(*since) >= self.period {
+            *alerted = true;
+            tracing::warn!(
+                "Served head has lagged the engine by more than {} blocks for {}s, now {} blocks",
+                self.threshold,
+                self.period.as_secs(),
+                lag
+            );
+        }
+    }
+
+    pub fn spawn(
+        self: &Arc<Self>,
+        tracker: Arc<HeadTracker>,
+        health: Arc<UpstreamHealth>,
+        interval: Duration,
+    ) -> JoinHandle<()> {
+        let monitor = self.clone();
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let (Some(served), Some(upstream)) = (tracker.latest(), health.upstream_height())
+                {
+                    monitor.check(served.block_height.0, upstream, Instant::now());
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::SyncLagMonitor;
+    use crate::engine::{
+        adapter::mock::{ledger_info, MockEngineAdapter},
+        health::UpstreamHealth,
+        tracker::HeadTracker,
+        EngineClient,
+    };
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn updates_gauge_when_lag_exceeds_threshold() {
+        let monitor = SyncLagMonitor::new(10, Duration::from_secs(30));
+        let start = Instant::now();
+
+        monitor.check(100, 105, start);
+        assert_eq!(monitor.sync_lag_blocks(), 5);
+        assert!(monitor.behind_since.lock().unwrap().is_none());
+
+        monitor.check(100, 150, start);
+        assert_eq!(monitor.sync_lag_blocks(), 50);
+        monitor.check(100, 160, start + Duration::from_secs(10));
+        assert_eq!(*monitor.behind_since.lock().unwrap(), Some((start, false)));
+
+        monitor.check(100, 170, start + Duration::from_secs(30));
+        assert_eq!(monitor.sync_lag_blocks(), 70);
+        assert_eq!(*monitor.behind_since.lock().unwrap(), Some((start, true)));
+        assert!(monitor.render().contains("\nsync_lag_blocks 70\n"));
+
+        monitor.check(170, 170, start + Duration::from_secs(40));
+        assert_eq!(monitor.sync_lag_blocks(), 0);
+        assert!(monitor.behind_since.lock().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn compares_served_head_with_probed_head() {
+        let tracker = Arc::new(HeadTracker::new(
+            EngineClient::new(Box::new(MockEngineAdapter::new())),
+            Duration::from_millis(100),
+        ));
+        tracker.update(ledger_info(4, 100));
+        let health = Arc::new(UpstreamHealth::new());
+        health
+            .probe(&MockEngineAdapter::new().with_ledger_info(Some(ledger_info(4, 250))))
+            .await;
+        assert_eq!(health.upstream_height(), Some(250));
+
+        let monitor = Arc::new(SyncLagMonitor::new(10, Duration::from_secs(30)));
+        let task = monitor.spawn(tracker, health, Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        task.abort();
+
+        assert_eq!(monitor.sync_lag_blocks(), 150);
+    }
+}
@@ -0,0 +1,304 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{adapter::EngineAdapter, EngineClient};
+use anyhow::{Context, Result};
+use aptos_api_types::IndexResponse;
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::{runtime::Handle, sync::watch, task::JoinHandle};
+
+/// Interval at which [`HeadTracker::wait_for_height`] polls the engine while fetching on
+/// demand, as no background poll moves the head then.
+pub const ON_DEMAND_WAIT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Keeps the most recent ledger info observed from the engine.
+///
+/// The cached value is refreshed by a background poll. Synchronous RPC methods read it
+/// through [`HeadTracker::latest_or_fetch`], which falls back to a one-shot fetch while
+/// the first poll has not completed yet.
+///
+/// Without a background poll, see [`HeadTracker::with_on_demand_fetch`], every read fetches
+/// the ledger info instead, trading the latency of an engine round trip per request for
+/// never serving a stale head. Waiting for a height then polls the engine itself.
+pub struct HeadTracker {
+    client: EngineClient,
+    latest: RwLock<Option<IndexResponse>>,
+    /// Ledger info with the highest version ever observed, which is ahead of `latest` when
+    /// the engine is served by replicas at different heights and a lagging one answered.
+    highest: RwLock<Option<IndexResponse>>,
+    /// Height of the latest ledger info, watched by [`HeadTracker::wait_for_height`].
+    height: watch::Sender<u64>,
+    fallback_timeout: Duration,
+    on_demand: bool,
+}
+
+impl HeadTracker {
+    pub fn new(client: EngineClient, fallback_timeout: Duration) -> Self {
+        Self {
+            client,
+            latest: RwLock::new(None),
+            highest: RwLock::new(None),
+            height: watch::Sender::new(0),
+            fallback_timeout,
+            on_demand: false,
+        }
+    }
+
+    /// Fetches the ledger info on every [`HeadTracker::latest_or_fetch`], for when the
+    /// background poll is disabled.
+    pub fn with_on_demand_fetch(mut self) -> Self {
+        self.on_demand = true;
+        self
+    }
+
+    /// Whether the ledger info is fetched on every read, see
+    /// [`HeadTracker::with_on_demand_fetch`].
+    pub fn is_on_demand(&self) -> bool {
+        self.on_demand
+    }
+
+    /// Returns the ledger info last observed, which is only kept current by the background
+    /// poll; see [`HeadTracker::current`] for the head as of now.
+    pub fn latest(&self) -> Option<IndexResponse> {
+        self.latest.read().unwrap().clone()
+    }
+
+    /// Returns the cached ledger info, or a freshly fetched one when fetching on demand, in
+    /// which case `None` if the fetch fails.
+    ///
+    /// Unlike [`HeadTracker::latest_or_fetch`], nothing is fetched while the first poll has
+    /// not completed yet. Fetching on demand blocks the current worker thread, so it must be
+    /// called from a multi-threaded runtime.
+    pub fn current(&self) -> Option<IndexResponse> {
+        if !self.on_demand {
+            return self.latest();
+        }
+
+        self.latest_or_fetch()
+            .inspect_err(|e| tracing::warn!("Failed to fetch ledger info: {}", e))
+            .ok()
+    }
+
+    pub fn highest(&self) -> Option<IndexResponse> {
+        self.highest.read().unwrap().clone()
+    }
+
+    pub fn update(&self, info: IndexResponse) {
+        let height = info.block_height.0;
+        {
+            let mut highest = self.highest.write().unwrap();
+            if highest
+                .as_ref()
+                .is_none_or(|highest| highest.ledger_version.0 < info.ledger_version.0)
+            {
+                *highest = Some(info.clone());
+            }
+        }
+        *self.latest.write().unwrap() = Some(info);
+        self.height.send_replace(height);
+    }
+
+    /// Waits until the cached ledger info reaches `height` and returns it.
+    ///
+    /// While fetching on demand, the engine is polled every [`ON_DEMAND_WAIT_INTERVAL`]
+    /// until it does.
+    pub async fn wait_for_height(&self, height: u64) -> IndexResponse {
+        if self.on_demand {
+            return self.poll_for_height(height).await;
+        }
+
+        let mut heights = self.height.subscribe();
+        loop {
+            if let Some(info) = self.latest().filter(|info| info.block_height.0 >= height) {
+                return info;
+            }
+            // The sender is owned by `self`, so the channel cannot close while waiting.
+            let _ = heights.changed().await;
+        }
+    }
+
+    async fn poll_for_height(&self, height: u64) -> IndexResponse {
+        let mut ticker = tokio::time::interval(ON_DEMAND_WAIT_INTERVAL);
+        loop {
+            ticker.tick().await;
+            match self.poll().await {
+                Ok(info) if info.block_height.0 >= height => return info,
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Failed to poll ledger info: {}", e),
+            }
+        }
+    }
+
+    /// Fetches the ledger info from the engine and caches it.
+    pub async fn poll(&self) -> Result<IndexResponse> {
+        let info = self.client.get_ledger_info().await?;
+        self.update(info.clone());
+
+        Ok(info)
+    }
+
+    /// Returns the cached ledger info, fetching it once with a short timeout if nothing has
+    /// been cached yet, or on every call when fetching on demand.
+    ///
+    /// The fetch blocks the current worker thread, so it must be called from a
+    /// multi-threaded runtime.
+    pub fn latest_or_fetch(&self) -> Result<IndexResponse> {
+        if !self.on_demand {
+            if let Some(info) = self.latest() {
+                return Ok(info);
+            }
+        }
+
+        tokio::task::block_in_place(|| {
+            Handle::current().block_on(async {
+                tokio::time::timeout(self.fallback_timeout, self.poll())
+                    .await
+                    .context("Timed out fetching ledger info")?
+            })
+        })
+    }
+
+    pub fn spawn(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let tracker = self.clone();
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = tracker.poll().await {
+                    tracing::warn!("Failed to poll ledger info: {}", e);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::HeadTracker;
+    use crate::engine::{
+        adapter::mock::{ledger_info, MockEngineAdapter},
+        EngineClient,
+    };
+    use std::time::Duration;
+
+    fn tracker(adapter: &MockEngineAdapter) -> HeadTracker {
+        HeadTracker::new(
+            EngineClient::new(Box::new(adapter.clone())),
+            Duration::from_millis(100),
+        )
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn fetches_once_before_first_poll() {
+        let adapter = MockEngineAdapter::new();
+        let tracker = tracker(&adapter);
+        assert!(tracker.latest().is_none());
+
+        let info = tracker.latest_or_fetch().unwrap();
+        assert_eq!(info.block_height.0, 42);
+        assert!(tracker.latest().is_some());
+
+        tracker.latest_or_fetch().unwrap();
+        assert_eq!(adapter.calls("get_ledger_info"), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn fetches_on_every_read_when_on_demand() {
+        let adapter = MockEngineAdapter::new();
+        let tracker = tracker(&adapter).with_on_demand_fetch();
+        tracker.update(ledger_info(4, 10));
+
+        assert_eq!(tracker.latest_or_fetch().unwrap().block_height.0, 42);
+        tracker.latest_or_fetch().unwrap();
+        assert_eq!(adapter.calls("get_ledger_info"), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reads_current_head_when_on_demand() {
+        let adapter = MockEngineAdapter::new();
+        let tracker = tracker(&adapter).with_on_demand_fetch();
+        tracker.update(ledger_info(4, 10));
+
+        assert_eq!(tracker.current().unwrap().block_height.0, 42);
+        assert_eq!(adapter.calls("get_ledger_info"), 1);
+
+        let tracker = self::tracker(&adapter);
+        tracker.update(ledger_info(4, 10));
+        assert_eq!(tracker.current().unwrap().block_height.0, 10);
+        assert_eq!(adapter.calls("get_ledger_info"), 1);
+    }
+
+    #[tokio::test]
+    async fn polls_for_height_when_on_demand() {
+        let adapter = MockEngineAdapter::new();
+        let tracker = tracker(&adapter).with_on_demand_fetch();
+
+        let info = tokio::time::timeout(Duration::from_secs(1), tracker.wait_for_height(42))
+            .await
+            .unwrap();
+        assert_eq!(info.block_height.0, 42);
+        assert_eq!(adapter.calls("get_ledger_info"), 1);
+
+        let waited =
+            tokio::time::timeout(Duration::from_millis(100), tracker.wait_for_height(43)).await;
+        assert!(waited.is_err());
+        assert_eq!(adapter.calls("get_ledger_info"), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn fails_when_upstream_is_not_ready() {
+        let tracker = tracker(&MockEngineAdapter::new().with_ledger_info(None));
+
+        assert!(tracker.latest_or_fetch().is_err());
+        assert!(tracker.latest().is_none());
+    }
+
+    #[tokio::test]
+    async fn waits_until_height_is_reached() {
+        let tracker = std::sync::Arc::new(tracker(&MockEngineAdapter::new()));
+        tracker.update(ledger_info(4, 10));
+
+        let waiter = tokio::spawn({
+            let tracker = tracker.clone();
+            async move { tracker.wait_for_height(12).await }
+        });
+        tracker.update(ledger_info(4, 11));
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+
+        tracker.update(ledger_info(4, 12));
+        let info = tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.block_height.0, 12);
+    }
+
+    #[test]
+    fn keeps_highest_ledger_info_observed() {
+        let tracker = tracker(&MockEngineAdapter::new());
+        tracker.update(ledger_info(4, 12));
+        tracker.update(ledger_info(4, 10));
+
+        assert_eq!(tracker.latest().unwrap().block_height.0, 10);
+        assert_eq!(tracker.highest().unwrap().block_height.0, 12);
+    }
+}
@@ -0,0 +1,98 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy_primitives::B256;
+use aptos_api_types::HashValue;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::RwLock,
+};
+
+/// Default number of transaction hashes kept for looking transactions up by EVM hash.
+pub const TRANSACTION_HASH_INDEX_SIZE: usize = 100_000;
+
+/// Engine hashes of the transactions submitted through the sidecar or seen in the blocks it
+/// converted, indexed by the hash of the EVM transaction they carry.
+///
+/// The engine only knows transactions by its own hash, so a transaction can only be looked
+/// up by EVM hash once it has been submitted here or its block has been served. Only the
+/// most recent transactions are kept once the index is full.
+#[derive(Serialize, Deserialize)]
+pub struct TransactionHashIndex {
+    inner: RwLock<Inner>,
+    #[serde(skip, default = "default_capacity")]
+    capacity: usize,
+}
+
+impl Default for TransactionHashIndex {
+    fn default() -> Self {
+        Self {
+            inner: Default::default(),
+            capacity: TRANSACTION_HASH_INDEX_SIZE,
+        }
+    }
+}
+
+fn default_capacity() -> usize {
+    TRANSACTION_HASH_INDEX_SIZE
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Inner {
+    engine_hashes: HashMap<B256, HashValue>,
+    order: VecDeque<B256>,
+}
+
+impl Inner {
+    /// Drops the oldest hashes until at most `capacity` are left.
+    fn truncate(&mut self, capacity: usize) {
+        while self.order.len() > capacity {
+            if let Some(hash) = self.order.pop_front() {
+                self.engine_hashes.remove(&hash);
+            }
+        }
+    }
+}
+
+impl TransactionHashIndex {
+    /// Keeps up to `capacity` hashes, dropping the oldest beyond it.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.inner.get_mut().unwrap().truncate(capacity);
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap().order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, hash: &B256) -> Option<HashValue> {
+        self.inner.read().unwrap().engine_hashes.get(hash).copied()
+    }
+
+    pub fn insert(&self, hash: B256, engine_hash: HashValue) {
+        let mut inner = self.inner.write().unwrap();
+        if inner.engine_hashes.insert(hash, engine_hash).is_none() {
+            inner.order.push_back(hash);
+        }
+        inner.truncate(self.capacity);
+    }
+}
@@ -0,0 +1,292 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::rpc::revert::revert_data;
+use aptos_api_types::{AptosError, AptosErrorCode};
+use aptos_rest_client::error::RestError;
+use jsonrpsee::types::{
+    error::{
+        CALL_EXECUTION_FAILED_CODE, INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE, METHOD_NOT_FOUND_CODE,
+    },
+    ErrorObjectOwned,
+};
+use move_core_types::vm_status::StatusCode;
+
+/// Error code of a method the server knows but does not support (EIP-1474).
+pub const METHOD_NOT_SUPPORTED_CODE: i32 = -32004;
+
+/// Error code of a request exceeding a server limit (EIP-1474).
+pub const LIMIT_EXCEEDED_CODE: i32 = -32005;
+
+/// Error code of a reverted call, like geth.
+pub const EXECUTION_REVERTED_CODE: i32 = 3;
+
+pub type Result<T, E = SidecarError> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SidecarError {
+    /// The engine processed the request and rejected it.
+    #[error("{0}")]
+    Engine(String),
+    /// The engine aborted the call, for the given reason.
+    #[error("execution reverted: {0}")]
+    Reverted(String),
+    /// The engine could not be reached or returned an unreadable response.
+    #[error("upstream error: {0}")]
+    Transport(String),
+    /// An engine type could not be represented as its EVM counterpart.
+    #[error("conversion error: {0}")]
+    Conversion(String),
+    /// The sidecar failed to serve the request for a reason of its own.
+    #[error("internal error: {0}")]
+    Internal(String),
+    /// The request parameters are invalid.
+    #[error("{0}")]
+    Validation(String),
+    /// The transaction nonce is below the sender's sequence number.
+    #[error("nonce too low")]
+    NonceTooLow,
+    /// The transaction nonce is ahead of the sender's sequence number.
+    #[error("nonce too high")]
+    NonceTooHigh,
+    /// A pending transaction with the same nonce pays at least as much gas.
+    #[error("replacement transaction underpriced")]
+    ReplacementUnderpriced,
+    /// Opening a subscription would exceed the configured subscription limits.
+    #[error("too many subscriptions")]
+    TooManySubscriptions,
+    /// No ledger info has been observed from the engine yet.
+    #[error("node not ready")]
+    NotReady,
+    /// The sidecar runs in read-only mode and does not accept transactions.
+    #[error("submission disabled")]
+    SubmissionDisabled,
+    /// The engine does not expose what the request needs.
+    #[error("{0} is not supported")]
+    Unsupported(&'static str),
+}
+
+impl SidecarError {
+    pub fn code(&self) -> i32 {
+        match self {
+            SidecarError::Reverted(_) => EXECUTION_REVERTED_CODE,
+            SidecarError::Engine(_)
+            | SidecarError::Transport(_)
+            | SidecarError::NonceTooLow
+            | SidecarError::NonceTooHigh
+            | SidecarError::ReplacementUnderpriced
+            | SidecarError::NotReady => CALL_EXECUTION_FAILED_CODE,
+            SidecarError::TooManySubscriptions => LIMIT_EXCEEDED_CODE,
+            SidecarError::Conversion(_) | SidecarError::Internal(_) => INTERNAL_ERROR_CODE,
+            SidecarError::Validation(_) => INVALID_PARAMS_CODE,
+            SidecarError::SubmissionDisabled | SidecarError::Unsupported(_) => {
+                METHOD_NOT_FOUND_CODE
+            }
+        }
+    }
+}
+
+impl From<RestError> for SidecarError {
+    fn from(err: RestError) -> Self {
+        match err {
+            RestError::Api(response) => match sequence_number_error(&response.error) {
+                Some(err) => err,
+                None => SidecarError::Engine(response.error.message),
+            },
+            err => SidecarError::Transport(err.to_string()),
+        }
+    }
+}
+
+/// Maps a stale or future sequence number rejection to the matching EVM nonce error.
+fn sequence_number_error(err: &AptosError) -> Option<SidecarError> {
+    const TOO_OLD: u64 = StatusCode::SEQUENCE_NUMBER_TOO_OLD as u64;
+    const TOO_NEW: u64 = StatusCode::SEQUENCE_NUMBER_TOO_NEW as u64;
+
+    match (err.error_code, err.vm_error_code) {
+        (AptosErrorCode::SequenceNumberTooOld, _) | (_, Some(TOO_OLD)) => {
+            Some(SidecarError::NonceTooLow)
+        }
+        (_, Some(TOO_NEW)) => Some(SidecarError::NonceTooHigh),
+        _ => None,
+    }
+}
+
+/// Classifies the errors of engine adapter calls.
+///
+/// Errors returned by the engine, or raised as a [`SidecarError`] by the adapter, keep their
+/// meaning, and failures to reach the engine in time are transport errors. Any other error
+/// happened within the sidecar, so it is reported as an internal error rather than blamed on
+/// the engine.
+impl From<anyhow::Error> for SidecarError {
+    fn from(err: anyhow::Error) -> Self {
+        let err = match err.downcast::<RestError>() {
+            Ok(err) => return err.into(),
+            Err(err) => err,
+        };
+        let err = match err.downcast::<SidecarError>() {
+            Ok(err) => return err,
+            Err(err) => err,
+        };
+
+        let unreachable = err
+            .chain()
+            .any(|cause| cause.is::<reqwest::Error>() || cause.is::<tokio::time::error::Elapsed>());
+        if unreachable {
+            SidecarError::Transport(format!("{:#}", err))
+        } else {
+            SidecarError::Internal(format!("{:#}", err))
+        }
+    }
+}
+
+impl From<SidecarError> for ErrorObjectOwned {
+    fn from(err: SidecarError) -> Self {
+        match err {
+            // Like geth, the revert data is returned as the error data, encoding the reason
+            // as an `Error(string)`.
+            SidecarError::Reverted(reason) => {
+                let data = revert_data(&reason);
+                ErrorObjectOwned::owned(
+                    EXECUTION_REVERTED_CODE,
+                    format!("execution reverted: {}", reason),
+                    Some(data),
+                )
+            }
+            err => ErrorObjectOwned::owned(err.code(), err.to_string(), None::<()>),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::SidecarError;
+    use aptos_api_types::{AptosError, AptosErrorCode};
+    use aptos_rest_client::error::{AptosErrorResponse, RestError};
+    use jsonrpsee::types::ErrorObjectOwned;
+    use move_core_types::vm_status::StatusCode;
+
+    fn api_error(error_code: AptosErrorCode, vm_status: Option<StatusCode>) -> RestError {
+        RestError::Api(AptosErrorResponse {
+            error: AptosError {
+                message: "Invalid transaction".into(),
+                error_code,
+                vm_error_code: vm_status.map(|status| status as u64),
+            },
+            state: None,
+            status_code: reqwest::StatusCode::BAD_REQUEST,
+        })
+    }
+
+    #[test]
+    fn maps_to_json_rpc_codes() {
+        let cases = [
+            (SidecarError::Engine("rejected".into()), -32000),
+            (SidecarError::NonceTooLow, -32000),
+            (SidecarError::NonceTooHigh, -32000),
+            (SidecarError::ReplacementUnderpriced, -32000),
+            (SidecarError::NotReady, -32000),
+            (SidecarError::TooManySubscriptions, -32005),
+            (SidecarError::Transport("refused".into()), -32000),
+            (SidecarError::Conversion("bad block".into()), -32603),
+            (SidecarError::Internal("poisoned lock".into()), -32603),
+            (SidecarError::Validation("bad address".into()), -32602),
+            (SidecarError::SubmissionDisabled, -32601),
+            (SidecarError::Unsupported("eth_getCode"), -32601),
+        ];
+
+        for (err, code) in cases {
+            let message = err.to_string();
+            let object = ErrorObjectOwned::from(err);
+            assert_eq!(object.code(), code);
+            assert_eq!(object.message(), message);
+        }
+    }
+
+    #[test]
+    fn returns_revert_reason_as_error_data() {
+        let object = ErrorObjectOwned::from(SidecarError::Reverted("insufficient balance".into()));
+
+        assert_eq!(object.code(), 3);
+        assert_eq!(object.message(), "execution reverted: insufficient balance");
+        assert_eq!(
+            object.data().map(|data| data.get()),
+            Some(concat!(
+                r#""0x08c379a0"#,
+                "0000000000000000000000000000000000000000000000000000000000000020",
+                "0000000000000000000000000000000000000000000000000000000000000014",
+                "696e73756666696369656e742062616c616e6365000000000000000000000000",
+                r#"""#
+            ))
+        );
+    }
+
+    #[test]
+    fn reports_other_anyhow_errors_as_internal() {
+        let err = SidecarError::from(anyhow::anyhow!("boom"));
+
+        assert!(matches!(err, SidecarError::Internal(message) if message == "boom"));
+    }
+
+    #[test]
+    fn keeps_engine_errors_raised_by_adapters() {
+        let err = anyhow::Error::from(SidecarError::Engine("rejected".into()));
+
+        assert!(
+            matches!(SidecarError::from(err), SidecarError::Engine(message) if message == "rejected")
+        );
+
+        let err = anyhow::Error::from(api_error(AptosErrorCode::VmError, None));
+        assert!(matches!(SidecarError::from(err), SidecarError::Engine(_)));
+    }
+
+    #[tokio::test]
+    async fn reports_missed_deadlines_as_transport_errors() {
+        let elapsed = tokio::time::timeout(std::time::Duration::ZERO, std::future::pending::<()>())
+            .await
+            .unwrap_err();
+        let err = anyhow::Error::from(elapsed).context("Engine request deadline exceeded");
+
+        let err = SidecarError::from(err);
+        assert!(matches!(err, SidecarError::Transport(_)));
+        assert_eq!(ErrorObjectOwned::from(err).code(), -32000);
+    }
+
+    #[test]
+    fn maps_stale_sequence_number_to_nonce_too_low() {
+        let err = SidecarError::from(api_error(AptosErrorCode::SequenceNumberTooOld, None));
+        assert!(matches!(err, SidecarError::NonceTooLow));
+        assert_eq!(err.to_string(), "nonce too low");
+
+        let err = SidecarError::from(api_error(
+            AptosErrorCode::VmError,
+            Some(StatusCode::SEQUENCE_NUMBER_TOO_OLD),
+        ));
+        assert!(matches!(err, SidecarError::NonceTooLow));
+    }
+
+    #[test]
+    fn maps_future_sequence_number_to_nonce_too_high() {
+        let err = SidecarError::from(api_error(
+            AptosErrorCode::VmError,
+            Some(StatusCode::SEQUENCE_NUMBER_TOO_NEW),
+        ));
+
+        assert!(matches!(err, SidecarError::NonceTooHigh));
+        assert_eq!(ErrorObjectOwned::from(err).code(), -32000);
+    }
+}
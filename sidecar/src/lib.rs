@@ -15,9 +15,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod aa;
 pub mod cli;
 pub mod config;
 pub mod engine;
+pub mod error;
 pub mod logger;
 pub mod rpc;
 pub mod server;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
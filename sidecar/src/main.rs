@@ -21,7 +21,5 @@ use clap::Parser;
 async fn main() -> anyhow::Result<()> {
     engine_sidecar::logger::enable_logger();
 
-    engine_sidecar::cli::Cli::parse().run().await;
-
-    Ok(())
+    engine_sidecar::cli::Cli::parse().run().await
 }
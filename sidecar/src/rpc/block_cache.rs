@@ -0,0 +1,222 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy_network::Ethereum;
+use alloy_rpc_types_eth::Log;
+use reth_rpc_eth_api::RpcBlock;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+/// Recently converted blocks and their logs, keyed by height.
+///
+/// Converting an engine block is repeated by every handler serving it, so the conversions
+/// of the most recently used blocks are kept. Only blocks below the head are cached, as the
+/// head block is also served as `pending`; it is converted again on every request.
+///
+/// The default cache has no capacity, so nothing is cached.
+#[derive(Default)]
+pub struct BlockCache {
+    blocks: Mutex<Lru<(u64, bool), RpcBlock<Ethereum>>>,
+    logs: Mutex<Lru<u64, Vec<Log>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl BlockCache {
+    /// Keeps up to `capacity` blocks, and the logs of up to `capacity` blocks.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            blocks: Mutex::new(Lru::new(capacity)),
+            logs: Mutex::new(Lru::new(capacity)),
+            hits: Default::default(),
+            misses: Default::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of lookups answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of lookups that had to convert the block again.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Returns the block at `height`, with its full transactions if `full`.
+    pub fn block(&self, height: u64, full: bool) -> Option<RpcBlock<Ethereum>> {
+        let block = self.blocks.lock().unwrap().get(&(height, full));
+        self.record(block.is_some());
+        block
+    }
+
+    /// Caches the block at `height` unless it is at or above `head`.
+    pub fn insert_block(&self, head: u64, full: bool, block: RpcBlock<Ethereum>) {
+        let height = block.header.inner.number;
+        if height < head {
+            self.blocks.lock().unwrap().insert((height, full), block);
+        }
+    }
+
+    /// Returns every log of the block at `height`.
+    pub fn logs(&self, height: u64) -> Option<Vec<Log>> {
+        let logs = self.logs.lock().unwrap().get(&height);
+        self.record(logs.is_some());
+        logs
+    }
+
+    /// Caches the logs of the block at `height` unless it is at or above `head`.
+    pub fn insert_logs(&self, head: u64, height: u64, logs: Vec<Log>) {
+        if height < head {
+            self.logs.lock().unwrap().insert(height, logs);
+        }
+    }
+
+    fn record(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl fmt::Debug for BlockCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockCache")
+            .field("len", &self.len())
+            .field("hits", &self.hits())
+            .field("misses", &self.misses())
+            .finish()
+    }
+}
+
+/// Map evicting its least recently used entry once it holds more than `capacity` entries.
+struct Lru<K, V> {
+    capacity: usize,
+    clock: u64,
+    entries: HashMap<K, (u64, V)>,
+    order: BTreeMap<u64, K>,
+}
+
+impl<K, V> Default for Lru<K, V> {
+    fn default() -> Self {
+        Self {
+            capacity: 0,
+            clock: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K, V> Lru<K, V>
+where
+    K: Copy + Eq + Hash,
+    V: Clone,
+{
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ..Default::default()
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn get(&mut self, key: &K) -> Option<V> {
+        let (used, value) = self.entries.get_mut(key)?;
+        self.order.remove(used);
+        self.clock += 1;
+        *used = self.clock;
+        self.order.insert(self.clock, *key);
+
+        Some(value.clone())
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.clock += 1;
+        if let Some((used, _)) = self.entries.insert(key, (self.clock, value)) {
+            self.order.remove(&used);
+        }
+        self.order.insert(self.clock, key);
+        if self.entries.len() > self.capacity {
+            if let Some((_, key)) = self.order.pop_first() {
+                self.entries.remove(&key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{BlockCache, Lru};
+
+    #[test]
+    fn evicts_least_recently_used_entry() {
+        let mut lru = Lru::new(2);
+        lru.insert(1, "a");
+        lru.insert(2, "b");
+        assert_eq!(lru.get(&1), Some("a"));
+
+        lru.insert(3, "c");
+
+        assert_eq!(lru.len(), 2);
+        assert_eq!(lru.get(&1), Some("a"));
+        assert_eq!(lru.get(&2), None);
+        assert_eq!(lru.get(&3), Some("c"));
+    }
+
+    #[test]
+    fn does_not_cache_head_block() {
+        let cache = BlockCache::new(4);
+
+        cache.insert_logs(10, 9, vec![]);
+        cache.insert_logs(10, 10, vec![]);
+
+        assert_eq!(cache.logs(9), Some(vec![]));
+        assert_eq!(cache.logs(10), None);
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+    }
+
+    #[test]
+    fn caches_nothing_by_default() {
+        let cache = BlockCache::default();
+
+        cache.insert_logs(10, 9, vec![]);
+
+        assert_eq!(cache.logs(9), None);
+    }
+}
@@ -0,0 +1,1506 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    aa::decode_evm_tx_from_entry_function,
+    error::{Result, SidecarError},
+};
+use alloy_consensus::{
+    transaction::Recovered, Receipt, ReceiptEnvelope, ReceiptWithBloom, Transaction as _,
+    TxEnvelope, TxType, Typed2718,
+};
+use alloy_eips::eip2718::Decodable2718;
+use alloy_network::Ethereum;
+use alloy_primitives::{keccak256, Address, Bloom, Bytes, LogData, B256, U256};
+use alloy_rpc_types_eth::{
+    Block, BlockTransactions, Header, Log, Transaction, TransactionInfo, TransactionReceipt,
+};
+use aptos_api_types::{
+    EntryFunctionId, TransactionPayload, UserTransaction, UserTransactionRequest,
+};
+use reth_ethereum_primitives::TransactionSigned;
+use reth_rpc_eth_api::RpcBlock;
+use reth_rpc_eth_types::utils::recover_raw_transaction;
+use serde::Deserialize;
+
+/// Values of the EVM header fields that are the same for every block.
+///
+/// The engine has neither proof of work nor a dynamic fee market, so headers are rendered as
+/// post-merge headers: the difficulty, total difficulty and nonce are zero, and the mix hash,
+/// which carries `prevRandao` after the merge, is zero as well since the engine exposes no
+/// beacon randomness. The base fee is a constant so that EIP-1559 clients find one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderConstants {
+    /// Gas limit of every block, in EVM gas units.
+    pub gas_limit: u64,
+    /// Base fee per gas of every block, in wei.
+    pub base_fee: u64,
+}
+
+impl Default for HeaderConstants {
+    fn default() -> Self {
+        Self {
+            gas_limit: 30_000_000,
+            base_fee: 0,
+        }
+    }
+}
+
+/// Default EVM gas reported per engine gas unit, see [`GasConversion`].
+pub const DEFAULT_EVM_GAS_PER_UNIT: u64 = 1000;
+
+/// Conversion between EVM gas and engine gas.
+///
+/// An engine gas unit does not measure the same work as an EVM gas unit, so amounts of gas
+/// are scaled by `evm_gas_per_unit`, the EVM gas reported per engine gas unit, and gas
+/// prices by its inverse on top of the scaling of the native coin to wei. The fee of a
+/// transaction, gas times gas price, is therefore the same in both units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasConversion {
+    evm_gas_per_unit: u64,
+    native_decimals: u8,
+}
+
+impl Default for GasConversion {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVM_GAS_PER_UNIT, 8)
+    }
+}
+
+impl GasConversion {
+    /// Converts with `evm_gas_per_unit` EVM gas per engine gas unit, raised to one, and
+    /// prices in a native coin of `native_decimals` decimals.
+    pub const fn new(evm_gas_per_unit: u64, native_decimals: u8) -> Self {
+        Self {
+            evm_gas_per_unit: if evm_gas_per_unit == 0 {
+                1
+            } else {
+                evm_gas_per_unit
+            },
+            native_decimals,
+        }
+    }
+
+    /// Returns the EVM gas of `units` engine gas units.
+    pub fn to_evm_gas(&self, units: u64) -> u64 {
+        units.saturating_mul(self.evm_gas_per_unit)
+    }
+
+    /// Returns the engine gas units of `gas` EVM gas, rounded up so that a gas limit still
+    /// covers the work it was set for.
+    pub fn to_engine_gas(&self, gas: u64) -> u64 {
+        gas.div_ceil(self.evm_gas_per_unit)
+    }
+
+    /// Returns the price per EVM gas, in wei, of `unit_price` native coin units per engine
+    /// gas unit.
+    pub fn to_wei_per_gas(&self, unit_price: u64) -> u128 {
+        let wei = U256::from(unit_price) * pow10(18)
+            / pow10(self.native_decimals)
+            / U256::from(self.evm_gas_per_unit);
+        u128::try_from(wei).unwrap_or(u128::MAX)
+    }
+
+    /// Returns the price per engine gas unit, in native coin units, of `wei_per_gas` wei per
+    /// EVM gas, rounded down.
+    pub fn to_unit_price(&self, wei_per_gas: u128) -> u64 {
+        let price = U256::from(wei_per_gas)
+            * U256::from(self.evm_gas_per_unit)
+            * pow10(self.native_decimals)
+            / pow10(18);
+        u64::try_from(price).unwrap_or(u64::MAX)
+    }
+}
+
+fn pow10(exponent: u8) -> U256 {
+    U256::from(10).pow(U256::from(exponent))
+}
+
+/// Returns the EVM hash of an engine block.
+///
+/// Engine block hashes are already 32-byte digests that commit to the block contents, so the
+/// EVM hash is the engine block hash taken verbatim. Recomputing a keccak hash over the
+/// synthesized header would not be stable: the header carries `parentHash`, so every hash
+/// would depend on the whole chain before it. The mapping is reversible through
+/// [`engine_block_hash`], which lets a hash returned by `eth_getBlockByNumber` be resolved
+/// back to the engine block.
+pub fn block_hash(hash: &aptos_api_types::HashValue) -> B256 {
+    B256::from_slice(hash.0.as_ref())
+}
+
+/// Returns the engine block hash that [`block_hash`] maps to `hash`.
+pub fn engine_block_hash(hash: &B256) -> aptos_api_types::HashValue {
+    aptos_sdk::crypto::HashValue::new(hash.0).into()
+}
+
+/// Converts an engine block into an EVM block listing the hashes of its EVM transactions.
+///
+/// `parent_hash` is the hash of the previous block as returned by [`block_hash`], or
+/// [`B256::ZERO`] for the genesis block. The block must be fetched with its transactions
+/// for them to be listed, see [`to_rpc_header`] for `constants` and `gas`, and
+/// [`evm_transactions`] for `entry_func`.
+///
+/// The hashes are the keccak hashes of the raw EVM transactions, so unlike
+/// [`to_rpc_full_block`] no signer is recovered. Recovering signers dominates the
+/// conversion of full blocks, which makes this path the one to use whenever only hashes are
+/// requested. Both list the same transactions, see [`evm_transactions`].
+pub fn to_rpc_block(
+    block: &aptos_api_types::Block,
+    parent_hash: B256,
+    constants: &HeaderConstants,
+    entry_func: &str,
+    gas: &GasConversion,
+) -> RpcBlock<Ethereum> {
+    let transactions = evm_transactions_with_bytes(block, entry_func)
+        .map(|(_, bytes)| keccak256(bytes))
+        .collect();
+
+    rpc_block(
+        block,
+        parent_hash,
+        constants,
+        entry_func,
+        gas,
+        BlockTransactions::Hashes(transactions),
+    )
+}
+
+/// Converts an engine block fetched with its transactions into an EVM block including its
+/// decoded EVM transactions.
+pub fn to_rpc_full_block(
+    block: &aptos_api_types::Block,
+    parent_hash: B256,
+    constants: &HeaderConstants,
+    entry_func: &str,
+    gas: &GasConversion,
+) -> Result<RpcBlock<Ethereum>> {
+    let hash = block_hash(&block.block_hash);
+    let transactions = evm_transactions(block, entry_func)
+        .enumerate()
+        .map(|(index, tx)| {
+            let info = TransactionInfo {
+                hash: Some(evm_transaction_hash(tx)?),
+                index: Some(index as u64),
+                block_hash: Some(hash),
+                block_number: Some(block.block_height.0),
+                base_fee: None,
+            };
+            to_rpc_transaction(tx, info)
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(rpc_block(
+        block,
+        parent_hash,
+        constants,
+        entry_func,
+        gas,
+        BlockTransactions::Full(transactions),
+    ))
+}
+
+/// Converts an engine block into the header of its EVM block.
+///
+/// Only the number, timestamp and parent hash are carried over; the engine has no
+/// counterpart for the remaining fields, which are either `constants`, see
+/// [`HeaderConstants`], or keep their default values. The block timestamp is truncated
+/// from microseconds to seconds.
+///
+/// The engine limits the gas of a block in its own units, so the gas limit is the
+/// configured one in EVM units. The gas used is the gas used by the EVM
+/// transactions of the block converted by `gas`, the same sum the cumulative gas used of
+/// its last receipt reports, and is therefore zero unless the block is fetched with its transactions. It is
+/// clamped to the gas limit, with a warning, should the limit be configured too low.
+pub fn to_rpc_header(
+    block: &aptos_api_types::Block,
+    parent_hash: B256,
+    constants: &HeaderConstants,
+    entry_func: &str,
+    gas: &GasConversion,
+) -> Header {
+    let mut gas_used = evm_transactions(block, entry_func)
+        .map(|tx| gas.to_evm_gas(tx.info.gas_used.0))
+        .sum::<u64>();
+    if gas_used > constants.gas_limit {
+        tracing::warn!(
+            "Block {} used {} gas, above the block gas limit of {}",
+            block.block_height.0,
+            gas_used,
+            constants.gas_limit
+        );
+        gas_used = constants.gas_limit;
+    }
+
+    let header = alloy_consensus::Header {
+        parent_hash,
+        number: block.block_height.0,
+        timestamp: block.block_timestamp.0 / 1_000_000,
+        gas_limit: constants.gas_limit,
+        gas_used,
+        difficulty: U256::ZERO,
+        nonce: Default::default(),
+        mix_hash: B256::ZERO,
+        base_fee_per_gas: Some(constants.base_fee),
+        ..Default::default()
+    };
+
+    Header {
+        hash: block_hash(&block.block_hash),
+        inner: header,
+        total_difficulty: Some(U256::ZERO),
+        size: None,
+    }
+}
+
+fn rpc_block(
+    block: &aptos_api_types::Block,
+    parent_hash: B256,
+    constants: &HeaderConstants,
+    entry_func: &str,
+    gas: &GasConversion,
+    transactions: BlockTransactions<Transaction>,
+) -> RpcBlock<Ethereum> {
+    Block {
+        header: to_rpc_header(block, parent_hash, constants, entry_func, gas),
+        uncles: vec![],
+        transactions,
+        withdrawals: None,
+    }
+}
+
+/// Synthesizes the empty EVM block an `eth_simulateV1` block of calls is reported in, on top
+/// of `parent`.
+///
+/// The block follows its parent by one second and keeps its gas limit and base fee. Like
+/// the genesis block, its hash is the keccak hash of its header, since no engine block
+/// backs it.
+pub fn simulated_block(parent: &Header) -> RpcBlock<Ethereum> {
+    let header = alloy_consensus::Header {
+        parent_hash: parent.hash,
+        number: parent.number + 1,
+        timestamp: parent.timestamp + 1,
+        gas_limit: parent.gas_limit,
+        base_fee_per_gas: parent.base_fee_per_gas,
+        ..Default::default()
+    };
+
+    Block {
+        header: Header {
+            total_difficulty: parent.total_difficulty,
+            ..Header::new(header)
+        },
+        uncles: vec![],
+        transactions: BlockTransactions::Hashes(vec![]),
+        withdrawals: None,
+    }
+}
+
+/// Synthesizes an EVM genesis block with an empty body.
+///
+/// Unlike converted engine blocks, the genesis hash is the keccak hash of its header, which
+/// only depends on `timestamp` and `extra_data`.
+pub fn genesis_block(timestamp: u64, extra_data: Bytes) -> RpcBlock<Ethereum> {
+    let header = alloy_consensus::Header {
+        timestamp,
+        extra_data,
+        ..Default::default()
+    };
+
+    Block {
+        header: Header::new(header),
+        uncles: vec![],
+        transactions: BlockTransactions::Hashes(vec![]),
+        withdrawals: None,
+    }
+}
+
+/// Returns the raw EVM transaction carried by an engine user transaction.
+///
+/// See [`crate::aa`] for the layout of the entry function the sidecar submits.
+pub fn evm_transaction_bytes(tx: &UserTransaction) -> Result<Vec<u8>> {
+    evm_request_bytes(&tx.request)
+}
+
+/// Returns the raw EVM transaction carried by an engine transaction request.
+///
+/// The sidecar submits every EVM transaction from the engine account it passes as the first
+/// argument, so a request whose sender differs is another entry function that happens to
+/// take an address and a byte vector.
+fn evm_request_bytes(request: &UserTransactionRequest) -> Result<Vec<u8>> {
+    let TransactionPayload::EntryFunctionPayload(payload) = &request.payload else {
+        return Err(SidecarError::Conversion(
+            "not an entry function transaction".into(),
+        ));
+    };
+
+    let (sender, evm_tx) = decode_evm_tx_from_entry_function(payload)?;
+    if &sender != request.sender.inner() {
+        return Err(SidecarError::Conversion(
+            "entry function sender differs from transaction sender".into(),
+        ));
+    }
+
+    Ok(evm_tx.into())
+}
+
+/// Returns the EVM hash of the transaction carried by an engine user transaction.
+pub fn evm_transaction_hash(tx: &UserTransaction) -> Result<B256> {
+    Ok(keccak256(evm_transaction_bytes(tx)?))
+}
+
+/// Returns the user transactions carrying an EVM transaction of a block fetched with its
+/// transactions, in block order.
+///
+/// EVM transactions are the user transactions calling `entry_func`, the entry function the
+/// sidecar submits them through. System transactions, such as block metadata and state
+/// checkpoints, and user transactions calling other entry functions are skipped, as are EVM
+/// transactions that do not decode as an EIP-2718 envelope, with a warning. EVM transaction
+/// indices are positions in this sequence, so the indices of blocks, transactions, logs and
+/// receipts all agree.
+pub fn evm_transactions<'a>(
+    block: &'a aptos_api_types::Block,
+    entry_func: &str,
+) -> impl Iterator<Item = &'a UserTransaction> {
+    evm_transactions_with_bytes(block, entry_func).map(|(tx, _)| tx)
+}
+
+/// Returns whether `tx` calls the entry function `entry_func`, which the sidecar submits
+/// EVM transactions through.
+///
+/// Functions are parsed rather than compared as text, as their addresses may be written in
+/// full or short.
+pub fn is_evm_transaction(tx: &UserTransaction, entry_func: &str) -> bool {
+    entry_func
+        .parse::<EntryFunctionId>()
+        .is_ok_and(|entry_func| calls(tx, &entry_func))
+}
+
+fn calls(tx: &UserTransaction, entry_func: &EntryFunctionId) -> bool {
+    matches!(
+        &tx.request.payload,
+        TransactionPayload::EntryFunctionPayload(payload) if &payload.function == entry_func
+    )
+}
+
+/// Returns the EVM hash of every EVM transaction of a block fetched with its transactions,
+/// along with the engine hash of the transaction carrying it, in block order.
+pub fn evm_transaction_hashes<'a>(
+    block: &'a aptos_api_types::Block,
+    entry_func: &str,
+) -> impl Iterator<Item = (B256, aptos_api_types::HashValue)> + 'a {
+    evm_transactions_with_bytes(block, entry_func)
+        .map(|(tx, bytes)| (keccak256(bytes), tx.info.hash))
+}
+
+/// Like [`evm_transactions`], along with the raw EVM transaction each one carries.
+fn evm_transactions_with_bytes<'a>(
+    block: &'a aptos_api_types::Block,
+    entry_func: &str,
+) -> impl Iterator<Item = (&'a UserTransaction, Vec<u8>)> {
+    let entry_func = entry_func.parse::<EntryFunctionId>().ok();
+
+    block
+        .transactions
+        .iter()
+        .flatten()
+        .filter_map(|tx| match tx {
+            aptos_api_types::Transaction::UserTransaction(user) => Some(user),
+            _ => None,
+        })
+        .filter(move |tx| {
+            entry_func
+                .as_ref()
+                .is_some_and(|entry_func| calls(tx, entry_func))
+        })
+        .filter_map(|tx| {
+            let bytes = evm_transaction_bytes(tx).ok()?;
+            // Decoding the envelope leaves out the signer recovery, so this stays cheap
+            // enough for blocks listing hashes only.
+            match TxEnvelope::decode_2718(&mut bytes.as_slice()) {
+                Ok(_) => Some((tx, bytes)),
+                Err(e) => {
+                    tracing::warn!(
+                        "Skipping undecodable EVM transaction {} in block {}: {}",
+                        keccak256(&bytes),
+                        block.block_height.0,
+                        e
+                    );
+                    None
+                }
+            }
+        })
+}
+
+/// Payload of the engine event that records an EVM log.
+#[derive(Deserialize)]
+struct LogEvent {
+    address: Address,
+    topics: Vec<B256>,
+    data: Bytes,
+}
+
+/// Payload of the engine event that records the address of a contract created by an EVM
+/// transaction.
+#[derive(Deserialize)]
+struct ContractCreatedEvent {
+    address: Address,
+}
+
+/// Returns the address of the contract created by an EVM contract-creation transaction.
+///
+/// The address is read from the `create_event` event of the transaction, which also covers
+/// addresses the engine derives differently from `CREATE`. If the engine emitted no such
+/// event, the address is computed from the sender and nonce with the `CREATE` formula.
+fn created_contract(
+    tx: &UserTransaction,
+    create_event: &str,
+    signer: Address,
+    nonce: u64,
+) -> Result<Address> {
+    let Some(event) = tx
+        .events
+        .iter()
+        .find(|event| event.typ.to_string() == create_event)
+    else {
+        return Ok(signer.create(nonce));
+    };
+
+    let event: ContractCreatedEvent = serde_json::from_value(event.data.clone())
+        .map_err(|e| SidecarError::Conversion(format!("invalid contract creation event: {}", e)))?;
+
+    Ok(event.address)
+}
+
+/// Extracts the EVM logs of a block fetched with its transactions.
+///
+/// Logs are recorded by the engine as `log_event` events of the transaction that emitted
+/// them. Transactions that do not carry an EVM transaction are skipped, and transaction
+/// indices count EVM transactions only, see [`evm_transactions`].
+pub fn to_logs(
+    block: &aptos_api_types::Block,
+    log_event: &str,
+    entry_func: &str,
+) -> Result<Vec<Log>> {
+    let mut logs = vec![];
+    for (transaction_index, tx) in evm_transactions(block, entry_func).enumerate() {
+        let transaction_hash = evm_transaction_hash(tx)?;
+
+        for event in tx.events.iter() {
+            if event.typ.to_string() != log_event {
+                continue;
+            }
+
+            let event: LogEvent = serde_json::from_value(event.data.clone())
+                .map_err(|e| SidecarError::Conversion(format!("invalid log event: {}", e)))?;
+            logs.push(Log {
+                inner: alloy_primitives::Log {
+                    address: event.address,
+                    data: LogData::new_unchecked(event.topics, event.data),
+                },
+                block_hash: Some(block_hash(&block.block_hash)),
+                block_number: Some(block.block_height.0),
+                block_timestamp: Some(block.block_timestamp.0 / 1_000_000),
+                transaction_hash: Some(transaction_hash),
+                transaction_index: Some(transaction_index as u64),
+                log_index: Some(logs.len() as u64),
+                removed: false,
+            });
+        }
+    }
+
+    Ok(logs)
+}
+
+/// Returns the bloom filter of `logs`.
+pub fn logs_bloom<'a>(logs: impl IntoIterator<Item = &'a Log>) -> Bloom {
+    let mut bloom = Bloom::ZERO;
+    for log in logs {
+        bloom.accrue_log(&log.inner);
+    }
+
+    bloom
+}
+
+/// Converts a committed EVM transaction, locating it among the EVM transactions of `block`,
+/// the block fetched with its transactions that it was committed in.
+///
+/// Its index is left out if it is not among them, such as when it calls another entry
+/// function than `entry_func`.
+pub fn to_rpc_committed_transaction(
+    tx: &UserTransaction,
+    block: &aptos_api_types::Block,
+    entry_func: &str,
+) -> Result<Transaction> {
+    let hash = evm_transaction_hash(tx)?;
+    let index = evm_transactions(block, entry_func)
+        .position(|candidate| evm_transaction_hash(candidate).ok() == Some(hash));
+    let info = TransactionInfo {
+        hash: Some(hash),
+        index: index.map(|index| index as u64),
+        block_hash: Some(block_hash(&block.block_hash)),
+        block_number: Some(block.block_height.0),
+        base_fee: None,
+    };
+
+    to_rpc_transaction(tx, info)
+}
+
+/// Converts an engine user transaction into an EVM transaction.
+///
+/// `from` is recovered from the signature of the embedded EVM transaction rather than taken
+/// from the engine sender, so it is the address that originally signed the transaction. The
+/// `type` and fee fields follow the decoded transaction type, so a legacy transaction is
+/// never rendered with EIP-1559 fields. Likewise, the signature is rendered as `r`, `s` and
+/// the EIP-155 `v` of a legacy transaction, or `r`, `s` and `yParity`, repeated as `v`, of a
+/// typed one, so clients can verify it against the signing hash of the transaction.
+///
+/// `chainId` is the chain id signed into the transaction, which the engine only accepts if it
+/// matches its own. It is rendered for typed and EIP-155 legacy transactions, and left out of
+/// pre-EIP-155 legacy transactions, which are valid on any chain.
+///
+/// `value` is taken from the embedded EVM transaction, which is already denominated in wei:
+/// the engine executes the signed transaction as submitted and never re-denominates its value
+/// into native coin units. Unlike balances, see [`crate::rpc::eth::to_wei`], it is therefore
+/// reported without scaling by the native coin decimals.
+pub fn to_rpc_transaction(tx: &UserTransaction, info: TransactionInfo) -> Result<Transaction> {
+    let bytes = evm_transaction_bytes(tx)?;
+    let recovered: Recovered<TransactionSigned> =
+        recover_raw_transaction(&bytes).map_err(|e| SidecarError::Conversion(e.to_string()))?;
+
+    Ok(Transaction::from_transaction(recovered.convert(), info))
+}
+
+/// Converts an engine transaction that is not committed yet into an EVM transaction.
+///
+/// Like geth, the block hash, block number and transaction index of a pending transaction
+/// are left empty.
+pub fn to_rpc_pending_transaction(tx: &aptos_api_types::PendingTransaction) -> Result<Transaction> {
+    let bytes = evm_request_bytes(&tx.request)?;
+    let recovered: Recovered<TransactionSigned> =
+        recover_raw_transaction(&bytes).map_err(|e| SidecarError::Conversion(e.to_string()))?;
+    let info = TransactionInfo {
+        hash: Some(keccak256(&bytes)),
+        ..Default::default()
+    };
+
+    Ok(Transaction::from_transaction(recovered.convert(), info))
+}
+
+/// Builds the receipt of the EVM transaction carried by an engine user transaction.
+///
+/// `logs` are the logs emitted by the transaction, see [`to_logs`]. The effective gas price
+/// is derived from the EVM transaction and `base_fee`, which is `None` as long as the engine
+/// has no dynamic base fee; an EIP-1559 transaction then pays its max fee per gas, the same
+/// price it was submitted with.
+///
+/// The contract address of a contract-creation transaction is read from its `create_event`
+/// event, see [`created_contract`].
+///
+/// `cumulative_gas_used` is the EVM gas used by the transaction and the EVM transactions
+/// before it in its block, see [`to_block_receipts`]. The engine gas the transaction used is
+/// converted to EVM gas by `gas`.
+pub fn to_rpc_receipt(
+    tx: &UserTransaction,
+    info: TransactionInfo,
+    cumulative_gas_used: u64,
+    logs: Vec<Log>,
+    base_fee: Option<u64>,
+    create_event: &str,
+    gas: &GasConversion,
+) -> Result<TransactionReceipt> {
+    let bytes = evm_transaction_bytes(tx)?;
+    let recovered: Recovered<TransactionSigned> =
+        recover_raw_transaction(&bytes).map_err(|e| SidecarError::Conversion(e.to_string()))?;
+    let signer = recovered.signer();
+
+    let receipt = ReceiptWithBloom {
+        logs_bloom: logs_bloom(&logs),
+        receipt: Receipt {
+            status: tx.info.success.into(),
+            cumulative_gas_used,
+            logs,
+        },
+    };
+    let tx_type =
+        TxType::try_from(recovered.ty()).map_err(|e| SidecarError::Conversion(e.to_string()))?;
+    let inner = match tx_type {
+        TxType::Legacy => ReceiptEnvelope::Legacy(receipt),
+        TxType::Eip2930 => ReceiptEnvelope::Eip2930(receipt),
+        TxType::Eip1559 => ReceiptEnvelope::Eip1559(receipt),
+        TxType::Eip4844 => ReceiptEnvelope::Eip4844(receipt),
+        TxType::Eip7702 => ReceiptEnvelope::Eip7702(receipt),
+    };
+
+    let to = recovered.to();
+    let contract_address = match to {
+        Some(_) => None,
+        None => Some(created_contract(
+            tx,
+            create_event,
+            signer,
+            recovered.nonce(),
+        )?),
+    };
+    Ok(TransactionReceipt {
+        inner,
+        transaction_hash: keccak256(&bytes),
+        transaction_index: info.index,
+        block_hash: info.block_hash,
+        block_number: info.block_number,
+        gas_used: gas.to_evm_gas(tx.info.gas_used.0),
+        effective_gas_price: recovered.effective_gas_price(base_fee),
+        blob_gas_used: None,
+        blob_gas_price: None,
+        from: signer,
+        to,
+        contract_address,
+    })
+}
+
+/// Builds the receipts of the EVM transactions of a block fetched with its transactions.
+///
+/// Engine gas is converted to EVM gas by `gas`, the inverse of the conversion of the gas
+/// limit of a submitted transaction, so the cumulative gas used of a receipt is the EVM gas
+/// of the engine gas used by the EVM transactions up to and including it.
+pub fn to_block_receipts(
+    block: &aptos_api_types::Block,
+    base_fee: Option<u64>,
+    log_event: &str,
+    create_event: &str,
+    entry_func: &str,
+    gas: &GasConversion,
+) -> Result<Vec<TransactionReceipt>> {
+    let hash = block_hash(&block.block_hash);
+    let mut logs = to_logs(block, log_event, entry_func)?
+        .into_iter()
+        .peekable();
+    let mut cumulative_gas_used = 0;
+
+    evm_transactions(block, entry_func)
+        .enumerate()
+        .map(|(index, tx)| {
+            cumulative_gas_used += gas.to_evm_gas(tx.info.gas_used.0);
+            let mut tx_logs = vec![];
+            while let Some(log) = logs.next_if(|log| log.transaction_index == Some(index as u64)) {
+                tx_logs.push(log);
+            }
+            let info = TransactionInfo {
+                hash: Some(evm_transaction_hash(tx)?),
+                index: Some(index as u64),
+                block_hash: Some(hash),
+                block_number: Some(block.block_height.0),
+                base_fee,
+            };
+
+            to_rpc_receipt(
+                tx,
+                info,
+                cumulative_gas_used,
+                tx_logs,
+                base_fee,
+                create_event,
+                gas,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{
+        block_hash, engine_block_hash, evm_transactions, genesis_block, logs_bloom,
+        to_block_receipts, to_logs, to_rpc_block, to_rpc_committed_transaction, to_rpc_full_block,
+        to_rpc_header, to_rpc_receipt, to_rpc_transaction, GasConversion, HeaderConstants,
+    };
+    use crate::engine::adapter::mock::{sign_evm_transaction, user_transaction};
+    use alloy_consensus::{
+        SignableTransaction, TxEip1559, TxEip2930, TxEip7702, TxEnvelope, TxLegacy, TxType,
+    };
+    use alloy_eips::{eip2718::Encodable2718, eip2930::AccessList};
+    use alloy_primitives::{keccak256, Address, Bytes, PrimitiveSignature, TxKind, B256, U256};
+    use alloy_rpc_types_eth::{BlockTransactions, TransactionInfo};
+
+    const CREATE_EVENT: &str = "0x100::evm::ContractCreated";
+    const LOG_EVENT: &str = "0x100::evm::Log";
+    const ENTRY_FUNC: &str = "0x100::evm::transact";
+
+    /// Hand-written block in the shape of a `GET /blocks/by_height/1024?with_transactions=true`
+    /// response: a block metadata transaction, a user transaction carrying a legacy 1 ETH
+    /// transfer that emits a `Transfer` log, and a state checkpoint transaction. Only the EVM
+    /// transaction, the signed example of EIP-155, is real; the engine fields are made up.
+    const BLOCK_BY_HEIGHT: &str = include_str!("fixtures/block_by_height.json");
+    const FIXTURE_BLOCK_HASH: &str =
+        "0x5d3b1b9c0e2f7a4d8c6e1f0a9b8c7d6e5f4a3b2c1d0e9f8a7b6c5d4e3f2a1b0c";
+    const FIXTURE_TX_HASH: &str =
+        "0x33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788";
+    const FIXTURE_SENDER: &str = "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f";
+    const FIXTURE_RECIPIENT: &str = "0x3535353535353535353535353535353535353535";
+    const GAS: GasConversion = GasConversion::new(1000, 8);
+    const HEADER: HeaderConstants = HeaderConstants {
+        gas_limit: 30_000_000,
+        base_fee: 7,
+    };
+
+    fn fixture_block() -> aptos_api_types::Block {
+        serde_json::from_str(BLOCK_BY_HEIGHT).unwrap()
+    }
+
+    fn fixture_log() -> serde_json::Value {
+        serde_json::json!({
+            "address": FIXTURE_RECIPIENT,
+            "topics": [
+                "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+                "0x0000000000000000000000009d8a62f656a8d1615c1294fd71e9cfb3e4855a4f",
+                "0x0000000000000000000000003535353535353535353535353535353535353535",
+            ],
+            "data": "0x0000000000000000000000000000000000000000000000000de0b6b3a7640000",
+            "blockHash": FIXTURE_BLOCK_HASH,
+            "blockNumber": "0x400",
+            "blockTimestamp": "0x6553f17b",
+            "transactionHash": FIXTURE_TX_HASH,
+            "transactionIndex": "0x0",
+            "logIndex": "0x0",
+            "removed": false,
+        })
+    }
+
+    fn block(height: u64) -> aptos_api_types::Block {
+        serde_json::from_value(serde_json::json!({
+            "block_height": height.to_string(),
+            "block_hash": format!("0x{:064x}", height + 1),
+            "block_timestamp": "1700000000000000",
+            "first_version": "0",
+            "last_version": "0",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn derives_stable_block_hashes() {
+        let first = to_rpc_block(&block(1), B256::ZERO, &HEADER, ENTRY_FUNC, &GAS);
+        let again = to_rpc_block(&block(1), B256::ZERO, &HEADER, ENTRY_FUNC, &GAS);
+        let second = to_rpc_block(&block(2), first.header.hash, &HEADER, ENTRY_FUNC, &GAS);
+
+        assert_eq!(first.header.hash, again.header.hash);
+        assert_ne!(first.header.hash, second.header.hash);
+    }
+
+    #[test]
+    fn maps_block_hashes_back_to_the_engine() {
+        let block = block(1);
+
+        assert_eq!(
+            engine_block_hash(&block_hash(&block.block_hash)),
+            block.block_hash
+        );
+    }
+
+    #[test]
+    fn derives_genesis_hash_from_configuration() {
+        let genesis = genesis_block(1_700_000_000, Bytes::from_static(b"noir"));
+
+        assert_eq!(genesis.header.inner.parent_hash, B256::ZERO);
+        assert_eq!(genesis.header.hash, genesis.header.inner.hash_slow());
+        assert_eq!(
+            genesis.header.hash,
+            genesis_block(1_700_000_000, Bytes::from_static(b"noir"))
+                .header
+                .hash
+        );
+        assert_ne!(
+            genesis.header.hash,
+            genesis_block(1_700_000_001, Bytes::from_static(b"noir"))
+                .header
+                .hash
+        );
+    }
+
+    #[test]
+    fn recovers_from_of_embedded_transaction() {
+        let tx = TxEip1559 {
+            chain_id: 4,
+            nonce: 3,
+            gas_limit: 50_000,
+            max_fee_per_gas: 200,
+            max_priority_fee_per_gas: 10,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            value: U256::from(1),
+            ..Default::default()
+        };
+        let (envelope, signer) = sign_evm_transaction(tx);
+
+        let rendered = to_rpc_transaction(
+            &user_transaction(&envelope.encoded_2718()),
+            TransactionInfo::default(),
+        )
+        .unwrap();
+
+        assert_eq!(rendered.inner.signer(), signer);
+        assert_ne!(rendered.inner.signer(), Address::repeat_byte(0x11));
+        assert_eq!(rendered.inner.tx_hash(), envelope.tx_hash());
+    }
+
+    #[test]
+    fn reports_legacy_gas_price_as_effective_gas_price() {
+        let tx = TxLegacy {
+            chain_id: Some(4),
+            nonce: 0,
+            gas_price: 150,
+            gas_limit: 21_000,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            ..Default::default()
+        };
+        let (envelope, _) = sign_evm_transaction(tx);
+
+        let receipt = to_rpc_receipt(
+            &user_transaction(&envelope.encoded_2718()),
+            TransactionInfo::default(),
+            20,
+            vec![],
+            None,
+            CREATE_EVENT,
+            &GAS,
+        )
+        .unwrap();
+
+        assert_eq!(receipt.inner.tx_type(), TxType::Legacy);
+        assert_eq!(receipt.effective_gas_price, 150);
+        assert_eq!(receipt.transaction_hash, *envelope.tx_hash());
+    }
+
+    #[test]
+    fn derives_eip1559_effective_gas_price_from_base_fee() {
+        let tx = TxEip1559 {
+            chain_id: 4,
+            nonce: 0,
+            gas_limit: 21_000,
+            max_fee_per_gas: 200,
+            max_priority_fee_per_gas: 10,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            ..Default::default()
+        };
+        let (envelope, _) = sign_evm_transaction(tx);
+        let user = user_transaction(&envelope.encoded_2718());
+
+        let receipt = to_rpc_receipt(
+            &user,
+            TransactionInfo::default(),
+            20,
+            vec![],
+            None,
+            CREATE_EVENT,
+            &GAS,
+        )
+        .unwrap();
+        assert_eq!(receipt.inner.tx_type(), TxType::Eip1559);
+        assert_eq!(receipt.effective_gas_price, 200);
+
+        let receipt = to_rpc_receipt(
+            &user,
+            TransactionInfo::default(),
+            20,
+            vec![],
+            Some(100),
+            CREATE_EVENT,
+            &GAS,
+        )
+        .unwrap();
+        assert_eq!(receipt.effective_gas_price, 110);
+    }
+
+    fn creation_transaction(nonce: u64) -> (Address, Vec<u8>) {
+        let tx = TxEip1559 {
+            chain_id: 4,
+            nonce,
+            gas_limit: 100_000,
+            max_fee_per_gas: 200,
+            to: TxKind::Create,
+            input: Bytes::from_static(&[0x60, 0x00]),
+            ..Default::default()
+        };
+        let (envelope, signer) = sign_evm_transaction(tx);
+
+        (signer, envelope.encoded_2718())
+    }
+
+    #[test]
+    fn reads_contract_address_from_creation_event() {
+        let (_, raw) = creation_transaction(3);
+        let mut user = user_transaction(&raw);
+        user.events = vec![serde_json::from_value(serde_json::json!({
+            "guid": {
+                "creation_number": "0",
+                "account_address": "0x0",
+            },
+            "sequence_number": "0",
+            "type": CREATE_EVENT,
+            "data": {
+                "address": "0x2222222222222222222222222222222222222222",
+            },
+        }))
+        .unwrap()];
+
+        let receipt = to_rpc_receipt(
+            &user,
+            TransactionInfo::default(),
+            20,
+            vec![],
+            None,
+            CREATE_EVENT,
+            &GAS,
+        )
+        .unwrap();
+        assert_eq!(receipt.contract_address, Some(Address::repeat_byte(0x22)));
+        assert_eq!(receipt.to, None);
+    }
+
+    #[test]
+    fn derives_contract_address_without_creation_event() {
+        let (signer, raw) = creation_transaction(3);
+
+        let receipt = to_rpc_receipt(
+            &user_transaction(&raw),
+            TransactionInfo::default(),
+            20,
+            vec![],
+            None,
+            CREATE_EVENT,
+            &GAS,
+        )
+        .unwrap();
+        assert_eq!(receipt.contract_address, Some(signer.create(3)));
+    }
+
+    #[test]
+    fn converts_gas_and_prices_between_evm_and_engine_units() {
+        assert_eq!(GAS.to_evm_gas(21), 21_000);
+        assert_eq!(GAS.to_engine_gas(21_000), 21);
+        assert_eq!(GAS.to_engine_gas(21_001), 22);
+
+        // 100 octas per engine gas unit is 1 gwei per EVM gas, at 1000 EVM gas per unit.
+        assert_eq!(GAS.to_wei_per_gas(100), 1_000_000_000);
+        assert_eq!(GAS.to_unit_price(1_000_000_000), 100);
+        assert_eq!(GAS.to_unit_price(u128::MAX), u64::MAX);
+
+        let fee = GAS.to_evm_gas(21) as u128 * GAS.to_wei_per_gas(100);
+        assert_eq!(fee, 21 * 100 * 10_000_000_000);
+
+        let eighteen = GasConversion::new(0, 18);
+        assert_eq!(eighteen.to_evm_gas(21), 21);
+        assert_eq!(eighteen.to_wei_per_gas(7), 7);
+    }
+
+    #[test]
+    fn accumulates_gas_used_over_block() {
+        let mut block = block(7);
+        block.transactions = Some(
+            [30u64, 50, 20]
+                .into_iter()
+                .enumerate()
+                .map(|(nonce, gas_used)| {
+                    let mut user = user_transaction(&creation_transaction(nonce as u64).1);
+                    user.info.gas_used = gas_used.into();
+                    aptos_api_types::Transaction::UserTransaction(user)
+                })
+                .collect(),
+        );
+
+        let receipts =
+            to_block_receipts(&block, None, LOG_EVENT, CREATE_EVENT, ENTRY_FUNC, &GAS).unwrap();
+
+        let gas = receipts
+            .iter()
+            .map(|receipt| (receipt.gas_used, receipt.inner.cumulative_gas_used()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            gas,
+            vec![(30_000, 30_000), (50_000, 80_000), (20_000, 100_000)]
+        );
+        assert_eq!(receipts[2].transaction_index, Some(2));
+        assert_eq!(receipts[2].block_number, Some(7));
+    }
+
+    #[test]
+    fn leaves_contract_address_empty_for_calls() {
+        let tx = TxLegacy {
+            chain_id: Some(4),
+            gas_price: 150,
+            gas_limit: 21_000,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            ..Default::default()
+        };
+        let raw = sign_evm_transaction(tx).0.encoded_2718();
+
+        let receipt = to_rpc_receipt(
+            &user_transaction(&raw),
+            TransactionInfo::default(),
+            20,
+            vec![],
+            None,
+            CREATE_EVENT,
+            &GAS,
+        )
+        .unwrap();
+        assert_eq!(receipt.contract_address, None);
+        assert_eq!(receipt.to, Some(Address::repeat_byte(0x11)));
+    }
+
+    /// Renders `tx`, signed, as returned by the RPC methods.
+    fn rendered<T>(tx: T) -> serde_json::Value
+    where
+        T: SignableTransaction<PrimitiveSignature>,
+        TxEnvelope: From<alloy_consensus::Signed<T>>,
+    {
+        rendered_with_signature(tx).0
+    }
+
+    /// Renders `tx` signed with a fixed key, along with the signature.
+    fn rendered_with_signature<T>(tx: T) -> (serde_json::Value, PrimitiveSignature)
+    where
+        T: SignableTransaction<PrimitiveSignature>,
+        TxEnvelope: From<alloy_consensus::Signed<T>>,
+    {
+        let (envelope, _) = sign_evm_transaction(tx);
+        let raw = envelope.encoded_2718();
+        let tx = to_rpc_transaction(&user_transaction(&raw), TransactionInfo::default()).unwrap();
+
+        (serde_json::to_value(tx).unwrap(), *envelope.signature())
+    }
+
+    #[test]
+    fn renders_eip155_v_of_legacy_signature() {
+        let (tx, signature) = rendered_with_signature(TxLegacy {
+            chain_id: Some(4),
+            gas_price: 150,
+            gas_limit: 21_000,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            ..Default::default()
+        });
+
+        assert_eq!(tx["r"], serde_json::to_value(signature.r()).unwrap());
+        assert_eq!(tx["s"], serde_json::to_value(signature.s()).unwrap());
+        assert_eq!(tx["v"], format!("{:#x}", 35 + 2 * 4 + signature.v() as u64));
+        assert!(tx.get("yParity").is_none());
+    }
+
+    #[test]
+    fn renders_y_parity_of_typed_signature() {
+        let (tx, signature) = rendered_with_signature(TxEip1559 {
+            chain_id: 4,
+            gas_limit: 50_000,
+            max_fee_per_gas: 200,
+            max_priority_fee_per_gas: 10,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            ..Default::default()
+        });
+
+        assert_eq!(tx["r"], serde_json::to_value(signature.r()).unwrap());
+        assert_eq!(tx["s"], serde_json::to_value(signature.s()).unwrap());
+        assert_eq!(tx["yParity"], format!("{:#x}", signature.v() as u8));
+        assert_eq!(tx["v"], tx["yParity"]);
+    }
+
+    #[test]
+    fn renders_signed_chain_id() {
+        let legacy = rendered(TxLegacy {
+            chain_id: Some(4),
+            gas_price: 150,
+            gas_limit: 21_000,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            ..Default::default()
+        });
+        let eip2930 = rendered(TxEip2930 {
+            chain_id: 4,
+            gas_price: 150,
+            gas_limit: 21_000,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            ..Default::default()
+        });
+        let eip1559 = rendered(TxEip1559 {
+            chain_id: 4,
+            gas_limit: 21_000,
+            max_fee_per_gas: 200,
+            max_priority_fee_per_gas: 10,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            ..Default::default()
+        });
+
+        assert_eq!(legacy["chainId"], "0x4");
+        assert_eq!(eip2930["chainId"], "0x4");
+        assert_eq!(eip1559["chainId"], "0x4");
+    }
+
+    #[test]
+    fn omits_chain_id_of_pre_eip155_transaction() {
+        let tx = rendered(TxLegacy {
+            chain_id: None,
+            gas_price: 150,
+            gas_limit: 21_000,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            ..Default::default()
+        });
+
+        assert!(tx.get("chainId").is_none());
+        assert!(tx["v"] == "0x1b" || tx["v"] == "0x1c", "{}", tx["v"]);
+    }
+
+    #[test]
+    fn renders_legacy_transaction_type() {
+        let tx = rendered(TxLegacy {
+            chain_id: Some(4),
+            gas_price: 150,
+            gas_limit: 21_000,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            ..Default::default()
+        });
+
+        assert_eq!(tx["type"], "0x0");
+        assert_eq!(tx["gasPrice"], "0x96");
+        assert!(tx.get("accessList").is_none());
+        assert!(tx.get("maxFeePerGas").is_none());
+        assert!(tx.get("maxPriorityFeePerGas").is_none());
+    }
+
+    #[test]
+    fn renders_eip2930_transaction_type() {
+        let tx = rendered(TxEip2930 {
+            chain_id: 4,
+            gas_price: 150,
+            gas_limit: 21_000,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            access_list: AccessList::default(),
+            ..Default::default()
+        });
+
+        assert_eq!(tx["type"], "0x1");
+        assert_eq!(tx["gasPrice"], "0x96");
+        assert_eq!(tx["accessList"], serde_json::json!([]));
+        assert!(tx.get("maxFeePerGas").is_none());
+    }
+
+    #[test]
+    fn renders_eip1559_transaction_type() {
+        let tx = rendered(TxEip1559 {
+            chain_id: 4,
+            gas_limit: 21_000,
+            max_fee_per_gas: 200,
+            max_priority_fee_per_gas: 10,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            ..Default::default()
+        });
+
+        assert_eq!(tx["type"], "0x2");
+        assert_eq!(tx["maxFeePerGas"], "0xc8");
+        assert_eq!(tx["maxPriorityFeePerGas"], "0xa");
+        assert_eq!(tx["accessList"], serde_json::json!([]));
+        assert!(tx.get("authorizationList").is_none());
+    }
+
+    #[test]
+    fn renders_eip7702_transaction_type() {
+        let tx = rendered(TxEip7702 {
+            chain_id: 4,
+            gas_limit: 50_000,
+            max_fee_per_gas: 200,
+            max_priority_fee_per_gas: 10,
+            to: Address::repeat_byte(0x11),
+            ..Default::default()
+        });
+
+        assert_eq!(tx["type"], "0x4");
+        assert_eq!(tx["maxFeePerGas"], "0xc8");
+        assert_eq!(tx["authorizationList"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn converts_fixture_header() {
+        let header = to_rpc_header(
+            &fixture_block(),
+            B256::repeat_byte(0xaa),
+            &HEADER,
+            ENTRY_FUNC,
+            &GAS,
+        );
+        let header = serde_json::to_value(header).unwrap();
+
+        assert_eq!(header["hash"], FIXTURE_BLOCK_HASH);
+        assert_eq!(header["parentHash"], format!("0x{}", "aa".repeat(32)));
+        assert_eq!(header["number"], "0x400");
+        assert_eq!(header["timestamp"], "0x6553f17b");
+    }
+
+    #[test]
+    fn reports_gas_of_evm_transactions_in_header() {
+        let header = to_rpc_header(&fixture_block(), B256::ZERO, &HEADER, ENTRY_FUNC, &GAS);
+
+        assert_eq!(header.gas_limit, HEADER.gas_limit);
+        // Only the EVM transfer of the fixture block uses gas, 12 engine gas units.
+        assert_eq!(header.gas_used, 12_000);
+        assert_eq!(
+            to_rpc_block(&fixture_block(), B256::ZERO, &HEADER, ENTRY_FUNC, &GAS)
+                .header
+                .gas_used,
+            12_000
+        );
+        let receipts = to_block_receipts(
+            &fixture_block(),
+            None,
+            LOG_EVENT,
+            CREATE_EVENT,
+            ENTRY_FUNC,
+            &GAS,
+        )
+        .unwrap();
+        assert_eq!(
+            receipts.last().unwrap().inner.cumulative_gas_used(),
+            header.gas_used
+        );
+        // Without its transactions, the gas used of a block is unknown.
+        let mut block = fixture_block();
+        block.transactions = None;
+        assert_eq!(
+            to_rpc_header(&block, B256::ZERO, &HEADER, ENTRY_FUNC, &GAS).gas_used,
+            0
+        );
+    }
+
+    #[test]
+    fn clamps_gas_used_to_gas_limit() {
+        let constants = HeaderConstants {
+            gas_limit: 10,
+            ..HEADER
+        };
+        let header = to_rpc_header(&fixture_block(), B256::ZERO, &constants, ENTRY_FUNC, &GAS);
+
+        assert_eq!(header.gas_limit, 10);
+        assert_eq!(header.gas_used, 10);
+    }
+
+    #[test]
+    fn renders_post_merge_header_fields() {
+        let header = to_rpc_header(&fixture_block(), B256::ZERO, &HEADER, ENTRY_FUNC, &GAS);
+        let header = serde_json::to_value(header).unwrap();
+
+        assert_eq!(header["difficulty"], "0x0");
+        assert_eq!(header["totalDifficulty"], "0x0");
+        assert_eq!(header["nonce"], "0x0000000000000000");
+        assert_eq!(header["mixHash"], format!("0x{}", "00".repeat(32)));
+        assert_eq!(header["baseFeePerGas"], "0x7");
+        assert_eq!(header["gasLimit"], "0x1c9c380");
+    }
+
+    #[test]
+    fn lists_only_evm_transactions_of_fixture_block() {
+        let block = fixture_block();
+        let rendered =
+            serde_json::to_value(to_rpc_block(&block, B256::ZERO, &HEADER, ENTRY_FUNC, &GAS))
+                .unwrap();
+
+        assert_eq!(evm_transactions(&block, ENTRY_FUNC).count(), 1);
+        assert_eq!(
+            rendered["transactions"],
+            serde_json::json!([FIXTURE_TX_HASH])
+        );
+    }
+
+    #[test]
+    fn converts_fixture_transaction() {
+        let block =
+            to_rpc_full_block(&fixture_block(), B256::ZERO, &HEADER, ENTRY_FUNC, &GAS).unwrap();
+        let rendered = serde_json::to_value(block).unwrap();
+        let tx = &rendered["transactions"][0];
+
+        assert_eq!(tx["hash"], FIXTURE_TX_HASH);
+        assert_eq!(tx["from"], FIXTURE_SENDER);
+        assert_eq!(tx["to"], FIXTURE_RECIPIENT);
+        assert_eq!(tx["nonce"], "0x9");
+        assert_eq!(tx["value"], "0xde0b6b3a7640000");
+        assert_eq!(tx["gas"], "0x5208");
+        assert_eq!(tx["gasPrice"], "0x4a817c800");
+        assert_eq!(tx["input"], "0x");
+        assert_eq!(tx["chainId"], "0x1");
+        assert_eq!(tx["type"], "0x0");
+        assert_eq!(
+            tx["r"],
+            "0x28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276"
+        );
+        assert_eq!(
+            tx["s"],
+            "0x67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+        assert_eq!(tx["blockHash"], FIXTURE_BLOCK_HASH);
+        assert_eq!(tx["blockNumber"], "0x400");
+        assert_eq!(tx["transactionIndex"], "0x0");
+    }
+
+    #[test]
+    fn locates_committed_transaction_in_its_block() {
+        let block = fixture_block();
+        let Some(aptos_api_types::Transaction::UserTransaction(tx)) =
+            block.transactions.iter().flatten().nth(1)
+        else {
+            panic!("expected a user transaction");
+        };
+
+        let tx =
+            serde_json::to_value(to_rpc_committed_transaction(tx, &block, ENTRY_FUNC).unwrap())
+                .unwrap();
+        assert_eq!(tx["hash"], FIXTURE_TX_HASH);
+        assert_eq!(tx["blockHash"], FIXTURE_BLOCK_HASH);
+        assert_eq!(tx["blockNumber"], "0x400");
+        assert_eq!(tx["transactionIndex"], "0x0");
+    }
+
+    #[test]
+    fn converts_fixture_logs() {
+        let logs = to_logs(&fixture_block(), LOG_EVENT, ENTRY_FUNC).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&logs).unwrap(),
+            serde_json::json!([fixture_log()])
+        );
+    }
+
+    #[test]
+    fn converts_fixture_receipt() {
+        let block = fixture_block();
+        let logs = to_logs(&block, LOG_EVENT, ENTRY_FUNC).unwrap();
+        let receipts =
+            to_block_receipts(&block, None, LOG_EVENT, CREATE_EVENT, ENTRY_FUNC, &GAS).unwrap();
+        assert_eq!(receipts.len(), 1);
+        let receipt = serde_json::to_value(&receipts[0]).unwrap();
+
+        assert_eq!(receipt["transactionHash"], FIXTURE_TX_HASH);
+        assert_eq!(receipt["transactionIndex"], "0x0");
+        assert_eq!(receipt["blockHash"], FIXTURE_BLOCK_HASH);
+        assert_eq!(receipt["blockNumber"], "0x400");
+        assert_eq!(receipt["from"], FIXTURE_SENDER);
+        assert_eq!(receipt["to"], FIXTURE_RECIPIENT);
+        assert_eq!(receipt["contractAddress"], serde_json::Value::Null);
+        assert_eq!(receipt["status"], "0x1");
+        assert_eq!(receipt["type"], "0x0");
+        assert_eq!(receipt["gasUsed"], "0x2ee0");
+        assert_eq!(receipt["cumulativeGasUsed"], "0x2ee0");
+        assert_eq!(receipt["effectiveGasPrice"], "0x4a817c800");
+        assert_eq!(receipt["logs"], serde_json::json!([fixture_log()]));
+        assert_eq!(
+            receipt["logsBloom"],
+            serde_json::to_value(logs_bloom(&logs)).unwrap()
+        );
+    }
+
+    #[test]
+    fn indexes_only_evm_transactions_of_mixed_block() {
+        let fixture: serde_json::Value = serde_json::from_str(BLOCK_BY_HEIGHT).unwrap();
+        let system = |index: usize| -> aptos_api_types::Transaction {
+            serde_json::from_value(fixture["transactions"][index].clone()).unwrap()
+        };
+        let (_, first) = creation_transaction(0);
+        let (_, second) = creation_transaction(1);
+        let call = |function: &str, arguments: serde_json::Value| {
+            let mut user = user_transaction(&first);
+            user.request.payload = serde_json::from_value(serde_json::json!({
+                "type": "entry_function_payload",
+                "function": function,
+                "type_arguments": [],
+                "arguments": arguments,
+            }))
+            .unwrap();
+            aptos_api_types::Transaction::UserTransaction(user)
+        };
+        let relayed = |raw: &[u8]| {
+            let mut user = user_transaction(raw);
+            let mut payload = serde_json::to_value(&user.request.payload).unwrap();
+            payload["function"] = "0x200::bridge::relay".into();
+            user.request.payload = serde_json::from_value(payload).unwrap();
+            aptos_api_types::Transaction::UserTransaction(user)
+        };
+        let mut block = block(7);
+        block.transactions = Some(vec![
+            system(0),
+            aptos_api_types::Transaction::UserTransaction(user_transaction(&first)),
+            call(
+                "0x1::aptos_account::transfer",
+                serde_json::json!([format!("0x{}", "11".repeat(32)), "1000"]),
+            ),
+            call(
+                "0x1::object::transfer_call",
+                serde_json::json!(["0x22", "0x10"]),
+            ),
+            // Takes its arguments like the entry function, but is another function.
+            relayed(&second),
+            aptos_api_types::Transaction::UserTransaction(user_transaction(&second)),
+            system(2),
+        ]);
+        let hashes = vec![keccak256(&first), keccak256(&second)];
+
+        assert_eq!(
+            to_rpc_block(&block, B256::ZERO, &HEADER, ENTRY_FUNC, &GAS).transactions,
+            BlockTransactions::Hashes(hashes.clone())
+        );
+        let BlockTransactions::Full(transactions) =
+            to_rpc_full_block(&block, B256::ZERO, &HEADER, ENTRY_FUNC, &GAS)
+                .unwrap()
+                .transactions
+        else {
+            panic!("expected full transactions");
+        };
+        let indexed = transactions
+            .iter()
+            .map(|tx| (*tx.inner.tx_hash(), tx.transaction_index))
+            .collect::<Vec<_>>();
+        assert_eq!(indexed, vec![(hashes[0], Some(0)), (hashes[1], Some(1))]);
+        let receipts =
+            to_block_receipts(&block, None, LOG_EVENT, CREATE_EVENT, ENTRY_FUNC, &GAS).unwrap();
+        let indexed = receipts
+            .iter()
+            .map(|receipt| (receipt.transaction_hash, receipt.transaction_index))
+            .collect::<Vec<_>>();
+        assert_eq!(indexed, vec![(hashes[0], Some(0)), (hashes[1], Some(1))]);
+        assert_eq!(
+            evm_transactions(&block, &format!("0x{:0>64}::evm::transact", "100")).count(),
+            2
+        );
+    }
+
+    #[test]
+    fn lists_hashes_without_recovering_signers() {
+        // No signer can be recovered from a zero `r`, so only a conversion that recovers
+        // signers fails on it.
+        let tx = TxLegacy {
+            chain_id: Some(4),
+            nonce: 0,
+            gas_price: 100,
+            gas_limit: 21_000,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            value: U256::from(1),
+            input: Bytes::new(),
+        };
+        let signature = PrimitiveSignature::new(U256::ZERO, U256::from(1), false);
+        let raw = TxEnvelope::from(tx.into_signed(signature)).encoded_2718();
+        let mut block = block(7);
+        block.transactions = Some(vec![aptos_api_types::Transaction::UserTransaction(
+            user_transaction(&raw),
+        )]);
+
+        assert_eq!(
+            to_rpc_block(&block, B256::ZERO, &HEADER, ENTRY_FUNC, &GAS).transactions,
+            BlockTransactions::Hashes(vec![keccak256(&raw)])
+        );
+        assert!(to_rpc_full_block(&block, B256::ZERO, &HEADER, ENTRY_FUNC, &GAS).is_err());
+    }
+}
@@ -15,41 +15,727 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::engine::adapter::EngineAdapter;
-use alloy_consensus::transaction::Recovered;
+use crate::{
+    config::{
+        engine::BalanceConfig,
+        server::{BlockFetchFailure, ProofUnavailable},
+    },
+    engine::{
+        accounts::AccountCache,
+        adapter::{EngineAdapter, EngineGas, EvmCall, GasPriceEstimate},
+        indexes::Indexes,
+        pending::PendingTransaction,
+        tracker::HeadTracker,
+    },
+    error::{Result, SidecarError, METHOD_NOT_SUPPORTED_CODE},
+    rpc::{
+        block_cache::BlockCache,
+        convert::{self, GasConversion, HeaderConstants, DEFAULT_EVM_GAS_PER_UNIT},
+        filter::fetch_blocks,
+        revert::{revert_data, AbortReasons},
+        sidecar::{SidecarReceiptsApiServer, SidecarSubmitApiServer},
+    },
+};
+use alloy_consensus::{transaction::Recovered, Transaction};
 use alloy_dyn_abi::TypedData;
 use alloy_eips::{BlockId, BlockNumberOrTag};
 use alloy_network::Ethereum;
 use alloy_rpc_types_eth::{
-    simulate::{SimulatePayload, SimulatedBlock},
+    simulate::{
+        SimCallResult, SimulateError, SimulatePayload, SimulatedBlock, MAX_SIMULATE_BLOCKS,
+    },
     state::StateOverride,
     AccessListResult, BlockOverrides, Bundle, EIP1186AccountProofResponse, EthCallResponse,
-    FeeHistory, Index, StateContext, SyncStatus, TransactionRequest, Work,
+    FeeHistory, Index, StateContext, SyncInfo, SyncStatus, Transaction as RpcTransaction,
+    TransactionIndex, TransactionInfo, TransactionInput, TransactionReceipt, TransactionRequest,
+    Work,
 };
-use alloy_serde::JsonStorageKey;
+use alloy_serde::{JsonStorageKey, WithOtherFields};
 use jsonrpsee::{
     core::RpcResult,
-    types::{error::INTERNAL_ERROR_CODE, ErrorObjectOwned},
+    proc_macros::rpc,
+    types::{error::METHOD_NOT_FOUND_CODE, ErrorObjectOwned},
 };
 use reth_ethereum_primitives::TransactionSigned;
 use reth_rpc_eth_api::{EthApiServer, RpcBlock};
-use reth_rpc_eth_types::utils::recover_raw_transaction;
+use reth_rpc_eth_types::{utils::recover_raw_transaction, EthApiError};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Receipt of an EVM transaction, with the revert reason of a failed one as `revertReason`.
+pub type RpcReceipt = WithOtherFields<TransactionReceipt>;
+
+/// Largest number of blocks reported by `eth_feeHistory`, like geth.
+const MAX_FEE_HISTORY_BLOCKS: u64 = 1024;
+
+/// Largest number of blocks whose receipts `sidecar_getBlockReceiptsRange` returns at once.
+const MAX_RECEIPTS_BLOCKS: u64 = 100;
+
+/// EVM state of an engine account.
+///
+/// An account the engine does not know is empty: nonce 0, no code and zeroed storage. Its
+/// balance is still read, as native balances kept in a contract do not need an account.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountView {
+    /// Whether the engine knows the account.
+    pub exists: bool,
+    pub nonce: u64,
+    /// Native balance in wei.
+    pub balance: alloy_primitives::U256,
+}
+
+/// Gas prices estimated by the engine, reused for a short time.
+///
+/// Wallets poll the gas price before every transaction, so the estimate is fetched again
+/// only once it is older than the TTL. A zero TTL fetches it on every request.
+#[derive(Debug, Default)]
+struct GasPriceCache {
+    ttl: Duration,
+    estimate: Mutex<Option<(Instant, GasPriceEstimate)>>,
+}
+
+impl GasPriceCache {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            estimate: Mutex::new(None),
+        }
+    }
 
+    fn get(&self) -> Option<GasPriceEstimate> {
+        self.estimate
+            .lock()
+            .unwrap()
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < self.ttl)
+            .map(|(_, estimate)| estimate)
+    }
+
+    fn insert(&self, estimate: GasPriceEstimate) {
+        *self.estimate.lock().unwrap() = Some((Instant::now(), estimate));
+    }
+}
+
+#[derive(Clone)]
 pub struct EthApi<Adapter> {
     adapter: Adapter,
+    tracker: Arc<HeadTracker>,
+    indexes: Arc<Indexes>,
+    accounts: Arc<AccountCache>,
+    blocks: Arc<BlockCache>,
+    native_decimals: u8,
+    gas: GasConversion,
+    genesis: Option<RpcBlock<Ethereum>>,
+    max_raw_tx_bytes: Option<usize>,
+    max_calldata_bytes: Option<usize>,
+    min_gas_price: u128,
+    blob_base_fee: u128,
+    default_priority_fee: u128,
+    gas_prices: Arc<GasPriceCache>,
+    header: HeaderConstants,
+    chain_id: Option<u64>,
+    entry_func: String,
+    log_event: String,
+    create_event: String,
+    balance: BalanceConfig,
+    code_func: Option<String>,
+    storage_func: Option<String>,
+    reject_pending_calls: bool,
+    proof_unavailable: ProofUnavailable,
+    proof_unavailable_hint: Option<String>,
+    sync_lag_threshold: Option<u64>,
+    block_fetch_failure: BlockFetchFailure,
+    abort_reasons: AbortReasons,
 }
 
 impl<Adapter> EthApi<Adapter>
 where
     Adapter: EngineAdapter + Send + Sync + 'static,
 {
-    pub fn new(adapter: Adapter) -> Self {
-        Self { adapter }
+    pub fn new(adapter: Adapter, tracker: Arc<HeadTracker>, native_decimals: u8) -> Self {
+        Self {
+            adapter,
+            tracker,
+            indexes: Default::default(),
+            accounts: Default::default(),
+            blocks: Default::default(),
+            native_decimals,
+            gas: GasConversion::new(DEFAULT_EVM_GAS_PER_UNIT, native_decimals),
+            genesis: None,
+            max_raw_tx_bytes: None,
+            max_calldata_bytes: None,
+            min_gas_price: 0,
+            blob_base_fee: 1,
+            default_priority_fee: 0,
+            gas_prices: Default::default(),
+            header: Default::default(),
+            chain_id: None,
+            entry_func: "0x100::evm::transact".into(),
+            log_event: "0x100::evm::Log".into(),
+            create_event: "0x100::evm::ContractCreated".into(),
+            balance: BalanceConfig::CoinStore,
+            code_func: None,
+            storage_func: None,
+            reject_pending_calls: false,
+            proof_unavailable: ProofUnavailable::MethodNotFound,
+            proof_unavailable_hint: None,
+            sync_lag_threshold: None,
+            block_fetch_failure: BlockFetchFailure::FailFast,
+            abort_reasons: Default::default(),
+        }
+    }
+
+    /// Shares `indexes` with the other handlers instead of keeping them to this API.
+    pub fn with_indexes(mut self, indexes: Arc<Indexes>) -> Self {
+        self.indexes = indexes;
+        self
+    }
+
+    /// Serves the accounts kept in `accounts` without fetching them.
+    pub fn with_account_cache(mut self, accounts: Arc<AccountCache>) -> Self {
+        self.accounts = accounts;
+        self
+    }
+
+    /// Serves recently converted blocks from `blocks`, shared with the other handlers.
+    pub fn with_block_cache(mut self, blocks: Arc<BlockCache>) -> Self {
+        self.blocks = blocks;
+        self
+    }
+
+    /// Fails or skips the blocks that cannot be fetched when reading receipts over a range.
+    pub fn with_block_fetch_failure(mut self, policy: BlockFetchFailure) -> Self {
+        self.block_fetch_failure = policy;
+        self
+    }
+
+    /// Rejects raw transactions larger than `max_raw_tx_bytes` before decoding them.
+    pub fn with_max_raw_tx_bytes(mut self, max_raw_tx_bytes: usize) -> Self {
+        self.max_raw_tx_bytes = Some(max_raw_tx_bytes);
+        self
+    }
+
+    /// Rejects simulated calls whose calldata is larger than `max_calldata_bytes` before they
+    /// reach the engine.
+    pub fn with_max_calldata_bytes(mut self, max_calldata_bytes: usize) -> Self {
+        self.max_calldata_bytes = Some(max_calldata_bytes);
+        self
+    }
+
+    /// Reports `evm_gas_per_unit` EVM gas for every unit of engine gas, and converts the gas
+    /// limits and prices of submitted transactions back at the same rate.
+    pub fn with_evm_gas_per_unit(mut self, evm_gas_per_unit: u64) -> Self {
+        self.gas = GasConversion::new(evm_gas_per_unit, self.native_decimals);
+        self
+    }
+
+    /// Reports `blob_base_fee` wei as the blob base fee.
+    ///
+    /// The engine has no EIP-4844 blob market, so the fee is a nominal constant. Blob
+    /// transactions are still forwarded to the engine like any other typed transaction, which
+    /// does not charge this fee for them.
+    pub fn with_blob_base_fee(mut self, blob_base_fee: u128) -> Self {
+        self.blob_base_fee = blob_base_fee;
+        self
+    }
+
+    /// Lists the user transactions calling `entry_func` as the EVM transactions of a block.
+    pub fn with_entry_func(mut self, entry_func: String) -> Self {
+        self.entry_func = entry_func;
+        self
+    }
+
+    /// Reads the logs of receipts from `log_event` events and the addresses of created
+    /// contracts from `create_event` events.
+    pub fn with_events(mut self, log_event: String, create_event: String) -> Self {
+        self.log_event = log_event;
+        self.create_event = create_event;
+        self
+    }
+
+    /// Reports `priority_fee` wei as the priority fee of every block in `eth_feeHistory`.
+    ///
+    /// The engine has no dynamic fee market, so the fee cannot be derived from the blocks.
+    pub fn with_default_priority_fee(mut self, priority_fee: u128) -> Self {
+        self.default_priority_fee = priority_fee;
+        self
+    }
+
+    /// Serves `chain_id` as the EVM chain id instead of the engine chain id.
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// Renders the fields of every block header that the engine has no counterpart for
+    /// from `header`, whose base fee `eth_feeHistory` reports as well.
+    pub fn with_header_constants(mut self, header: HeaderConstants) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Reuses the gas prices estimated by the engine for `ttl`.
+    pub fn with_gas_price_ttl(mut self, ttl: Duration) -> Self {
+        self.gas_prices = Arc::new(GasPriceCache::new(ttl));
+        self
+    }
+
+    /// Never reports gas prices below `min_gas_price` wei.
+    pub fn with_min_gas_price(mut self, min_gas_price: u128) -> Self {
+        self.min_gas_price = min_gas_price;
+        self
+    }
+
+    /// Rejects calls at the `pending` block instead of simulating them against the latest
+    /// ledger, see [`EthApi::state_version`].
+    pub fn with_reject_pending_calls(mut self, reject_pending_calls: bool) -> Self {
+        self.reject_pending_calls = reject_pending_calls;
+        self
+    }
+
+    /// Fails `eth_getProof` with the error code of `response`, and `hint` as the error
+    /// data.
+    pub fn with_proof_unavailable(mut self, response: ProofUnavailable, hint: String) -> Self {
+        self.proof_unavailable = response;
+        self.proof_unavailable_hint = Some(hint);
+        self
+    }
+
+    /// Reports the sidecar as syncing while the ledger it serves lags the highest ledger
+    /// ever observed by more than `sync_lag_threshold` versions.
+    pub fn with_sync_lag_threshold(mut self, sync_lag_threshold: u64) -> Self {
+        self.sync_lag_threshold = Some(sync_lag_threshold);
+        self
+    }
+
+    /// Reports the calls aborted by the engine as reverts with the reasons of `abort_reasons`,
+    /// and the same reasons in the receipts of aborted transactions.
+    pub fn with_abort_reasons(mut self, abort_reasons: AbortReasons) -> Self {
+        self.abort_reasons = abort_reasons;
+        self
+    }
+
+    /// Reads native balances from `balance` instead of the native coin store.
+    pub fn with_balance(mut self, balance: BalanceConfig) -> Self {
+        self.balance = balance;
+        self
+    }
+
+    /// Reads the EVM code of accounts from the view function `code_func`, which takes the
+    /// mapped account address and returns the code as hex.
+    pub fn with_code_view(mut self, code_func: String) -> Self {
+        self.code_func = Some(code_func);
+        self
+    }
+
+    /// Reads the storage of accounts from the view function `storage_func`, which takes the
+    /// mapped account address and the slot as hex and returns the stored word as hex.
+    pub fn with_storage_view(mut self, storage_func: String) -> Self {
+        self.storage_func = Some(storage_func);
+        self
+    }
+
+    /// Serves `genesis` in place of the engine block at height 0.
+    pub fn with_genesis(mut self, genesis: RpcBlock<Ethereum>) -> Self {
+        self.genesis = Some(genesis);
+        self
+    }
+
+    async fn account(&self, address: &alloy_primitives::Address) -> Result<AccountView> {
+        let address = to_aptos_address(address);
+        let account = async {
+            match self.accounts.get(&address) {
+                Some(account) => Ok(Some(account)),
+                None => self.adapter.get_account(address).await,
+            }
+        };
+        let (account, balance) = futures::join!(account, self.native_balance(address));
+
+        Ok(AccountView {
+            balance: to_wei(balance?, self.native_decimals),
+            ..account_or_empty(account)?
+        })
+    }
+
+    /// Returns the native balance of `address`, in native coin units.
+    async fn native_balance(
+        &self,
+        address: move_core_types::account_address::AccountAddress,
+    ) -> Result<u64> {
+        match &self.balance {
+            BalanceConfig::CoinStore => Ok(self
+                .adapter
+                .get_account_balance(address, self.adapter.coin_type())
+                .await?),
+            BalanceConfig::ViewFunction(function) => self.view_balance(function, address).await,
+        }
+    }
+
+    /// Returns the EVM code deployed at `address` as read by the configured code view.
+    async fn view_code(
+        &self,
+        function: &str,
+        address: move_core_types::account_address::AccountAddress,
+    ) -> Result<alloy_primitives::Bytes> {
+        let values = self
+            .adapter
+            .view(
+                function,
+                vec![serde_json::Value::String(address.to_hex_literal())],
+            )
+            .await?;
+
+        values
+            .first()
+            .and_then(|value| value.as_str())
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| {
+                SidecarError::Conversion(format!("unexpected code from {}: {:?}", function, values))
+            })
+    }
+
+    async fn view_storage(
+        &self,
+        function: &str,
+        address: move_core_types::account_address::AccountAddress,
+        slot: alloy_primitives::B256,
+    ) -> Result<alloy_primitives::B256> {
+        let values = self
+            .adapter
+            .view(
+                function,
+                vec![
+                    serde_json::Value::String(address.to_hex_literal()),
+                    serde_json::Value::String(slot.to_string()),
+                ],
+            )
+            .await?;
+
+        values
+            .first()
+            .and_then(|value| value.as_str())
+            .and_then(|value| value.parse::<alloy_primitives::U256>().ok())
+            .map(|word| alloy_primitives::B256::from(word.to_be_bytes::<32>()))
+            .ok_or_else(|| {
+                SidecarError::Conversion(format!(
+                    "unexpected storage from {}: {:?}",
+                    function, values
+                ))
+            })
+    }
+
+    /// Returns the gas unit prices estimated by the engine.
+    async fn gas_price_estimate(&self) -> Result<GasPriceEstimate> {
+        match self.gas_prices.get() {
+            Some(estimate) => Ok(estimate),
+            None => {
+                let estimate = self.adapter.estimate_gas_price().await?;
+                self.gas_prices.insert(estimate);
+                Ok(estimate)
+            }
+        }
+    }
+
+    /// Returns the EVM gas price of an engine gas unit price, raised to the configured floor.
+    fn to_gas_price(&self, unit_price: u64) -> u128 {
+        self.gas.to_wei_per_gas(unit_price).max(self.min_gas_price)
+    }
+
+    fn ledger_info(&self) -> Result<aptos_api_types::IndexResponse> {
+        self.tracker.latest_or_fetch().map_err(|e| {
+            tracing::warn!("Ledger info unavailable: {}", e);
+            SidecarError::NotReady
+        })
+    }
+
+    /// Reads the balance of `address` from the view `function`, which returns it as a Move
+    /// `u64`.
+    async fn view_balance(
+        &self,
+        function: &str,
+        address: move_core_types::account_address::AccountAddress,
+    ) -> Result<u64> {
+        let values = self
+            .adapter
+            .view(
+                function,
+                vec![serde_json::Value::String(address.to_hex_literal())],
+            )
+            .await?;
+
+        values
+            .first()
+            .and_then(|value| value.as_str())
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| {
+                SidecarError::Conversion(format!(
+                    "unexpected balance from {}: {:?}",
+                    function, values
+                ))
+            })
+    }
+
+    /// Returns the EVM hash of the block at `height`.
+    async fn block_hash(&self, height: u64) -> Result<alloy_primitives::B256> {
+        if let (0, Some(genesis)) = (height, &self.genesis) {
+            return Ok(genesis.header.hash);
+        }
+
+        let block = self.adapter.get_block_by_height(height, false).await?;
+        let hash = convert::block_hash(&block.block_hash);
+        self.indexes.block_hashes.insert(height, hash);
+
+        Ok(hash)
+    }
+
+    /// Builds the receipts of the EVM transactions of `block`.
+    ///
+    /// A failed transaction only reports its VM status, so its receipt carries the revert
+    /// reason the abort is mapped to by the configured abort reasons, or the VM status
+    /// itself, like the revert reason of `eth_call`.
+    fn receipts(&self, block: &aptos_api_types::Block) -> Result<Vec<RpcReceipt>> {
+        self.indexes.insert_transactions(block, &self.entry_func);
+        let receipts = convert::to_block_receipts(
+            block,
+            None,
+            &self.log_event,
+            &self.create_event,
+            &self.entry_func,
+            &self.gas,
+        )?;
+
+        Ok(convert::evm_transactions(block, &self.entry_func)
+            .zip(receipts)
+            .map(|(tx, receipt)| {
+                let mut receipt = WithOtherFields::new(receipt);
+                if !tx.info.success {
+                    let reason = self
+                        .abort_reasons
+                        .reason(&tx.info.vm_status)
+                        .unwrap_or_else(|| tx.info.vm_status.clone());
+                    receipt.other.insert("revertReason".into(), reason.into());
+                }
+                receipt
+            })
+            .collect())
+    }
+
+    /// Converts a committed transaction, locating it in its block.
+    async fn committed_transaction(
+        &self,
+        tx: &aptos_api_types::UserTransaction,
+    ) -> Result<RpcTransaction> {
+        let block = self
+            .adapter
+            .get_block_by_version(tx.info.version.0, true)
+            .await?;
+        self.indexes
+            .block_hashes
+            .insert(block.block_height.0, convert::block_hash(&block.block_hash));
+
+        convert::to_rpc_committed_transaction(tx, &block, &self.entry_func)
+    }
+
+    /// Returns the EVM transaction at `index` in the block at `height`, or `None` if the
+    /// block has fewer EVM transactions.
+    async fn transaction_at(&self, height: u64, index: Index) -> Result<Option<RpcTransaction>> {
+        if let (0, Some(_)) = (height, &self.genesis) {
+            return Ok(None);
+        }
+
+        let block = self.adapter.get_block_by_height(height, true).await?;
+        let block_hash = convert::block_hash(&block.block_hash);
+        self.indexes.block_hashes.insert(height, block_hash);
+        self.indexes.insert_transactions(&block, &self.entry_func);
+
+        let Some(tx) = convert::evm_transactions(&block, &self.entry_func).nth(index.0) else {
+            return Ok(None);
+        };
+        let info = TransactionInfo {
+            hash: Some(convert::evm_transaction_hash(tx)?),
+            index: Some(index.0 as u64),
+            block_hash: Some(block_hash),
+            block_number: Some(height),
+            base_fee: None,
+        };
+
+        convert::to_rpc_transaction(tx, info).map(Some)
+    }
+
+    /// Resolves `number` to a block height.
+    ///
+    /// `earliest` is the oldest block the engine still serves, which is above 0 once the
+    /// node has pruned its history.
+    fn block_height(&self, number: BlockNumberOrTag) -> Result<u64> {
+        match number {
+            BlockNumberOrTag::Number(height) => Ok(height),
+            BlockNumberOrTag::Earliest => Ok(self.ledger_info()?.oldest_block_height.0),
+            _ => Ok(self.ledger_info()?.block_height.0),
+        }
+    }
+
+    /// Returns the ledger version to simulate calls against at `block_id`, or `None` for the
+    /// latest ledger.
+    ///
+    /// The engine can only simulate against committed state, so the transactions the sidecar
+    /// submitted but that are not committed yet are never visible to a call. A call at the
+    /// `pending` block therefore runs against the latest ledger, unless such calls are
+    /// rejected with [`EthApi::with_reject_pending_calls`].
+    async fn state_version(&self, block_id: Option<BlockId>) -> Result<Option<u64>> {
+        match block_id.unwrap_or_default() {
+            BlockId::Number(BlockNumberOrTag::Pending) if self.reject_pending_calls => {
+                Err(SidecarError::Unsupported("calls against pending state"))
+            }
+            BlockId::Number(
+                BlockNumberOrTag::Latest
+                | BlockNumberOrTag::Pending
+                | BlockNumberOrTag::Safe
+                | BlockNumberOrTag::Finalized,
+            ) => Ok(None),
+            BlockId::Number(number) => {
+                let height = self.block_height(number)?;
+                let block = self.adapter.get_block_by_height(height, false).await?;
+                Ok(Some(block.last_version.0))
+            }
+            BlockId::Hash(_) => Err(SidecarError::Unsupported("calls at a block hash")),
+        }
+    }
+
+    /// Simulates `request` against the ledger at `version`.
+    ///
+    /// The calldata is read from either `input` or `data`, see [`calldata`].
+    async fn simulate(
+        &self,
+        request: TransactionRequest,
+        version: Option<u64>,
+    ) -> Result<alloy_primitives::Bytes> {
+        let data = calldata(request.input)?;
+        if let Some(max_calldata_bytes) = self.max_calldata_bytes {
+            if data.len() > max_calldata_bytes {
+                return Err(SidecarError::Validation(format!(
+                    "calldata too large: {} bytes exceeds the limit of {}",
+                    data.len(),
+                    max_calldata_bytes
+                )));
+            }
+        }
+
+        let call = EvmCall {
+            from: request.from.unwrap_or_default(),
+            to: request.to.and_then(|to| to.to().copied()),
+            data: data.to_vec(),
+            value: request.value.unwrap_or_default(),
+        };
+
+        let output = self
+            .adapter
+            .call(call, version)
+            .await
+            .map_err(|e| self.abort_reasons.revert(e.into()))?;
+
+        Ok(output.into())
+    }
+
+    /// Validates and submits a raw transaction, whose engine transaction expires at
+    /// `expiration` if given, see [`EngineAdapter::submit_transaction`].
+    async fn submit_raw_transaction(
+        &self,
+        bytes: alloy_primitives::Bytes,
+        expiration: Option<u64>,
+    ) -> RpcResult<alloy_primitives::B256> {
+        if bytes.is_empty() {
+            return Err(EthApiError::EmptyRawTransactionData.into());
+        }
+        if let Some(max_raw_tx_bytes) = self.max_raw_tx_bytes {
+            if bytes.len() > max_raw_tx_bytes {
+                return Err(SidecarError::Validation(format!(
+                    "transaction too large: {} bytes exceeds the limit of {}",
+                    bytes.len(),
+                    max_raw_tx_bytes
+                ))
+                .into());
+            }
+        }
+
+        let recovered: Recovered<TransactionSigned> = recover_raw_transaction(&bytes)?;
+        let signer = recovered.signer();
+
+        // The engine would reject a transaction signed for another chain with an opaque
+        // authentication error, so reject it here with the expected chain id instead.
+        let chain_id = match self.chain_id {
+            Some(chain_id) => chain_id,
+            None => u64::from(self.ledger_info()?.chain_id),
+        };
+        if let Some(tx_chain_id) = recovered.chain_id() {
+            if tx_chain_id != chain_id {
+                return Err(SidecarError::Validation(format!(
+                    "invalid chain id: expected {}, got {}",
+                    chain_id, tx_chain_id
+                ))
+                .into());
+            }
+        }
+
+        // The full EIP-2718 envelope is forwarded, so typed-transaction fields such as the
+        // EIP-2930 access list reach the engine untouched.
+        let sender = to_aptos_address(&signer);
+        let gas_price = recovered.effective_gas_price(None);
+        let gas = EngineGas {
+            max_gas_amount: self.gas.to_engine_gas(recovered.gas_limit()),
+            gas_unit_price: self.gas.to_unit_price(gas_price),
+        };
+        // The nonce is submitted as the engine sequence number as is, without looking the
+        // account up, so the first transaction of a sender whose engine account does not
+        // exist yet goes through with sequence number 0, which `eth_getTransactionCount`
+        // also reports for it. Creating the account is left to the engine, see
+        // `sponsor_account_creation`.
+        //
+        // A transaction reusing the nonce of a pending one replaces it, which the engine
+        // only accepts for a higher gas price.
+        let nonce = recovered.nonce();
+        if let Some(existing) = self.indexes.pending.get(&signer, nonce) {
+            if gas_price <= existing.gas_price {
+                return Err(SidecarError::ReplacementUnderpriced.into());
+            }
+        }
+
+        let pending = self
+            .adapter
+            .submit_transaction(sender, bytes.0.to_vec(), nonce, gas, expiration)
+            .await
+            .map_err(SidecarError::from)
+            .inspect_err(|e| {
+                if matches!(e, SidecarError::NonceTooLow | SidecarError::NonceTooHigh) {
+                    self.indexes.pending.clear(&signer);
+                }
+            })?;
+
+        tracing::debug!("Submitted transaction: {:?}", pending);
+
+        // The cached sequence number of the sender falls behind once the transaction is
+        // committed, so it is looked up on the engine until the cache is refreshed.
+        self.accounts.invalidate(&to_aptos_address(&signer));
+
+        self.indexes
+            .transaction_hashes
+            .insert(*recovered.hash(), pending.hash);
+
+        self.indexes.pending.insert(
+            signer,
+            nonce,
+            PendingTransaction {
+                hash: *recovered.hash(),
+                gas_price,
+            },
+        );
+
+        Ok(*recovered.hash())
     }
 }
 
 #[async_trait::async_trait]
-impl<Adapter> EthApiServer<(), RpcBlock<Ethereum>, (), ()> for EthApi<Adapter>
+impl<Adapter> EthApiServer<RpcTransaction, RpcBlock<Ethereum>, RpcReceipt, ()> for EthApi<Adapter>
 where
     Adapter: EngineAdapter + Send + Sync + 'static,
 {
@@ -60,7 +746,31 @@ where
 
     /// Returns an object with data about the sync status or false.
     fn syncing(&self) -> RpcResult<SyncStatus> {
-        unimplemented!();
+        tracing::debug!("syncing rpc request received");
+
+        let ledger_info = self.ledger_info()?;
+        let (Some(threshold), Some(highest)) = (self.sync_lag_threshold, self.tracker.highest())
+        else {
+            return Ok(SyncStatus::None);
+        };
+        if highest
+            .ledger_version
+            .0
+            .saturating_sub(ledger_info.ledger_version.0)
+            <= threshold
+        {
+            return Ok(SyncStatus::None);
+        }
+
+        let current_block = alloy_primitives::U256::from(ledger_info.block_height.0);
+        Ok(SyncStatus::Info(Box::new(SyncInfo {
+            starting_block: current_block,
+            current_block,
+            highest_block: alloy_primitives::U256::from(highest.block_height.0),
+            warp_chunks_amount: None,
+            warp_chunks_processed: None,
+            stages: None,
+        })))
     }
 
     /// Returns the client coinbase address.
@@ -75,18 +785,25 @@ where
 
     /// Returns the number of most recent block.
     fn block_number(&self) -> RpcResult<alloy_primitives::U256> {
-        unimplemented!();
+        tracing::debug!("block_number rpc request received");
+
+        let ledger_info = self.ledger_info()?;
+
+        Ok(alloy_primitives::U256::from(ledger_info.block_height.0))
     }
 
     /// Returns the chain ID of the current network.
     async fn chain_id(&self) -> RpcResult<Option<alloy_primitives::U64>> {
         tracing::debug!("chain_id rpc request received");
 
+        if let Some(chain_id) = self.chain_id {
+            return Ok(Some(alloy_primitives::U64::from(chain_id)));
+        }
         let ledger_info = self
             .adapter
             .get_ledger_info()
             .await
-            .map_err(|e| internal_error(e.to_string()))?;
+            .map_err(SidecarError::from)?;
 
         Ok(Some(alloy_primitives::U64::from(ledger_info.chain_id)))
     }
@@ -106,7 +823,60 @@ where
         number: BlockNumberOrTag,
         full: bool,
     ) -> RpcResult<Option<RpcBlock<Ethereum>>> {
-        unimplemented!();
+        tracing::debug!("block_by_number rpc request received");
+
+        let height = self.block_height(number)?;
+        if let (0, Some(genesis)) = (height, &self.genesis) {
+            return Ok(Some(genesis.clone()));
+        }
+        if let Some(block) = self.blocks.block(height, full) {
+            return Ok(Some(block));
+        }
+
+        // The head is read before the block is fetched and converted rather than failing the
+        // request once the work is done; a head older than the block only keeps it uncached.
+        let ledger_info = self.ledger_info()?;
+
+        // Blocks listing hashes only are fetched with their transactions as well, as the EVM
+        // hashes and the gas used of the header are only known from them; only decoding the
+        // transactions is skipped.
+        let block = self
+            .adapter
+            .get_block_by_height(height, true)
+            .await
+            .map_err(SidecarError::from)?;
+        self.indexes
+            .block_hashes
+            .insert(height, convert::block_hash(&block.block_hash));
+        self.indexes.insert_transactions(&block, &self.entry_func);
+        // The parent of the oldest block of a pruned node cannot be fetched anymore.
+        let parent_hash = match height {
+            0 => alloy_primitives::B256::ZERO,
+            _ if height <= ledger_info.oldest_block_height.0 => alloy_primitives::B256::ZERO,
+            _ => self.block_hash(height - 1).await?,
+        };
+
+        let block = if full {
+            convert::to_rpc_full_block(
+                &block,
+                parent_hash,
+                &self.header,
+                &self.entry_func,
+                &self.gas,
+            )?
+        } else {
+            convert::to_rpc_block(
+                &block,
+                parent_hash,
+                &self.header,
+                &self.entry_func,
+                &self.gas,
+            )
+        };
+        self.blocks
+            .insert_block(ledger_info.block_height.0, full, block.clone());
+
+        Ok(Some(block))
     }
 
     /// Returns the number of transactions in a block from a block matching the given block hash.
@@ -142,8 +912,30 @@ where
     }
 
     /// Returns all transaction receipts for a given block.
-    async fn block_receipts(&self, block_id: BlockId) -> RpcResult<Option<Vec<()>>> {
-        unimplemented!();
+    async fn block_receipts(&self, block_id: BlockId) -> RpcResult<Option<Vec<RpcReceipt>>> {
+        tracing::debug!(
+            "block_receipts rpc request received: block_id={:?}",
+            block_id
+        );
+
+        let BlockId::Number(number) = block_id else {
+            return Err(SidecarError::Unsupported("eth_getBlockReceipts by block hash").into());
+        };
+        let height = self.block_height(number)?;
+        if let (0, Some(_)) = (height, &self.genesis) {
+            return Ok(Some(vec![]));
+        }
+
+        let block = self
+            .adapter
+            .get_block_by_height(height, true)
+            .await
+            .map_err(SidecarError::from)?;
+        self.indexes
+            .block_hashes
+            .insert(height, convert::block_hash(&block.block_hash));
+
+        Ok(Some(self.receipts(&block)?))
     }
 
     /// Returns an uncle block of the given block and index.
@@ -175,8 +967,33 @@ where
     }
 
     /// Returns the information about a transaction requested by transaction hash.
-    async fn transaction_by_hash(&self, hash: alloy_primitives::B256) -> RpcResult<Option<()>> {
-        unimplemented!();
+    ///
+    /// A transaction is known by its EVM hash once it has been submitted through the sidecar
+    /// or its block has been served, see [`Indexes::insert_transactions`].
+    async fn transaction_by_hash(
+        &self,
+        hash: alloy_primitives::B256,
+    ) -> RpcResult<Option<RpcTransaction>> {
+        tracing::debug!("transaction_by_hash rpc request received: hash={}", hash);
+
+        let Some(engine_hash) = self.indexes.transaction_hashes.get(&hash) else {
+            return Ok(None);
+        };
+        let transaction = self
+            .adapter
+            .get_transaction_by_hash(engine_hash)
+            .await
+            .map_err(SidecarError::from)?;
+
+        match transaction {
+            Some(aptos_api_types::Transaction::PendingTransaction(pending)) => {
+                Ok(Some(convert::to_rpc_pending_transaction(&pending)?))
+            }
+            Some(aptos_api_types::Transaction::UserTransaction(user)) => {
+                Ok(Some(self.committed_transaction(&user).await?))
+            }
+            _ => Ok(None),
+        }
     }
 
     /// Returns information about a raw transaction by block hash and transaction index position.
@@ -193,8 +1010,14 @@ where
         &self,
         hash: alloy_primitives::B256,
         index: Index,
-    ) -> RpcResult<Option<()>> {
-        unimplemented!();
+    ) -> RpcResult<Option<RpcTransaction>> {
+        tracing::debug!("transaction_by_block_hash_and_index rpc request received");
+
+        let Some(height) = self.indexes.block_hashes.height(&hash) else {
+            return Ok(None);
+        };
+
+        Ok(self.transaction_at(height, index).await?)
     }
 
     /// Returns information about a raw transaction by block number and transaction index
@@ -212,8 +1035,12 @@ where
         &self,
         number: BlockNumberOrTag,
         index: Index,
-    ) -> RpcResult<Option<()>> {
-        unimplemented!();
+    ) -> RpcResult<Option<RpcTransaction>> {
+        tracing::debug!("transaction_by_block_number_and_index rpc request received");
+
+        let height = self.block_height(number)?;
+
+        Ok(self.transaction_at(height, index).await?)
     }
 
     /// Returns information about a transaction by sender and nonce.
@@ -221,13 +1048,46 @@ where
         &self,
         address: alloy_primitives::Address,
         nonce: alloy_primitives::U64,
-    ) -> RpcResult<Option<()>> {
+    ) -> RpcResult<Option<RpcTransaction>> {
         unimplemented!();
     }
 
     /// Returns the receipt of a transaction by transaction hash.
-    async fn transaction_receipt(&self, hash: alloy_primitives::B256) -> RpcResult<Option<()>> {
-        unimplemented!();
+    ///
+    /// The receipt is built along with the receipts of the EVM transactions before it in its
+    /// block, which its cumulative gas used and log indices depend on. Pending transactions
+    /// have no receipt.
+    async fn transaction_receipt(
+        &self,
+        hash: alloy_primitives::B256,
+    ) -> RpcResult<Option<RpcReceipt>> {
+        tracing::debug!("transaction_receipt rpc request received: hash={}", hash);
+
+        let Some(engine_hash) = self.indexes.transaction_hashes.get(&hash) else {
+            return Ok(None);
+        };
+        let transaction = self
+            .adapter
+            .get_transaction_by_hash(engine_hash)
+            .await
+            .map_err(SidecarError::from)?;
+        let Some(aptos_api_types::Transaction::UserTransaction(tx)) = transaction else {
+            return Ok(None);
+        };
+
+        let block = self
+            .adapter
+            .get_block_by_version(tx.info.version.0, true)
+            .await
+            .map_err(SidecarError::from)?;
+        self.indexes
+            .block_hashes
+            .insert(block.block_height.0, convert::block_hash(&block.block_hash));
+        let receipts = self.receipts(&block)?;
+
+        Ok(receipts
+            .into_iter()
+            .find(|receipt| receipt.transaction_hash == hash))
     }
 
     /// Returns the balance of the account of given address.
@@ -242,14 +1102,7 @@ where
             block_number
         );
 
-        let aptos_address = to_aptos_address(&address);
-        let balance = self
-            .adapter
-            .get_account_balance(aptos_address)
-            .await
-            .map_err(|e| internal_error(e.to_string()))?;
-
-        Ok(alloy_primitives::U256::from(balance))
+        Ok(self.account(&address).await?.balance)
     }
 
     /// Returns the value from a storage position at a given address
@@ -259,7 +1112,24 @@ where
         index: JsonStorageKey,
         block_number: Option<BlockId>,
     ) -> RpcResult<alloy_primitives::B256> {
-        unimplemented!();
+        tracing::debug!(
+            "storage_at rpc request received: address={}, block_number={:?}",
+            address,
+            block_number
+        );
+
+        // Without a storage view, engine accounts are taken to hold empty storage, like they
+        // are taken to hold no code.
+        let Some(function) = &self.storage_func else {
+            return Ok(alloy_primitives::B256::ZERO);
+        };
+        if !self.account(&address).await?.exists {
+            return Ok(alloy_primitives::B256::ZERO);
+        }
+
+        Ok(self
+            .view_storage(function, to_aptos_address(&address), index.as_b256())
+            .await?)
     }
 
     /// Returns the number of transactions sent from an address at given block number.
@@ -268,7 +1138,21 @@ where
         address: alloy_primitives::Address,
         block_number: Option<BlockId>,
     ) -> RpcResult<alloy_primitives::U256> {
-        unimplemented!();
+        tracing::debug!(
+            "transaction_count rpc request received: address={}, block_number={:?}",
+            address,
+            block_number
+        );
+
+        let sequence_number = self.account(&address).await?.nonce;
+        let nonce = match block_number {
+            Some(BlockId::Number(BlockNumberOrTag::Pending)) => {
+                sequence_number + self.indexes.pending.count(&address, sequence_number)
+            }
+            _ => sequence_number,
+        };
+
+        Ok(alloy_primitives::U256::from(nonce))
     }
 
     /// Returns code at a given address at given block number.
@@ -277,7 +1161,22 @@ where
         address: alloy_primitives::Address,
         block_number: Option<BlockId>,
     ) -> RpcResult<alloy_primitives::Bytes> {
-        unimplemented!();
+        tracing::debug!(
+            "get_code rpc request received: address={}, block_number={:?}",
+            address,
+            block_number
+        );
+
+        // Without a code view, engine accounts are taken to hold no EVM code, as is the
+        // case for the externally owned accounts the engine creates for EVM senders.
+        let Some(function) = &self.code_func else {
+            return Ok(alloy_primitives::Bytes::new());
+        };
+        if !self.account(&address).await?.exists {
+            return Ok(alloy_primitives::Bytes::new());
+        }
+
+        Ok(self.view_code(function, to_aptos_address(&address)).await?)
     }
 
     /// Returns the block's header at given number.
@@ -292,12 +1191,103 @@ where
 
     /// `eth_simulateV1` executes an arbitrary number of transactions on top of the requested state.
     /// The transactions are packed into individual blocks. Overrides can be provided.
+    ///
+    /// Like `eth_callMany`, every call is simulated on its own against the state of the
+    /// requested block, so neither the calls of a block nor the blocks themselves see each
+    /// other's state changes. The simulated blocks are empty and chained on top of the
+    /// requested block, one second apart. The engine reports neither logs nor gas usage of a
+    /// call, so both are left empty. A call that reverts fails on its own with the revert
+    /// data as its output, while any other engine error fails the whole request. Overrides,
+    /// transfer tracing and validation cannot be honored and are rejected as unsupported.
     async fn simulate_v1(
         &self,
         opts: SimulatePayload,
         block_number: Option<BlockId>,
     ) -> RpcResult<Vec<SimulatedBlock<RpcBlock<Ethereum>>>> {
-        unimplemented!();
+        tracing::debug!(
+            "simulate_v1 rpc request received: blocks={}, block_number={:?}",
+            opts.block_state_calls.len(),
+            block_number
+        );
+
+        if opts.trace_transfers {
+            return Err(SidecarError::Unsupported("eth_simulateV1 transfer tracing").into());
+        }
+        if opts.validation {
+            return Err(SidecarError::Unsupported("eth_simulateV1 validation").into());
+        }
+        if opts
+            .block_state_calls
+            .iter()
+            .any(|block| block.block_overrides.is_some() || block.state_overrides.is_some())
+        {
+            return Err(SidecarError::Unsupported("eth_simulateV1 overrides").into());
+        }
+        if opts.block_state_calls.len() as u64 > MAX_SIMULATE_BLOCKS {
+            return Err(SidecarError::Validation(format!(
+                "too many blocks: {} exceeds the limit of {}",
+                opts.block_state_calls.len(),
+                MAX_SIMULATE_BLOCKS
+            ))
+            .into());
+        }
+
+        let version = self.state_version(block_number).await?;
+        let BlockId::Number(number) = block_number.unwrap_or_default() else {
+            return Err(SidecarError::Unsupported("calls at a block hash").into());
+        };
+        let height = self.block_height(number)?;
+        let base = self
+            .adapter
+            .get_block_by_height(height, false)
+            .await
+            .map_err(SidecarError::from)?;
+        // Only the hash, number and timestamp of the parent are carried over.
+        let mut parent = convert::to_rpc_header(
+            &base,
+            alloy_primitives::B256::ZERO,
+            &self.header,
+            &self.entry_func,
+            &self.gas,
+        );
+
+        let mut blocks = Vec::with_capacity(opts.block_state_calls.len());
+        for sim_block in opts.block_state_calls {
+            let mut calls = Vec::with_capacity(sim_block.calls.len());
+            for request in sim_block.calls {
+                let result = match self.simulate(request, version).await {
+                    Ok(return_data) => SimCallResult {
+                        return_data,
+                        logs: vec![],
+                        gas_used: 0,
+                        status: true,
+                        error: None,
+                    },
+                    Err(SidecarError::Reverted(reason)) => {
+                        let return_data = revert_data(&reason);
+                        let e = SidecarError::Reverted(reason);
+                        SimCallResult {
+                            return_data,
+                            logs: vec![],
+                            gas_used: 0,
+                            status: false,
+                            error: Some(SimulateError {
+                                code: e.code(),
+                                message: e.to_string(),
+                            }),
+                        }
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                calls.push(result);
+            }
+
+            let inner = convert::simulated_block(&parent);
+            parent = inner.header.clone();
+            blocks.push(SimulatedBlock { inner, calls });
+        }
+
+        Ok(blocks)
     }
 
     /// Executes a new message call immediately without creating a transaction on the block chain.
@@ -308,18 +1298,65 @@ where
         state_overrides: Option<StateOverride>,
         block_overrides: Option<Box<BlockOverrides>>,
     ) -> RpcResult<alloy_primitives::Bytes> {
-        unimplemented!();
+        tracing::debug!(
+            "call rpc request received: request={:?}, block_number={:?}",
+            request,
+            block_number
+        );
+
+        if state_overrides.is_some() || block_overrides.is_some() {
+            return Err(SidecarError::Unsupported("eth_call overrides").into());
+        }
+
+        let version = self.state_version(block_number).await?;
+        Ok(self.simulate(request, version).await?)
     }
 
     /// Simulate arbitrary number of transactions at an arbitrary blockchain index, with the
     /// optionality of state overrides
+    ///
+    /// The engine simulates every call on its own against the state of the requested block,
+    /// so state changes of a call do not carry over to the following calls of the bundle.
     async fn call_many(
         &self,
         bundle: Bundle,
         state_context: Option<StateContext>,
         state_override: Option<StateOverride>,
     ) -> RpcResult<Vec<EthCallResponse>> {
-        unimplemented!();
+        tracing::debug!(
+            "call_many rpc request received: bundle={:?}, state_context={:?}",
+            bundle,
+            state_context
+        );
+
+        if state_override.is_some() || bundle.block_override.is_some() {
+            return Err(SidecarError::Unsupported("eth_callMany overrides").into());
+        }
+        let state_context = state_context.unwrap_or_default();
+        if matches!(
+            state_context.transaction_index,
+            Some(TransactionIndex::Index(_))
+        ) {
+            return Err(SidecarError::Unsupported("eth_callMany at a transaction index").into());
+        }
+
+        let version = self.state_version(state_context.block_number).await?;
+        let mut responses = Vec::with_capacity(bundle.transactions.len());
+        for request in bundle.transactions {
+            let response = match self.simulate(request, version).await {
+                Ok(value) => EthCallResponse {
+                    value: Some(value),
+                    error: None,
+                },
+                Err(e) => EthCallResponse {
+                    value: None,
+                    error: Some(e.to_string()),
+                },
+            };
+            responses.push(response);
+        }
+
+        Ok(responses)
     }
 
     /// Generates an access list for a transaction.
@@ -357,7 +1394,10 @@ where
 
     /// Returns the current price per gas in wei.
     async fn gas_price(&self) -> RpcResult<alloy_primitives::U256> {
-        unimplemented!();
+        tracing::debug!("gas_price rpc request received");
+
+        let normal = self.gas_price_estimate().await?.normal;
+        Ok(alloy_primitives::U256::from(self.to_gas_price(normal)))
     }
 
     /// Returns the account details by specifying an address and a block number/tag
@@ -371,12 +1411,27 @@ where
 
     /// Introduced in EIP-1559, returns suggestion for the priority for dynamic fee transactions.
     async fn max_priority_fee_per_gas(&self) -> RpcResult<alloy_primitives::U256> {
-        unimplemented!();
+        tracing::debug!("max_priority_fee_per_gas rpc request received");
+
+        // The price of prioritized transactions, less the base fee reported in headers, raised
+        // to the floor only once the base fee is taken off so that the tip never drops below it.
+        let prioritized = self
+            .gas
+            .to_wei_per_gas(self.gas_price_estimate().await?.prioritized);
+        Ok(alloy_primitives::U256::from(
+            prioritized
+                .saturating_sub(u128::from(self.header.base_fee))
+                .max(self.min_gas_price),
+        ))
     }
 
     /// Introduced in EIP-4844, returns the current blob base fee in wei.
+    ///
+    /// The fee is nominal, see [`EthApi::with_blob_base_fee`].
     async fn blob_base_fee(&self) -> RpcResult<alloy_primitives::U256> {
-        unimplemented!();
+        tracing::debug!("blob_base_fee rpc request received");
+
+        Ok(alloy_primitives::U256::from(self.blob_base_fee))
     }
 
     /// Returns the Transaction fee history
@@ -386,13 +1441,58 @@ where
     /// Returns transaction base fee per gas and effective priority fee per gas for the
     /// requested/supported block range. The returned Fee history for the returned block range
     /// can be a subsection of the requested range if not all blocks are available.
+    ///
+    /// Every block reports the same fees, see [`EthApi::with_header_constants`] and
+    /// [`EthApi::with_default_priority_fee`], and a gas used
+    /// ratio of zero since the engine does not meter blocks in EVM gas.
     async fn fee_history(
         &self,
         block_count: alloy_primitives::U64,
         newest_block: BlockNumberOrTag,
         reward_percentiles: Option<Vec<f64>>,
     ) -> RpcResult<FeeHistory> {
-        unimplemented!();
+        tracing::debug!(
+            "fee_history rpc request received: block_count={}, newest_block={:?}",
+            block_count,
+            newest_block
+        );
+
+        if let Some(percentiles) = &reward_percentiles {
+            let ascending = percentiles.windows(2).all(|pair| pair[0] <= pair[1]);
+            if !ascending || percentiles.iter().any(|p| !(0.0..=100.0).contains(p)) {
+                return Err(SidecarError::Validation(format!(
+                    "invalid reward percentiles: {:?}",
+                    percentiles
+                ))
+                .into());
+            }
+        }
+
+        let ledger_info = self.ledger_info()?;
+        let newest = self
+            .block_height(newest_block)?
+            .min(ledger_info.block_height.0);
+        let oldest_available = ledger_info.oldest_block_height.0;
+        if block_count.is_zero() || newest < oldest_available {
+            return Ok(FeeHistory::default());
+        }
+        let count = block_count
+            .saturating_to::<u64>()
+            .min(MAX_FEE_HISTORY_BLOCKS)
+            .min(newest - oldest_available + 1);
+        let blocks = count as usize;
+
+        Ok(FeeHistory {
+            // The fees of the block after the newest one are included.
+            base_fee_per_gas: vec![self.header.base_fee.into(); blocks + 1],
+            gas_used_ratio: vec![0.0; blocks],
+            base_fee_per_blob_gas: vec![self.blob_base_fee; blocks + 1],
+            blob_gas_used_ratio: vec![0.0; blocks],
+            oldest_block: newest + 1 - count,
+            reward: reward_percentiles.map(|percentiles| {
+                vec![vec![self.default_priority_fee; percentiles.len()]; blocks]
+            }),
+        })
     }
 
     /// Returns whether the client is actively mining new blocks.
@@ -450,19 +1550,7 @@ where
     ) -> RpcResult<alloy_primitives::B256> {
         tracing::debug!("send_raw_transaction rpc request received: bytes={}", bytes);
 
-        let recovered: Recovered<TransactionSigned> = recover_raw_transaction(&bytes)?;
-        let signer = recovered.signer();
-
-        let sender = to_aptos_address(&signer);
-        let pending = self
-            .adapter
-            .submit_transaction(sender, bytes.0.to_vec())
-            .await
-            .map_err(|e| internal_error(e.to_string()))?;
-
-        tracing::debug!("Submitted transaction: {:?}", pending);
-
-        Ok(*recovered.hash())
+        self.submit_raw_transaction(bytes, None).await
     }
 
     /// Returns an Ethereum specific signature with: sign(keccak256("\x19Ethereum Signed Message:\n"
@@ -501,10 +1589,67 @@ where
         keys: Vec<JsonStorageKey>,
         block_number: Option<BlockId>,
     ) -> RpcResult<EIP1186AccountProofResponse> {
-        unimplemented!();
+        tracing::debug!("get_proof rpc request received");
+
+        // The engine serves no state proofs, so clients that optionally ask for them are
+        // told so instead of waiting on a response that never comes.
+        let code = match self.proof_unavailable {
+            ProofUnavailable::MethodNotFound => METHOD_NOT_FOUND_CODE,
+            ProofUnavailable::MethodNotSupported => METHOD_NOT_SUPPORTED_CODE,
+        };
+        Err(ErrorObjectOwned::owned(
+            code,
+            "proofs unsupported",
+            self.proof_unavailable_hint.clone(),
+        ))
+    }
+}
+
+/// Converts the result of an engine account lookup into the EVM view of the account.
+///
+/// An account the engine does not know is empty rather than an error, as every address
+/// exists in the EVM. Other lookup errors are propagated.
+pub fn account_or_empty(
+    result: anyhow::Result<Option<aptos_rest_client::types::Account>>,
+) -> Result<AccountView> {
+    Ok(match result? {
+        Some(account) => AccountView {
+            exists: true,
+            nonce: account.sequence_number,
+        },
+        None => AccountView::default(),
+    })
+}
+
+/// Scales an amount of the native coin to the 18 decimals EVM clients expect.
+///
+/// Only amounts read from the engine in native coin units, such as balances, are scaled.
+/// Transaction values are read from the signed EVM transaction and are already in wei.
+/// Coins with more than 18 decimals are scaled down, dropping what is below one wei.
+pub fn to_wei(amount: u64, decimals: u8) -> alloy_primitives::U256 {
+    let amount = alloy_primitives::U256::from(amount);
+    let ten = alloy_primitives::U256::from(10);
+
+    if decimals <= 18 {
+        amount * ten.pow(alloy_primitives::U256::from(18 - decimals))
+    } else {
+        amount / ten.pow(alloy_primitives::U256::from(decimals - 18))
     }
 }
 
+/// Returns the calldata of a transaction request, which clients pass as either `input` or
+/// `data`.
+///
+/// Like geth, `input` is used when both are given, and the request is rejected if they
+/// differ.
+pub fn calldata(input: TransactionInput) -> Result<alloy_primitives::Bytes> {
+    let calldata = input
+        .try_into_unique_input()
+        .map_err(|e| SidecarError::Validation(e.to_string()))?;
+
+    Ok(calldata.unwrap_or_default())
+}
+
 pub fn to_aptos_address(
     address: &alloy_primitives::Address,
 ) -> move_core_types::account_address::AccountAddress {
@@ -514,16 +1659,166 @@ pub fn to_aptos_address(
     move_core_types::account_address::AccountAddress::new(bytes)
 }
 
-pub fn internal_error(message: impl Into<String>) -> ErrorObjectOwned {
-    ErrorObjectOwned::owned(INTERNAL_ERROR_CODE, message, None::<()>)
-}
-
-#[cfg(test)]
-pub mod tests {
-    use super::to_aptos_address;
-    use alloy_primitives::hex::FromHex;
-
-    #[test]
+#[async_trait::async_trait]
+impl<Adapter> SidecarReceiptsApiServer for EthApi<Adapter>
+where
+    Adapter: EngineAdapter + Send + Sync + 'static,
+{
+    /// Handler for `sidecar_getBlockReceiptsRange`
+    async fn block_receipts_range(
+        &self,
+        from_block: BlockNumberOrTag,
+        to_block: BlockNumberOrTag,
+    ) -> RpcResult<Vec<RpcReceipt>> {
+        tracing::debug!(
+            "block_receipts_range rpc request received: from_block={}, to_block={}",
+            from_block,
+            to_block
+        );
+
+        let (from, to) = (self.block_height(from_block)?, self.block_height(to_block)?);
+        if from > to {
+            return Err(SidecarError::Validation(format!(
+                "fromBlock {} is after toBlock {}",
+                from, to
+            ))
+            .into());
+        }
+        if to - from >= MAX_RECEIPTS_BLOCKS {
+            return Err(SidecarError::Validation(format!(
+                "block range too large: {} blocks exceeds the limit of {}",
+                to - from + 1,
+                MAX_RECEIPTS_BLOCKS
+            ))
+            .into());
+        }
+
+        let blocks = fetch_blocks(
+            &self.adapter,
+            from..to + 1,
+            self.block_fetch_failure,
+            "sidecar_getBlockReceiptsRange",
+        )
+        .await?;
+        let mut receipts = vec![];
+        for block in blocks {
+            self.indexes
+                .block_hashes
+                .insert(block.block_height.0, convert::block_hash(&block.block_hash));
+            receipts.extend(self.receipts(&block)?);
+        }
+
+        Ok(receipts)
+    }
+}
+
+#[async_trait::async_trait]
+impl<Adapter> SidecarSubmitApiServer for EthApi<Adapter>
+where
+    Adapter: EngineAdapter + Send + Sync + 'static,
+{
+    /// Handler for `sidecar_sendRawTransactionWithExpiration`
+    async fn send_raw_transaction_with_expiration(
+        &self,
+        bytes: alloy_primitives::Bytes,
+        expiration: u64,
+    ) -> RpcResult<alloy_primitives::B256> {
+        tracing::debug!(
+            "send_raw_transaction_with_expiration rpc request received: bytes={}, expiration={}",
+            bytes,
+            expiration
+        );
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        if expiration <= now {
+            return Err(SidecarError::Validation(format!(
+                "expiration {} is not in the future",
+                expiration
+            ))
+            .into());
+        }
+
+        self.submit_raw_transaction(bytes, Some(expiration)).await
+    }
+
+    /// Handler for `sidecar_refreshAccount`
+    async fn refresh_account(
+        &self,
+        address: alloy_primitives::Address,
+    ) -> RpcResult<alloy_primitives::U64> {
+        tracing::debug!("refresh_account rpc request received: address={}", address);
+
+        let account = self
+            .accounts
+            .refresh_account(&self.adapter, to_aptos_address(&address))
+            .await;
+        self.indexes.pending.clear(&address);
+        let nonce = account_or_empty(account)?.nonce;
+        tracing::info!("Refreshed account {}: sequence number {}", address, nonce);
+
+        Ok(alloy_primitives::U64::from(nonce))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{account_or_empty, calldata, to_aptos_address, to_wei, AccountView, EthApi};
+    use crate::config::engine::AbortReasonConfig;
+    use crate::config::{
+        engine::BalanceConfig,
+        server::{BlockFetchFailure, ProofUnavailable},
+    };
+    use crate::engine::{
+        accounts::AccountCache,
+        adapter::{
+            mock::{
+                block, ledger_info, pending_transaction, pending_user_transaction,
+                sign_evm_transaction, user_transaction, MockEngineAdapter,
+            },
+            EngineGas, GasPriceEstimate,
+        },
+        pending::PendingTransaction,
+        tracker::HeadTracker,
+        EngineClient,
+    };
+    use crate::error::SidecarError;
+    use crate::rpc::{
+        block_cache::BlockCache,
+        convert::{self, HeaderConstants},
+        revert::{revert_data, AbortReasons},
+        sidecar::{SidecarReceiptsApiServer, SidecarSubmitApiServer},
+    };
+    use alloy_consensus::{Transaction, TxEip2930, TxEnvelope};
+    use alloy_eips::{
+        eip2718::{Decodable2718, Encodable2718},
+        eip2930::{AccessList, AccessListItem},
+        BlockId, BlockNumberOrTag,
+    };
+    use alloy_primitives::{hex::FromHex, Address, Bytes, TxKind, B256, U256, U64};
+    use alloy_rpc_types_eth::{
+        simulate::{SimBlock, SimulatePayload},
+        BlockTransactions, Bundle, Index, StateContext, SyncStatus, TransactionInput,
+        TransactionRequest,
+    };
+    use alloy_serde::JsonStorageKey;
+    use reth_primitives_traits::crypto::secp256k1::recover_signer;
+    use reth_rpc_eth_api::EthApiServer;
+    use std::{sync::Arc, time::Duration};
+
+    async fn eth_api(adapter: MockEngineAdapter) -> EthApi<MockEngineAdapter> {
+        let tracker = Arc::new(HeadTracker::new(
+            EngineClient::new(Box::new(adapter.clone())),
+            Duration::from_millis(100),
+        ));
+        tracker.poll().await.unwrap();
+
+        EthApi::new(adapter, tracker, 8)
+    }
+
+    #[test]
     fn to_bytes32_test() {
         let eth_address =
             alloy_primitives::Address::from_hex("0xC96aAa54E2d44c299564da76e1cD3184A2386B8D")
@@ -538,4 +1833,1477 @@ pub mod tests {
             .unwrap()
         );
     }
+
+    #[tokio::test]
+    async fn forwards_access_list_transaction_intact() {
+        let adapter = MockEngineAdapter::new().expect_submit(pending_transaction());
+        let api = eth_api(adapter.clone()).await;
+
+        let access_list = AccessList(vec![AccessListItem {
+            address: Address::repeat_byte(0x22),
+            storage_keys: vec![B256::repeat_byte(0x33)],
+        }]);
+        let tx = TxEip2930 {
+            chain_id: 4,
+            nonce: 0,
+            gas_price: 100_000_000_000,
+            gas_limit: 50_000,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            value: U256::from(1),
+            access_list: access_list.clone(),
+            input: Bytes::new(),
+        };
+        let (envelope, _) = sign_evm_transaction(tx);
+        let raw = envelope.encoded_2718();
+
+        let hash = api.send_raw_transaction(raw.clone().into()).await.unwrap();
+        assert_eq!(hash, *envelope.tx_hash());
+
+        let submitted = adapter.submitted();
+        assert_eq!(submitted[0].1, raw);
+        assert_eq!(
+            submitted[0].3,
+            EngineGas {
+                max_gas_amount: 50,
+                gas_unit_price: 10_000,
+            }
+        );
+
+        let decoded = TxEnvelope::decode_2718(&mut submitted[0].1.as_slice()).unwrap();
+        assert_eq!(decoded.access_list(), Some(&access_list));
+    }
+
+    #[tokio::test]
+    async fn rejects_transaction_for_another_chain() {
+        let adapter = MockEngineAdapter::new();
+        let api = eth_api(adapter.clone()).await;
+
+        let tx = TxEip2930 {
+            chain_id: 5,
+            nonce: 0,
+            gas_price: 100,
+            gas_limit: 50_000,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            value: U256::from(1),
+            access_list: AccessList::default(),
+            input: Bytes::new(),
+        };
+        let raw = sign_evm_transaction(tx).0.encoded_2718();
+
+        let err = api.send_raw_transaction(raw.into()).await.unwrap_err();
+        assert_eq!(err.code(), -32602);
+        assert_eq!(err.message(), "invalid chain id: expected 4, got 5");
+        assert_eq!(adapter.calls("submit_transaction"), 0);
+    }
+
+    #[tokio::test]
+    async fn counts_pending_transactions_in_pending_nonce() {
+        let api = eth_api(
+            MockEngineAdapter::new()
+                .expect_submit(pending_transaction())
+                .with_sequence_number(0),
+        )
+        .await;
+        let mut sender = Address::ZERO;
+
+        for nonce in 0..2 {
+            let tx = TxEip2930 {
+                chain_id: 4,
+                nonce,
+                gas_price: 100,
+                gas_limit: 50_000,
+                to: TxKind::Call(Address::repeat_byte(0x11)),
+                value: U256::from(1),
+                access_list: AccessList::default(),
+                input: Bytes::new(),
+            };
+            let (envelope, signer) = sign_evm_transaction(tx);
+            sender = signer;
+            api.send_raw_transaction(envelope.encoded_2718().into())
+                .await
+                .unwrap();
+        }
+
+        let pending = BlockId::Number(BlockNumberOrTag::Pending);
+        let latest = BlockId::Number(BlockNumberOrTag::Latest);
+        assert_eq!(
+            api.transaction_count(sender, Some(pending)).await.unwrap(),
+            U256::from(2)
+        );
+        assert_eq!(
+            api.transaction_count(sender, Some(latest)).await.unwrap(),
+            U256::ZERO
+        );
+    }
+
+    #[tokio::test]
+    async fn treats_missing_account_as_empty() {
+        let api = eth_api(MockEngineAdapter::new()).await;
+        let address = Address::repeat_byte(0x44);
+
+        assert_eq!(
+            api.transaction_count(address, None).await.unwrap(),
+            U256::ZERO
+        );
+        assert_eq!(api.get_code(address, None).await.unwrap(), Bytes::new());
+        assert_eq!(
+            api.storage_at(address, Default::default(), None)
+                .await
+                .unwrap(),
+            B256::ZERO
+        );
+        assert_eq!(api.balance(address, None).await.unwrap(), U256::ZERO);
+    }
+
+    #[tokio::test]
+    async fn reads_code_of_existing_account_from_code_view() {
+        let adapter = MockEngineAdapter::new()
+            .with_sequence_number(1)
+            .with_view_result(
+                "0x100::evm::code_of",
+                vec![serde_json::Value::String("0x6001".into())],
+            );
+        let address = Address::repeat_byte(0x44);
+
+        let api = eth_api(adapter.clone()).await;
+        assert_eq!(api.get_code(address, None).await.unwrap(), Bytes::new());
+        assert!(adapter.views().is_empty());
+
+        let api = api.with_code_view("0x100::evm::code_of".into());
+        assert_eq!(
+            api.get_code(address, None).await.unwrap(),
+            Bytes::from(vec![0x60, 0x01])
+        );
+        assert_eq!(
+            adapter.views(),
+            vec![(
+                "0x100::evm::code_of".to_string(),
+                vec![serde_json::Value::String(
+                    to_aptos_address(&address).to_hex_literal()
+                )]
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn reads_storage_of_existing_account_from_storage_view() {
+        let adapter = MockEngineAdapter::new()
+            .with_sequence_number(1)
+            .with_view_result(
+                "0x100::evm::storage_at",
+                vec![serde_json::Value::String("0x2a".into())],
+            );
+        let address = Address::repeat_byte(0x44);
+        let slot = JsonStorageKey::from(U256::from(1));
+
+        let api = eth_api(adapter.clone()).await;
+        assert_eq!(
+            api.storage_at(address, slot, None).await.unwrap(),
+            B256::ZERO
+        );
+        assert!(adapter.views().is_empty());
+
+        let api = api.with_storage_view("0x100::evm::storage_at".into());
+        assert_eq!(
+            api.storage_at(address, slot, None).await.unwrap(),
+            B256::with_last_byte(0x2a)
+        );
+        assert_eq!(
+            adapter.views(),
+            vec![(
+                "0x100::evm::storage_at".to_string(),
+                vec![
+                    serde_json::Value::String(to_aptos_address(&address).to_hex_literal()),
+                    serde_json::Value::String(B256::with_last_byte(1).to_string()),
+                ]
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_code_view_for_missing_account() {
+        let adapter = MockEngineAdapter::new();
+        let api = eth_api(adapter.clone())
+            .await
+            .with_code_view("0x100::evm::code_of".into());
+
+        assert_eq!(
+            api.get_code(Address::repeat_byte(0x44), None)
+                .await
+                .unwrap(),
+            Bytes::new()
+        );
+        assert!(adapter.views().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reports_balance_of_account_view() {
+        let address = Address::repeat_byte(0x44);
+
+        let api = eth_api(
+            MockEngineAdapter::new()
+                .with_sequence_number(3)
+                .with_balance(5),
+        )
+        .await;
+        assert_eq!(
+            api.account(&address).await.unwrap(),
+            AccountView {
+                exists: true,
+                nonce: 3,
+                balance: U256::from(50_000_000_000u64),
+            }
+        );
+
+        // A balance kept in a contract needs no engine account.
+        let api = eth_api(MockEngineAdapter::new().with_balance(5))
+            .await
+            .with_balance(BalanceConfig::ViewFunction("0x100::evm::balance_of".into()));
+        assert_eq!(
+            api.account(&address).await.unwrap(),
+            AccountView {
+                balance: U256::from(50_000_000_000u64),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_cached_account_nonce() {
+        let address = Address::repeat_byte(0x44);
+        let accounts = Arc::new(AccountCache::new(vec![to_aptos_address(&address)]));
+        accounts
+            .refresh(&MockEngineAdapter::new().with_sequence_number(5))
+            .await
+            .unwrap();
+        let adapter = MockEngineAdapter::new();
+        let api = eth_api(adapter.clone()).await.with_account_cache(accounts);
+
+        assert_eq!(
+            api.transaction_count(address, None).await.unwrap(),
+            U256::from(5)
+        );
+        assert_eq!(adapter.calls("get_account"), 0);
+    }
+
+    #[tokio::test]
+    async fn invalidates_cached_account_of_sender_on_submit() {
+        let (_, raw) = signed_transaction(5, 100_000_000_000);
+        let envelope = TxEnvelope::decode_2718(&mut raw.as_ref()).unwrap();
+        let signer = recover_signer(envelope.signature(), envelope.signature_hash()).unwrap();
+        let accounts = Arc::new(AccountCache::new(vec![to_aptos_address(&signer)]));
+        accounts
+            .refresh(&MockEngineAdapter::new().with_sequence_number(5))
+            .await
+            .unwrap();
+        let adapter = MockEngineAdapter::new()
+            .with_sequence_number(6)
+            .expect_submit(pending_transaction());
+        let api = eth_api(adapter.clone())
+            .await
+            .with_account_cache(accounts.clone());
+
+        api.send_raw_transaction(raw).await.unwrap();
+
+        assert!(accounts.get(&to_aptos_address(&signer)).is_none());
+        assert_eq!(
+            api.transaction_count(signer, None).await.unwrap(),
+            U256::from(6)
+        );
+    }
+
+    #[tokio::test]
+    async fn refreshes_cached_account_on_request() {
+        let address = Address::repeat_byte(0x44);
+        let accounts = Arc::new(AccountCache::new(vec![to_aptos_address(&address)]));
+        accounts
+            .refresh(&MockEngineAdapter::new().with_sequence_number(5))
+            .await
+            .unwrap();
+        let adapter = MockEngineAdapter::new().with_sequence_number(9);
+        let api = eth_api(adapter.clone())
+            .await
+            .with_account_cache(accounts.clone());
+        api.indexes.pending.insert(
+            address,
+            5,
+            PendingTransaction {
+                hash: B256::repeat_byte(0x01),
+                gas_price: 100,
+            },
+        );
+
+        assert_eq!(
+            api.refresh_account(address).await.unwrap(),
+            alloy_primitives::U64::from(9)
+        );
+        assert_eq!(adapter.calls("get_account"), 1);
+        assert!(api.indexes.pending.is_empty());
+        assert_eq!(
+            api.transaction_count(address, None).await.unwrap(),
+            U256::from(9)
+        );
+        assert_eq!(adapter.calls("get_account"), 1);
+    }
+
+    #[test]
+    fn maps_unknown_account_to_empty_view() {
+        assert_eq!(account_or_empty(Ok(None)).unwrap(), AccountView::default());
+
+        let account = serde_json::from_value(serde_json::json!({
+            "authentication_key": format!("0x{}", "00".repeat(32)),
+            "sequence_number": "7",
+        }))
+        .unwrap();
+        assert_eq!(
+            account_or_empty(Ok(Some(account))).unwrap(),
+            AccountView {
+                exists: true,
+                nonce: 7,
+                ..Default::default()
+            }
+        );
+
+        assert!(account_or_empty(Err(anyhow::anyhow!("connection refused"))).is_err());
+    }
+
+    #[tokio::test]
+    async fn returns_existing_account_nonce() {
+        let api = eth_api(MockEngineAdapter::new().with_sequence_number(7)).await;
+
+        assert_eq!(
+            api.transaction_count(Address::repeat_byte(0x44), None)
+                .await
+                .unwrap(),
+            U256::from(7)
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_configured_blob_base_fee() {
+        let api = eth_api(MockEngineAdapter::new()).await;
+        assert_eq!(api.blob_base_fee().await.unwrap(), U256::from(1));
+
+        let api = api.with_blob_base_fee(7);
+        assert_eq!(api.blob_base_fee().await.unwrap(), U256::from(7));
+    }
+
+    #[test]
+    fn scales_native_amounts_to_wei() {
+        assert_eq!(to_wei(1, 8), U256::from(10_000_000_000u64));
+        assert_eq!(to_wei(5, 18), U256::from(5));
+        assert_eq!(to_wei(5_000_000, 24), U256::from(5));
+        assert_eq!(to_wei(5, 24), U256::ZERO);
+    }
+
+    #[test]
+    fn reads_calldata_from_input_or_data() {
+        let calldata_of = |input, data| calldata(TransactionInput { input, data });
+        let ok = Bytes::from_static(b"ok");
+
+        assert_eq!(calldata_of(None, Some(ok.clone())).unwrap(), ok);
+        assert_eq!(calldata_of(Some(ok.clone()), None).unwrap(), ok);
+        assert_eq!(calldata_of(Some(ok.clone()), Some(ok.clone())).unwrap(), ok);
+        assert_eq!(calldata_of(None, None).unwrap(), Bytes::new());
+    }
+
+    #[test]
+    fn rejects_conflicting_input_and_data() {
+        let input = TransactionInput {
+            input: Some(Bytes::from_static(b"input")),
+            data: Some(Bytes::from_static(b"data")),
+        };
+
+        assert!(matches!(calldata(input), Err(SidecarError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn scales_balance_by_native_decimals() {
+        let api = eth_api(MockEngineAdapter::new().with_balance(100_000_000)).await;
+
+        assert_eq!(
+            api.balance(Address::repeat_byte(0x44), None).await.unwrap(),
+            U256::from(10).pow(U256::from(18))
+        );
+    }
+
+    #[tokio::test]
+    async fn chains_parent_hash_to_previous_block() {
+        let api = eth_api(MockEngineAdapter::new()).await;
+
+        let first = api
+            .block_by_number(BlockNumberOrTag::Number(7), false)
+            .await
+            .unwrap()
+            .unwrap();
+        let second = api
+            .block_by_number(BlockNumberOrTag::Number(8), false)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(second.header.inner.parent_hash, first.header.hash);
+        assert_ne!(second.header.hash, first.header.hash);
+    }
+
+    #[tokio::test]
+    async fn uses_zero_parent_hash_for_genesis() {
+        let api = eth_api(MockEngineAdapter::new()).await;
+
+        let genesis = api
+            .block_by_number(BlockNumberOrTag::Earliest, false)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(genesis.header.inner.number, 0);
+        assert_eq!(genesis.header.inner.parent_hash, B256::ZERO);
+    }
+
+    #[tokio::test]
+    async fn resolves_earliest_to_oldest_unpruned_block() {
+        let mut info = ledger_info(4, 42);
+        info.oldest_block_height = 17u64.into();
+        let adapter = MockEngineAdapter::new().with_ledger_info(Some(info));
+        let api = eth_api(adapter.clone()).await;
+
+        let earliest = api
+            .block_by_number(BlockNumberOrTag::Earliest, false)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(earliest.header.inner.number, 17);
+        // The pruned parent is never fetched.
+        assert_eq!(earliest.header.inner.parent_hash, B256::ZERO);
+        assert_eq!(adapter.calls("get_block_by_height"), 1);
+    }
+
+    #[tokio::test]
+    async fn queries_native_coin_balance() {
+        let adapter = MockEngineAdapter::new().with_balance(5);
+        let api = eth_api(adapter.clone()).await;
+        let address = Address::repeat_byte(0x44);
+
+        assert_eq!(
+            api.balance(address, None).await.unwrap(),
+            U256::from(50_000_000_000u64)
+        );
+        assert_eq!(adapter.calls("get_account_balance"), 1);
+        assert_eq!(
+            adapter.balance_queries(),
+            vec![(
+                to_aptos_address(&address),
+                "0x1::aptos_coin::AptosCoin".to_string()
+            )]
+        );
+        assert!(adapter.views().is_empty());
+    }
+
+    #[tokio::test]
+    async fn queries_native_balance_from_view_function() {
+        let adapter = MockEngineAdapter::new().with_balance(5);
+        let api = eth_api(adapter.clone())
+            .await
+            .with_balance(BalanceConfig::ViewFunction("0x100::evm::balance_of".into()));
+        let address = Address::repeat_byte(0x44);
+
+        assert_eq!(
+            api.balance(address, None).await.unwrap(),
+            U256::from(50_000_000_000u64)
+        );
+        assert_eq!(adapter.calls("get_account_balance"), 0);
+        assert_eq!(
+            adapter.views(),
+            vec![(
+                "0x100::evm::balance_of".to_string(),
+                vec![serde_json::Value::String(
+                    to_aptos_address(&address).to_hex_literal()
+                )]
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn returns_engine_chain_id() {
+        let adapter = MockEngineAdapter::new().with_ledger_info(Some(ledger_info(9, 1)));
+        let api = eth_api(adapter.clone()).await;
+        let calls = adapter.calls("get_ledger_info");
+
+        assert_eq!(
+            api.chain_id().await.unwrap(),
+            Some(alloy_primitives::U64::from(9))
+        );
+        assert_eq!(adapter.calls("get_ledger_info"), calls + 1);
+    }
+
+    #[tokio::test]
+    async fn serves_configured_evm_chain_id() {
+        let adapter = MockEngineAdapter::new();
+        let api = eth_api(adapter.clone()).await.with_chain_id(42161);
+        let calls = adapter.calls("get_ledger_info");
+
+        assert_eq!(
+            api.chain_id().await.unwrap(),
+            Some(alloy_primitives::U64::from(42161))
+        );
+        assert_eq!(adapter.calls("get_ledger_info"), calls);
+
+        // Transactions signed for the engine chain id are now signed for another chain.
+        let (_, raw) = signed_transaction(0, 100);
+        let err = api.send_raw_transaction(raw).await.unwrap_err();
+        assert_eq!(err.code(), -32602);
+    }
+
+    #[tokio::test]
+    async fn chains_first_block_to_synthesized_genesis() {
+        let genesis = convert::genesis_block(1_700_000_000, Bytes::from_static(b"noir"));
+        let api = eth_api(MockEngineAdapter::new())
+            .await
+            .with_genesis(genesis.clone());
+
+        let first = api
+            .block_by_number(BlockNumberOrTag::Number(0), false)
+            .await
+            .unwrap()
+            .unwrap();
+        let second = api
+            .block_by_number(BlockNumberOrTag::Number(1), false)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(first.header.hash, genesis.header.hash);
+        assert_eq!(first.header.inner.timestamp, 1_700_000_000);
+        assert_eq!(second.header.inner.parent_hash, genesis.header.hash);
+    }
+
+    fn signed_transaction(nonce: u64, gas_price: u128) -> (B256, Bytes) {
+        let tx = TxEip2930 {
+            chain_id: 4,
+            nonce,
+            gas_price,
+            gas_limit: 50_000,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            value: U256::from(1),
+            access_list: AccessList::default(),
+            input: Bytes::new(),
+        };
+        let (envelope, _) = sign_evm_transaction(tx);
+
+        (*envelope.tx_hash(), envelope.encoded_2718().into())
+    }
+
+    #[tokio::test]
+    async fn replaces_pending_transaction_with_higher_fee() {
+        let adapter = MockEngineAdapter::new().expect_submit(pending_transaction());
+        let api = eth_api(adapter.clone()).await;
+
+        let (_, original) = signed_transaction(5, 100_000_000_000);
+        let (hash, replacement) = signed_transaction(5, 200_000_000_000);
+        api.send_raw_transaction(original).await.unwrap();
+
+        assert_eq!(api.send_raw_transaction(replacement).await.unwrap(), hash);
+        let submitted = adapter.submitted();
+        assert_eq!(submitted.len(), 2);
+        assert_eq!(submitted[1].2, 5);
+        assert_eq!(submitted[1].3.gas_unit_price, 20_000);
+    }
+
+    #[tokio::test]
+    async fn rejects_underpriced_replacement() {
+        let adapter = MockEngineAdapter::new().expect_submit(pending_transaction());
+        let api = eth_api(adapter.clone()).await;
+
+        let (_, original) = signed_transaction(5, 100);
+        let (_, replacement) = signed_transaction(5, 100);
+        api.send_raw_transaction(original).await.unwrap();
+
+        let err = api.send_raw_transaction(replacement).await.unwrap_err();
+        assert_eq!(err.code(), -32000);
+        assert_eq!(err.message(), "replacement transaction underpriced");
+        assert_eq!(adapter.submitted().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn rejects_empty_raw_transaction() {
+        let adapter = MockEngineAdapter::new().expect_submit(pending_transaction());
+        let api = eth_api(adapter.clone()).await;
+
+        let err = api.send_raw_transaction(Bytes::new()).await.unwrap_err();
+        assert_eq!(err.code(), -32602);
+        assert_eq!(err.message(), "empty transaction data");
+        assert!(adapter.submitted().is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_raw_transaction_before_decoding() {
+        let adapter = MockEngineAdapter::new().expect_submit(pending_transaction());
+        let api = eth_api(adapter.clone()).await.with_max_raw_tx_bytes(64);
+
+        let err = api
+            .send_raw_transaction(Bytes::from(vec![0xff; 65]))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), -32602);
+        assert_eq!(
+            err.message(),
+            "transaction too large: 65 bytes exceeds the limit of 64"
+        );
+        assert!(adapter.submitted().is_empty());
+    }
+
+    #[tokio::test]
+    async fn accepts_raw_transaction_within_limit() {
+        let adapter = MockEngineAdapter::new().expect_submit(pending_transaction());
+        let (hash, raw) = signed_transaction(0, 100);
+        let api = eth_api(adapter.clone())
+            .await
+            .with_max_raw_tx_bytes(raw.len());
+
+        assert_eq!(api.send_raw_transaction(raw).await.unwrap(), hash);
+        assert_eq!(adapter.submitted().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn rejects_expiration_in_the_past() {
+        let adapter = MockEngineAdapter::new().expect_submit(pending_transaction());
+        let (_, raw) = signed_transaction(0, 100);
+        let api = eth_api(adapter.clone()).await;
+
+        let err = api
+            .send_raw_transaction_with_expiration(raw, 1)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), -32602);
+        assert_eq!(err.message(), "expiration 1 is not in the future");
+        assert!(adapter.submitted().is_empty());
+    }
+
+    #[tokio::test]
+    async fn simulates_each_call_of_bundle() {
+        let adapter = MockEngineAdapter::new();
+        let api = eth_api(adapter.clone()).await;
+        let from = Address::repeat_byte(0x44);
+        let to = Address::repeat_byte(0x11);
+        let bundle = Bundle::from(vec![
+            TransactionRequest::default()
+                .from(from)
+                .to(to)
+                .input(Bytes::from_static(b"ok").into()),
+            TransactionRequest::default().from(from).to(to),
+        ]);
+        let context = StateContext {
+            block_number: Some(BlockId::Number(BlockNumberOrTag::Number(3))),
+            transaction_index: None,
+        };
+
+        let responses = api.call_many(bundle, Some(context), None).await.unwrap();
+
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].value, Some(Bytes::from_static(b"ok")));
+        assert_eq!(responses[0].error, None);
+        assert_eq!(responses[1].value, None);
+        assert_eq!(responses[1].error.as_deref(), Some("execution reverted"));
+
+        let simulated = adapter.simulated();
+        assert_eq!(simulated.len(), 2);
+        assert_eq!(simulated[0].0.from, from);
+        assert_eq!(simulated[0].0.to, Some(to));
+        // Both calls run against the last version of block 3.
+        assert_eq!(simulated[0].1, Some(39));
+        assert_eq!(simulated[1].1, Some(39));
+    }
+
+    #[tokio::test]
+    async fn simulates_single_block_with_single_call() {
+        let adapter = MockEngineAdapter::new();
+        let api = eth_api(adapter.clone()).await;
+        let request = TransactionRequest::default()
+            .from(Address::repeat_byte(0x44))
+            .to(Address::repeat_byte(0x11))
+            .input(Bytes::from_static(b"ok").into());
+        let payload = SimulatePayload::default().extend(SimBlock::default().call(request));
+
+        let blocks = api
+            .simulate_v1(payload, Some(BlockId::Number(BlockNumberOrTag::Number(3))))
+            .await
+            .unwrap();
+
+        assert_eq!(blocks.len(), 1);
+        let header = &blocks[0].inner.header;
+        assert_eq!(header.number, 4);
+        assert_eq!(
+            header.parent_hash,
+            convert::block_hash(&block(3).block_hash)
+        );
+        assert_eq!(header.timestamp, block(3).block_timestamp.0 / 1_000_000 + 1);
+        assert_eq!(blocks[0].calls.len(), 1);
+        assert!(blocks[0].calls[0].status);
+        assert_eq!(blocks[0].calls[0].return_data, Bytes::from_static(b"ok"));
+        assert!(blocks[0].calls[0].error.is_none());
+        // The call runs against the last version of block 3.
+        assert_eq!(adapter.simulated()[0].1, Some(39));
+    }
+
+    #[tokio::test]
+    async fn fails_reverted_calls_of_simulation_and_propagates_engine_errors() {
+        let adapter = MockEngineAdapter::new()
+            .with_call_error(b"abort", "Move abort in 0x100::evm: 0x10003")
+            .with_call_error(b"unknown", "Move abort in 0x100::evm: 0x10004");
+        let api = eth_api(adapter)
+            .await
+            .with_abort_reasons(AbortReasons::new(&[AbortReasonConfig {
+                module: "0x100::evm".into(),
+                code: 0x10003,
+                reason: "insufficient balance for transfer".into(),
+            }]));
+        let request = |data: &'static [u8]| {
+            TransactionRequest::default()
+                .from(Address::repeat_byte(0x44))
+                .to(Address::repeat_byte(0x11))
+                .input(Bytes::from_static(data).into())
+        };
+
+        let payload =
+            SimulatePayload::default().extend(SimBlock::default().call(request(b"abort")));
+        let blocks = api.simulate_v1(payload, None).await.unwrap();
+        let call = &blocks[0].calls[0];
+        assert!(!call.status);
+        assert_eq!(
+            call.return_data,
+            revert_data("insufficient balance for transfer")
+        );
+        let error = call.error.as_ref().unwrap();
+        assert_eq!(error.code, 3);
+        assert_eq!(
+            error.message,
+            "execution reverted: insufficient balance for transfer"
+        );
+
+        let payload =
+            SimulatePayload::default().extend(SimBlock::default().call(request(b"unknown")));
+        let err = api.simulate_v1(payload, None).await.unwrap_err();
+        assert_eq!(err.code(), -32000);
+    }
+
+    #[tokio::test]
+    async fn rejects_simulation_with_validation() {
+        let api = eth_api(MockEngineAdapter::new()).await;
+        let payload = SimulatePayload::default()
+            .extend(SimBlock::default())
+            .with_validation();
+
+        let err = api.simulate_v1(payload, None).await.unwrap_err();
+        assert_eq!(err.code(), -32601);
+    }
+
+    #[tokio::test]
+    async fn reports_default_fees_in_fee_history() {
+        let api = eth_api(MockEngineAdapter::new())
+            .await
+            .with_header_constants(HeaderConstants {
+                base_fee: 7,
+                ..Default::default()
+            })
+            .with_default_priority_fee(1_000_000_000);
+
+        let history = api
+            .fee_history(
+                U64::from(3),
+                BlockNumberOrTag::Number(5),
+                Some(vec![25.0, 75.0]),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(history.oldest_block, 3);
+        assert_eq!(history.base_fee_per_gas, vec![7; 4]);
+        assert_eq!(history.gas_used_ratio, vec![0.0; 3]);
+        assert_eq!(history.reward, Some(vec![vec![1_000_000_000; 2]; 3]));
+    }
+
+    #[tokio::test]
+    async fn returns_transaction_by_block_hash_and_index() {
+        let (first, first_raw) = signed_transaction(0, 100);
+        let (second, second_raw) = signed_transaction(1, 100);
+        let adapter = MockEngineAdapter::new().with_block_transactions(vec![
+            user_transaction(&first_raw),
+            user_transaction(&second_raw),
+        ]);
+        let api = eth_api(adapter).await;
+        let block = api
+            .block_by_number(BlockNumberOrTag::Number(7), false)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let tx = api
+            .transaction_by_block_hash_and_index(block.header.hash, Index(1))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tx.inner.tx_hash(), &second);
+        assert_eq!(tx.block_hash, Some(block.header.hash));
+        assert_eq!(tx.block_number, Some(7));
+        assert_eq!(tx.transaction_index, Some(1));
+
+        let by_number = api
+            .transaction_by_block_number_and_index(BlockNumberOrTag::Number(7), Index(0))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(by_number.inner.tx_hash(), &first);
+
+        assert!(api
+            .transaction_by_block_hash_and_index(block.header.hash, Index(2))
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn returns_no_transaction_for_unknown_block_hash() {
+        let adapter = MockEngineAdapter::new();
+        let api = eth_api(adapter.clone()).await;
+
+        assert!(api
+            .transaction_by_block_hash_and_index(B256::repeat_byte(0x77), Index(0))
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(adapter.calls("get_block_by_height"), 0);
+    }
+
+    #[tokio::test]
+    async fn raises_zero_gas_price_to_floor() {
+        let api = eth_api(MockEngineAdapter::new().with_gas_price(0))
+            .await
+            .with_min_gas_price(1_000_000_000);
+
+        assert_eq!(api.gas_price().await.unwrap(), U256::from(1_000_000_000));
+        assert_eq!(
+            api.max_priority_fee_per_gas().await.unwrap(),
+            U256::from(1_000_000_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn raises_priority_fee_to_floor_after_base_fee() {
+        let api = eth_api(MockEngineAdapter::new().with_gas_price(0))
+            .await
+            .with_header_constants(HeaderConstants {
+                gas_limit: 30_000_000,
+                base_fee: 2_000_000_000,
+            })
+            .with_min_gas_price(1_000_000_000);
+
+        assert_eq!(
+            api.max_priority_fee_per_gas().await.unwrap(),
+            U256::from(1_000_000_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_engine_gas_price_above_floor() {
+        let api = eth_api(MockEngineAdapter::new().with_gas_price(10_000))
+            .await
+            .with_min_gas_price(1_000_000_000);
+
+        assert_eq!(
+            api.gas_price().await.unwrap(),
+            U256::from(100_000_000_000u64)
+        );
+    }
+
+    #[tokio::test]
+    async fn suggests_gas_prices_from_estimate_tiers() {
+        let adapter = MockEngineAdapter::new().with_gas_price_estimate(GasPriceEstimate {
+            deprioritized: 100,
+            normal: 150,
+            prioritized: 300,
+        });
+        let api = eth_api(adapter.clone())
+            .await
+            .with_header_constants(HeaderConstants {
+                gas_limit: 30_000_000,
+                base_fee: 1_000_000_000,
+            })
+            .with_gas_price_ttl(Duration::from_secs(60));
+
+        // Engine unit prices of 150 and 300 octas are 1.5 and 3 gwei per EVM gas.
+        assert_eq!(api.gas_price().await.unwrap(), U256::from(1_500_000_000));
+        assert_eq!(
+            api.max_priority_fee_per_gas().await.unwrap(),
+            U256::from(2_000_000_000)
+        );
+        assert_eq!(adapter.calls("estimate_gas_price"), 1);
+    }
+
+    #[tokio::test]
+    async fn scales_gas_price_by_native_decimals() {
+        let adapter = MockEngineAdapter::new().with_gas_price(1_500_000_000_000);
+        let tracker = Arc::new(HeadTracker::new(
+            EngineClient::new(Box::new(adapter.clone())),
+            Duration::from_millis(100),
+        ));
+        tracker.poll().await.unwrap();
+        let api = EthApi::new(adapter, tracker, 18);
+
+        // A coin with 18 decimals is already denominated in wei, so only the gas units are
+        // converted.
+        assert_eq!(api.gas_price().await.unwrap(), U256::from(1_500_000_000));
+    }
+
+    #[tokio::test]
+    async fn fetches_gas_prices_again_without_ttl() {
+        let adapter = MockEngineAdapter::new().with_gas_price(150);
+        let api = eth_api(adapter.clone()).await;
+
+        api.gas_price().await.unwrap();
+        api.gas_price().await.unwrap();
+        assert_eq!(adapter.calls("estimate_gas_price"), 2);
+    }
+
+    #[tokio::test]
+    async fn returns_pending_transaction_without_block() {
+        let (hash, raw) = signed_transaction(0, 100);
+        let adapter = MockEngineAdapter::new()
+            .expect_submit(pending_transaction())
+            .with_transaction(aptos_api_types::Transaction::PendingTransaction(
+                pending_user_transaction(&raw),
+            ));
+        let api = eth_api(adapter).await;
+        api.send_raw_transaction(raw).await.unwrap();
+
+        let tx = api.transaction_by_hash(hash).await.unwrap().unwrap();
+        assert_eq!(tx.inner.tx_hash(), &hash);
+        assert_eq!(tx.block_hash, None);
+        assert_eq!(tx.block_number, None);
+        assert_eq!(tx.transaction_index, None);
+    }
+
+    #[tokio::test]
+    async fn populates_block_fields_once_pending_transaction_commits() {
+        let (hash, raw) = signed_transaction(0, 100);
+        let adapter = MockEngineAdapter::new()
+            .expect_submit(pending_transaction())
+            .with_transaction(aptos_api_types::Transaction::PendingTransaction(
+                pending_user_transaction(&raw),
+            ))
+            .with_block_transactions(vec![user_transaction(&raw)]);
+        let api = eth_api(adapter.clone()).await;
+        api.send_raw_transaction(raw.clone()).await.unwrap();
+
+        let json = serde_json::to_value(api.transaction_by_hash(hash).await.unwrap()).unwrap();
+        assert_eq!(json["hash"], serde_json::json!(hash));
+        assert!(json["blockHash"].is_null(), "{}", json);
+        assert!(json["blockNumber"].is_null(), "{}", json);
+        assert!(json["transactionIndex"].is_null(), "{}", json);
+
+        adapter.set_transaction(aptos_api_types::Transaction::UserTransaction(
+            user_transaction(&raw),
+        ));
+        let json = serde_json::to_value(api.transaction_by_hash(hash).await.unwrap()).unwrap();
+        let block = convert::to_rpc_block(
+            &block(10),
+            B256::ZERO,
+            &Default::default(),
+            "0x100::evm::transact",
+            &Default::default(),
+        );
+        assert_eq!(json["blockHash"], serde_json::json!(block.header.hash));
+        assert_eq!(json["blockNumber"], "0xa");
+        assert_eq!(json["transactionIndex"], "0x0");
+    }
+
+    #[tokio::test]
+    async fn finds_transaction_submitted_elsewhere_once_its_block_is_served() {
+        let (hash, raw) = signed_transaction(0, 100);
+        let adapter = MockEngineAdapter::new()
+            .with_transaction(aptos_api_types::Transaction::UserTransaction(
+                user_transaction(&raw),
+            ))
+            .with_block_transactions(vec![user_transaction(&raw)]);
+        let api = eth_api(adapter.clone()).await;
+        assert!(api.transaction_by_hash(hash).await.unwrap().is_none());
+
+        api.block_by_number(BlockNumberOrTag::Number(10), false)
+            .await
+            .unwrap();
+
+        let tx = api.transaction_by_hash(hash).await.unwrap().unwrap();
+        assert_eq!(tx.inner.tx_hash(), &hash);
+        assert_eq!(tx.block_number, Some(10));
+    }
+
+    #[tokio::test]
+    async fn returns_committed_transaction_with_block() {
+        let (_, other) = signed_transaction(0, 100);
+        let (hash, raw) = signed_transaction(1, 100);
+        let adapter = MockEngineAdapter::new()
+            .expect_submit(pending_transaction())
+            .with_transaction(aptos_api_types::Transaction::UserTransaction(
+                user_transaction(&raw),
+            ))
+            .with_block_transactions(vec![user_transaction(&other), user_transaction(&raw)]);
+        let api = eth_api(adapter.clone()).await;
+        api.send_raw_transaction(raw).await.unwrap();
+
+        let tx = api.transaction_by_hash(hash).await.unwrap().unwrap();
+        let block = convert::to_rpc_block(
+            &block(10),
+            B256::ZERO,
+            &Default::default(),
+            "0x100::evm::transact",
+            &Default::default(),
+        );
+        assert_eq!(tx.inner.tx_hash(), &hash);
+        assert_eq!(tx.block_hash, Some(block.header.hash));
+        assert_eq!(tx.block_number, Some(10));
+        assert_eq!(tx.transaction_index, Some(1));
+
+        assert!(api
+            .transaction_by_hash(B256::repeat_byte(0x77))
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(adapter.calls("get_transaction_by_hash"), 1);
+    }
+
+    #[tokio::test]
+    async fn reports_transaction_value_in_wei() {
+        let tx = TxEip2930 {
+            chain_id: 4,
+            gas_price: 100,
+            gas_limit: 50_000,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            value: U256::from(10).pow(U256::from(18)),
+            ..Default::default()
+        };
+        let (envelope, _) = sign_evm_transaction(tx);
+        let raw: Bytes = envelope.encoded_2718().into();
+        let adapter = MockEngineAdapter::new()
+            .expect_submit(pending_transaction())
+            .with_transaction(aptos_api_types::Transaction::UserTransaction(
+                user_transaction(&raw),
+            ))
+            .with_block_transactions(vec![user_transaction(&raw)]);
+        // Balances are scaled from 8 native decimals; the value must not be.
+        let api = eth_api(adapter).await;
+        api.send_raw_transaction(raw).await.unwrap();
+
+        let tx = api
+            .transaction_by_hash(*envelope.tx_hash())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tx.inner.value(), U256::from(10).pow(U256::from(18)));
+    }
+
+    #[tokio::test]
+    async fn lists_same_evm_transactions_with_and_without_decoding() {
+        let (first, first_raw) = signed_transaction(0, 100);
+        let (second, second_raw) = signed_transaction(1, 100);
+        let mut invalid = user_transaction(&second_raw);
+        invalid.request.payload = serde_json::from_value(serde_json::json!({
+            "type": "entry_function_payload",
+            "function": "0x1::aptos_account::transfer",
+            "type_arguments": [],
+            "arguments": ["0x1"],
+        }))
+        .unwrap();
+        // Carried like an EVM transaction, but not one; both paths skip it.
+        let garbage = user_transaction(&[0xde, 0xad]);
+        let adapter = MockEngineAdapter::new().with_block_transactions(vec![
+            user_transaction(&first_raw),
+            invalid,
+            garbage,
+            user_transaction(&second_raw),
+        ]);
+        let api = eth_api(adapter).await;
+
+        let block = api
+            .block_by_number(BlockNumberOrTag::Number(7), false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            block.transactions,
+            BlockTransactions::Hashes(vec![first, second])
+        );
+
+        let block = api
+            .block_by_number(BlockNumberOrTag::Number(7), true)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            block.transactions.hashes().collect::<Vec<_>>(),
+            vec![first, second]
+        );
+    }
+
+    #[tokio::test]
+    async fn includes_decoded_transactions_in_full_blocks() {
+        let (first, first_raw) = signed_transaction(0, 100);
+        let (second, second_raw) = signed_transaction(1, 100);
+        let adapter = MockEngineAdapter::new().with_block_transactions(vec![
+            user_transaction(&first_raw),
+            user_transaction(&second_raw),
+        ]);
+        let api = eth_api(adapter).await;
+
+        let block = api
+            .block_by_number(BlockNumberOrTag::Number(7), true)
+            .await
+            .unwrap()
+            .unwrap();
+        let BlockTransactions::Full(transactions) = &block.transactions else {
+            panic!("expected full transactions");
+        };
+        assert_eq!(
+            transactions
+                .iter()
+                .map(|tx| *tx.inner.tx_hash())
+                .collect::<Vec<_>>(),
+            vec![first, second]
+        );
+        assert_eq!(transactions[1].transaction_index, Some(1));
+        assert_eq!(transactions[1].block_hash, Some(block.header.hash));
+        assert_eq!(transactions[1].block_number, Some(7));
+    }
+
+    #[tokio::test]
+    async fn reports_syncing_while_lagging_highest_observed_ledger() {
+        let api = eth_api(MockEngineAdapter::new())
+            .await
+            .with_sync_lag_threshold(50);
+        assert_eq!(api.syncing().unwrap(), SyncStatus::None);
+
+        // A replica 10 blocks (100 versions) behind answers after a more recent one.
+        api.tracker.update(ledger_info(4, 52));
+        api.tracker.update(ledger_info(4, 42));
+
+        let SyncStatus::Info(info) = api.syncing().unwrap() else {
+            panic!("expected syncing info");
+        };
+        assert_eq!(info.current_block, U256::from(42));
+        assert_eq!(info.highest_block, U256::from(52));
+
+        // Within the threshold again.
+        api.tracker.update(ledger_info(4, 48));
+        assert_eq!(api.syncing().unwrap(), SyncStatus::None);
+    }
+
+    #[tokio::test]
+    async fn serves_repeated_block_fetches_from_cache() {
+        let adapter = MockEngineAdapter::new();
+        let blocks = Arc::new(BlockCache::new(16));
+        let api = eth_api(adapter.clone())
+            .await
+            .with_block_cache(blocks.clone());
+
+        let block = api
+            .block_by_number(BlockNumberOrTag::Number(7), false)
+            .await
+            .unwrap();
+        let calls = adapter.calls("get_block_by_height");
+        let cached = api
+            .block_by_number(BlockNumberOrTag::Number(7), false)
+            .await
+            .unwrap();
+
+        assert_eq!(cached, block);
+        assert_eq!(adapter.calls("get_block_by_height"), calls);
+        assert_eq!((blocks.hits(), blocks.misses()), (1, 1));
+
+        // The head block is converted again on every fetch.
+        api.block_by_number(BlockNumberOrTag::Latest, false)
+            .await
+            .unwrap();
+        api.block_by_number(BlockNumberOrTag::Latest, false)
+            .await
+            .unwrap();
+        assert_eq!(blocks.hits(), 1);
+        assert_eq!(blocks.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn accepts_first_transaction_of_new_sender() {
+        let adapter = MockEngineAdapter::new().expect_submit(pending_transaction());
+        let api = eth_api(adapter.clone()).await;
+        let (_, raw) = signed_transaction(0, 100);
+        let envelope = TxEnvelope::decode_2718(&mut raw.as_ref()).unwrap();
+        let sender = recover_signer(envelope.signature(), envelope.signature_hash()).unwrap();
+
+        assert_eq!(
+            api.transaction_count(sender, None).await.unwrap(),
+            U256::ZERO
+        );
+        api.send_raw_transaction(raw).await.unwrap();
+
+        let submitted = adapter.submitted();
+        assert_eq!(submitted.len(), 1);
+        assert_eq!(submitted[0].0, to_aptos_address(&sender));
+        assert_eq!(submitted[0].2, 0);
+    }
+
+    #[tokio::test]
+    async fn threads_block_tag_into_simulation() {
+        let adapter = MockEngineAdapter::new();
+        let api = eth_api(adapter.clone()).await;
+        let request = TransactionRequest::default().to(Address::repeat_byte(0x11));
+
+        for tag in [
+            BlockNumberOrTag::Latest,
+            BlockNumberOrTag::Pending,
+            BlockNumberOrTag::Number(3),
+        ] {
+            api.call(request.clone(), Some(BlockId::Number(tag)), None, None)
+                .await
+                .unwrap_err();
+        }
+
+        let versions: Vec<_> = adapter
+            .simulated()
+            .into_iter()
+            .map(|(_, version)| version)
+            .collect();
+        assert_eq!(versions, vec![None, None, Some(39)]);
+    }
+
+    #[tokio::test]
+    async fn rejects_pending_calls_when_configured() {
+        let adapter = MockEngineAdapter::new();
+        let api = eth_api(adapter.clone())
+            .await
+            .with_reject_pending_calls(true);
+        let request = TransactionRequest::default().to(Address::repeat_byte(0x11));
+
+        let err = api
+            .call(
+                request,
+                Some(BlockId::Number(BlockNumberOrTag::Pending)),
+                None,
+                None,
+            )
+            .await
+            .unwrap_err();
+
+        assert!(err.message().contains("pending"), "{}", err.message());
+        assert!(adapter.simulated().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reports_proofs_unsupported_with_hint() {
+        let api = eth_api(MockEngineAdapter::new()).await;
+        let err = api
+            .get_proof(Address::repeat_byte(0x44), vec![], None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), -32601);
+        assert_eq!(err.message(), "proofs unsupported");
+        assert!(err.data().is_none());
+
+        let api = api.with_proof_unavailable(
+            ProofUnavailable::MethodNotSupported,
+            "see https://proofs.example".into(),
+        );
+        let err = api
+            .get_proof(Address::repeat_byte(0x44), vec![], None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), -32004);
+        assert_eq!(err.message(), "proofs unsupported");
+        assert_eq!(
+            err.data().map(|data| data.get()),
+            Some(r#""see https://proofs.example""#)
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reads_head_before_fetching_block() {
+        let adapter = MockEngineAdapter::new().with_ledger_info(None);
+        let tracker = Arc::new(HeadTracker::new(
+            EngineClient::new(Box::new(adapter.clone())),
+            Duration::from_millis(100),
+        ));
+        let api = EthApi::new(adapter.clone(), tracker, 8);
+
+        let err = api
+            .block_by_number(BlockNumberOrTag::Number(5), false)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), -32000);
+        assert_eq!(adapter.calls("get_block_by_height"), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn fetches_block_number_on_demand_without_tracker() {
+        let adapter = MockEngineAdapter::new();
+        let tracker = HeadTracker::new(
+            EngineClient::new(Box::new(adapter.clone())),
+            Duration::from_millis(100),
+        )
+        .with_on_demand_fetch();
+        tracker.update(ledger_info(4, 10));
+        let api = EthApi::new(adapter.clone(), Arc::new(tracker), 8);
+
+        assert_eq!(api.block_number().unwrap(), U256::from(42));
+        assert_eq!(api.block_number().unwrap(), U256::from(42));
+        assert_eq!(adapter.calls("get_ledger_info"), 2);
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_calldata_before_simulating() {
+        let adapter = MockEngineAdapter::new();
+        let api = eth_api(adapter.clone()).await.with_max_calldata_bytes(4);
+        let request = |data: &'static [u8]| {
+            TransactionRequest::default()
+                .to(Address::repeat_byte(0x11))
+                .input(Bytes::from_static(data).into())
+        };
+
+        let err = api
+            .call(request(&[1; 5]), None, None, None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), -32602);
+        assert_eq!(
+            err.message(),
+            "calldata too large: 5 bytes exceeds the limit of 4"
+        );
+        assert!(adapter.simulated().is_empty());
+
+        assert_eq!(
+            api.call(request(&[1; 4]), None, None, None).await.unwrap(),
+            Bytes::from_static(&[1; 4])
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_receipt_of_committed_transaction() {
+        let (_, other) = signed_transaction(0, 100);
+        let (hash, raw) = signed_transaction(1, 100);
+        let adapter = MockEngineAdapter::new()
+            .expect_submit(pending_transaction())
+            .with_transaction(aptos_api_types::Transaction::UserTransaction(
+                user_transaction(&raw),
+            ))
+            .with_block_transactions(vec![user_transaction(&other), user_transaction(&raw)]);
+        let api = eth_api(adapter).await;
+        api.send_raw_transaction(raw).await.unwrap();
+
+        let receipt =
+            serde_json::to_value(api.transaction_receipt(hash).await.unwrap().unwrap()).unwrap();
+        assert_eq!(receipt["transactionHash"], serde_json::json!(hash));
+        assert_eq!(
+            receipt["blockHash"],
+            serde_json::json!(convert::block_hash(&block(10).block_hash))
+        );
+        assert_eq!(receipt["blockNumber"], "0xa");
+        assert_eq!(receipt["transactionIndex"], "0x1");
+        assert_eq!(receipt["status"], "0x1");
+        assert_eq!(receipt["gasUsed"], "0x5208");
+        assert_eq!(receipt["cumulativeGasUsed"], "0xa410");
+    }
+
+    #[tokio::test]
+    async fn reports_revert_reason_of_failed_transaction_in_receipt() {
+        let failed = |raw: &[u8], vm_status: &str| {
+            let mut tx = user_transaction(raw);
+            tx.info.success = false;
+            tx.info.vm_status = vm_status.into();
+            tx
+        };
+        let (succeeded, succeeded_raw) = signed_transaction(0, 100);
+        let (mapped, mapped_raw) = signed_transaction(1, 100);
+        let (unmapped, unmapped_raw) = signed_transaction(2, 100);
+        let transactions = vec![
+            user_transaction(&succeeded_raw),
+            failed(&mapped_raw, "Move abort in 0x100::evm: 0x10003"),
+            failed(&unmapped_raw, "Move abort in 0x100::evm: 0x10004"),
+        ];
+        let adapter = MockEngineAdapter::new().with_block_transactions(transactions.clone());
+        let api = eth_api(adapter.clone())
+            .await
+            .with_abort_reasons(AbortReasons::new(&[AbortReasonConfig {
+                module: "0x100::evm".into(),
+                code: 0x10003,
+                reason: "insufficient balance for transfer".into(),
+            }]));
+
+        let mut receipts = vec![];
+        for (hash, tx) in [succeeded, mapped, unmapped].into_iter().zip(transactions) {
+            api.indexes.transaction_hashes.insert(hash, tx.info.hash);
+            adapter.set_transaction(aptos_api_types::Transaction::UserTransaction(tx));
+            let receipt = api.transaction_receipt(hash).await.unwrap().unwrap();
+            receipts.push(serde_json::to_value(receipt).unwrap());
+        }
+
+        assert_eq!(receipts[0]["status"], "0x1");
+        assert!(receipts[0].get("revertReason").is_none());
+        assert_eq!(receipts[1]["status"], "0x0");
+        assert_eq!(
+            receipts[1]["revertReason"],
+            "insufficient balance for transfer"
+        );
+        assert_eq!(receipts[2]["status"], "0x0");
+        assert_eq!(
+            receipts[2]["revertReason"],
+            "Move abort in 0x100::evm: 0x10004"
+        );
+    }
+
+    #[tokio::test]
+    async fn returns_receipts_of_block_range_by_fetch_failure_policy() {
+        let (_, raw) = signed_transaction(0, 100);
+        let adapter = MockEngineAdapter::new()
+            .with_block_transactions(vec![user_transaction(&raw)])
+            .with_failing_block(6);
+        let range = (BlockNumberOrTag::Number(5), BlockNumberOrTag::Number(7));
+
+        let api = eth_api(adapter.clone()).await;
+        let err = api
+            .block_receipts_range(range.0, range.1)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), -32000);
+
+        let api = eth_api(adapter.clone())
+            .await
+            .with_block_fetch_failure(BlockFetchFailure::SkipAndWarn);
+        let receipts = api.block_receipts_range(range.0, range.1).await.unwrap();
+        let numbers: Vec<_> = receipts.iter().map(|r| r.block_number).collect();
+        assert_eq!(numbers, vec![Some(5), Some(7)]);
+
+        let err = api
+            .block_receipts_range(range.1, range.0)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), -32602);
+        let err = api
+            .block_receipts_range(BlockNumberOrTag::Number(0), BlockNumberOrTag::Number(100))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), -32602);
+    }
+
+    #[tokio::test]
+    async fn returns_no_receipt_for_pending_or_unknown_transaction() {
+        let (hash, raw) = signed_transaction(0, 100);
+        let adapter = MockEngineAdapter::new()
+            .expect_submit(pending_transaction())
+            .with_transaction(aptos_api_types::Transaction::PendingTransaction(
+                pending_user_transaction(&raw),
+            ));
+        let api = eth_api(adapter.clone()).await;
+
+        assert!(api.transaction_receipt(hash).await.unwrap().is_none());
+        assert_eq!(adapter.calls("get_transaction_by_hash"), 0);
+
+        api.send_raw_transaction(raw).await.unwrap();
+        assert!(api.transaction_receipt(hash).await.unwrap().is_none());
+    }
 }
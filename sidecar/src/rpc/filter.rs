@@ -0,0 +1,603 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    config::server::BlockFetchFailure,
+    engine::{adapter::EngineAdapter, indexes::Indexes, tracker::HeadTracker},
+    error::{Result, SidecarError},
+    rpc::{block_cache::BlockCache, convert},
+};
+use alloy_eips::BlockNumberOrTag;
+use alloy_rpc_types_eth::{
+    Filter, FilterBlockOption, FilterChanges, FilterId, FilteredParams, Log,
+    PendingTransactionFilterKind,
+};
+use futures::{stream, Stream, StreamExt};
+use jsonrpsee::core::RpcResult;
+use reth_rpc_eth_api::EthFilterApiServer;
+use std::{
+    ops::{Range, RangeInclusive},
+    sync::Arc,
+};
+
+/// Number of blocks fetched ahead while streaming logs.
+const STREAM_CONCURRENCY: usize = 8;
+
+/// `eth` filter API implementation.
+pub struct EthFilter<Adapter> {
+    adapter: Adapter,
+    tracker: Arc<HeadTracker>,
+    log_event: String,
+    entry_func: String,
+    indexes: Arc<Indexes>,
+    blocks: Arc<BlockCache>,
+    max_block_range: Option<u64>,
+    clamp_block_range: bool,
+    block_fetch_failure: BlockFetchFailure,
+}
+
+impl<Adapter> EthFilter<Adapter>
+where
+    Adapter: EngineAdapter + Send + Sync + 'static,
+{
+    pub fn new(adapter: Adapter, tracker: Arc<HeadTracker>, log_event: String) -> Self {
+        Self {
+            adapter,
+            tracker,
+            log_event,
+            entry_func: "0x100::evm::transact".into(),
+            indexes: Default::default(),
+            blocks: Default::default(),
+            max_block_range: None,
+            clamp_block_range: false,
+            block_fetch_failure: BlockFetchFailure::FailFast,
+        }
+    }
+
+    /// Reads logs from the user transactions calling `entry_func` only, see
+    /// [`convert::evm_transactions`].
+    pub fn with_entry_func(mut self, entry_func: String) -> Self {
+        self.entry_func = entry_func;
+        self
+    }
+
+    /// Shares `indexes` with the other handlers instead of keeping them to this API.
+    pub fn with_indexes(mut self, indexes: Arc<Indexes>) -> Self {
+        self.indexes = indexes;
+        self
+    }
+
+    /// Serves the logs of recently converted blocks from `blocks`, shared with the other
+    /// handlers.
+    pub fn with_block_cache(mut self, blocks: Arc<BlockCache>) -> Self {
+        self.blocks = blocks;
+        self
+    }
+
+    /// Limits `eth_getLogs` to ranges of `max_block_range` blocks.
+    ///
+    /// Larger ranges are rejected, or with `clamp` cut down to their first
+    /// `max_block_range` blocks, in which case only the logs of those blocks are returned.
+    pub fn with_max_block_range(mut self, max_block_range: u64, clamp: bool) -> Self {
+        self.max_block_range = Some(max_block_range);
+        self.clamp_block_range = clamp;
+        self
+    }
+
+    /// Sets whether `eth_getLogs` fails when a block in its range cannot be fetched, or
+    /// returns the logs of the other blocks.
+    pub fn with_block_fetch_failure(mut self, policy: BlockFetchFailure) -> Self {
+        self.block_fetch_failure = policy;
+        self
+    }
+
+    /// Resolves `number` to a block height, the latest block if it is omitted.
+    ///
+    /// `earliest` is the oldest block the engine still serves, which is above 0 once the
+    /// node has pruned its history.
+    fn block_height(&self, number: Option<BlockNumberOrTag>) -> Result<u64> {
+        match number {
+            Some(BlockNumberOrTag::Number(height)) => Ok(height),
+            Some(BlockNumberOrTag::Earliest) => Ok(self.ledger_info()?.oldest_block_height.0),
+            _ => Ok(self.ledger_info()?.block_height.0),
+        }
+    }
+
+    fn ledger_info(&self) -> Result<aptos_api_types::IndexResponse> {
+        self.tracker.latest_or_fetch().map_err(|e| {
+            tracing::warn!("Ledger info unavailable: {}", e);
+            SidecarError::NotReady
+        })
+    }
+
+    /// Returns the current head height, or 0 while it is unknown so that nothing is cached
+    /// until it is known.
+    fn head(&self) -> u64 {
+        self.tracker
+            .current()
+            .map_or(0, |ledger_info| ledger_info.block_height.0)
+    }
+
+    /// Splits `heights` into the ranges of blocks that may contain logs matching `filter`.
+    ///
+    /// Blocks whose bloom has been seen before and excludes the filter addresses or topics
+    /// are left out, so they are never fetched again. Blocks never fetched before have no
+    /// bloom yet and are always candidates.
+    fn candidates(&self, heights: RangeInclusive<u64>, filter: &Filter) -> Vec<Range<u64>> {
+        let address = FilteredParams::address_filter(&filter.address);
+        let topics = FilteredParams::topics_filter(&filter.topics);
+
+        let mut ranges: Vec<Range<u64>> = vec![];
+        for height in heights {
+            let skip = self.indexes.blooms.get(height).is_some_and(|bloom| {
+                !FilteredParams::matches_address(bloom, &address)
+                    || !FilteredParams::matches_topics(bloom, &topics)
+            });
+            if skip {
+                continue;
+            }
+
+            match ranges.last_mut() {
+                Some(range) if range.end == height => range.end = height + 1,
+                _ => ranges.push(height..height + 1),
+            }
+        }
+
+        ranges
+    }
+
+    /// Returns the last height of the range `from..=to` that is served, applying the
+    /// configured `max_block_range`.
+    fn limit_range(&self, from: u64, to: u64) -> Result<u64> {
+        let Some(max_block_range) = self.max_block_range else {
+            return Ok(to);
+        };
+        let blocks = to.saturating_sub(from).saturating_add(1);
+        if blocks <= max_block_range {
+            return Ok(to);
+        }
+
+        if !self.clamp_block_range {
+            return Err(SidecarError::Validation(format!(
+                "block range too large: {} blocks exceeds the limit of {}",
+                blocks, max_block_range
+            )));
+        }
+
+        let clamped = from.saturating_add(max_block_range.max(1) - 1);
+        tracing::warn!(
+            "Truncated eth_getLogs range {}..={} to {}..={}",
+            from,
+            to,
+            from,
+            clamped
+        );
+        Ok(clamped)
+    }
+
+    /// Returns the first and last heights of the blocks `filter` spans.
+    fn filter_range(&self, filter: &Filter) -> Result<(u64, u64)> {
+        match filter.block_option {
+            FilterBlockOption::Range {
+                from_block,
+                to_block,
+            } => Ok((self.block_height(from_block)?, self.block_height(to_block)?)),
+            FilterBlockOption::AtBlockHash(_) => {
+                Err(SidecarError::Unsupported("eth_getLogs by block hash"))
+            }
+        }
+    }
+
+    /// Extracts every log of a block fetched with its transactions, indexes the block and its
+    /// transactions, and caches its logs unless it is at or above `head`.
+    fn block_logs(&self, block: &aptos_api_types::Block, head: u64) -> Result<Vec<Log>> {
+        let height = block.block_height.0;
+        let logs = convert::to_logs(block, &self.log_event, &self.entry_func)?;
+        self.indexes
+            .blooms
+            .insert(height, convert::logs_bloom(&logs));
+        self.indexes
+            .block_hashes
+            .insert(height, convert::block_hash(&block.block_hash));
+        self.indexes.insert_transactions(block, &self.entry_func);
+        self.blocks.insert_logs(head, height, logs.clone());
+
+        Ok(logs)
+    }
+
+    /// Returns the logs of each block in `heights`, fetching only the blocks whose logs are
+    /// not cached.
+    async fn range_logs(&self, heights: Range<u64>, head: u64) -> Result<Vec<Vec<Log>>> {
+        let mut logs = vec![];
+        let mut missing = heights.start..heights.start;
+        for height in heights {
+            match self.blocks.logs(height) {
+                Some(cached) => {
+                    logs.extend(self.fetch_logs(missing, head).await?);
+                    logs.push(cached);
+                    missing = height + 1..height + 1;
+                }
+                None => missing.end = height + 1,
+            }
+        }
+        logs.extend(self.fetch_logs(missing, head).await?);
+
+        Ok(logs)
+    }
+
+    async fn fetch_logs(&self, heights: Range<u64>, head: u64) -> Result<Vec<Vec<Log>>> {
+        if heights.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let blocks = fetch_blocks(
+            &self.adapter,
+            heights,
+            self.block_fetch_failure,
+            "eth_getLogs",
+        )
+        .await?;
+
+        let mut logs = vec![];
+        for block in blocks {
+            logs.push(self.block_logs(&block, head)?);
+        }
+
+        Ok(logs)
+    }
+
+    async fn logs(&self, filter: Filter) -> Result<Vec<Log>> {
+        let (from, to) = self.filter_range(&filter)?;
+        let to = self.limit_range(from, to)?;
+        let head = self.head();
+
+        let params = FilteredParams::new(Some(filter.clone()));
+        let mut logs = vec![];
+        for range in self.candidates(from..=to, &filter) {
+            for block_logs in self.range_logs(range, head).await? {
+                logs.extend(matching(block_logs, &params));
+            }
+        }
+
+        Ok(logs)
+    }
+
+    /// Streams the logs matching `filter` block by block, in block order, for queries whose
+    /// results are too large to be returned at once.
+    ///
+    /// The logs are never buffered beyond the blocks being fetched, so unlike `eth_getLogs`
+    /// the range is not limited by `max_block_range`. The stream ends after the first error.
+    pub fn stream_logs(
+        self: Arc<Self>,
+        filter: Filter,
+    ) -> Result<impl Stream<Item = Result<Vec<Log>>> + Send + 'static> {
+        let (from, to) = self.filter_range(&filter)?;
+        let head = self.head();
+        let heights = self.candidates(from..=to, &filter).into_iter().flatten();
+        let params = Arc::new(FilteredParams::new(Some(filter)));
+
+        Ok(stream::iter(heights)
+            .map(move |height| {
+                let this = self.clone();
+                let params = params.clone();
+                async move {
+                    let logs = match this.blocks.logs(height) {
+                        Some(logs) => logs,
+                        None => {
+                            let block = this.adapter.get_block_by_height(height, true).await?;
+                            this.block_logs(&block, head)?
+                        }
+                    };
+                    Ok(matching(logs, &params))
+                }
+            })
+            .buffered(STREAM_CONCURRENCY))
+    }
+}
+
+/// Fetches the blocks in `heights` with their transactions, in ascending order, for
+/// `method`.
+///
+/// Under [`BlockFetchFailure::FailFast`] the blocks are fetched as a range and the first
+/// failure fails the whole fetch. Under [`BlockFetchFailure::SkipAndWarn`] they are fetched
+/// one by one from the start, so a failing block costs only its own fetch, and the heights
+/// of those that fail are left out and logged in a single warning.
+pub async fn fetch_blocks<Adapter>(
+    adapter: &Adapter,
+    heights: Range<u64>,
+    policy: BlockFetchFailure,
+    method: &str,
+) -> Result<Vec<aptos_api_types::Block>>
+where
+    Adapter: EngineAdapter + Sync,
+{
+    if policy == BlockFetchFailure::FailFast {
+        return Ok(adapter.get_blocks(heights, true).await?);
+    }
+
+    let fetched: Vec<_> = stream::iter(heights)
+        .map(|height| async move { (height, adapter.get_block_by_height(height, true).await) })
+        .buffered(STREAM_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut blocks = vec![];
+    let mut skipped = vec![];
+    for (height, block) in fetched {
+        match block {
+            Ok(block) => blocks.push(block),
+            Err(e) => {
+                tracing::debug!("Failed to fetch block {}: {:#}", height, e);
+                skipped.push(height);
+            }
+        }
+    }
+    if !skipped.is_empty() {
+        tracing::warn!(
+            "Skipped blocks {:?} that could not be fetched from {} results",
+            skipped,
+            method
+        );
+    }
+
+    Ok(blocks)
+}
+
+/// Keeps the logs matching the filter addresses and topics.
+fn matching(logs: Vec<Log>, params: &FilteredParams) -> Vec<Log> {
+    logs.into_iter()
+        .filter(|log| params.filter_address(&log.address()) && params.filter_topics(log.topics()))
+        .collect()
+}
+
+#[async_trait::async_trait]
+impl<Adapter> EthFilterApiServer<()> for EthFilter<Adapter>
+where
+    Adapter: EngineAdapter + Send + Sync + 'static,
+{
+    /// Creates a new filter and returns its id.
+    ///
+    /// Installed filters are not supported, see [`EthFilter::logs`] for queries by range.
+    async fn new_filter(&self, _filter: Filter) -> RpcResult<FilterId> {
+        Err(SidecarError::Unsupported("eth_newFilter").into())
+    }
+
+    /// Creates a new block filter and returns its id.
+    async fn new_block_filter(&self) -> RpcResult<FilterId> {
+        Err(SidecarError::Unsupported("eth_newBlockFilter").into())
+    }
+
+    /// Creates a pending transaction filter and returns its id.
+    async fn new_pending_transaction_filter(
+        &self,
+        _kind: Option<PendingTransactionFilterKind>,
+    ) -> RpcResult<FilterId> {
+        Err(SidecarError::Unsupported("eth_newPendingTransactionFilter").into())
+    }
+
+    /// Returns all filter changes since last poll.
+    async fn filter_changes(&self, _id: FilterId) -> RpcResult<FilterChanges<()>> {
+        Err(SidecarError::Unsupported("eth_getFilterChanges").into())
+    }
+
+    /// Returns all logs matching given filter (in a range 'from' - 'to').
+    async fn filter_logs(&self, _id: FilterId) -> RpcResult<Vec<Log>> {
+        Err(SidecarError::Unsupported("eth_getFilterLogs").into())
+    }
+
+    /// Uninstalls filter.
+    async fn uninstall_filter(&self, _id: FilterId) -> RpcResult<bool> {
+        Err(SidecarError::Unsupported("eth_uninstallFilter").into())
+    }
+
+    /// Returns logs matching given filter object.
+    ///
+    /// Only blocks whose bloom is already indexed can be skipped without fetching them, and
+    /// a bloom is indexed once its block has been fetched, so the first scan of a range
+    /// still fetches every block in it; repeated scans skip the blocks that cannot match.
+    async fn logs(&self, filter: Filter) -> RpcResult<Vec<Log>> {
+        tracing::debug!("logs rpc request received: filter={:?}", filter);
+
+        Ok(EthFilter::logs(self, filter).await?)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{EthFilter, EthFilterApiServer};
+    use crate::{
+        config::server::BlockFetchFailure,
+        engine::{
+            adapter::mock::{
+                ledger_info, sign_evm_transaction, user_transaction, MockEngineAdapter,
+            },
+            tracker::HeadTracker,
+            EngineClient,
+        },
+        error::SidecarError,
+        rpc::block_cache::BlockCache,
+    };
+    use alloy_consensus::TxLegacy;
+    use alloy_eips::eip2718::Encodable2718;
+    use alloy_primitives::{Address, Bloom};
+    use alloy_rpc_types_eth::{Filter, FilterId};
+    use std::{sync::Arc, time::Duration};
+
+    fn eth_filter(adapter: &MockEngineAdapter) -> EthFilter<MockEngineAdapter> {
+        let tracker = Arc::new(HeadTracker::new(
+            EngineClient::new(Box::new(adapter.clone())),
+            Duration::from_millis(100),
+        ));
+
+        EthFilter::new(adapter.clone(), tracker, "0x100::evm::Log".into())
+    }
+
+    /// Returns an EVM transaction that emits a log of `address`.
+    fn logging_transaction(address: Address) -> aptos_api_types::UserTransaction {
+        let tx = TxLegacy {
+            chain_id: Some(4),
+            gas_limit: 21_000,
+            ..Default::default()
+        };
+        let mut tx = user_transaction(&sign_evm_transaction(tx).0.encoded_2718());
+        tx.events = vec![serde_json::from_value(serde_json::json!({
+            "guid": {
+                "creation_number": "0",
+                "account_address": "0x0",
+            },
+            "sequence_number": "0",
+            "type": "0x100::evm::Log",
+            "data": {
+                "address": address,
+                "topics": [],
+                "data": "0x",
+            },
+        }))
+        .unwrap()];
+        tx
+    }
+
+    #[tokio::test]
+    async fn rejects_installed_filters_as_unsupported() {
+        let filter = eth_filter(&MockEngineAdapter::new());
+
+        let err = EthFilterApiServer::new_filter(&filter, Filter::new())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), -32601);
+        let err = filter.filter_changes(FilterId::Num(1)).await.unwrap_err();
+        assert_eq!(err.message(), "eth_getFilterChanges is not supported");
+    }
+
+    #[tokio::test]
+    async fn skips_blocks_whose_bloom_excludes_filter() {
+        let adapter = MockEngineAdapter::new();
+        let filter = eth_filter(&adapter);
+        filter.indexes.blooms.insert(5, Bloom::ZERO);
+
+        let query = Filter::new()
+            .from_block(5)
+            .to_block(6)
+            .address(Address::repeat_byte(0x11));
+        assert!(filter.logs(query.clone()).await.unwrap().is_empty());
+        assert_eq!(adapter.calls("get_block_by_height"), 1);
+
+        // Block 6 has been seen now, and its empty bloom excludes the address too.
+        assert!(filter.logs(query).await.unwrap().is_empty());
+        assert_eq!(adapter.calls("get_block_by_height"), 1);
+    }
+
+    #[tokio::test]
+    async fn fetches_every_block_for_an_open_filter() {
+        let adapter = MockEngineAdapter::new();
+        let filter = eth_filter(&adapter);
+        filter.indexes.blooms.insert(5, Bloom::ZERO);
+
+        filter
+            .logs(Filter::new().from_block(5).to_block(6))
+            .await
+            .unwrap();
+
+        assert_eq!(adapter.calls("get_block_by_height"), 2);
+    }
+
+    #[tokio::test]
+    async fn rejects_range_above_max_block_range() {
+        let adapter = MockEngineAdapter::new();
+        let filter = eth_filter(&adapter).with_max_block_range(2, false);
+
+        let err = filter
+            .logs(Filter::new().from_block(5).to_block(7))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SidecarError::Validation(_)));
+        assert_eq!(adapter.calls("get_block_by_height"), 0);
+
+        filter
+            .logs(Filter::new().from_block(5).to_block(6))
+            .await
+            .unwrap();
+        assert_eq!(adapter.calls("get_block_by_height"), 2);
+    }
+
+    #[tokio::test]
+    async fn clamps_range_to_max_block_range() {
+        let adapter = MockEngineAdapter::new();
+        let filter = eth_filter(&adapter).with_max_block_range(2, true);
+
+        filter
+            .logs(Filter::new().from_block(5).to_block(9))
+            .await
+            .unwrap();
+
+        assert_eq!(adapter.calls("get_block_by_height"), 2);
+        assert!(filter.indexes.blooms.get(6).is_some());
+        assert!(filter.indexes.blooms.get(7).is_none());
+    }
+
+    #[tokio::test]
+    async fn serves_repeated_queries_from_cached_logs() {
+        let adapter = MockEngineAdapter::new();
+        let blocks = Arc::new(BlockCache::new(16));
+        let filter = eth_filter(&adapter).with_block_cache(blocks.clone());
+        filter.tracker.update(ledger_info(4, 6));
+        let query = Filter::new().from_block(4).to_block(6);
+
+        filter.logs(query.clone()).await.unwrap();
+        assert_eq!(adapter.calls("get_block_by_height"), 3);
+
+        // Blocks 4 and 5 are cached; the head block 6 is fetched again.
+        filter.logs(query).await.unwrap();
+        assert_eq!(adapter.calls("get_block_by_height"), 4);
+        assert_eq!((blocks.hits(), blocks.misses()), (2, 4));
+    }
+
+    #[tokio::test]
+    async fn fails_range_on_block_fetch_failure_by_default() {
+        let adapter = MockEngineAdapter::new().with_failing_block(6);
+        let filter = eth_filter(&adapter);
+
+        assert!(filter
+            .logs(Filter::new().from_block(5).to_block(7))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn skips_blocks_that_fail_to_fetch_when_configured() {
+        let adapter = MockEngineAdapter::new()
+            .with_block_transactions(vec![logging_transaction(Address::repeat_byte(0x11))])
+            .with_failing_block(6);
+        let filter = eth_filter(&adapter).with_block_fetch_failure(BlockFetchFailure::SkipAndWarn);
+
+        let logs = filter
+            .logs(Filter::new().from_block(5).to_block(7))
+            .await
+            .unwrap();
+
+        let heights = logs.iter().map(|log| log.block_number).collect::<Vec<_>>();
+        assert_eq!(heights, vec![Some(5), Some(7)]);
+        assert!(logs
+            .iter()
+            .all(|log| log.address() == Address::repeat_byte(0x11)));
+        // Each block is fetched once, without first failing on the whole range.
+        assert_eq!(adapter.calls("get_block_by_height"), 3);
+        assert!(filter.indexes.blooms.get(5).is_some());
+        assert!(filter.indexes.blooms.get(6).is_none());
+        assert!(filter.indexes.blooms.get(7).is_some());
+    }
+}
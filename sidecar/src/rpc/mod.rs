@@ -15,5 +15,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod block_cache;
+pub mod convert;
 pub mod eth;
+pub mod filter;
 pub mod net;
+pub mod revert;
+pub mod sidecar;
+pub mod web3;
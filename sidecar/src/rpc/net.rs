@@ -15,20 +15,49 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::{engine::tracker::HeadTracker, error::SidecarError};
 use alloy_primitives::U64;
 use jsonrpsee::core::RpcResult as Result;
 use reth_rpc_api::NetApiServer;
+use std::sync::Arc;
 
 /// `Net` API implementation.
 ///
 /// This type provides the functionality for handling `net` related requests.
-pub struct NetApi;
+pub struct NetApi {
+    tracker: Arc<HeadTracker>,
+    chain_id: Option<u64>,
+}
+
+impl NetApi {
+    pub fn new(tracker: Arc<HeadTracker>) -> Self {
+        Self {
+            tracker,
+            chain_id: None,
+        }
+    }
+
+    /// Serves `chain_id` as the network id instead of the engine chain id.
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+}
 
 impl NetApiServer for NetApi {
     /// Handler for `net_version`
     fn version(&self) -> Result<String> {
         tracing::debug!("version rpc request received");
-        Ok(U64::from_be_slice(&hex::decode("deadbeef").unwrap()).to_string())
+
+        if let Some(chain_id) = self.chain_id {
+            return Ok(chain_id.to_string());
+        }
+        let ledger_info = self.tracker.latest_or_fetch().map_err(|e| {
+            tracing::warn!("Ledger info unavailable: {}", e);
+            SidecarError::NotReady
+        })?;
+
+        Ok(ledger_info.chain_id.to_string())
     }
 
     /// Handler for `net_peerCount`
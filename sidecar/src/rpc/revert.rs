@@ -0,0 +1,146 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{config::engine::AbortReasonConfig, error::SidecarError};
+use alloy_dyn_abi::DynSolValue;
+use alloy_primitives::Bytes;
+use std::collections::HashMap;
+
+/// Selector of the `Error(string)` revert data of a revert reason.
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Revert reasons of the Move abort codes raised by the engine.
+///
+/// A call or transaction that aborts only reports the VM status of the abort, such as
+/// `Move abort in 0x100::evm: 0x10003`. Known abort codes are reported with their
+/// configured reason instead, and unknown ones with the VM status itself.
+#[derive(Debug, Clone, Default)]
+pub struct AbortReasons {
+    reasons: HashMap<(String, u64), String>,
+}
+
+impl AbortReasons {
+    pub fn new(config: &[AbortReasonConfig]) -> Self {
+        Self {
+            reasons: config
+                .iter()
+                .map(|abort| ((abort.module.clone(), abort.code), abort.reason.clone()))
+                .collect(),
+        }
+    }
+
+    /// Returns the revert reason of the abort reported by `vm_status`, or `None` if it does not
+    /// report an abort.
+    pub fn reason(&self, vm_status: &str) -> Option<String> {
+        let (module, code) = parse_abort(vm_status)?;
+
+        Some(
+            self.reasons
+                .get(&(module.to_string(), code))
+                .cloned()
+                .unwrap_or_else(|| vm_status.to_string()),
+        )
+    }
+
+    /// Turns an engine error reporting an abort into a revert carrying its reason.
+    pub fn revert(&self, err: SidecarError) -> SidecarError {
+        match &err {
+            SidecarError::Engine(message) => match self.reason(message) {
+                Some(reason) => SidecarError::Reverted(reason),
+                None => err,
+            },
+            _ => err,
+        }
+    }
+}
+
+/// Encodes `reason` as the revert data of `revert("<reason>")`, an `Error(string)`.
+pub fn revert_data(reason: &str) -> Bytes {
+    let encoded =
+        DynSolValue::Tuple(vec![DynSolValue::String(reason.to_string())]).abi_encode_params();
+
+    [ERROR_SELECTOR.as_slice(), &encoded].concat().into()
+}
+
+/// Extracts the module and code of an abort from a VM status, which reads either
+/// `Move abort in <module>: <code>` or `Move abort in <module>: <NAME>(<code>): <description>`.
+fn parse_abort(vm_status: &str) -> Option<(&str, u64)> {
+    let (_, abort) = vm_status.split_once("Move abort in ")?;
+    let (module, rest) = abort.split_once(": ")?;
+    let code = rest.split(':').next()?.trim();
+    let code = match code.split_once('(') {
+        Some((_, code)) => code.strip_suffix(')')?,
+        None => code,
+    };
+
+    let code = u64::from_str_radix(code.strip_prefix("0x")?, 16).ok()?;
+    Some((module, code))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{parse_abort, AbortReasons};
+    use crate::{config::engine::AbortReasonConfig, error::SidecarError};
+
+    fn reasons() -> AbortReasons {
+        AbortReasons::new(&[AbortReasonConfig {
+            module: "0x100::evm".into(),
+            code: 0x10003,
+            reason: "insufficient balance for transfer".into(),
+        }])
+    }
+
+    #[test]
+    fn parses_abort_locations_and_codes() {
+        assert_eq!(
+            parse_abort("Move abort in 0x100::evm: 0x10003"),
+            Some(("0x100::evm", 0x10003))
+        );
+        assert_eq!(
+            parse_abort(
+                "Move abort in 0x1::coin: EINSUFFICIENT_BALANCE(0x10006): Not enough coins"
+            ),
+            Some(("0x1::coin", 0x10006))
+        );
+        assert_eq!(parse_abort("Out of gas"), None);
+    }
+
+    #[test]
+    fn resolves_mapped_abort_code() {
+        let err = reasons().revert(SidecarError::Engine(
+            "Move abort in 0x100::evm: 0x10003".into(),
+        ));
+
+        let SidecarError::Reverted(reason) = err else {
+            panic!("expected a revert, got {:?}", err);
+        };
+        assert_eq!(reason, "insufficient balance for transfer");
+    }
+
+    #[test]
+    fn falls_back_to_vm_status_for_unmapped_abort_code() {
+        let vm_status = "Move abort in 0x100::evm: 0x10004";
+
+        let err = reasons().revert(SidecarError::Engine(vm_status.into()));
+
+        assert!(matches!(err, SidecarError::Reverted(reason) if reason == vm_status));
+        assert!(matches!(
+            reasons().revert(SidecarError::Engine("Out of gas".into())),
+            SidecarError::Engine(_)
+        ));
+    }
+}
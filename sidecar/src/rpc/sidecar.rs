@@ -0,0 +1,664 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    engine::{
+        accounts::AccountCache,
+        adapter::EngineAdapter,
+        health::{EndpointStatus, UpstreamHealth},
+        indexes::Indexes,
+        tracker::HeadTracker,
+    },
+    error::{Result, SidecarError},
+    rpc::{
+        block_cache::BlockCache,
+        convert::{self, GasConversion, HeaderConstants},
+        eth::{to_aptos_address, RpcReceipt},
+    },
+    server::subscription::SubscriptionManager,
+};
+use alloy_eips::BlockNumberOrTag;
+use alloy_primitives::{Address, Bytes, B256, U256, U64};
+use alloy_rpc_types_eth::{Header, Transaction};
+use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use serde::Serialize;
+use std::{sync::Arc, time::Duration};
+
+/// Number of engine transactions `sidecar_getAccountTransactions` reads if no limit is given.
+const DEFAULT_ACCOUNT_TRANSACTIONS: u64 = 25;
+
+/// Largest number of engine transactions `sidecar_getAccountTransactions` reads at once.
+const MAX_ACCOUNT_TRANSACTIONS: u64 = 100;
+
+/// Non-standard methods exposed by the sidecar.
+#[rpc(server, namespace = "sidecar")]
+pub trait SidecarApi {
+    /// Returns the balance of an allowlisted asset type, in the asset's base units.
+    #[method(name = "getAssetBalance")]
+    async fn asset_balance(&self, address: Address, asset: String) -> RpcResult<U256>;
+
+    /// Returns the state of the upstream engine and of the sidecar caches.
+    #[method(name = "status")]
+    fn status(&self) -> RpcResult<SidecarStatus>;
+
+    /// Waits until the head reaches `min_height` and returns the header of the block at
+    /// `min_height`, or `None` if it is not reached within `timeout` milliseconds.
+    ///
+    /// Lets clients that cannot subscribe to `newHeads` follow the chain without tight
+    /// polling. The timeout is capped by the server.
+    #[method(name = "waitForBlock")]
+    async fn wait_for_block(
+        &self,
+        min_height: U64,
+        timeout: Option<u64>,
+    ) -> RpcResult<Option<Header>>;
+
+    /// Returns the EVM transactions sent by `address` in nonce order, starting at nonce
+    /// `start`, or at the first transaction if omitted.
+    ///
+    /// At most `limit` engine transactions are read, 25 by default and never more than 100,
+    /// so a page holds fewer EVM transactions if the account also sent other engine
+    /// transactions. The next page starts after the nonce of the last transaction returned.
+    #[method(name = "getAccountTransactions")]
+    async fn account_transactions(
+        &self,
+        address: Address,
+        start: Option<U64>,
+        limit: Option<U64>,
+    ) -> RpcResult<Vec<Transaction>>;
+}
+
+/// Non-standard methods that read receipts, served along with the `eth` methods.
+#[rpc(server, namespace = "sidecar")]
+pub trait SidecarReceiptsApi {
+    /// Returns the receipts of the EVM transactions of every block from `from_block` to
+    /// `to_block` inclusive, in block order, spanning at most 100 blocks.
+    ///
+    /// Blocks that cannot be fetched fail the call, or are left out and logged if the
+    /// server skips them, like `eth_getLogs`; the block number of each receipt tells which.
+    #[method(name = "getBlockReceiptsRange")]
+    async fn block_receipts_range(
+        &self,
+        from_block: BlockNumberOrTag,
+        to_block: BlockNumberOrTag,
+    ) -> RpcResult<Vec<RpcReceipt>>;
+}
+
+/// Non-standard methods that submit transactions or reset submission state.
+#[rpc(server, namespace = "sidecar")]
+pub trait SidecarSubmitApi {
+    /// Submits a raw transaction like `eth_sendRawTransaction`, with its engine transaction
+    /// expiring at `expiration`, in seconds since the Unix epoch, instead of after the
+    /// configured timeout.
+    ///
+    /// The expiration is capped by the server, so the transaction may expire earlier. An
+    /// expiration that is not in the future is rejected.
+    #[method(name = "sendRawTransactionWithExpiration")]
+    async fn send_raw_transaction_with_expiration(
+        &self,
+        bytes: Bytes,
+        expiration: u64,
+    ) -> RpcResult<B256>;
+
+    /// Evicts the cached engine account of `address` and the transactions tracked as pending
+    /// for it, and returns its sequence number fetched again from the engine.
+    ///
+    /// Lets a relayer whose nonce is stuck recover without restarting the sidecar.
+    #[method(name = "refreshAccount")]
+    async fn refresh_account(&self, address: Address) -> RpcResult<U64>;
+}
+
+/// Introspection summary returned by `sidecar_status`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarStatus {
+    /// Latest ledger observed from the engine, or `None` before the first poll.
+    pub upstream: Option<UpstreamStatus>,
+    /// Health and state of each engine endpoint, empty if the engine is not probed.
+    pub upstream_endpoints: Vec<EndpointStatus>,
+    pub caches: CacheStatus,
+    pub active_subscriptions: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamStatus {
+    pub chain_id: u8,
+    pub ledger_height: u64,
+    pub ledger_version: u64,
+    /// Ledger timestamp in seconds.
+    pub ledger_timestamp: u64,
+}
+
+/// Number of entries in each cache.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStatus {
+    pub pending_transactions: usize,
+    pub blooms: usize,
+    pub block_hashes: usize,
+    pub transaction_hashes: usize,
+    pub accounts: usize,
+    pub blocks: usize,
+    /// Block and log lookups answered from the block cache.
+    pub block_cache_hits: u64,
+    /// Block and log lookups that had to convert the block again.
+    pub block_cache_misses: u64,
+}
+
+/// `sidecar` API implementation.
+pub struct SidecarApi<Adapter> {
+    adapter: Adapter,
+    tracker: Arc<HeadTracker>,
+    assets: Vec<String>,
+    indexes: Arc<Indexes>,
+    accounts: Arc<AccountCache>,
+    blocks: Arc<BlockCache>,
+    subscriptions: Option<SubscriptionManager>,
+    health: Option<Arc<UpstreamHealth>>,
+    genesis: Option<Header>,
+    max_block_wait: Duration,
+    header: HeaderConstants,
+    gas: GasConversion,
+    entry_func: String,
+}
+
+impl<Adapter> SidecarApi<Adapter> {
+    pub fn new(adapter: Adapter, tracker: Arc<HeadTracker>, assets: Vec<String>) -> Self {
+        Self {
+            adapter,
+            tracker,
+            assets,
+            indexes: Default::default(),
+            accounts: Default::default(),
+            blocks: Default::default(),
+            subscriptions: None,
+            health: None,
+            genesis: None,
+            max_block_wait: Duration::from_secs(20),
+            header: Default::default(),
+            gas: Default::default(),
+            entry_func: "0x100::evm::transact".into(),
+        }
+    }
+
+    /// Reports the sizes of the indexes shared with the other handlers.
+    pub fn with_indexes(mut self, indexes: Arc<Indexes>) -> Self {
+        self.indexes = indexes;
+        self
+    }
+
+    pub fn with_account_cache(mut self, accounts: Arc<AccountCache>) -> Self {
+        self.accounts = accounts;
+        self
+    }
+
+    /// Reports the size and hit rate of the block cache shared with the other handlers.
+    pub fn with_block_cache(mut self, blocks: Arc<BlockCache>) -> Self {
+        self.blocks = blocks;
+        self
+    }
+
+    pub fn with_subscriptions(mut self, subscriptions: SubscriptionManager) -> Self {
+        self.subscriptions = Some(subscriptions);
+        self
+    }
+
+    /// Reports the health of the engine endpoints probed by `health`.
+    pub fn with_upstream_health(mut self, health: Arc<UpstreamHealth>) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    /// Serves the header of `genesis` in place of the engine block at height 0.
+    pub fn with_genesis(mut self, genesis: Header) -> Self {
+        self.genesis = Some(genesis);
+        self
+    }
+
+    /// Longest time `sidecar_waitForBlock` waits for a block.
+    pub fn with_max_block_wait(mut self, max_block_wait: Duration) -> Self {
+        self.max_block_wait = max_block_wait;
+        self
+    }
+
+    /// Lists the user transactions calling `entry_func` as EVM transactions.
+    pub fn with_entry_func(mut self, entry_func: String) -> Self {
+        self.entry_func = entry_func;
+        self
+    }
+
+    /// Renders the fields of every block header that the engine has no counterpart for
+    /// from `header`.
+    pub fn with_header_constants(mut self, header: HeaderConstants) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Reports the gas used by blocks in EVM gas converted by `gas`, like the receipts.
+    pub fn with_gas_conversion(mut self, gas: GasConversion) -> Self {
+        self.gas = gas;
+        self
+    }
+}
+
+impl<Adapter> SidecarApi<Adapter>
+where
+    Adapter: EngineAdapter,
+{
+    /// Returns the EVM hash of the block at `height`.
+    async fn block_hash(&self, height: u64) -> Result<B256> {
+        if let (0, Some(genesis)) = (height, &self.genesis) {
+            return Ok(genesis.hash);
+        }
+        if let Some(hash) = self.indexes.block_hashes.hash(height) {
+            return Ok(hash);
+        }
+
+        let block = self.adapter.get_block_by_height(height, false).await?;
+        let hash = convert::block_hash(&block.block_hash);
+        self.indexes.block_hashes.insert(height, hash);
+
+        Ok(hash)
+    }
+
+    /// Converts the committed EVM transactions among `transactions`, locating each in its
+    /// block. Consecutive transactions of the same block share a single block fetch.
+    async fn committed_transactions(
+        &self,
+        transactions: &[aptos_api_types::Transaction],
+    ) -> Result<Vec<Transaction>> {
+        let mut rendered = vec![];
+        let mut cached: Option<aptos_api_types::Block> = None;
+        for tx in transactions {
+            let aptos_api_types::Transaction::UserTransaction(tx) = tx else {
+                continue;
+            };
+            if !convert::is_evm_transaction(tx, &self.entry_func)
+                || convert::evm_transaction_hash(tx).is_err()
+            {
+                continue;
+            }
+
+            let version = tx.info.version.0;
+            let block = match cached.take() {
+                Some(block)
+                    if (block.first_version.0..=block.last_version.0).contains(&version) =>
+                {
+                    block
+                }
+                _ => self.adapter.get_block_by_version(version, true).await?,
+            };
+            self.indexes
+                .block_hashes
+                .insert(block.block_height.0, convert::block_hash(&block.block_hash));
+            rendered.push(convert::to_rpc_committed_transaction(
+                tx,
+                &block,
+                &self.entry_func,
+            )?);
+            cached = Some(block);
+        }
+
+        Ok(rendered)
+    }
+}
+
+#[async_trait::async_trait]
+impl<Adapter> SidecarApiServer for SidecarApi<Adapter>
+where
+    Adapter: EngineAdapter + Send + Sync + 'static,
+{
+    /// Handler for `sidecar_getAssetBalance`
+    async fn asset_balance(&self, address: Address, asset: String) -> RpcResult<U256> {
+        tracing::debug!("asset_balance rpc request received");
+
+        if !self.assets.contains(&asset) {
+            return Err(SidecarError::Validation(format!("asset {} is not allowed", asset)).into());
+        }
+
+        let balance = self
+            .adapter
+            .get_account_balance(to_aptos_address(&address), &asset)
+            .await
+            .map_err(SidecarError::from)?;
+
+        Ok(U256::from(balance))
+    }
+
+    /// Handler for `sidecar_status`
+    fn status(&self) -> RpcResult<SidecarStatus> {
+        tracing::debug!("status rpc request received");
+
+        let upstream = self.tracker.latest().map(|info| UpstreamStatus {
+            chain_id: info.chain_id,
+            ledger_height: info.block_height.0,
+            ledger_version: info.ledger_version.0,
+            ledger_timestamp: info.ledger_timestamp.0 / 1_000_000,
+        });
+
+        Ok(SidecarStatus {
+            upstream,
+            upstream_endpoints: self
+                .health
+                .as_ref()
+                .map(|health| health.endpoints())
+                .unwrap_or_default(),
+            caches: CacheStatus {
+                pending_transactions: self.indexes.pending.len(),
+                blooms: self.indexes.blooms.len(),
+                block_hashes: self.indexes.block_hashes.len(),
+                transaction_hashes: self.indexes.transaction_hashes.len(),
+                accounts: self.accounts.len(),
+                blocks: self.blocks.len(),
+                block_cache_hits: self.blocks.hits(),
+                block_cache_misses: self.blocks.misses(),
+            },
+            active_subscriptions: self
+                .subscriptions
+                .as_ref()
+                .map_or(0, SubscriptionManager::total),
+        })
+    }
+
+    /// Handler for `sidecar_waitForBlock`
+    async fn wait_for_block(
+        &self,
+        min_height: U64,
+        timeout: Option<u64>,
+    ) -> RpcResult<Option<Header>> {
+        tracing::debug!("wait_for_block rpc request received");
+
+        let height = min_height.to::<u64>();
+        let timeout = timeout
+            .map(Duration::from_millis)
+            .map_or(self.max_block_wait, |timeout| {
+                timeout.min(self.max_block_wait)
+            });
+        if tokio::time::timeout(timeout, self.tracker.wait_for_height(height))
+            .await
+            .is_err()
+        {
+            return Ok(None);
+        }
+
+        if let (0, Some(genesis)) = (height, &self.genesis) {
+            return Ok(Some(genesis.clone()));
+        }
+
+        // The transactions are needed for the gas used by the block.
+        let block = self
+            .adapter
+            .get_block_by_height(height, true)
+            .await
+            .map_err(SidecarError::from)?;
+        self.indexes
+            .block_hashes
+            .insert(height, convert::block_hash(&block.block_hash));
+        let parent_hash = match height {
+            0 => B256::ZERO,
+            _ => self.block_hash(height - 1).await?,
+        };
+
+        Ok(Some(convert::to_rpc_header(
+            &block,
+            parent_hash,
+            &self.header,
+            &self.entry_func,
+            &self.gas,
+        )))
+    }
+
+    /// Handler for `sidecar_getAccountTransactions`
+    async fn account_transactions(
+        &self,
+        address: Address,
+        start: Option<U64>,
+        limit: Option<U64>,
+    ) -> RpcResult<Vec<Transaction>> {
+        tracing::debug!("account_transactions rpc request received");
+
+        let limit = limit
+            .map_or(DEFAULT_ACCOUNT_TRANSACTIONS, |limit| limit.to::<u64>())
+            .min(MAX_ACCOUNT_TRANSACTIONS);
+        let transactions = self
+            .adapter
+            .get_account_transactions(
+                to_aptos_address(&address),
+                start.map(|start| start.to::<u64>()),
+                limit,
+            )
+            .await
+            .map_err(SidecarError::from)?;
+
+        Ok(self.committed_transactions(&transactions).await?)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{SidecarApi, SidecarApiServer};
+    use crate::{
+        engine::{
+            adapter::mock::{
+                ledger_info, sign_evm_transaction, user_transaction, MockEngineAdapter,
+            },
+            health::UpstreamHealth,
+            tracker::HeadTracker,
+            EngineClient,
+        },
+        rpc::eth::to_aptos_address,
+        server::subscription::SubscriptionManager,
+    };
+    use alloy_consensus::{Transaction as _, TxLegacy};
+    use alloy_eips::eip2718::Encodable2718;
+    use alloy_primitives::{Address, TxKind, B256, U256, U64};
+    use jsonrpsee::ConnectionId;
+    use std::{sync::Arc, time::Duration};
+
+    const USDC: &str = "0x1::fungible_asset::USDC";
+
+    fn sidecar_api(adapter: &MockEngineAdapter) -> SidecarApi<MockEngineAdapter> {
+        let tracker = Arc::new(HeadTracker::new(
+            EngineClient::new(Box::new(adapter.clone())),
+            Duration::from_millis(100),
+        ));
+
+        SidecarApi::new(adapter.clone(), tracker, vec![USDC.into()])
+    }
+
+    #[tokio::test]
+    async fn returns_allowlisted_asset_balance() {
+        let adapter = MockEngineAdapter::new().with_balance(250);
+        let api = sidecar_api(&adapter);
+        let address = Address::repeat_byte(0x44);
+
+        assert_eq!(
+            api.asset_balance(address, USDC.into()).await.unwrap(),
+            U256::from(250)
+        );
+        assert_eq!(
+            adapter.balance_queries(),
+            vec![(to_aptos_address(&address), USDC.to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_asset_outside_allowlist() {
+        let adapter = MockEngineAdapter::new();
+        let api = sidecar_api(&adapter);
+
+        let err = api
+            .asset_balance(
+                Address::repeat_byte(0x44),
+                "0x1::fungible_asset::DAI".into(),
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.code(), -32602);
+        assert_eq!(adapter.calls("get_account_balance"), 0);
+    }
+
+    #[tokio::test]
+    async fn reports_upstream_and_cache_status() {
+        let adapter = MockEngineAdapter::new();
+        let subscriptions = SubscriptionManager::new(2, 10);
+        let _permit = subscriptions.acquire(ConnectionId(1)).unwrap();
+        let api = sidecar_api(&adapter).with_subscriptions(subscriptions);
+        api.indexes.block_hashes.insert(7, B256::repeat_byte(0x07));
+
+        let status = serde_json::to_value(api.status().unwrap()).unwrap();
+        assert_eq!(status["upstream"], serde_json::Value::Null);
+
+        api.tracker.poll().await.unwrap();
+        let status = serde_json::to_value(api.status().unwrap()).unwrap();
+        assert_eq!(
+            status,
+            serde_json::json!({
+                "upstream": {
+                    "chainId": 4,
+                    "ledgerHeight": 42,
+                    "ledgerVersion": 420,
+                    "ledgerTimestamp": 1_700_000_000,
+                },
+                "upstreamEndpoints": [],
+                "caches": {
+                    "pendingTransactions": 0,
+                    "blooms": 0,
+                    "blockHashes": 1,
+                    "transactionHashes": 0,
+                    "accounts": 0,
+                    "blocks": 0,
+                    "blockCacheHits": 0,
+                    "blockCacheMisses": 0,
+                },
+                "activeSubscriptions": 1,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_state_of_each_endpoint() {
+        let adapter = MockEngineAdapter::new().with_write_endpoint(None);
+        let health = Arc::new(UpstreamHealth::new().with_failure_threshold(1));
+        health.probe(&adapter).await;
+        let api = sidecar_api(&adapter).with_upstream_health(health);
+
+        let status = serde_json::to_value(api.status().unwrap()).unwrap();
+        assert_eq!(
+            status["upstreamEndpoints"],
+            serde_json::json!([
+                {
+                    "endpoint": "read",
+                    "state": "up",
+                    "error": null,
+                    "blockHeight": 42,
+                },
+                {
+                    "endpoint": "write",
+                    "state": "down",
+                    "error": "ledger info unavailable",
+                    "blockHeight": null,
+                },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn returns_header_when_head_reaches_height() {
+        let api = Arc::new(sidecar_api(&MockEngineAdapter::new()));
+        api.tracker.update(ledger_info(4, 9));
+
+        let waiter = tokio::spawn({
+            let api = api.clone();
+            async move { api.wait_for_block(U64::from(10), Some(5_000)).await }
+        });
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+
+        api.tracker.update(ledger_info(4, 10));
+        let header = waiter.await.unwrap().unwrap().unwrap();
+        assert_eq!(header.number, 10);
+        assert_eq!(header.hash, B256::with_last_byte(11));
+        assert_eq!(header.parent_hash, B256::with_last_byte(10));
+    }
+
+    #[tokio::test]
+    async fn returns_none_when_wait_times_out() {
+        let adapter = MockEngineAdapter::new();
+        let api = sidecar_api(&adapter).with_max_block_wait(Duration::from_millis(10));
+        api.tracker.update(ledger_info(4, 9));
+
+        let header = api
+            .wait_for_block(U64::from(10), Some(60_000))
+            .await
+            .unwrap();
+        assert!(header.is_none());
+        assert_eq!(adapter.calls("get_block_by_height"), 0);
+    }
+
+    /// Returns a committed user transaction carrying an EVM transaction with `nonce`, at
+    /// ledger version `100 + nonce`, all of them in block 10.
+    fn account_transaction(nonce: u64) -> aptos_api_types::UserTransaction {
+        let tx = TxLegacy {
+            chain_id: Some(4),
+            nonce,
+            gas_price: 100,
+            gas_limit: 21_000,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            ..Default::default()
+        };
+        let mut user = user_transaction(&sign_evm_transaction(tx).0.encoded_2718());
+        user.request.sequence_number = nonce.into();
+        user.info.version = (100 + nonce).into();
+        user
+    }
+
+    #[tokio::test]
+    async fn pages_through_account_transactions() {
+        let transactions = (0..3).map(account_transaction).collect::<Vec<_>>();
+        let adapter = MockEngineAdapter::new()
+            .with_account_transactions(transactions.clone())
+            .with_block_transactions(transactions);
+        let api = sidecar_api(&adapter);
+        let address = Address::repeat_byte(0x44);
+
+        let first = api
+            .account_transactions(address, None, Some(U64::from(2)))
+            .await
+            .unwrap();
+        let page = first
+            .iter()
+            .map(|tx| (tx.inner.nonce(), tx.block_number, tx.transaction_index))
+            .collect::<Vec<_>>();
+        assert_eq!(page, vec![(0, Some(10), Some(0)), (1, Some(10), Some(1))]);
+        // Both transactions are in the same block, which is fetched once.
+        assert_eq!(adapter.calls("get_block_by_version"), 1);
+
+        let next = first.last().unwrap().inner.nonce() + 1;
+        let second = api
+            .account_transactions(address, Some(U64::from(next)), Some(U64::from(2)))
+            .await
+            .unwrap();
+        let page = second
+            .iter()
+            .map(|tx| (tx.inner.nonce(), tx.transaction_index))
+            .collect::<Vec<_>>();
+        assert_eq!(page, vec![(2, Some(2))]);
+    }
+}
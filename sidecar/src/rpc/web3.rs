@@ -0,0 +1,75 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy_primitives::{keccak256, Bytes, B256};
+use jsonrpsee::core::RpcResult as Result;
+use reth_rpc_api::Web3ApiServer;
+
+/// `web3` API implementation.
+///
+/// This type provides the functionality for handling `web3` related requests.
+pub struct Web3Api;
+
+#[async_trait::async_trait]
+impl Web3ApiServer for Web3Api {
+    /// Handler for `web3_clientVersion`
+    async fn client_version(&self) -> Result<String> {
+        tracing::debug!("client_version rpc request received");
+        Ok(client_version())
+    }
+
+    /// Handler for `web3_sha3`
+    fn sha3(&self, input: Bytes) -> Result<B256> {
+        Ok(keccak256(input))
+    }
+}
+
+pub fn client_version() -> String {
+    format!(
+        "{}/v{}/{}-{}",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::Web3Api;
+    use alloy_primitives::{b256, Bytes};
+    use reth_rpc_api::Web3ApiServer;
+
+    #[tokio::test]
+    async fn client_version_includes_crate_version() {
+        let version = Web3Api.client_version().await.unwrap();
+
+        assert!(version.starts_with(&format!("engine-sidecar/v{}/", env!("CARGO_PKG_VERSION"))));
+    }
+
+    #[test]
+    fn sha3_hashes_input() {
+        assert_eq!(
+            Web3Api.sha3(Bytes::new()).unwrap(),
+            b256!("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+        );
+        assert_eq!(
+            Web3Api.sha3(Bytes::from_static(b"hello")).unwrap(),
+            b256!("1c8aff950685c2ed4bc3174f3472287b56d9517b9c948127319a09a7a36deac8")
+        );
+    }
+}
@@ -0,0 +1,222 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::Context;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use std::{
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::Arc,
+};
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Address of the client that sent a request, resolved by [`resolve_client_ip`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// A network in CIDR notation, or a single address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cidr {
+    network: IpAddr,
+    prefix: u32,
+}
+
+impl Cidr {
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (network, prefix) = match s.split_once('/') {
+            Some((network, prefix)) => (network, Some(prefix)),
+            None => (s, None),
+        };
+        let network = IpAddr::from_str(network)?.to_canonical();
+        let max_prefix = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse()?,
+            None => max_prefix,
+        };
+        anyhow::ensure!(prefix <= max_prefix, "prefix length {} is too long", prefix);
+
+        Ok(Self { network, prefix })
+    }
+}
+
+/// Proxies trusted to report the address of the client they forward a request for.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(Vec<Cidr>);
+
+impl TrustedProxies {
+    pub fn parse(proxies: &[String]) -> anyhow::Result<Self> {
+        proxies
+            .iter()
+            .map(|proxy| {
+                proxy
+                    .parse()
+                    .with_context(|| format!("Invalid trusted proxy {}", proxy))
+            })
+            .collect::<anyhow::Result<_>>()
+            .map(Self)
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.0.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    /// Resolves the address of the client of a request received from `peer`.
+    ///
+    /// `X-Forwarded-For` is only believed when `peer` is a trusted proxy. Each proxy appends
+    /// the address it received the request from, so the client is the last forwarded address
+    /// that is not a trusted proxy itself; addresses to its left may be forged by the client.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.contains(peer) {
+            return peer;
+        }
+
+        let forwarded = headers
+            .get_all(X_FORWARDED_FOR)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect::<Vec<_>>();
+
+        let mut client = peer;
+        for address in forwarded.into_iter().rev() {
+            let Ok(ip) = address.trim().parse::<IpAddr>() else {
+                break;
+            };
+            client = ip;
+            if !self.contains(ip) {
+                break;
+            }
+        }
+
+        client
+    }
+}
+
+/// Middleware that resolves the [`ClientIp`] of a request from its socket peer address and
+/// stores it in the request extensions.
+pub async fn resolve_client_ip(
+    State(proxies): State<Arc<TrustedProxies>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if let Some(ConnectInfo(peer)) = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .copied()
+    {
+        let ip = proxies.client_ip(peer.ip(), request.headers());
+        request.extensions_mut().insert(ClientIp(ip));
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{resolve_client_ip, ClientIp, TrustedProxies};
+    use axum::{
+        body::{to_bytes, Body},
+        extract::ConnectInfo,
+        http::Request,
+        middleware::from_fn_with_state,
+        routing::get,
+        Extension, Router,
+    };
+    use std::{
+        net::{IpAddr, SocketAddr},
+        sync::Arc,
+    };
+    use tower::ServiceExt;
+
+    async fn resolved_ip(peer: &str, forwarded_for: &str) -> String {
+        let proxies = TrustedProxies::parse(&["10.0.0.0/8".into(), "::1".into()]).unwrap();
+        let app = Router::new()
+            .route(
+                "/",
+                get(|Extension(ClientIp(ip)): Extension<ClientIp>| async move { ip.to_string() }),
+            )
+            .layer(from_fn_with_state(Arc::new(proxies), resolve_client_ip));
+
+        let request = Request::get("/")
+            .header("x-forwarded-for", forwarded_for)
+            .extension(ConnectInfo(peer.parse::<SocketAddr>().unwrap()))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn uses_forwarded_address_from_trusted_proxy() {
+        assert_eq!(
+            resolved_ip("10.1.2.3:4000", "203.0.113.7").await,
+            "203.0.113.7"
+        );
+        // Addresses added by other trusted proxies are skipped, and those left of the client
+        // are ignored as they may be forged.
+        assert_eq!(
+            resolved_ip("[::1]:4000", "198.51.100.1, 203.0.113.7, 10.9.9.9").await,
+            "203.0.113.7"
+        );
+    }
+
+    #[tokio::test]
+    async fn ignores_forwarded_address_from_untrusted_peer() {
+        assert_eq!(
+            resolved_ip("192.0.2.10:4000", "203.0.113.7").await,
+            "192.0.2.10"
+        );
+    }
+
+    #[test]
+    fn matches_addresses_within_networks() {
+        let proxies = TrustedProxies::parse(&["10.0.0.0/8".into(), "fd00::/8".into()]).unwrap();
+
+        assert!(proxies.contains(IpAddr::from([10, 255, 0, 1])));
+        assert!(!proxies.contains(IpAddr::from([11, 0, 0, 1])));
+        assert!(proxies.contains("fd12::1".parse().unwrap()));
+        assert!(proxies.contains("::ffff:10.0.0.1".parse().unwrap()));
+        assert!(TrustedProxies::parse(&["10.0.0.0/33".into()]).is_err());
+        assert!(TrustedProxies::parse(&["proxy.internal".into()]).is_err());
+    }
+}
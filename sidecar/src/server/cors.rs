@@ -15,22 +15,144 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::config::server::ItemOrList;
-use axum::http::HeaderValue;
-use tower_http::cors::{AllowOrigin, CorsLayer};
+use crate::config::server::CorsConfig;
+use anyhow::bail;
+use axum::http::{HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
 
-pub fn cors_layer(cors: Option<ItemOrList<String>>) -> anyhow::Result<CorsLayer> {
-    let origins = cors.map(|c| c.into_list()).unwrap_or_default();
+pub fn cors_layer(cors: Option<CorsConfig>) -> anyhow::Result<CorsLayer> {
+    let Some(cors) = cors else {
+        return Ok(CorsLayer::new());
+    };
+    let credentials = cors.allow_credentials();
 
-    match origins.as_slice() {
-        [] => Ok(CorsLayer::new()),
-        [origin] if origin == "*" || origin == "all" => Ok(CorsLayer::permissive()),
+    let origins = cors.allowed_origins();
+    let mut layer = match origins.as_slice() {
+        [] => CorsLayer::new(),
+        [origin] if is_wildcard(origin) => {
+            if credentials {
+                bail!("CORS credentials cannot be allowed for a wildcard origin");
+            }
+            CorsLayer::new().allow_origin(AllowOrigin::any())
+        }
         origins => {
             let list = origins
                 .iter()
                 .map(|o| HeaderValue::from_str(o))
                 .collect::<Result<Vec<_>, _>>()?;
-            Ok(CorsLayer::new().allow_origin(AllowOrigin::list(list)))
+            CorsLayer::new().allow_origin(AllowOrigin::list(list))
+        }
+    };
+
+    // Browsers ignore `*` for methods and headers on credentialed requests, so those mirror
+    // the preflight request instead.
+    layer = match cors.allowed_methods() {
+        Some(methods) if methods.iter().any(|m| is_wildcard(m)) => {
+            if credentials {
+                bail!("CORS credentials cannot be allowed for wildcard methods");
+            }
+            layer.allow_methods(AllowMethods::any())
+        }
+        Some(methods) => {
+            let list = methods
+                .iter()
+                .map(|m| Method::from_bytes(m.as_bytes()))
+                .collect::<Result<Vec<_>, _>>()?;
+            layer.allow_methods(AllowMethods::list(list))
         }
+        None if credentials => layer.allow_methods(AllowMethods::mirror_request()),
+        None => layer.allow_methods(AllowMethods::any()),
+    };
+
+    layer = match cors.allowed_headers() {
+        Some(headers) if headers.iter().any(|h| is_wildcard(h)) => {
+            if credentials {
+                bail!("CORS credentials cannot be allowed for wildcard headers");
+            }
+            layer.allow_headers(AllowHeaders::any())
+        }
+        Some(headers) => {
+            let list = headers
+                .iter()
+                .map(|h| HeaderName::from_bytes(h.as_bytes()))
+                .collect::<Result<Vec<_>, _>>()?;
+            layer.allow_headers(AllowHeaders::list(list))
+        }
+        None if credentials => layer.allow_headers(AllowHeaders::mirror_request()),
+        None => layer.allow_headers(AllowHeaders::any()),
+    };
+
+    if let Some(max_age) = cors.max_age() {
+        layer = layer.max_age(max_age);
+    }
+
+    Ok(layer.allow_credentials(credentials))
+}
+
+fn is_wildcard(value: &str) -> bool {
+    value == "*" || value == "all"
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::cors_layer;
+    use crate::config::server::{CorsConfig, ItemOrList};
+    use axum::{
+        body::Body,
+        http::{header, Method, Request},
+        routing::post,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn allows_credentialed_requests_from_listed_origins() {
+        let config = CorsConfig {
+            allowed_origins: Some(ItemOrList::Item("https://app.example".into())),
+            allowed_methods: Some(ItemOrList::List(vec!["POST".into(), "OPTIONS".into()])),
+            allow_credentials: Some(true),
+            max_age_seconds: Some(600),
+            ..Default::default()
+        };
+        let app = Router::new()
+            .route("/", post(|| async {}))
+            .layer(cors_layer(Some(config)).unwrap());
+
+        let request = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/")
+            .header(header::ORIGIN, "https://app.example")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let headers = response.headers();
+
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_METHODS],
+            "POST,OPTIONS"
+        );
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_HEADERS],
+            "content-type"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+    }
+
+    #[test]
+    fn rejects_credentials_with_wildcard_origin() {
+        let config = CorsConfig {
+            allowed_origins: Some(ItemOrList::Item("*".into())),
+            allow_credentials: Some(true),
+            ..Default::default()
+        };
+
+        assert!(cors_layer(Some(config)).is_err());
     }
 }
@@ -0,0 +1,202 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{engine::EngineClient, error::SidecarError, rpc::filter::EthFilter};
+use alloy_rpc_types_eth::{Filter, Log};
+use axum::{
+    body::{Body, Bytes},
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use futures::{future, StreamExt};
+use serde::Deserialize;
+use std::{convert::Infallible, sync::Arc};
+
+#[derive(Deserialize)]
+struct StreamQuery {
+    /// `eth_getLogs` filter object, JSON-encoded.
+    filter: String,
+}
+
+/// Serves `GET /logs/stream?filter=<filter>`, which streams the logs matching an
+/// `eth_getLogs` filter as newline-delimited JSON, one log per line.
+///
+/// The logs are written block by block as they are fetched. A failure after the response
+/// has started is reported as a final `{"error": ...}` line.
+pub fn logs_stream_router(filter: Arc<EthFilter<EngineClient>>) -> Router {
+    Router::new()
+        .route("/logs/stream", get(stream_logs))
+        .with_state(filter)
+}
+
+async fn stream_logs(
+    State(filter_api): State<Arc<EthFilter<EngineClient>>>,
+    Query(query): Query<StreamQuery>,
+) -> Response {
+    let filter: Filter = match serde_json::from_str(&query.filter) {
+        Ok(filter) => filter,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, format!("invalid filter: {}", e)).into_response()
+        }
+    };
+    let logs = match filter_api.stream_logs(filter) {
+        Ok(logs) => logs,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    let body = logs
+        .scan(false, |failed, logs| {
+            let chunk = if *failed {
+                None
+            } else {
+                *failed = logs.is_err();
+                Some(ndjson(logs))
+            };
+            future::ready(chunk)
+        })
+        .filter(|chunk| future::ready(!chunk.is_empty()))
+        .map(Ok::<_, Infallible>);
+
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+/// Encodes the logs of a block, or the error that ended the stream, as JSON lines.
+fn ndjson(logs: Result<Vec<Log>, SidecarError>) -> Bytes {
+    let mut chunk = vec![];
+    match logs {
+        Ok(logs) => {
+            for log in logs {
+                serde_json::to_writer(&mut chunk, &log).expect("Failed to serialize log");
+                chunk.push(b'\n');
+            }
+        }
+        Err(e) => {
+            tracing::warn!("Log stream failed: {}", e);
+            serde_json::to_writer(&mut chunk, &serde_json::json!({ "error": e.to_string() }))
+                .expect("Failed to serialize error");
+            chunk.push(b'\n');
+        }
+    }
+
+    chunk.into()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::logs_stream_router;
+    use crate::{
+        engine::{
+            adapter::mock::{sign_evm_transaction, user_transaction, MockEngineAdapter},
+            tracker::HeadTracker,
+            EngineClient,
+        },
+        rpc::filter::EthFilter,
+    };
+    use alloy_consensus::TxLegacy;
+    use alloy_eips::eip2718::Encodable2718;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use futures::StreamExt;
+    use std::{sync::Arc, time::Duration};
+    use tower::ServiceExt;
+
+    const LOG_EVENT: &str = "0x100::evm::Log";
+
+    fn filter_api(adapter: MockEngineAdapter) -> Arc<EthFilter<EngineClient>> {
+        let client = EngineClient::new(Box::new(adapter));
+        let tracker = Arc::new(HeadTracker::new(client.clone(), Duration::from_millis(100)));
+
+        Arc::new(EthFilter::new(client, tracker, LOG_EVENT.into()))
+    }
+
+    fn logging_transaction() -> aptos_api_types::UserTransaction {
+        let tx = TxLegacy {
+            chain_id: Some(4),
+            gas_limit: 21_000,
+            ..Default::default()
+        };
+        let mut tx = user_transaction(&sign_evm_transaction(tx).0.encoded_2718());
+        tx.events = vec![serde_json::from_value(serde_json::json!({
+            "guid": {
+                "creation_number": "0",
+                "account_address": "0x0",
+            },
+            "sequence_number": "0",
+            "type": LOG_EVENT,
+            "data": {
+                "address": "0x1111111111111111111111111111111111111111",
+                "topics": [],
+                "data": "0x",
+            },
+        }))
+        .unwrap()];
+        tx
+    }
+
+    #[tokio::test]
+    async fn streams_logs_block_by_block() {
+        let adapter = MockEngineAdapter::new().with_block_transactions(vec![logging_transaction()]);
+        let filter = r#"{"fromBlock":"0x5","toBlock":"0x7"}"#;
+        let request = Request::get(format!(
+            "/logs/stream?filter={}",
+            filter
+                .replace('{', "%7B")
+                .replace('}', "%7D")
+                .replace('"', "%22")
+        ))
+        .body(Body::empty())
+        .unwrap();
+
+        let response = logs_stream_router(filter_api(adapter))
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let mut chunks = response.into_body().into_data_stream();
+        for height in 5..=7 {
+            let chunk = chunks.next().await.unwrap().unwrap();
+            let line = std::str::from_utf8(&chunk).unwrap();
+            assert_eq!(line.lines().count(), 1);
+            let log: serde_json::Value = serde_json::from_str(line.trim_end()).unwrap();
+            assert_eq!(log["blockNumber"], format!("0x{:x}", height));
+        }
+        assert!(chunks.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_filter() {
+        let request = Request::get("/logs/stream?filter=nope")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = logs_stream_router(filter_api(MockEngineAdapter::new()))
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,207 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    engine::{
+        accounts::AccountCache, adapter::EngineAdapter, indexes::Indexes, sync_lag::SyncLagMonitor,
+        EngineClient,
+    },
+    error::{LIMIT_EXCEEDED_CODE, METHOD_NOT_SUPPORTED_CODE},
+    rpc::block_cache::BlockCache,
+};
+use axum::{extract::State, http::header, response::IntoResponse, routing::get, Router};
+use jsonrpsee::types::error::{
+    CALL_EXECUTION_FAILED_CODE, INVALID_PARAMS_CODE, INVALID_REQUEST_CODE, METHOD_NOT_FOUND_CODE,
+    PARSE_ERROR_CODE,
+};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, Mutex},
+};
+
+/// Category of the result of a JSON-RPC call, derived from its error code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Outcome {
+    Success,
+    /// The request was malformed, named an unknown method or exceeded a limit.
+    ClientError,
+    /// The engine rejected or failed the request (-32000).
+    UpstreamError,
+    /// The sidecar failed to serve the request.
+    ServerError,
+}
+
+impl Outcome {
+    /// Classifies a call by its error code, or as a success without one.
+    pub fn from_code(code: Option<i64>) -> Self {
+        let Some(code) = code else {
+            return Outcome::Success;
+        };
+
+        match i32::try_from(code) {
+            Ok(
+                PARSE_ERROR_CODE
+                | INVALID_REQUEST_CODE
+                | METHOD_NOT_FOUND_CODE
+                | INVALID_PARAMS_CODE
+                | METHOD_NOT_SUPPORTED_CODE
+                | LIMIT_EXCEEDED_CODE,
+            ) => Outcome::ClientError,
+            Ok(CALL_EXECUTION_FAILED_CODE) => Outcome::UpstreamError,
+            _ => Outcome::ServerError,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Outcome::Success => "success",
+            Outcome::ClientError => "client_error",
+            Outcome::UpstreamError => "upstream_error",
+            Outcome::ServerError => "server_error",
+        }
+    }
+}
+
+/// Counters of the JSON-RPC calls served, by method and outcome.
+#[derive(Debug, Default)]
+pub struct RequestMetrics {
+    counts: Mutex<BTreeMap<(String, Outcome), u64>>,
+}
+
+impl RequestMetrics {
+    pub fn record(&self, method: &str, outcome: Outcome) {
+        *self
+            .counts
+            .lock()
+            .unwrap()
+            .entry((method.to_string(), outcome))
+            .or_default() += 1;
+    }
+
+    /// Number of calls to `method` with `outcome`.
+    pub fn count(&self, method: &str, outcome: Outcome) -> u64 {
+        self.counts
+            .lock()
+            .unwrap()
+            .get(&(method.to_string(), outcome))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn render(&self) -> String {
+        let mut output = String::from(
+            "# HELP rpc_requests_total JSON-RPC calls served, by method and outcome.\n\
+             # TYPE rpc_requests_total counter\n",
+        );
+        for ((method, outcome), count) in self.counts.lock().unwrap().iter() {
+            let _ = writeln!(
+                output,
+                "rpc_requests_total{{method=\"{}\",outcome=\"{}\"}} {}",
+                method,
+                outcome.label(),
+                count
+            );
+        }
+        output
+    }
+}
+
+/// Gauges served at `/metrics` in the Prometheus text format.
+#[derive(Clone)]
+pub struct Metrics {
+    client: EngineClient,
+    indexes: Arc<Indexes>,
+    blocks: Arc<BlockCache>,
+    accounts: Arc<AccountCache>,
+    sync_lag: Option<Arc<SyncLagMonitor>>,
+    requests: Option<Arc<RequestMetrics>>,
+}
+
+impl Metrics {
+    pub fn new(
+        client: EngineClient,
+        indexes: Arc<Indexes>,
+        blocks: Arc<BlockCache>,
+        accounts: Arc<AccountCache>,
+    ) -> Self {
+        Self {
+            client,
+            indexes,
+            blocks,
+            accounts,
+            sync_lag: None,
+            requests: None,
+        }
+    }
+
+    pub fn with_sync_lag(mut self, monitor: Arc<SyncLagMonitor>) -> Self {
+        self.sync_lag = Some(monitor);
+        self
+    }
+
+    /// Also serves the counters of the calls recorded in `requests`.
+    pub fn with_requests(mut self, requests: Arc<RequestMetrics>) -> Self {
+        self.requests = Some(requests);
+        self
+    }
+
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        let _ = write!(
+            output,
+            "# HELP upstream_requests_in_use Requests to the engine in flight.\n\
+             # TYPE upstream_requests_in_use gauge\n\
+             upstream_requests_in_use {}\n",
+            self.client.upstream_requests_in_use()
+        );
+        output.push_str(
+            "# HELP cache_entries Entries held by each in-memory cache.\n\
+             # TYPE cache_entries gauge\n",
+        );
+        for (cache, entries) in [
+            ("pending", self.indexes.pending.len()),
+            ("blooms", self.indexes.blooms.len()),
+            ("block_hashes", self.indexes.block_hashes.len()),
+            ("transaction_hashes", self.indexes.transaction_hashes.len()),
+            ("blocks", self.blocks.len()),
+            ("accounts", self.accounts.len()),
+        ] {
+            let _ = writeln!(output, "cache_entries{{cache=\"{}\"}} {}", cache, entries);
+        }
+        if let Some(monitor) = &self.sync_lag {
+            output.push_str(&monitor.render());
+        }
+        if let Some(requests) = &self.requests {
+            output.push_str(&requests.render());
+        }
+        output
+    }
+}
+
+pub fn metrics_router(metrics: Metrics) -> Router {
+    Router::new()
+        .route("/metrics", get(serve_metrics))
+        .with_state(metrics)
+}
+
+async fn serve_metrics(State(metrics): State<Metrics>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.render(),
+    )
+}
@@ -15,29 +15,81 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod client_ip;
 pub mod cors;
+pub mod logs_stream;
+pub mod metrics;
+pub mod params;
+pub mod rate_limit;
+pub mod readiness;
 pub mod router;
 pub mod rpc;
+pub mod subscription;
+pub mod ws;
 
 use crate::{
-    config::server::{ItemOrList, ServerConfig},
-    engine::EngineClient,
-    rpc::{eth::EthApi, net::NetApi},
+    config::{
+        engine::{BalanceConfig, GenesisConfig},
+        server::{BlockFetchFailure, CorsConfig, ProofUnavailable, ServerConfig},
+    },
+    engine::{
+        accounts::AccountCache,
+        health::UpstreamHealth,
+        indexes::{IndexCapacities, Indexes},
+        sync_lag::SyncLagMonitor,
+        tracker::HeadTracker,
+        EngineClient,
+    },
+    error::SidecarError,
+    rpc::{
+        block_cache::BlockCache,
+        convert::{genesis_block, GasConversion, HeaderConstants},
+        eth::EthApi,
+        filter::EthFilter,
+        net::NetApi,
+        revert::AbortReasons,
+        sidecar::{SidecarApi, SidecarApiServer, SidecarReceiptsApiServer, SidecarSubmitApiServer},
+        web3::Web3Api,
+    },
 };
-use axum::{error_handling::HandleErrorLayer, http::StatusCode};
+use axum::{error_handling::HandleErrorLayer, http::StatusCode, middleware::from_fn_with_state};
+use client_ip::{resolve_client_ip, TrustedProxies};
 use cors::cors_layer;
-use jsonrpsee::RpcModule;
-use reth_rpc_api::NetApiServer;
-use reth_rpc_eth_api::EthApiServer;
-use std::{net::SocketAddr, time::Duration};
+use jsonrpsee::{core::RegisterMethodError, Methods, RpcModule};
+use logs_stream::logs_stream_router;
+use metrics::{metrics_router, Metrics, RequestMetrics};
+use rate_limit::{rate_limit, RateLimiter};
+use readiness::{readiness_router, Readiness};
+use reth_rpc_api::{NetApiServer, Web3ApiServer};
+use reth_rpc_eth_api::{EthApiServer, EthFilterApiServer};
+use rpc::{LogSampler, RpcState};
+use std::{
+    collections::HashMap, future::Future, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration,
+};
+use subscription::SubscriptionManager;
 use tokio::signal;
 use tower::{BoxError, ServiceBuilder};
 use tower_http::ServiceBuilderExt;
+use ws::{ws_router, WsState};
 
 pub struct Server {
     addr: SocketAddr,
     request_timeout: Duration,
-    cors: Option<ItemOrList<String>>,
+    cors: Option<CorsConfig>,
+    trusted_proxies: Vec<String>,
+    rate_limit: Option<(f64, u32)>,
+    max_request_bytes: usize,
+    max_response_bytes: usize,
+    stream_logs: bool,
+    persist_state_path: Option<PathBuf>,
+    read_only: bool,
+    max_head_staleness: Duration,
+    upstream_health: Option<Arc<UpstreamHealth>>,
+    index_capacities: IndexCapacities,
+    sync_lag: Option<Arc<SyncLagMonitor>>,
+    log_sample_rate: f64,
+    method_aliases: HashMap<String, String>,
+    strict_params: bool,
 }
 
 impl Server {
@@ -45,15 +97,79 @@ impl Server {
         Server {
             addr: config.addr(),
             request_timeout: config.request_timeout(),
-            cors: config.cors,
+            max_request_bytes: config.max_request_bytes(),
+            max_response_bytes: config.max_response_bytes(),
+            stream_logs: config.stream_logs(),
+            log_sample_rate: config.log_sample_rate(),
+            method_aliases: config.method_aliases(),
+            strict_params: config.strict_params(),
+            persist_state_path: config.persist_state_path().map(Into::into),
+            trusted_proxies: config.trusted_proxies().to_vec(),
+            rate_limit: config.rate_limit(),
+            cors: config.cors(),
+            read_only: false,
+            max_head_staleness: Duration::from_secs(60),
+            upstream_health: None,
+            index_capacities: Default::default(),
+            sync_lag: None,
         }
     }
 
-    pub async fn start(&self, client: EngineClient) {
+    /// Rejects transaction submission and signing, see [`disable_submission`].
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Reports not ready once the engine ledger lags the wall clock by `max_head_staleness`.
+    pub fn with_max_head_staleness(mut self, max_head_staleness: Duration) -> Self {
+        self.max_head_staleness = max_head_staleness;
+        self
+    }
+
+    /// Reports not ready while the engine health probe fails.
+    pub fn with_upstream_health(mut self, health: Arc<UpstreamHealth>) -> Self {
+        self.upstream_health = Some(health);
+        self
+    }
+
+    /// Bounds the in-memory indexes by `capacities`.
+    pub fn with_index_capacities(mut self, capacities: IndexCapacities) -> Self {
+        self.index_capacities = capacities;
+        self
+    }
+
+    /// Also serves the gauge of `monitor` at `/metrics`.
+    pub fn with_sync_lag(mut self, monitor: Arc<SyncLagMonitor>) -> Self {
+        self.sync_lag = Some(monitor);
+        self
+    }
+
+    pub async fn start(
+        &self,
+        client: EngineClient,
+        tracker: Arc<HeadTracker>,
+        options: RpcOptions,
+    ) -> anyhow::Result<()> {
         let listener = tokio::net::TcpListener::bind(self.addr)
             .await
             .expect("Failed to bind to address");
 
+        self.serve(listener, client, tracker, options, shutdown_signal())
+            .await
+    }
+
+    /// Serves on `listener` until `shutdown` completes, ignoring the configured address.
+    ///
+    /// Fails before serving if the JSON-RPC methods cannot be registered, see [`rpc_module`].
+    pub async fn serve(
+        &self,
+        listener: tokio::net::TcpListener,
+        client: EngineClient,
+        tracker: Arc<HeadTracker>,
+        options: RpcOptions,
+        shutdown: impl Future<Output = ()> + Send + 'static,
+    ) -> anyhow::Result<()> {
         let middleware = ServiceBuilder::new()
             .layer(HandleErrorLayer::new(|err: BoxError| async move {
                 if err.is::<tower::timeout::error::Elapsed>() {
@@ -64,19 +180,298 @@ impl Server {
             }))
             .timeout(self.request_timeout)
             .trace_for_http()
-            .layer(cors_layer(self.cors.clone()).expect("Failed to create CORS layer"));
+            .layer(cors_layer(self.cors.clone()).expect("Failed to create CORS layer"))
+            .layer(from_fn_with_state(
+                Arc::new(
+                    TrustedProxies::parse(&self.trusted_proxies)
+                        .expect("Failed to parse trusted proxies"),
+                ),
+                resolve_client_ip,
+            ))
+            .option_layer(self.rate_limit.map(|(rate, burst)| {
+                from_fn_with_state(Arc::new(RateLimiter::new(rate, burst)), rate_limit)
+            }));
 
-        let mut module = RpcModule::new(());
-        module.merge(EthApi::new(client).into_rpc()).unwrap();
-        module.merge(NetApi.into_rpc()).unwrap();
+        let indexes = Arc::new(
+            match &self.persist_state_path {
+                Some(path) => Indexes::load_or_default(path),
+                None => Indexes::default(),
+            }
+            .with_capacities(self.index_capacities),
+        );
+        let mut readiness = Readiness::new(tracker.clone(), self.max_head_staleness);
+        if let Some(health) = &self.upstream_health {
+            readiness = readiness.with_health(health.clone());
+        }
+        let requests = Arc::new(RequestMetrics::default());
+        let mut metrics = Metrics::new(
+            client.clone(),
+            indexes.clone(),
+            options.blocks.clone(),
+            options.accounts.clone(),
+        )
+        .with_requests(requests.clone());
+        if let Some(monitor) = &self.sync_lag {
+            metrics = metrics.with_sync_lag(monitor.clone());
+        }
+        let logs_stream = self.stream_logs.then(|| {
+            EthFilter::new(client.clone(), tracker.clone(), options.log_event.clone())
+                .with_entry_func(options.entry_func.clone())
+                .with_indexes(indexes.clone())
+        });
+        let subscriptions = options.subscriptions.clone();
+        let mut module = rpc_module(client, tracker.clone(), indexes.clone(), options)?;
+        if self.read_only {
+            tracing::info!("Transaction submission is disabled");
+            disable_submission(&mut module);
+        }
 
-        let app = router::create_router(module).layer(middleware.into_inner());
+        for (alias, method) in &self.method_aliases {
+            if !module.method_names().any(|name| name == method) {
+                tracing::warn!("Method alias {} maps to unknown method {}", alias, method);
+            }
+        }
 
-        tracing::info!("Starting server at {}", self.addr);
+        let state = RpcState {
+            module,
+            max_request_bytes: self.max_request_bytes,
+            max_response_bytes: self.max_response_bytes,
+            request_timeout: self.request_timeout,
+            sampler: Arc::new(LogSampler::new(self.log_sample_rate)),
+            method_aliases: Arc::new(self.method_aliases.clone()),
+            param_counts: Arc::new(params::param_counts()),
+            strict_params: self.strict_params,
+            metrics: requests,
+        };
+        let mut app = router::create_router(state.clone())
+            .merge(ws_router(WsState::new(state, tracker, subscriptions)))
+            .merge(readiness_router(readiness))
+            .merge(metrics_router(metrics));
+        if let Some(filter) = logs_stream {
+            app = app.merge(logs_stream_router(Arc::new(filter)));
+        }
+        let app = app.layer(middleware.into_inner());
 
-        axum::serve(listener, app)
-            .with_graceful_shutdown(shutdown_signal())
-            .await
+        tracing::info!(
+            "Starting server at {}",
+            listener.local_addr().unwrap_or(self.addr)
+        );
+
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown)
+        .await
+        .unwrap();
+
+        if let Some(path) = &self.persist_state_path {
+            match indexes.save(path) {
+                Ok(()) => tracing::info!("Saved indexes to {}", path.display()),
+                Err(e) => tracing::error!("{:#}", e),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Settings of the JSON-RPC methods served by the sidecar.
+#[derive(Debug, Clone)]
+pub struct RpcOptions {
+    pub native_decimals: u8,
+    /// Assets that can be queried with `sidecar_getAssetBalance`.
+    pub assets: Vec<String>,
+    /// Genesis served in place of the engine block at height 0.
+    pub genesis: Option<GenesisConfig>,
+    /// Entry function the EVM transactions are submitted through.
+    pub entry_func: String,
+    /// Type of the engine event that records an EVM log.
+    pub log_event: String,
+    /// Type of the engine event that records the address of a created contract.
+    pub create_event: String,
+    /// Largest raw transaction accepted by `eth_sendRawTransaction`.
+    pub max_raw_tx_bytes: usize,
+    /// Largest calldata of a call simulated by `eth_call` and similar methods.
+    pub max_calldata_bytes: usize,
+    /// Floor of the gas prices reported by `eth_gasPrice` and `eth_maxPriorityFeePerGas`.
+    pub min_gas_price_wei: u64,
+    /// How long the gas prices estimated by the engine are reused.
+    pub gas_price_ttl: Duration,
+    /// Nominal blob base fee reported by `eth_blobBaseFee`.
+    pub blob_base_fee_wei: u64,
+    /// Base fee of every block header, also reported by `eth_feeHistory`.
+    pub default_base_fee_wei: u64,
+    /// Priority fee of every block reported by `eth_feeHistory`.
+    pub default_priority_fee_wei: u64,
+    /// Gas limit reported in block headers.
+    pub block_gas_limit: u64,
+    /// EVM gas reported for every unit of engine gas.
+    pub evm_gas_per_unit: u64,
+    /// Chain id served to EVM clients instead of the engine chain id.
+    pub evm_chain_id: Option<u64>,
+    /// Largest number of blocks an `eth_getLogs` query may span.
+    pub max_block_range: u64,
+    /// Whether larger `eth_getLogs` ranges are clamped instead of rejected.
+    pub clamp_block_range: bool,
+    /// Whether blocks that cannot be fetched fail `eth_getLogs` or are skipped.
+    pub block_fetch_failure: BlockFetchFailure,
+    /// Whether calls at the `pending` block are rejected instead of run on the latest ledger.
+    pub reject_pending_calls: bool,
+    /// Error returned by `eth_getProof`.
+    pub proof_unavailable: ProofUnavailable,
+    /// Error data of `eth_getProof` telling clients where proofs can be obtained instead.
+    pub proof_unavailable_hint: String,
+    /// Converted blocks kept in memory, shared by the handlers.
+    pub blocks: Arc<BlockCache>,
+    /// Versions the served ledger may lag the highest observed one before reporting syncing.
+    pub sync_lag_threshold: u64,
+    /// Revert reasons of the abort codes raised by the engine.
+    pub abort_reasons: AbortReasons,
+    /// Where native balances are read from.
+    pub balance: BalanceConfig,
+    /// View function returning the EVM code of an account, if the engine has one.
+    pub code_func: Option<String>,
+    /// View function returning a storage slot of an account, if the engine has one.
+    pub storage_func: Option<String>,
+    /// Accounts served from memory.
+    pub accounts: Arc<AccountCache>,
+    /// Open subscriptions, reported by `sidecar_status`.
+    pub subscriptions: SubscriptionManager,
+    /// Longest time `sidecar_waitForBlock` waits for a block.
+    pub max_block_wait: Duration,
+    /// Health of the engine endpoints, reported by `sidecar_status`.
+    pub upstream_health: Option<Arc<UpstreamHealth>>,
+}
+
+/// Builds the JSON-RPC methods served by the sidecar.
+///
+/// Fails with the name of the method if two APIs register a method of the same name.
+pub fn rpc_module(
+    client: EngineClient,
+    tracker: Arc<HeadTracker>,
+    indexes: Arc<Indexes>,
+    options: RpcOptions,
+) -> anyhow::Result<RpcModule<()>> {
+    let blocks = options.blocks;
+    let header = HeaderConstants {
+        gas_limit: options.block_gas_limit,
+        base_fee: options.default_base_fee_wei,
+    };
+    let mut eth = EthApi::new(client.clone(), tracker.clone(), options.native_decimals)
+        .with_indexes(indexes.clone())
+        .with_block_cache(blocks.clone())
+        .with_max_raw_tx_bytes(options.max_raw_tx_bytes)
+        .with_max_calldata_bytes(options.max_calldata_bytes)
+        .with_min_gas_price(options.min_gas_price_wei.into())
+        .with_gas_price_ttl(options.gas_price_ttl)
+        .with_blob_base_fee(options.blob_base_fee_wei.into())
+        .with_events(options.log_event.clone(), options.create_event)
+        .with_entry_func(options.entry_func.clone())
+        .with_header_constants(header)
+        .with_evm_gas_per_unit(options.evm_gas_per_unit)
+        .with_default_priority_fee(options.default_priority_fee_wei.into())
+        .with_balance(options.balance)
+        .with_reject_pending_calls(options.reject_pending_calls)
+        .with_proof_unavailable(options.proof_unavailable, options.proof_unavailable_hint)
+        .with_sync_lag_threshold(options.sync_lag_threshold)
+        .with_abort_reasons(options.abort_reasons)
+        .with_block_fetch_failure(options.block_fetch_failure)
+        .with_account_cache(options.accounts.clone());
+    let mut sidecar = SidecarApi::new(client.clone(), tracker.clone(), options.assets)
+        .with_indexes(indexes.clone())
+        .with_account_cache(options.accounts)
+        .with_block_cache(blocks.clone())
+        .with_subscriptions(options.subscriptions)
+        .with_max_block_wait(options.max_block_wait)
+        .with_header_constants(header)
+        .with_gas_conversion(GasConversion::new(
+            options.evm_gas_per_unit,
+            options.native_decimals,
+        ))
+        .with_entry_func(options.entry_func.clone());
+    if let Some(health) = options.upstream_health {
+        sidecar = sidecar.with_upstream_health(health);
+    }
+    if let Some(genesis) = options.genesis {
+        let genesis = genesis_block(genesis.timestamp(), genesis.extra_data()?);
+        sidecar = sidecar.with_genesis(genesis.header.clone());
+        eth = eth.with_genesis(genesis);
+    }
+    let mut net = NetApi::new(tracker.clone());
+    if let Some(code_func) = options.code_func {
+        eth = eth.with_code_view(code_func);
+    }
+    if let Some(storage_func) = options.storage_func {
+        eth = eth.with_storage_view(storage_func);
+    }
+    if let Some(chain_id) = options.evm_chain_id {
+        eth = eth.with_chain_id(chain_id);
+        net = net.with_chain_id(chain_id);
+    }
+
+    let mut module = RpcModule::new(());
+    merge(
+        &mut module,
+        "sidecar",
+        SidecarSubmitApiServer::into_rpc(eth.clone()),
+    )?;
+    merge(
+        &mut module,
+        "sidecar",
+        SidecarReceiptsApiServer::into_rpc(eth.clone()),
+    )?;
+    merge(&mut module, "eth", EthApiServer::into_rpc(eth))?;
+    merge(
+        &mut module,
+        "eth filter",
+        EthFilter::new(client.clone(), tracker.clone(), options.log_event)
+            .with_entry_func(options.entry_func)
+            .with_indexes(indexes)
+            .with_block_cache(blocks)
+            .with_max_block_range(options.max_block_range, options.clamp_block_range)
+            .with_block_fetch_failure(options.block_fetch_failure)
+            .into_rpc(),
+    )?;
+    merge(&mut module, "sidecar", sidecar.into_rpc())?;
+    merge(&mut module, "net", net.into_rpc())?;
+    merge(&mut module, "web3", Web3Api.into_rpc())?;
+
+    Ok(module)
+}
+
+/// Merges the methods of the `api` API into `module`, naming the conflicting method if one
+/// is already registered.
+fn merge(module: &mut RpcModule<()>, api: &str, methods: impl Into<Methods>) -> anyhow::Result<()> {
+    module.merge(methods).map_err(|e| match e {
+        RegisterMethodError::AlreadyRegistered(method) => anyhow::anyhow!(
+            "Failed to register the {} API: method {} is already registered",
+            api,
+            method
+        ),
+        e => anyhow::Error::new(e).context(format!("Failed to register the {} API", api)),
+    })
+}
+
+/// Methods that submit or sign transactions, or reset the state of submissions.
+const SUBMISSION_METHODS: [&str; 7] = [
+    "eth_sendRawTransaction",
+    "sidecar_sendRawTransactionWithExpiration",
+    "sidecar_refreshAccount",
+    "eth_sendTransaction",
+    "eth_sign",
+    "eth_signTransaction",
+    "eth_signTypedData",
+];
+
+/// Replaces the methods that submit or sign transactions with ones that always fail.
+pub fn disable_submission(module: &mut RpcModule<()>) {
+    for method in SUBMISSION_METHODS {
+        module.remove_method(method);
+        module
+            .register_method(method, |_, _, _| {
+                Err::<(), _>(SidecarError::SubmissionDisabled)
+            })
             .unwrap();
     }
 }
@@ -104,3 +499,109 @@ async fn shutdown_signal() {
         _ = terminate => {},
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::{disable_submission, merge, params::param_counts, rpc_module};
+    use crate::rpc::web3::Web3Api;
+    use crate::{
+        engine::{
+            adapter::mock::{ledger_info, MockEngineAdapter},
+            tracker::HeadTracker,
+            EngineClient,
+        },
+        test_utils::rpc_options,
+    };
+    use jsonrpsee::RpcModule;
+    use reth_rpc_api::Web3ApiServer;
+    use std::{sync::Arc, time::Duration};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn serves_engine_chain_id() {
+        let adapter = MockEngineAdapter::new().with_ledger_info(Some(ledger_info(4, 1)));
+        let client = EngineClient::new(Box::new(adapter));
+        let tracker = Arc::new(HeadTracker::new(client.clone(), Duration::from_millis(100)));
+        let module = rpc_module(client, tracker, Default::default(), rpc_options()).unwrap();
+
+        let (response, _) = module
+            .raw_json_request(
+                r#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId","params":[]}"#,
+                1,
+            )
+            .await
+            .unwrap();
+        assert_eq!(response, r#"{"jsonrpc":"2.0","id":1,"result":"0x4"}"#);
+
+        let (response, _) = module
+            .raw_json_request(
+                r#"{"jsonrpc":"2.0","id":2,"method":"net_version","params":[]}"#,
+                1,
+            )
+            .await
+            .unwrap();
+        assert_eq!(response, r#"{"jsonrpc":"2.0","id":2,"result":"4"}"#);
+    }
+
+    #[test]
+    fn names_method_registered_twice() {
+        let mut module = RpcModule::new(());
+        merge(&mut module, "web3", Web3Api.into_rpc()).unwrap();
+
+        let mut conflicting = RpcModule::new(());
+        conflicting
+            .register_method("web3_clientVersion", |_, _, _| "other")
+            .unwrap();
+
+        let error = merge(&mut module, "other", conflicting).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Failed to register the other API: method web3_clientVersion is already registered"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn rejects_submission_in_read_only_mode() {
+        let adapter = MockEngineAdapter::new().with_ledger_info(Some(ledger_info(4, 1)));
+        let client = EngineClient::new(Box::new(adapter.clone()));
+        let tracker = Arc::new(HeadTracker::new(client.clone(), Duration::from_millis(100)));
+        let mut module = rpc_module(client, tracker, Default::default(), rpc_options()).unwrap();
+        disable_submission(&mut module);
+
+        let (response, _) = module
+            .raw_json_request(
+                r#"{"jsonrpc":"2.0","id":1,"method":"eth_sendRawTransaction","params":["0x02"]}"#,
+                1,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            response,
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"submission disabled"}}"#
+        );
+        assert_eq!(adapter.calls("submit_transaction"), 0);
+
+        let (response, _) = module
+            .raw_json_request(
+                r#"{"jsonrpc":"2.0","id":2,"method":"eth_chainId","params":[]}"#,
+                1,
+            )
+            .await
+            .unwrap();
+        assert_eq!(response, r#"{"jsonrpc":"2.0","id":2,"result":"0x4"}"#);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn declares_param_count_of_every_method() {
+        let client = EngineClient::new(Box::new(MockEngineAdapter::new()));
+        let tracker = Arc::new(HeadTracker::new(client.clone(), Duration::from_millis(100)));
+        let module = rpc_module(client, tracker, Default::default(), rpc_options()).unwrap();
+        let counts = param_counts();
+
+        let mut missing: Vec<_> = module
+            .method_names()
+            .filter(|method| !counts.contains_key(*method))
+            .collect();
+        missing.sort();
+        assert!(missing.is_empty(), "no param count for {:?}", missing);
+    }
+}
@@ -0,0 +1,126 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Positional params accepted by the JSON-RPC methods.
+//!
+//! The generated method handlers read the params they take and silently ignore the rest,
+//! so the number of params a method takes is declared here to reject or drop the extra
+//! ones before dispatching the call.
+
+use jsonrpsee::core::JsonValue as Value;
+use std::collections::HashMap;
+
+/// Largest number of positional params of the methods served by the sidecar.
+const PARAM_COUNTS: &[(&str, usize)] = &[
+    ("eth_protocolVersion", 0),
+    ("eth_syncing", 0),
+    ("eth_coinbase", 0),
+    ("eth_accounts", 0),
+    ("eth_blockNumber", 0),
+    ("eth_chainId", 0),
+    ("eth_getBlockByHash", 2),
+    ("eth_getBlockByNumber", 2),
+    ("eth_getBlockTransactionCountByHash", 1),
+    ("eth_getBlockTransactionCountByNumber", 1),
+    ("eth_getUncleCountByBlockHash", 1),
+    ("eth_getUncleCountByBlockNumber", 1),
+    ("eth_getBlockReceipts", 1),
+    ("eth_getUncleByBlockHashAndIndex", 2),
+    ("eth_getUncleByBlockNumberAndIndex", 2),
+    ("eth_getRawTransactionByHash", 1),
+    ("eth_getTransactionByHash", 1),
+    ("eth_getRawTransactionByBlockHashAndIndex", 2),
+    ("eth_getTransactionByBlockHashAndIndex", 2),
+    ("eth_getRawTransactionByBlockNumberAndIndex", 2),
+    ("eth_getTransactionByBlockNumberAndIndex", 2),
+    ("eth_getTransactionBySenderAndNonce", 2),
+    ("eth_getTransactionReceipt", 1),
+    ("eth_getBalance", 2),
+    ("eth_getStorageAt", 3),
+    ("eth_getTransactionCount", 2),
+    ("eth_getCode", 2),
+    ("eth_getHeaderByNumber", 1),
+    ("eth_getHeaderByHash", 1),
+    ("eth_simulateV1", 2),
+    ("eth_call", 4),
+    ("eth_callMany", 3),
+    ("eth_createAccessList", 2),
+    ("eth_estimateGas", 3),
+    ("eth_gasPrice", 0),
+    ("eth_getAccount", 2),
+    ("eth_maxPriorityFeePerGas", 0),
+    ("eth_blobBaseFee", 0),
+    ("eth_feeHistory", 3),
+    ("eth_mining", 0),
+    ("eth_hashrate", 0),
+    ("eth_getWork", 0),
+    ("eth_submitHashrate", 2),
+    ("eth_submitWork", 3),
+    ("eth_sendTransaction", 1),
+    ("eth_sendRawTransaction", 1),
+    ("eth_sign", 2),
+    ("eth_signTransaction", 1),
+    ("eth_signTypedData", 2),
+    ("eth_getProof", 3),
+    ("eth_newFilter", 1),
+    ("eth_newBlockFilter", 0),
+    ("eth_newPendingTransactionFilter", 1),
+    ("eth_getFilterChanges", 1),
+    ("eth_getFilterLogs", 1),
+    ("eth_uninstallFilter", 1),
+    ("eth_getLogs", 1),
+    ("net_version", 0),
+    ("net_peerCount", 0),
+    ("net_listening", 0),
+    ("web3_clientVersion", 0),
+    ("web3_sha3", 1),
+    ("sidecar_sendRawTransactionWithExpiration", 2),
+    ("sidecar_refreshAccount", 1),
+    ("sidecar_getAssetBalance", 2),
+    ("sidecar_status", 0),
+    ("sidecar_waitForBlock", 2),
+    ("sidecar_getAccountTransactions", 3),
+    ("sidecar_getBlockReceiptsRange", 2),
+];
+
+/// Returns the largest number of positional params of every method served by the sidecar.
+pub fn param_counts() -> HashMap<String, usize> {
+    PARAM_COUNTS
+        .iter()
+        .map(|(method, count)| (method.to_string(), *count))
+        .collect()
+}
+
+/// Returns the number of params the method of `call` takes if `call` passes more positional
+/// params than that.
+///
+/// Calls of methods missing from `counts` and calls passing params by name are never
+/// reported.
+pub fn exceeded(counts: &HashMap<String, usize>, call: &Value) -> Option<usize> {
+    let method = call.get("method").and_then(Value::as_str)?;
+    let count = *counts.get(method)?;
+    let params = call.get("params").and_then(Value::as_array)?;
+
+    (params.len() > count).then_some(count)
+}
+
+/// Drops the positional params of `call` beyond the first `count`.
+pub fn truncate(call: &mut Value, count: usize) {
+    if let Some(params) = call.get_mut("params").and_then(Value::as_array_mut) {
+        params.truncate(count);
+    }
+}
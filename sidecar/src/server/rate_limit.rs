@@ -0,0 +1,223 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::client_ip::ClientIp;
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::{
+    collections::{BTreeSet, HashMap},
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+/// Number of clients whose buckets are kept; the least recently seen are evicted beyond it.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Token bucket of a client: it holds up to `burst` tokens, refilled at `rate` per second,
+/// and every request takes one.
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Buckets of the tracked clients, also ordered by when their client was last seen so the
+/// least recently seen is found without a scan.
+#[derive(Default)]
+struct Buckets {
+    by_ip: HashMap<IpAddr, Bucket>,
+    by_last_seen: BTreeSet<(Instant, IpAddr)>,
+}
+
+/// Limits the request rate of every client IP.
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64, burst: u32) -> Self {
+        Self {
+            rate,
+            burst: f64::from(burst.max(1)),
+            buckets: Default::default(),
+        }
+    }
+
+    /// Takes a token from the bucket of `ip`, or returns how many seconds to wait until a
+    /// token is available.
+    pub fn check(&self, ip: IpAddr) -> Result<(), u64> {
+        self.check_at(ip, Instant::now())
+    }
+
+    fn check_at(&self, ip: IpAddr, now: Instant) -> Result<(), u64> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let Buckets {
+            by_ip,
+            by_last_seen,
+        } = &mut *buckets;
+        if !by_ip.contains_key(&ip) && by_ip.len() >= MAX_TRACKED_CLIENTS {
+            if let Some((_, idle)) = by_last_seen.pop_first() {
+                by_ip.remove(&idle);
+            }
+        }
+
+        let bucket = by_ip.entry(ip).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        by_last_seen.remove(&(bucket.updated, ip));
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated = now;
+        by_last_seen.insert((now, ip));
+
+        if bucket.tokens < 1.0 {
+            let wait = (1.0 - bucket.tokens) / self.rate;
+            return Err((wait.ceil() as u64).max(1));
+        }
+
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+}
+
+/// Middleware that rejects requests over the rate limit of their [`ClientIp`] with
+/// `429 Too Many Requests`.
+///
+/// Requests whose client address is unknown are not limited.
+pub async fn rate_limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let Some(ClientIp(ip)) = request.extensions().get::<ClientIp>().copied() {
+        if let Err(retry_after) = limiter.check(ip) {
+            tracing::debug!("Rate limited {}", ip);
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                "Too many requests",
+            )
+                .into_response();
+        }
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{rate_limit, RateLimiter, MAX_TRACKED_CLIENTS};
+    use crate::server::client_ip::{resolve_client_ip, TrustedProxies};
+    use axum::{
+        body::Body,
+        extract::ConnectInfo,
+        http::{header, Request, StatusCode},
+        middleware::from_fn_with_state,
+        routing::get,
+        Router,
+    };
+    use std::{
+        net::{IpAddr, SocketAddr},
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn rejects_requests_over_the_limit() {
+        let app = Router::new()
+            .route("/", get(|| async { "OK" }))
+            .layer(from_fn_with_state(
+                Arc::new(RateLimiter::new(0.1, 2)),
+                rate_limit,
+            ))
+            .layer(from_fn_with_state(
+                Arc::new(TrustedProxies::default()),
+                resolve_client_ip,
+            ));
+        let request = |peer: &str| {
+            Request::get("/")
+                .extension(ConnectInfo(peer.parse::<SocketAddr>().unwrap()))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let mut statuses = vec![];
+        for _ in 0..3 {
+            let response = app
+                .clone()
+                .oneshot(request("192.0.2.1:4000"))
+                .await
+                .unwrap();
+            statuses.push(response.status());
+            if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                assert_eq!(response.headers()[header::RETRY_AFTER], "10");
+            }
+        }
+        assert_eq!(
+            statuses,
+            vec![
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::TOO_MANY_REQUESTS
+            ]
+        );
+
+        // Other clients have their own budget.
+        let response = app.oneshot(request("192.0.2.2:4000")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn refills_tokens_over_time() {
+        let limiter = RateLimiter::new(1.0, 1);
+        let ip = IpAddr::from([192, 0, 2, 1]);
+        let start = Instant::now();
+
+        assert_eq!(limiter.check_at(ip, start), Ok(()));
+        assert_eq!(limiter.check_at(ip, start), Err(1));
+        assert_eq!(limiter.check_at(ip, start + Duration::from_secs(1)), Ok(()));
+    }
+
+    #[test]
+    fn evicts_least_recently_seen_client() {
+        let limiter = RateLimiter::new(0.001, 1);
+        let client = |i: usize| IpAddr::from((0x0a00_0000 + i as u32).to_be_bytes());
+        let start = Instant::now();
+
+        for i in 0..MAX_TRACKED_CLIENTS {
+            let at = start + Duration::from_millis(i as u64);
+            assert_eq!(limiter.check_at(client(i), at), Ok(()));
+        }
+        // Seeing the first client again makes the second one the least recently seen.
+        let later = start + Duration::from_secs(60);
+        assert!(limiter.check_at(client(0), later).is_err());
+
+        let new_client = client(MAX_TRACKED_CLIENTS);
+        assert_eq!(limiter.check_at(new_client, later), Ok(()));
+        assert!(limiter.check_at(client(0), later).is_err());
+        // The evicted client starts over with a full bucket.
+        assert_eq!(limiter.check_at(client(1), later), Ok(()));
+    }
+}
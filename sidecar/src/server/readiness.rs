@@ -0,0 +1,203 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::engine::{health::UpstreamHealth, tracker::HeadTracker};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Decides whether the sidecar can serve requests: the engine must be reachable and its
+/// ledger must keep advancing.
+#[derive(Clone)]
+pub struct Readiness {
+    tracker: Arc<HeadTracker>,
+    max_head_staleness: Duration,
+    health: Option<Arc<UpstreamHealth>>,
+}
+
+impl Readiness {
+    pub fn new(tracker: Arc<HeadTracker>, max_head_staleness: Duration) -> Self {
+        Self {
+            tracker,
+            max_head_staleness,
+            health: None,
+        }
+    }
+
+    /// Reports not ready without reaching the engine while `health` reports it unhealthy.
+    pub fn with_health(mut self, health: Arc<UpstreamHealth>) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    /// Fetches the ledger info and returns why the sidecar is not ready, if it is not.
+    ///
+    /// An engine that still answers but whose ledger timestamp lags the wall clock by more
+    /// than the tolerance is considered stalled.
+    pub async fn check(&self) -> Result<(), String> {
+        if let Some(e) = self.health.as_ref().and_then(|health| health.error()) {
+            return Err(format!("engine unhealthy: {}", e));
+        }
+
+        let info = self
+            .tracker
+            .poll()
+            .await
+            .map_err(|e| format!("engine unreachable: {}", e))?;
+
+        let ledger_time = UNIX_EPOCH + Duration::from_micros(info.ledger_timestamp.0);
+        let staleness = SystemTime::now()
+            .duration_since(ledger_time)
+            .unwrap_or_default();
+        if staleness > self.max_head_staleness {
+            return Err(format!(
+                "engine stalled: ledger is {}s behind",
+                staleness.as_secs()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+pub fn readiness_router(readiness: Readiness) -> Router {
+    Router::new()
+        .route("/ready", get(ready))
+        .with_state(readiness)
+}
+
+async fn ready(State(readiness): State<Readiness>) -> Response {
+    match readiness.check().await {
+        Ok(()) => (StatusCode::OK, "OK").into_response(),
+        Err(reason) => {
+            tracing::warn!("Not ready: {}", reason);
+            (StatusCode::SERVICE_UNAVAILABLE, reason).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{readiness_router, Readiness};
+    use crate::engine::{
+        adapter::{
+            mock::{ledger_info, MockEngineAdapter},
+            Endpoint,
+        },
+        health::UpstreamHealth,
+        tracker::HeadTracker,
+        EngineClient,
+    };
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use std::{
+        sync::Arc,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+    use tower::ServiceExt;
+
+    async fn ready(adapter: MockEngineAdapter) -> StatusCode {
+        let tracker = Arc::new(HeadTracker::new(
+            EngineClient::new(Box::new(adapter)),
+            Duration::from_millis(100),
+        ));
+        let readiness = Readiness::new(tracker, Duration::from_secs(30));
+
+        readiness_router(readiness)
+            .oneshot(Request::get("/ready").body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn reports_ready_with_fresh_ledger() {
+        let mut info = ledger_info(4, 1);
+        info.ledger_timestamp = (SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_micros() as u64)
+            .into();
+
+        assert_eq!(
+            ready(MockEngineAdapter::new().with_ledger_info(Some(info))).await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_not_ready_with_stale_ledger() {
+        // The fixture ledger timestamp is in 2023.
+        assert_eq!(
+            ready(MockEngineAdapter::new().with_ledger_info(Some(ledger_info(4, 1)))).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_not_ready_when_engine_is_unreachable() {
+        assert_eq!(
+            ready(MockEngineAdapter::new().with_ledger_info(None)).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_not_ready_while_engine_is_unhealthy() {
+        let adapter = MockEngineAdapter::new();
+        let tracker = Arc::new(HeadTracker::new(
+            EngineClient::new(Box::new(adapter.clone())),
+            Duration::from_millis(100),
+        ));
+        let health = Arc::new(UpstreamHealth::new());
+        health.record(Endpoint::Read, Err("connection refused".into()));
+        let readiness = Readiness::new(tracker, Duration::from_secs(30)).with_health(health);
+
+        assert_eq!(
+            readiness.check().await.unwrap_err(),
+            "engine unhealthy: read endpoint: connection refused"
+        );
+        assert_eq!(adapter.calls("get_ledger_info"), 0);
+    }
+
+    #[tokio::test]
+    async fn reports_not_ready_while_write_endpoint_is_unhealthy() {
+        let adapter = MockEngineAdapter::new().with_write_endpoint(None);
+        let client = EngineClient::new(Box::new(adapter));
+        let tracker = Arc::new(HeadTracker::new(client.clone(), Duration::from_millis(100)));
+        let health = Arc::new(UpstreamHealth::new());
+        health.probe(&client).await;
+        let readiness = Readiness::new(tracker, Duration::from_secs(30)).with_health(health);
+
+        let reason = readiness.check().await.unwrap_err();
+        assert!(
+            reason.starts_with("engine unhealthy: write endpoint:"),
+            "{}",
+            reason
+        );
+    }
+}
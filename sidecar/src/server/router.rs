@@ -15,16 +15,20 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::rpc::handle_rpc;
+use super::rpc::{handle_rpc, RpcState};
 use axum::{
+    extract::DefaultBodyLimit,
     routing::{get, post},
     Router,
 };
-use jsonrpsee::RpcModule;
 
-pub fn create_router(module: RpcModule<()>) -> Router {
+/// Routes JSON-RPC requests, rejecting bodies over `max_request_bytes` before reading them
+/// whole.
+pub fn create_router(state: RpcState) -> Router {
+    let body_limit = DefaultBodyLimit::max(state.max_request_bytes);
+
     Router::new()
         .route("/health", get(|| async { "OK" }))
-        .route("/", post(handle_rpc))
-        .with_state(module)
+        .route("/", post(handle_rpc).layer(body_limit))
+        .with_state(state)
 }
@@ -15,34 +15,764 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use axum::{extract::State, http::StatusCode, Json};
+use super::{
+    client_ip::ClientIp,
+    metrics::{Outcome, RequestMetrics},
+    params,
+};
+use crate::engine::within_request_deadline;
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
 use jsonrpsee::{
     core::JsonValue as Value,
-    types::{ErrorCode, ErrorObject},
+    types::{error::CALL_EXECUTION_FAILED_CODE, ErrorCode, ErrorObject},
     RpcModule,
 };
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{field, Instrument, Span};
+
+#[derive(Clone)]
+pub struct RpcState {
+    pub module: RpcModule<()>,
+    /// Largest request body read.
+    pub max_request_bytes: usize,
+    /// Largest response served, for a single call or summed over the calls of a batch.
+    pub max_response_bytes: usize,
+    /// How long the engine calls made for a request, or all calls of a batch, may take in
+    /// total.
+    pub request_timeout: Duration,
+    pub sampler: Arc<LogSampler>,
+    /// Method names that are dispatched to the method they map to.
+    pub method_aliases: Arc<HashMap<String, String>>,
+    /// Largest number of positional params of each method, see [`params::param_counts`].
+    pub param_counts: Arc<HashMap<String, usize>>,
+    /// Whether calls passing more params than their method takes are rejected instead of
+    /// having the extra params dropped.
+    pub strict_params: bool,
+    /// Counters of the calls served, by method and outcome.
+    pub metrics: Arc<RequestMetrics>,
+}
+
+/// Picks the calls that are traced, spreading them evenly so that `rate` of all calls are.
+#[derive(Debug)]
+pub struct LogSampler {
+    rate: f64,
+    calls: AtomicU64,
+}
+
+impl LogSampler {
+    pub fn new(rate: f64) -> Self {
+        Self {
+            rate: rate.clamp(0.0, 1.0),
+            calls: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns whether the next call is traced.
+    pub fn sample(&self) -> bool {
+        let n = self.calls.fetch_add(1, Ordering::Relaxed) as f64;
+        ((n + 1.0) * self.rate).floor() > (n * self.rate).floor()
+    }
+}
 
 pub async fn handle_rpc(
-    State(module): State<RpcModule<()>>,
-    Json(payload): Json<Value>,
-) -> (StatusCode, Json<Value>) {
-    let raw_request = serde_json::to_string(&payload).unwrap();
-
-    match module.raw_json_request(&raw_request, 1).await {
-        Ok((response, _)) => (
-            StatusCode::OK,
-            serde_json::from_str::<Value>(&response).map(Json).unwrap(),
-        ),
-        Err(e) => (
-            StatusCode::BAD_REQUEST,
-            Json(
-                serde_json::to_value(ErrorObject::owned(
-                    ErrorCode::ParseError.code(),
+    State(state): State<RpcState>,
+    client_ip: Option<Extension<ClientIp>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if !is_json_content_type(&headers) {
+        return (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "Expected request with `Content-Type: application/json`",
+        )
+            .into_response();
+    }
+
+    let client = client_info(&headers);
+    let ip = client_ip.map_or_else(
+        || "unknown".to_string(),
+        |Extension(ClientIp(ip))| ip.to_string(),
+    );
+
+    let payload = match serde_json::from_slice::<Value>(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(error_envelope(
+                    Value::Null,
+                    ErrorCode::ParseError,
                     e.to_string(),
-                    None::<()>,
-                ))
-                .unwrap(),
+                )),
+            )
+                .into_response()
+        }
+    };
+
+    let (status, response) = dispatch_payload(&state, payload, client, &ip).await;
+    (status, Json(response)).into_response()
+}
+
+/// Dispatches a single call or a batch of calls within the request deadline, responding to
+/// the whole payload.
+pub async fn dispatch_payload(
+    state: &RpcState,
+    payload: Value,
+    client: &str,
+    ip: &str,
+) -> (StatusCode, Value) {
+    within_request_deadline(
+        state.request_timeout,
+        dispatch_calls(state, payload, client, ip),
+    )
+    .await
+}
+
+async fn dispatch_calls(
+    state: &RpcState,
+    payload: Value,
+    client: &str,
+    ip: &str,
+) -> (StatusCode, Value) {
+    match payload {
+        Value::Array(calls) if calls.is_empty() => (
+            StatusCode::BAD_REQUEST,
+            error_envelope(
+                Value::Null,
+                ErrorCode::InvalidRequest,
+                "Empty batch request".into(),
             ),
         ),
+        Value::Array(calls) => {
+            // The calls of a batch share the response size limit, so the calls that come
+            // after it is used up fail instead of growing the response further.
+            let mut remaining = state.max_response_bytes;
+            let mut responses = Vec::with_capacity(calls.len());
+            for call in &calls {
+                let response = match traced_dispatch(state, call, remaining, client, ip).await {
+                    Ok(response) | Err((_, response)) => response,
+                };
+                remaining = remaining.saturating_sub(serialized_len(&response));
+                responses.push(response);
+            }
+            (StatusCode::OK, Value::Array(responses))
+        }
+        call => match traced_dispatch(state, &call, state.max_response_bytes, client, ip).await {
+            Ok(response) => (StatusCode::OK, response),
+            Err((status, response)) => (status, response),
+        },
+    }
+}
+
+/// Dispatches a single JSON-RPC call within a span carrying its method, id and client, and
+/// records the outcome and latency of the call on the span and in the request metrics.
+///
+/// Only the calls picked by the sampler are traced while they run. Failed calls that were
+/// not picked are still reported, with a span created once they complete.
+async fn traced_dispatch(
+    state: &RpcState,
+    call: &Value,
+    max_response_bytes: usize,
+    client: &str,
+    ip: &str,
+) -> Result<Value, (StatusCode, Value)> {
+    let started = Instant::now();
+    let span = if state.sampler.sample() {
+        call_span(call, client, ip)
+    } else {
+        Span::none()
+    };
+    let result = dispatch(state, call, max_response_bytes)
+        .instrument(span.clone())
+        .await;
+
+    let response = match &result {
+        Ok(response) | Err((_, response)) => response,
+    };
+    let code = response.get("error").map(|error| {
+        error
+            .get("code")
+            .and_then(Value::as_i64)
+            .unwrap_or_default()
+    });
+    state
+        .metrics
+        .record(metric_method(state, call), Outcome::from_code(code));
+
+    let status = match &result {
+        Ok(response) if response.get("error").is_none() => "ok",
+        _ => "error",
+    };
+    let span = if span.is_none() && status == "error" {
+        call_span(call, client, ip)
+    } else {
+        span
+    };
+    span.record("status", status);
+    span.record("latency_ms", started.elapsed().as_millis() as u64);
+    span.in_scope(|| tracing::debug!("rpc call completed"));
+
+    result
+}
+
+/// Names the method of `call` in the request metrics, after resolving aliases.
+///
+/// Methods the server does not serve are counted together, so clients cannot grow the
+/// metrics with arbitrary method names.
+fn metric_method<'a>(state: &'a RpcState, call: &'a Value) -> &'a str {
+    let method = call
+        .get("method")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let method = state
+        .method_aliases
+        .get(method)
+        .map_or(method, String::as_str);
+
+    match state.module.method(method) {
+        Some(_) => method,
+        None => "unknown",
+    }
+}
+
+fn call_span(call: &Value, client: &str, ip: &str) -> Span {
+    let method = call
+        .get("method")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+
+    tracing::info_span!(
+        "rpc",
+        method = %method,
+        id = %request_id(call),
+        client = %client,
+        ip = %ip,
+        status = field::Empty,
+        latency_ms = field::Empty,
+    )
+}
+
+/// Dispatches a single JSON-RPC call, echoing its `id` in the error envelope when the call
+/// cannot be handled or its response exceeds `max_response_bytes`.
+async fn dispatch(
+    state: &RpcState,
+    call: &Value,
+    max_response_bytes: usize,
+) -> Result<Value, (StatusCode, Value)> {
+    let id = request_id(call);
+
+    let mut call = resolve_alias(&state.method_aliases, call);
+    if let Some(count) = params::exceeded(&state.param_counts, &call) {
+        if state.strict_params {
+            return Ok(error_envelope(
+                id,
+                ErrorCode::InvalidParams,
+                format!("too many params: expected at most {}", count),
+            ));
+        }
+        params::truncate(call.to_mut(), count);
+    }
+    let raw_request = serde_json::to_string(&call).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            error_envelope(id.clone(), ErrorCode::ParseError, e.to_string()),
+        )
+    })?;
+
+    let (response, _) = state
+        .module
+        .raw_json_request(&raw_request, 1)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                error_envelope(id.clone(), ErrorCode::InvalidRequest, e.to_string()),
+            )
+        })?;
+
+    if response.len() > max_response_bytes {
+        return Ok(error_envelope(
+            id,
+            ErrorCode::ServerError(CALL_EXECUTION_FAILED_CODE),
+            format!(
+                "response too large: {} bytes exceeds the limit of {} bytes, try narrowing the query",
+                response.len(),
+                max_response_bytes
+            ),
+        ));
+    }
+
+    serde_json::from_str::<Value>(&response).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            error_envelope(id, ErrorCode::InternalError, e.to_string()),
+        )
+    })
+}
+
+/// Renames the method of `call` to the method it is an alias of, if any.
+fn resolve_alias<'a>(aliases: &HashMap<String, String>, call: &'a Value) -> Cow<'a, Value> {
+    let Some(method) = call
+        .get("method")
+        .and_then(Value::as_str)
+        .and_then(|method| aliases.get(method))
+    else {
+        return Cow::Borrowed(call);
+    };
+
+    let mut call = call.clone();
+    call["method"] = Value::String(method.clone());
+    Cow::Owned(call)
+}
+
+/// Returns the length of `value` serialized as JSON, without allocating it.
+fn serialized_len(value: &Value) -> usize {
+    struct Counter(usize);
+
+    impl std::io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    serde_json::to_writer(&mut counter, value).map_or(0, |_| counter.0)
+}
+
+/// Extracts the request id, which the JSON-RPC spec restricts to a number, a string or null.
+pub fn request_id(call: &Value) -> Value {
+    match call.get("id") {
+        Some(id @ (Value::Number(_) | Value::String(_))) => id.clone(),
+        _ => Value::Null,
+    }
+}
+
+/// Identifies the client by its user agent.
+pub fn client_info(headers: &HeaderMap) -> &str {
+    headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("unknown")
+}
+
+fn is_json_content_type(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
+        .unwrap_or(false)
+}
+
+pub fn error_envelope(id: Value, code: ErrorCode, message: String) -> Value {
+    let error = ErrorObject::owned(code.code(), message, None::<()>);
+
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "error": error,
+        "id": id,
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{LogSampler, RpcState};
+    use crate::server::metrics::Outcome;
+    use crate::server::router::create_router;
+    use axum::{
+        body::{to_bytes, Body},
+        http::{header, Request, StatusCode},
+    };
+    use jsonrpsee::{core::JsonValue as Value, types::ErrorObjectOwned, RpcModule};
+    use std::{
+        collections::HashMap,
+        fmt::Debug,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+    use tower::ServiceExt;
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        Subscriber,
+    };
+    use tracing_subscriber::{layer::Context, prelude::*, Layer};
+
+    /// Collects the fields recorded on every span.
+    #[derive(Clone, Default)]
+    struct SpanFields(Arc<Mutex<HashMap<String, String>>>);
+
+    impl Visit for SpanFields {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(field.name().to_string(), format!("{:?}", value));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(field.name().to_string(), value.to_string());
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for SpanFields {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+            attrs.record(&mut self.clone());
+        }
+
+        fn on_record(&self, _id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+            values.record(&mut self.clone());
+        }
+    }
+
+    fn state(max_response_bytes: usize) -> RpcState {
+        sampled_state(max_response_bytes, 1.0)
+    }
+
+    fn sampled_state(max_response_bytes: usize, log_sample_rate: f64) -> RpcState {
+        let mut module = RpcModule::new(());
+        module
+            .register_method("test_ping", |_, _, _| "pong")
+            .unwrap();
+        module
+            .register_method("test_large", |_, _, _| "x".repeat(4096))
+            .unwrap();
+        module
+            .register_method("test_internal", |_, _, _| {
+                Err::<(), _>(ErrorObjectOwned::owned(-32603, "internal", None::<()>))
+            })
+            .unwrap();
+        module
+            .register_method("test_params", |params, _, _| {
+                params.parse::<Vec<String>>().unwrap_or_default()
+            })
+            .unwrap();
+
+        RpcState {
+            module,
+            max_request_bytes: 1024,
+            max_response_bytes,
+            request_timeout: Duration::from_secs(1),
+            sampler: Arc::new(LogSampler::new(log_sample_rate)),
+            method_aliases: Default::default(),
+            param_counts: Arc::new(HashMap::from([
+                ("test_ping".to_string(), 0),
+                ("test_params".to_string(), 1),
+            ])),
+            strict_params: true,
+            metrics: Default::default(),
+        }
+    }
+
+    async fn call(content_type: &str, body: &str) -> (StatusCode, String) {
+        call_with(state(usize::MAX), content_type, body).await
+    }
+
+    async fn call_with(state: RpcState, content_type: &str, body: &str) -> (StatusCode, String) {
+        let request = Request::post("/")
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(body.to_owned()))
+            .unwrap();
+        let response = create_router(state).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_content_type() {
+        let (status, _) = call(
+            "text/plain",
+            r#"{"jsonrpc":"2.0","method":"test_ping","id":1}"#,
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_json() {
+        let (status, body) = call("application/json", r#"{"jsonrpc":"2.0","#).await;
+        let body = serde_json::from_str::<Value>(&body).unwrap();
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["jsonrpc"], "2.0");
+        assert_eq!(body["error"]["code"], -32700);
+        assert_eq!(body["id"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn handles_valid_request() {
+        let (status, body) = call(
+            "application/json; charset=utf-8",
+            r#"{"jsonrpc":"2.0","method":"test_ping","id":1}"#,
+        )
+        .await;
+        let body = serde_json::from_str::<Value>(&body).unwrap();
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["result"], "pong");
+        assert_eq!(body["id"], 1);
+    }
+
+    #[tokio::test]
+    async fn counts_calls_by_outcome() {
+        let state = state(usize::MAX);
+        let metrics = state.metrics.clone();
+
+        for body in [
+            r#"{"jsonrpc":"2.0","method":"test_ping","id":1}"#,
+            r#"{"jsonrpc":"2.0","method":"test_missing","id":2}"#,
+            r#"{"jsonrpc":"2.0","method":"test_internal","id":3}"#,
+        ] {
+            call_with(state.clone(), "application/json", body).await;
+        }
+
+        assert_eq!(metrics.count("test_ping", Outcome::Success), 1);
+        assert_eq!(metrics.count("unknown", Outcome::ClientError), 1);
+        assert_eq!(metrics.count("test_internal", Outcome::ServerError), 1);
+        assert_eq!(metrics.count("test_internal", Outcome::ClientError), 0);
+        assert!(metrics
+            .render()
+            .contains(r#"rpc_requests_total{method="unknown",outcome="client_error"} 1"#));
+    }
+
+    #[tokio::test]
+    async fn routes_aliased_method_to_its_handler() {
+        let mut state = state(usize::MAX);
+        state.method_aliases = Arc::new(HashMap::from([(
+            "test_legacyPing".to_string(),
+            "test_ping".to_string(),
+        )]));
+
+        let (status, body) = call_with(
+            state,
+            "application/json",
+            r#"{"jsonrpc":"2.0","method":"test_legacyPing","id":1}"#,
+        )
+        .await;
+        let body = serde_json::from_str::<Value>(&body).unwrap();
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["result"], "pong");
+        assert_eq!(body["id"], 1);
+    }
+
+    #[tokio::test]
+    async fn rejects_extra_params_when_strict() {
+        let (status, body) = call(
+            "application/json",
+            r#"{"jsonrpc":"2.0","method":"test_ping","params":["extra"],"id":1}"#,
+        )
+        .await;
+        let body = serde_json::from_str::<Value>(&body).unwrap();
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["error"]["code"], -32602);
+        assert_eq!(body["id"], 1);
+    }
+
+    #[tokio::test]
+    async fn drops_extra_params_when_lenient() {
+        let mut state = state(usize::MAX);
+        state.strict_params = false;
+
+        let (status, body) = call_with(
+            state,
+            "application/json",
+            r#"{"jsonrpc":"2.0","method":"test_params","params":["a","extra"],"id":1}"#,
+        )
+        .await;
+        let body = serde_json::from_str::<Value>(&body).unwrap();
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["result"], serde_json::json!(["a"]));
+    }
+
+    #[tokio::test]
+    async fn preserves_id_on_success_and_error() {
+        let (_, body) = call(
+            "application/json",
+            r#"{"jsonrpc":"2.0","method":"test_ping","id":"abc"}"#,
+        )
+        .await;
+        let body = serde_json::from_str::<Value>(&body).unwrap();
+        assert_eq!(body["id"], "abc");
+
+        let (status, body) = call("application/json", r#"{"jsonrpc":"2.0","id":7}"#).await;
+        let body = serde_json::from_str::<Value>(&body).unwrap();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], -32600);
+        assert_eq!(body["id"], 7);
+    }
+
+    #[tokio::test]
+    async fn preserves_ids_in_batch() {
+        let (status, body) = call(
+            "application/json",
+            r#"[
+                {"jsonrpc":"2.0","method":"test_ping","id":1},
+                {"jsonrpc":"2.0","id":"two"},
+                {"jsonrpc":"2.0","method":"test_missing","id":3}
+            ]"#,
+        )
+        .await;
+        let body = serde_json::from_str::<Value>(&body).unwrap();
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body[0]["result"], "pong");
+        assert_eq!(body[0]["id"], 1);
+        assert_eq!(body[1]["error"]["code"], -32600);
+        assert_eq!(body[1]["id"], "two");
+        assert_eq!(body[2]["error"]["code"], -32601);
+        assert_eq!(body[2]["id"], 3);
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_response() {
+        let (status, body) = call_with(
+            state(1024),
+            "application/json",
+            r#"{"jsonrpc":"2.0","method":"test_large","id":1}"#,
+        )
+        .await;
+        let body = serde_json::from_str::<Value>(&body).unwrap();
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["error"]["code"], -32000);
+        assert_eq!(body["id"], 1);
+
+        let (_, body) = call_with(
+            state(1024),
+            "application/json",
+            r#"{"jsonrpc":"2.0","method":"test_ping","id":2}"#,
+        )
+        .await;
+        let body = serde_json::from_str::<Value>(&body).unwrap();
+
+        assert_eq!(body["result"], "pong");
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_request_before_parsing() {
+        let padding = "x".repeat(1024);
+        let (status, _) = call(
+            "application/json",
+            &format!(
+                r#"{{"jsonrpc":"2.0","method":"test_ping","id":1,"padding":"{}"}}"#,
+                padding
+            ),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn caps_total_response_size_of_batch() {
+        let (status, body) = call_with(
+            state(6000),
+            "application/json",
+            r#"[
+                {"jsonrpc":"2.0","method":"test_large","id":1},
+                {"jsonrpc":"2.0","method":"test_large","id":2},
+                {"jsonrpc":"2.0","method":"test_ping","id":3}
+            ]"#,
+        )
+        .await;
+        let body = serde_json::from_str::<Value>(&body).unwrap();
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body[0]["result"].as_str().unwrap().len(), 4096);
+        assert_eq!(body[1]["error"]["code"], -32000);
+        assert_eq!(body[1]["id"], 2);
+        assert_eq!(body[2]["result"], "pong");
+    }
+
+    fn span_fields() -> (SpanFields, tracing::subscriber::DefaultGuard) {
+        let fields = SpanFields::default();
+        let guard = tracing_subscriber::registry()
+            .with(fields.clone())
+            .set_default();
+
+        (fields, guard)
+    }
+
+    async fn traced_call(state: RpcState, body: &'static str) {
+        let request = Request::post("/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::USER_AGENT, "test-client")
+            .body(Body::from(body))
+            .unwrap();
+        create_router(state).oneshot(request).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn records_call_in_span() {
+        let (fields, _guard) = span_fields();
+
+        traced_call(
+            state(usize::MAX),
+            r#"{"jsonrpc":"2.0","method":"test_ping","id":7}"#,
+        )
+        .await;
+
+        let fields = fields.0.lock().unwrap();
+        assert_eq!(fields["method"], "test_ping");
+        assert_eq!(fields["id"], "7");
+        assert_eq!(fields["client"], "test-client");
+        assert_eq!(fields["ip"], "unknown");
+        assert_eq!(fields["status"], "ok");
+        assert!(fields.contains_key("latency_ms"));
+    }
+
+    #[tokio::test]
+    async fn reports_errors_regardless_of_sampling() {
+        let (fields, _guard) = span_fields();
+        let state = sampled_state(usize::MAX, 0.0);
+
+        traced_call(
+            state.clone(),
+            r#"{"jsonrpc":"2.0","method":"test_ping","id":1}"#,
+        )
+        .await;
+        assert!(fields.0.lock().unwrap().is_empty());
+
+        traced_call(state, r#"{"jsonrpc":"2.0","method":"test_missing","id":2}"#).await;
+        let fields = fields.0.lock().unwrap();
+        assert_eq!(fields["method"], "test_missing");
+        assert_eq!(fields["status"], "error");
+    }
+
+    #[test]
+    fn samples_calls_at_rate() {
+        let sampler = LogSampler::new(0.25);
+        let sampled = (0..100).filter(|_| sampler.sample()).count();
+
+        assert_eq!(sampled, 25);
+        assert!((0..10).all(|_| LogSampler::new(1.0).sample()));
     }
 }
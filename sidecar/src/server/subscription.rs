@@ -0,0 +1,413 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    config::server::ServerConfig,
+    error::{Result, SidecarError},
+};
+use jsonrpsee::ConnectionId;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::{
+    sync::{mpsc, Notify},
+    time::Instant,
+};
+
+/// WebSocket close code sent to a subscriber that cannot keep up (policy violation).
+pub const SLOW_SUBSCRIBER_CLOSE_CODE: u16 = 1008;
+
+/// Tracks open subscriptions and enforces the configured limits.
+#[derive(Debug, Clone)]
+pub struct SubscriptionManager {
+    max_per_connection: usize,
+    max_total: usize,
+    queue_size: usize,
+    send_timeout: Duration,
+    /// Ping interval and pong timeout of the connections, if they are kept alive.
+    keepalive: Option<(Duration, Duration)>,
+    counts: Arc<Mutex<SubscriptionCounts>>,
+}
+
+#[derive(Debug, Default)]
+struct SubscriptionCounts {
+    total: usize,
+    connections: HashMap<ConnectionId, usize>,
+}
+
+impl SubscriptionManager {
+    pub fn new(max_per_connection: usize, max_total: usize) -> Self {
+        Self {
+            max_per_connection,
+            max_total,
+            queue_size: 1024,
+            send_timeout: Duration::from_secs(10),
+            keepalive: None,
+            counts: Default::default(),
+        }
+    }
+
+    pub fn from_config(config: &ServerConfig) -> Self {
+        let manager = Self::new(
+            config.max_subscriptions_per_connection(),
+            config.max_total_subscriptions(),
+        )
+        .with_outbound_queue(
+            config.subscription_queue_size(),
+            config.subscription_send_timeout(),
+        );
+
+        match config.ws_ping_interval() {
+            Some(interval) => manager.with_keepalive(interval, config.ws_pong_timeout()),
+            None => manager,
+        }
+    }
+
+    /// Bounds the outbound queue of each connection to `queue_size` messages, dropping
+    /// subscribers whose queue stays full for `send_timeout`.
+    pub fn with_outbound_queue(mut self, queue_size: usize, send_timeout: Duration) -> Self {
+        self.queue_size = queue_size;
+        self.send_timeout = send_timeout;
+        self
+    }
+
+    /// Pings each connection every `interval`, closing those that do not answer a ping
+    /// within `pong_timeout`.
+    pub fn with_keepalive(mut self, interval: Duration, pong_timeout: Duration) -> Self {
+        self.keepalive = Some((interval, pong_timeout));
+        self
+    }
+
+    /// Creates the keepalive schedule of a new connection, if connections are kept alive.
+    pub fn keepalive(&self) -> Option<Keepalive> {
+        self.keepalive
+            .map(|(interval, pong_timeout)| Keepalive::new(interval, pong_timeout))
+    }
+
+    /// How long a write to a connection may wait before its peer is deemed not reading.
+    pub fn send_timeout(&self) -> Duration {
+        self.send_timeout
+    }
+
+    /// Creates the outbound queue of `connection`, shared by all of its subscriptions.
+    pub fn outbound(&self, connection: ConnectionId) -> (OutboundSender, OutboundReceiver) {
+        let (sender, receiver) = mpsc::channel(self.queue_size);
+        let disconnect = Arc::new(Disconnect::default());
+
+        (
+            OutboundSender {
+                connection,
+                send_timeout: self.send_timeout,
+                sender,
+                disconnect: disconnect.clone(),
+            },
+            OutboundReceiver {
+                receiver,
+                disconnect,
+            },
+        )
+    }
+
+    /// Reserves a subscription slot for `connection`.
+    ///
+    /// The slot is held until the returned permit is dropped, which happens when the
+    /// subscription is unsubscribed or its connection is closed.
+    pub fn acquire(&self, connection: ConnectionId) -> Result<SubscriptionPermit> {
+        let mut counts = self.counts.lock().unwrap();
+        let open = counts.connections.get(&connection).copied().unwrap_or(0);
+        if open >= self.max_per_connection || counts.total >= self.max_total {
+            return Err(SidecarError::TooManySubscriptions);
+        }
+
+        counts.total += 1;
+        counts.connections.insert(connection, open + 1);
+
+        Ok(SubscriptionPermit {
+            connection,
+            counts: self.counts.clone(),
+        })
+    }
+
+    /// Returns the number of open subscriptions across all connections.
+    pub fn total(&self) -> usize {
+        self.counts.lock().unwrap().total
+    }
+}
+
+/// A subscription slot held by an open subscription.
+pub struct SubscriptionPermit {
+    connection: ConnectionId,
+    counts: Arc<Mutex<SubscriptionCounts>>,
+}
+
+impl Drop for SubscriptionPermit {
+    fn drop(&mut self) {
+        let mut counts = self.counts.lock().unwrap();
+        counts.total -= 1;
+        if let Some(open) = counts.connections.get_mut(&self.connection) {
+            *open -= 1;
+            if *open == 0 {
+                counts.connections.remove(&self.connection);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Disconnect {
+    lagged: AtomicBool,
+    notify: Notify,
+}
+
+/// Queues subscription messages for a connection.
+#[derive(Clone)]
+pub struct OutboundSender {
+    connection: ConnectionId,
+    send_timeout: Duration,
+    sender: mpsc::Sender<String>,
+    disconnect: Arc<Disconnect>,
+}
+
+impl OutboundSender {
+    /// Queues `message`, waiting for room while the queue is full.
+    ///
+    /// Returns `false` if the connection is gone, or if the queue stayed full for the send
+    /// timeout, in which case the connection is dropped rather than buffering without bound.
+    pub async fn send(&self, message: String) -> bool {
+        match tokio::time::timeout(self.send_timeout, self.sender.send(message)).await {
+            Ok(sent) => sent.is_ok(),
+            Err(_) => {
+                tracing::warn!(
+                    "Dropping connection {} whose subscriber is not keeping up",
+                    self.connection.0
+                );
+                self.disconnect.lagged.store(true, Ordering::Relaxed);
+                self.disconnect.notify.notify_one();
+                false
+            }
+        }
+    }
+}
+
+/// Receives the subscription messages queued for a connection, to be written to its socket.
+pub struct OutboundReceiver {
+    receiver: mpsc::Receiver<String>,
+    disconnect: Arc<Disconnect>,
+}
+
+impl OutboundReceiver {
+    /// Returns the next message to write, or `None` once the connection should be closed.
+    ///
+    /// After `None`, the connection is closed with [`SLOW_SUBSCRIBER_CLOSE_CODE`] if
+    /// [`lagged`](Self::lagged) is set. Messages still queued for a lagging subscriber are
+    /// discarded.
+    pub async fn recv(&mut self) -> Option<String> {
+        if !self.lagged() {
+            tokio::select! {
+                biased;
+                _ = self.disconnect.notify.notified() => {}
+                message = self.receiver.recv() => return message,
+            }
+        }
+
+        // Fail further sends right away instead of waiting on the full queue.
+        self.receiver.close();
+        None
+    }
+
+    /// Whether the connection is dropped because its subscriber fell behind.
+    pub fn lagged(&self) -> bool {
+        self.disconnect.lagged.load(Ordering::Relaxed)
+    }
+}
+
+/// What the socket of a kept-alive connection does next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeepaliveAction {
+    /// Send a ping and report it with [`Keepalive::ping_sent`].
+    Ping,
+    /// Close the connection, which stopped answering pings.
+    Close,
+}
+
+/// Schedules the pings of a connection, so proxies do not drop it while idle and a peer that
+/// silently went away is detected instead of being kept as a half-open connection.
+///
+/// The socket loop waits on [`Keepalive::tick`] alongside the socket, and reports the pongs
+/// it reads with [`Keepalive::pong`].
+#[derive(Debug)]
+pub struct Keepalive {
+    interval: Duration,
+    pong_timeout: Duration,
+    state: Mutex<KeepaliveState>,
+}
+
+#[derive(Debug)]
+struct KeepaliveState {
+    next_ping: Instant,
+    /// When the unanswered ping was sent, if one is.
+    awaiting_pong: Option<Instant>,
+}
+
+impl Keepalive {
+    pub fn new(interval: Duration, pong_timeout: Duration) -> Self {
+        Self {
+            interval,
+            pong_timeout,
+            state: Mutex::new(KeepaliveState {
+                next_ping: Instant::now() + interval,
+                awaiting_pong: None,
+            }),
+        }
+    }
+
+    /// Waits until the next ping is due, or until the unanswered ping times out.
+    ///
+    /// Cancelling the wait loses nothing, so it can be raced against the socket.
+    pub async fn tick(&self) -> KeepaliveAction {
+        let (deadline, action) = {
+            let state = self.state.lock().unwrap();
+            match state.awaiting_pong {
+                Some(sent) => (sent + self.pong_timeout, KeepaliveAction::Close),
+                None => (state.next_ping, KeepaliveAction::Ping),
+            }
+        };
+        tokio::time::sleep_until(deadline).await;
+
+        action
+    }
+
+    /// Records that a ping was sent, after [`KeepaliveAction::Ping`].
+    pub fn ping_sent(&self) {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        state.awaiting_pong = Some(now);
+        state.next_ping = now + self.interval;
+    }
+
+    /// Records a pong from the peer.
+    pub fn pong(&self) {
+        self.state.lock().unwrap().awaiting_pong = None;
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{Keepalive, KeepaliveAction, SubscriptionManager};
+    use crate::error::SidecarError;
+    use jsonrpsee::ConnectionId;
+    use std::time::Duration;
+
+    #[test]
+    fn rejects_subscriptions_over_connection_limit() {
+        let manager = SubscriptionManager::new(2, 10);
+
+        let permits = (0..2)
+            .map(|_| manager.acquire(ConnectionId(1)).unwrap())
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            manager.acquire(ConnectionId(1)),
+            Err(SidecarError::TooManySubscriptions)
+        ));
+        assert!(manager.acquire(ConnectionId(2)).is_ok());
+
+        drop(permits);
+        assert_eq!(manager.total(), 0);
+        assert!(manager.acquire(ConnectionId(1)).is_ok());
+    }
+
+    #[test]
+    fn rejects_subscriptions_over_total_limit() {
+        let manager = SubscriptionManager::new(2, 3);
+
+        let _first = manager.acquire(ConnectionId(1)).unwrap();
+        let _second = manager.acquire(ConnectionId(1)).unwrap();
+        let third = manager.acquire(ConnectionId(2)).unwrap();
+        assert!(matches!(
+            manager.acquire(ConnectionId(3)),
+            Err(SidecarError::TooManySubscriptions)
+        ));
+
+        drop(third);
+        assert!(manager.acquire(ConnectionId(3)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn disconnects_slow_subscriber() {
+        let manager =
+            SubscriptionManager::new(2, 10).with_outbound_queue(2, Duration::from_millis(50));
+        let (sender, mut receiver) = manager.outbound(ConnectionId(1));
+
+        assert!(sender.send("first".into()).await);
+        assert!(sender.send("second".into()).await);
+        // Nothing is read, so the third message finds the queue full.
+        assert!(!sender.send("third".into()).await);
+
+        assert!(receiver.lagged());
+        assert_eq!(receiver.recv().await, None);
+        assert!(!sender.send("fourth".into()).await);
+    }
+
+    #[tokio::test]
+    async fn delivers_to_subscriber_that_keeps_up() {
+        let manager =
+            SubscriptionManager::new(2, 10).with_outbound_queue(1, Duration::from_millis(50));
+        let (sender, mut receiver) = manager.outbound(ConnectionId(1));
+
+        for message in ["first", "second"] {
+            assert!(sender.send(message.into()).await);
+            assert_eq!(receiver.recv().await.as_deref(), Some(message));
+        }
+        assert!(!receiver.lagged());
+
+        drop(sender);
+        assert_eq!(receiver.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn closes_connection_that_stops_answering_pings() {
+        let keepalive = Keepalive::new(Duration::from_millis(20), Duration::from_millis(30));
+
+        assert_eq!(keepalive.tick().await, KeepaliveAction::Ping);
+        keepalive.ping_sent();
+        keepalive.pong();
+
+        assert_eq!(keepalive.tick().await, KeepaliveAction::Ping);
+        keepalive.ping_sent();
+        // The peer no longer answers, so the connection is closed at the pong timeout.
+        let closed = tokio::time::timeout(Duration::from_secs(1), keepalive.tick()).await;
+        assert_eq!(closed.unwrap(), KeepaliveAction::Close);
+    }
+
+    #[test]
+    fn keeps_connections_alive_only_when_configured() {
+        assert!(SubscriptionManager::new(2, 10).keepalive().is_none());
+
+        let config = toml::from_str("ws_ping_interval_seconds = 0").unwrap();
+        assert!(SubscriptionManager::from_config(&config)
+            .keepalive()
+            .is_none());
+        assert!(SubscriptionManager::from_config(&Default::default())
+            .keepalive()
+            .is_some());
+    }
+}
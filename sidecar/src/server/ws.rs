@@ -0,0 +1,342 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{
+    client_ip::ClientIp,
+    rpc::{client_info, dispatch_payload, error_envelope, request_id, RpcState},
+    subscription::{
+        KeepaliveAction, OutboundReceiver, OutboundSender, SubscriptionManager, SubscriptionPermit,
+        SLOW_SUBSCRIBER_CLOSE_CODE,
+    },
+};
+use crate::{engine::tracker::HeadTracker, error::SidecarError};
+use axum::{
+    body::Bytes,
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    http::HeaderMap,
+    response::Response,
+    routing::get,
+    Extension, Router,
+};
+use jsonrpsee::{
+    core::JsonValue as Value,
+    types::{ErrorCode, ErrorObjectOwned},
+    ConnectionId,
+};
+use std::{
+    collections::HashMap,
+    future,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use tokio::task::JoinHandle;
+
+/// State of the WebSocket endpoint, shared by its connections.
+#[derive(Clone)]
+pub struct WsState {
+    rpc: RpcState,
+    tracker: Arc<HeadTracker>,
+    subscriptions: SubscriptionManager,
+    connections: Arc<AtomicUsize>,
+    subscription_ids: Arc<AtomicU64>,
+}
+
+impl WsState {
+    pub fn new(
+        rpc: RpcState,
+        tracker: Arc<HeadTracker>,
+        subscriptions: SubscriptionManager,
+    ) -> Self {
+        Self {
+            rpc,
+            tracker,
+            subscriptions,
+            connections: Default::default(),
+            subscription_ids: Default::default(),
+        }
+    }
+}
+
+/// Serves JSON-RPC over WebSocket connections upgraded from `GET /`.
+///
+/// Every method served over HTTP is served over the connection as well, along with
+/// `eth_subscribe` and `eth_unsubscribe` for `newHeads` subscriptions. The subscriptions
+/// count against the limits of the [`SubscriptionManager`], and their notifications go
+/// through the bounded outbound queue of the connection, which is closed with
+/// [`SLOW_SUBSCRIBER_CLOSE_CODE`] once its subscriber falls behind. Connections are pinged
+/// if the manager keeps them alive, and closed once they stop answering.
+pub fn ws_router(state: WsState) -> Router {
+    Router::new().route("/", get(upgrade)).with_state(state)
+}
+
+async fn upgrade(
+    State(state): State<WsState>,
+    client_ip: Option<Extension<ClientIp>>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let client = client_info(&headers).to_string();
+    let ip = client_ip.map_or_else(
+        || "unknown".to_string(),
+        |Extension(ClientIp(ip))| ip.to_string(),
+    );
+
+    ws.max_message_size(state.rpc.max_request_bytes)
+        .on_upgrade(move |socket| {
+            let (connection, queued) = Connection::new(state, client, ip);
+            connection.serve(socket, queued)
+        })
+}
+
+/// A WebSocket connection and the subscriptions opened over it.
+struct Connection {
+    state: WsState,
+    id: ConnectionId,
+    client: String,
+    ip: String,
+    outbound: OutboundSender,
+    subscriptions: HashMap<String, JoinHandle<()>>,
+}
+
+impl Connection {
+    fn new(state: WsState, client: String, ip: String) -> (Self, OutboundReceiver) {
+        let id = ConnectionId(state.connections.fetch_add(1, Ordering::Relaxed));
+        let (outbound, queued) = state.subscriptions.outbound(id);
+        let connection = Self {
+            state,
+            id,
+            client,
+            ip,
+            outbound,
+            subscriptions: HashMap::new(),
+        };
+
+        (connection, queued)
+    }
+
+    async fn serve(mut self, mut socket: WebSocket, mut queued: OutboundReceiver) {
+        let keepalive = self.state.subscriptions.keepalive();
+        let send_timeout = self.state.subscriptions.send_timeout();
+
+        loop {
+            let tick = async {
+                match &keepalive {
+                    Some(keepalive) => keepalive.tick().await,
+                    None => future::pending().await,
+                }
+            };
+
+            let message = tokio::select! {
+                message = socket.recv() => match message {
+                    Some(Ok(Message::Text(text))) => self.handle(&text).await,
+                    Some(Ok(Message::Pong(_))) => {
+                        if let Some(keepalive) = &keepalive {
+                            keepalive.pong();
+                        }
+                        continue;
+                    }
+                    // Pings are answered by the socket itself.
+                    Some(Ok(Message::Ping(_) | Message::Binary(_))) => continue,
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                },
+                message = queued.recv() => match message {
+                    Some(notification) => Message::Text(notification.into()),
+                    None => {
+                        if queued.lagged() {
+                            close(&mut socket, SLOW_SUBSCRIBER_CLOSE_CODE, "subscriber too slow")
+                                .await;
+                        }
+                        break;
+                    }
+                },
+                action = tick => match action {
+                    KeepaliveAction::Ping => {
+                        if let Some(keepalive) = &keepalive {
+                            keepalive.ping_sent();
+                        }
+                        Message::Ping(Bytes::new())
+                    }
+                    KeepaliveAction::Close => {
+                        tracing::debug!(
+                            "Closing connection {} that stopped answering pings",
+                            self.id.0
+                        );
+                        close(&mut socket, close_code::AWAY, "ping timeout").await;
+                        break;
+                    }
+                },
+            };
+
+            // A peer that stops reading would otherwise hold the connection in a write
+            // forever, as it does the queued notifications.
+            match tokio::time::timeout(send_timeout, socket.send(message)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(_)) => break,
+                Err(_) => {
+                    tracing::warn!(
+                        "Dropping connection {} whose peer is not reading",
+                        self.id.0
+                    );
+                    break;
+                }
+            }
+        }
+
+        for (_, subscription) in self.subscriptions.drain() {
+            subscription.abort();
+        }
+    }
+
+    /// Answers a text message, serving the subscription methods and dispatching every
+    /// other call like an HTTP request.
+    async fn handle(&mut self, text: &str) -> Message {
+        let response = match serde_json::from_str::<Value>(text) {
+            Ok(call) => match call.get("method").and_then(Value::as_str) {
+                Some("eth_subscribe") => self.subscribe(&call),
+                Some("eth_unsubscribe") => self.unsubscribe(&call),
+                _ => {
+                    dispatch_payload(&self.state.rpc, call, &self.client, &self.ip)
+                        .await
+                        .1
+                }
+            },
+            Err(e) => error_envelope(Value::Null, ErrorCode::ParseError, e.to_string()),
+        };
+
+        Message::Text(response.to_string().into())
+    }
+
+    fn subscribe(&mut self, call: &Value) -> Value {
+        let id = request_id(call);
+        let kind = call
+            .get("params")
+            .and_then(|params| params.get(0))
+            .and_then(Value::as_str);
+        if kind != Some("newHeads") {
+            let err = SidecarError::Unsupported("subscriptions other than newHeads");
+            return response(id, Err(err.into()));
+        }
+        let permit = match self.state.subscriptions.acquire(self.id) {
+            Ok(permit) => permit,
+            Err(e) => return response(id, Err(e.into())),
+        };
+
+        let subscription = format!(
+            "{:#x}",
+            self.state.subscription_ids.fetch_add(1, Ordering::Relaxed) + 1
+        );
+        let task = tokio::spawn(new_heads(
+            self.state.clone(),
+            subscription.clone(),
+            self.outbound.clone(),
+            permit,
+        ));
+        self.subscriptions.insert(subscription.clone(), task);
+
+        response(id, Ok(Value::String(subscription)))
+    }
+
+    fn unsubscribe(&mut self, call: &Value) -> Value {
+        let id = request_id(call);
+        let subscription = call
+            .get("params")
+            .and_then(|params| params.get(0))
+            .and_then(Value::as_str)
+            .and_then(|subscription| self.subscriptions.remove(subscription));
+
+        // Aborting the task drops its permit, which frees the subscription slot.
+        let found = subscription.inspect(JoinHandle::abort).is_some();
+        response(id, Ok(Value::Bool(found)))
+    }
+}
+
+/// Notifies `subscription` of every block after the current head, until the subscription is
+/// aborted or its connection is gone. Before the tracker knows the head, the first one it
+/// learns counts as current, so history is never replayed from genesis.
+///
+/// A block whose header cannot be fetched is skipped with a warning.
+async fn new_heads(
+    state: WsState,
+    subscription: String,
+    outbound: OutboundSender,
+    _permit: SubscriptionPermit,
+) {
+    let mut next = state.tracker.wait_for_height(0).await.block_height.0 + 1;
+
+    loop {
+        let head = state.tracker.wait_for_height(next).await.block_height.0;
+        for height in next..=head {
+            let block = state
+                .rpc
+                .module
+                .call::<_, Value>("eth_getBlockByNumber", (format!("{:#x}", height), false))
+                .await;
+            let mut header = match block {
+                Ok(header) => header,
+                Err(e) => {
+                    tracing::warn!("Failed to fetch new head {}: {}", height, e);
+                    continue;
+                }
+            };
+            if let Some(header) = header.as_object_mut() {
+                header.remove("transactions");
+                header.remove("uncles");
+            }
+
+            let notification = serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "eth_subscription",
+                "params": {
+                    "subscription": subscription,
+                    "result": header,
+                },
+            });
+            if !outbound.send(notification.to_string()).await {
+                return;
+            }
+        }
+        next = head + 1;
+    }
+}
+
+fn response(id: Value, result: Result<Value, ErrorObjectOwned>) -> Value {
+    match result {
+        Ok(result) => serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": result,
+            "id": id,
+        }),
+        Err(error) => serde_json::json!({
+            "jsonrpc": "2.0",
+            "error": error,
+            "id": id,
+        }),
+    }
+}
+
+async fn close(socket: &mut WebSocket, code: u16, reason: &str) {
+    let frame = CloseFrame {
+        code,
+        reason: reason.into(),
+    };
+    let _ = socket.send(Message::Close(Some(frame))).await;
+}
@@ -0,0 +1,140 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers for tests that run the sidecar, enabled by the `test-utils` feature.
+
+use crate::{
+    config::server::ServerConfig,
+    engine::{adapter::mock::MockEngineAdapter, tracker::HeadTracker, EngineClient},
+    server::{subscription::SubscriptionManager, RpcOptions, Server},
+};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+use tokio::{net::TcpListener, sync::oneshot, task::JoinHandle};
+
+/// Stops a server spawned by [`spawn_test_server`] when dropped.
+pub struct ShutdownGuard {
+    shutdown: Option<oneshot::Sender<()>>,
+    server: Option<JoinHandle<()>>,
+    tracker: JoinHandle<()>,
+}
+
+impl ShutdownGuard {
+    /// Stops the server and waits for it to finish serving open requests.
+    pub async fn shutdown(mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(server) = self.server.take() {
+            let _ = server.await;
+        }
+    }
+}
+
+impl Drop for ShutdownGuard {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        self.tracker.abort();
+    }
+}
+
+/// Settings of the JSON-RPC methods suitable for tests.
+pub fn rpc_options() -> RpcOptions {
+    RpcOptions {
+        native_decimals: 8,
+        assets: vec![],
+        genesis: None,
+        entry_func: "0x100::evm::transact".into(),
+        log_event: "0x100::evm::Log".into(),
+        create_event: "0x100::evm::ContractCreated".into(),
+        max_raw_tx_bytes: 128 * 1024,
+        max_calldata_bytes: 128 * 1024,
+        min_gas_price_wei: 1_000_000_000,
+        gas_price_ttl: Duration::ZERO,
+        blob_base_fee_wei: 1,
+        default_base_fee_wei: 0,
+        default_priority_fee_wei: 1_000_000_000,
+        block_gas_limit: 30_000_000,
+        evm_gas_per_unit: 1000,
+        evm_chain_id: None,
+        max_block_range: 10_000,
+        clamp_block_range: false,
+        block_fetch_failure: Default::default(),
+        reject_pending_calls: false,
+        proof_unavailable: Default::default(),
+        proof_unavailable_hint: "query a node with proof support".into(),
+        blocks: Default::default(),
+        sync_lag_threshold: 1000,
+        abort_reasons: Default::default(),
+        balance: Default::default(),
+        code_func: None,
+        storage_func: None,
+        accounts: Default::default(),
+        subscriptions: SubscriptionManager::new(1024, 10_000),
+        max_block_wait: Duration::from_secs(20),
+        upstream_health: None,
+    }
+}
+
+/// Spawns the full server backed by a [`MockEngineAdapter`] with its default responses,
+/// see [`spawn_test_server_with`].
+pub async fn spawn_test_server(config: ServerConfig) -> (SocketAddr, ShutdownGuard) {
+    spawn_test_server_with(config, MockEngineAdapter::new(), rpc_options()).await
+}
+
+/// Spawns the full server backed by `adapter` on an ephemeral port of the loopback
+/// interface, returning its address and a guard that stops it.
+///
+/// The configured address is ignored so tests can run in parallel. The head is polled
+/// before the server starts, so the latest block is known from the first request on.
+pub async fn spawn_test_server_with(
+    config: ServerConfig,
+    adapter: MockEngineAdapter,
+    options: RpcOptions,
+) -> (SocketAddr, ShutdownGuard) {
+    let client = EngineClient::new(Box::new(adapter));
+    let tracker = Arc::new(HeadTracker::new(client.clone(), Duration::from_millis(100)));
+    tracker
+        .poll()
+        .await
+        .expect("Failed to poll the engine head");
+    let tracker_task = tracker.spawn(Duration::from_millis(100));
+
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Failed to bind to address");
+    let addr = listener.local_addr().expect("Failed to read local address");
+    let (shutdown, signal) = oneshot::channel();
+    let server = tokio::spawn(async move {
+        Server::new(config)
+            .serve(listener, client, tracker, options, async {
+                let _ = signal.await;
+            })
+            .await
+            .expect("Failed to serve");
+    });
+
+    (
+        addr,
+        ShutdownGuard {
+            shutdown: Some(shutdown),
+            server: Some(server),
+            tracker: tracker_task,
+        },
+    )
+}
@@ -0,0 +1,298 @@
+// This file is part of Noir.
+
+// Copyright (c) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy_consensus::TxLegacy;
+use alloy_eips::eip2718::Encodable2718;
+use alloy_primitives::{Address, Bytes, TxKind, U256};
+use engine_sidecar::{
+    config::server::ServerConfig,
+    engine::adapter::mock::{
+        pending_user_transaction, sign_evm_transaction, user_transaction, MockEngineAdapter,
+    },
+    server::subscription::SubscriptionManager,
+    test_utils::{rpc_options, spawn_test_server, spawn_test_server_with},
+};
+use futures::{SinkExt, StreamExt};
+use std::{net::SocketAddr, time::Duration};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+type WsClient = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Sends a JSON-RPC request to `url` and returns the response.
+async fn request(url: &str, method: &str, params: serde_json::Value) -> serde_json::Value {
+    reqwest::Client::new()
+        .post(url)
+        .json(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
+
+/// Sends a JSON-RPC request over `ws` and returns the response, skipping pings.
+async fn ws_request(
+    ws: &mut WsClient,
+    method: &str,
+    params: serde_json::Value,
+) -> serde_json::Value {
+    let call = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    });
+    ws.send(Message::Text(call.to_string().into()))
+        .await
+        .unwrap();
+
+    loop {
+        match ws.next().await.unwrap().unwrap() {
+            Message::Text(text) => return serde_json::from_str(&text).unwrap(),
+            Message::Ping(_) | Message::Pong(_) => continue,
+            message => panic!("unexpected message {:?}", message),
+        }
+    }
+}
+
+async fn connect_ws(addr: SocketAddr) -> WsClient {
+    connect_async(format!("ws://{}/", addr)).await.unwrap().0
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn serves_json_rpc_until_shut_down() {
+    let (addr, guard) = spawn_test_server(ServerConfig::default()).await;
+    let url = format!("http://{}/", addr);
+
+    let response: serde_json::Value = reqwest::Client::new()
+        .post(&url)
+        .json(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_chainId",
+            "params": [],
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(response["result"], "0x4");
+
+    guard.shutdown().await;
+    assert!(reqwest::get(format!("{}health", url)).await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn submits_signed_transaction_and_serves_its_receipt() {
+    let tx = TxLegacy {
+        chain_id: Some(4),
+        nonce: 0,
+        gas_price: 1_000_000_000,
+        gas_limit: 21_000,
+        to: TxKind::Call(Address::repeat_byte(0x22)),
+        value: U256::from(1),
+        input: Bytes::new(),
+    };
+    let (envelope, signer) = sign_evm_transaction(tx);
+    let raw = envelope.encoded_2718();
+
+    let adapter = MockEngineAdapter::new()
+        .expect_submit(pending_user_transaction(&raw))
+        .with_block_transactions(vec![user_transaction(&raw)]);
+    let (addr, guard) =
+        spawn_test_server_with(ServerConfig::default(), adapter.clone(), rpc_options()).await;
+    let url = format!("http://{}/", addr);
+
+    let response = request(
+        &url,
+        "eth_sendRawTransaction",
+        serde_json::json!([Bytes::from(raw.clone())]),
+    )
+    .await;
+    assert_eq!(response["result"], serde_json::json!(envelope.tx_hash()));
+    let submitted = adapter.submitted();
+    assert_eq!(submitted.len(), 1);
+    assert_eq!(submitted[0].1, raw);
+    assert_eq!(submitted[0].3.max_gas_amount, 21);
+
+    // The engine commits the transaction at version 100, in block 10.
+    adapter.set_transaction(aptos_api_types::Transaction::UserTransaction(
+        user_transaction(&raw),
+    ));
+    let response = request(
+        &url,
+        "eth_getTransactionReceipt",
+        serde_json::json!([envelope.tx_hash()]),
+    )
+    .await;
+    let receipt = &response["result"];
+    assert_eq!(
+        receipt["transactionHash"],
+        serde_json::json!(envelope.tx_hash())
+    );
+    assert_eq!(receipt["status"], "0x1");
+    assert_eq!(receipt["type"], "0x0");
+    assert_eq!(receipt["blockNumber"], "0xa");
+    assert_eq!(receipt["from"], serde_json::json!(signer));
+    assert_eq!(receipt["to"], serde_json::json!(Address::repeat_byte(0x22)));
+    assert_eq!(receipt["gasUsed"], "0x5208");
+    assert_eq!(receipt["cumulativeGasUsed"], "0x5208");
+    assert_eq!(receipt["effectiveGasPrice"], "0x3b9aca00");
+
+    guard.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn serves_receipt_of_transaction_only_seen_in_a_block() {
+    let tx = TxLegacy {
+        chain_id: Some(4),
+        nonce: 0,
+        gas_price: 1_000_000_000,
+        gas_limit: 21_000,
+        to: TxKind::Call(Address::repeat_byte(0x22)),
+        value: U256::from(1),
+        input: Bytes::new(),
+    };
+    let (envelope, _) = sign_evm_transaction(tx);
+    let raw = envelope.encoded_2718();
+
+    // The transaction was submitted through another sidecar and committed in block 10.
+    let adapter = MockEngineAdapter::new()
+        .with_transaction(aptos_api_types::Transaction::UserTransaction(
+            user_transaction(&raw),
+        ))
+        .with_block_transactions(vec![user_transaction(&raw)]);
+    let (addr, guard) =
+        spawn_test_server_with(ServerConfig::default(), adapter.clone(), rpc_options()).await;
+    let url = format!("http://{}/", addr);
+
+    let response = request(
+        &url,
+        "eth_getBlockByNumber",
+        serde_json::json!(["0xa", false]),
+    )
+    .await;
+    assert_eq!(
+        response["result"]["transactions"],
+        serde_json::json!([envelope.tx_hash()])
+    );
+
+    let response = request(
+        &url,
+        "eth_getTransactionReceipt",
+        serde_json::json!([envelope.tx_hash()]),
+    )
+    .await;
+    let receipt = &response["result"];
+    assert_eq!(
+        receipt["transactionHash"],
+        serde_json::json!(envelope.tx_hash())
+    );
+    assert_eq!(receipt["blockNumber"], "0xa");
+    assert_eq!(receipt["status"], "0x1");
+    assert!(adapter.submitted().is_empty());
+
+    guard.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn limits_subscriptions_over_websocket() {
+    let mut options = rpc_options();
+    options.subscriptions = SubscriptionManager::new(1, 10);
+    let (addr, guard) =
+        spawn_test_server_with(ServerConfig::default(), MockEngineAdapter::new(), options).await;
+    let mut ws = connect_ws(addr).await;
+
+    let response = ws_request(&mut ws, "eth_chainId", serde_json::json!([])).await;
+    assert_eq!(response["result"], "0x4");
+
+    let response = ws_request(&mut ws, "eth_subscribe", serde_json::json!(["newHeads"])).await;
+    let subscription = response["result"].as_str().unwrap().to_string();
+    let response = ws_request(&mut ws, "eth_subscribe", serde_json::json!(["newHeads"])).await;
+    assert_eq!(response["error"]["code"], -32005);
+
+    // Unsubscribing frees the slot.
+    let response = ws_request(
+        &mut ws,
+        "eth_unsubscribe",
+        serde_json::json!([subscription]),
+    )
+    .await;
+    assert_eq!(response["result"], true);
+    let response = ws_request(
+        &mut ws,
+        "eth_unsubscribe",
+        serde_json::json!([subscription]),
+    )
+    .await;
+    assert_eq!(response["result"], false);
+    let response = ws_request(&mut ws, "eth_subscribe", serde_json::json!(["newHeads"])).await;
+    assert!(response["result"].is_string());
+
+    guard.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn closes_websocket_that_stops_answering_pings() {
+    let mut options = rpc_options();
+    options.subscriptions = SubscriptionManager::new(1, 10)
+        .with_keepalive(Duration::from_millis(50), Duration::from_millis(50));
+    let (addr, guard) =
+        spawn_test_server_with(ServerConfig::default(), MockEngineAdapter::new(), options).await;
+
+    // A client that keeps reading answers the pings and stays connected.
+    let mut alive = connect_ws(addr).await;
+    let reading = tokio::time::timeout(Duration::from_millis(300), async {
+        loop {
+            match alive.next().await {
+                Some(Ok(Message::Ping(_))) => continue,
+                message => return message,
+            }
+        }
+    })
+    .await;
+    assert!(reading.is_err(), "connection ended: {:?}", reading);
+    let response = ws_request(&mut alive, "eth_chainId", serde_json::json!([])).await;
+    assert_eq!(response["result"], "0x4");
+
+    // A client that stops reading leaves the pings unanswered and is closed.
+    let mut silent = connect_ws(addr).await;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let closed = tokio::time::timeout(Duration::from_secs(1), async {
+        loop {
+            match silent.next().await {
+                Some(Ok(Message::Ping(_))) => continue,
+                Some(Ok(Message::Close(frame))) => return frame.map(|frame| u16::from(frame.code)),
+                message => panic!("unexpected message {:?}", message),
+            }
+        }
+    })
+    .await
+    .expect("connection was not closed");
+    assert_eq!(closed, Some(1001));
+
+    guard.shutdown().await;
+}